@@ -0,0 +1,253 @@
+// Generated by rocketcan::codegen, do not edit.
+
+use crate::canlog_reader::CanFrame;
+use crate::signal_layout::{extract_spans, pack_spans, BitSpan};
+
+/// Signed (0x400), 2 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Signed {
+    /// Reserved [0|0]
+    pub reserved: i8,
+    /// Temperature [-128|127] "degC"
+    pub temperature: i8,
+}
+
+impl Signed {
+    pub const ID: u32 = 0x400;
+    pub const NAME: &'static str = "Signed";
+    pub const LEN: u8 = 2;
+
+    pub const RESERVED_MIN: f64 = 0.0;
+    pub const RESERVED_MAX: f64 = 0.0;
+    pub const RESERVED_UNIT: &'static str = "";
+    const RESERVED_SPANS: &'static [BitSpan] = &[
+    ];
+
+    pub const TEMPERATURE_MIN: f64 = -128.0;
+    pub const TEMPERATURE_MAX: f64 = 127.0;
+    pub const TEMPERATURE_UNIT: &'static str = "degC";
+    const TEMPERATURE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::RESERVED_SPANS, &frame.data),
+            extract_spans(Self::TEMPERATURE_SPANS, &frame.data),
+        ];
+        Self {
+            reserved: 0i64 as i8,
+            temperature: (((raw[1] << 56) as i64) >> 56) as i8,
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.reserved;
+        pack_spans(Self::RESERVED_SPANS, &mut frame.data, (value as i64 as u64) & 0x0);
+        let value = self.temperature;
+        pack_spans(Self::TEMPERATURE_SPANS, &mut frame.data, (value as i64 as u64) & 0xFF);
+        frame
+    }
+}
+
+/// Values of Gear.Prndl
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GearPrndl {
+    /// 1 = P
+    P,
+    /// 2 = R
+    R,
+    /// 3 = N
+    N,
+    /// 4 = D
+    D,
+    /// 5 = S
+    S,
+    /// Value without a description
+    Other(i64),
+}
+
+impl GearPrndl {
+    pub fn from_raw(raw: i64) -> Self {
+        match raw {
+            1 => Self::P,
+            2 => Self::R,
+            3 => Self::N,
+            4 => Self::D,
+            5 => Self::S,
+            other => Self::Other(other),
+        }
+    }
+
+    pub fn raw(&self) -> i64 {
+        match self {
+            Self::P => 1,
+            Self::R => 2,
+            Self::N => 3,
+            Self::D => 4,
+            Self::S => 5,
+            Self::Other(raw) => *raw,
+        }
+    }
+
+    /// Label of the value in the DBC
+    pub fn label(&self) -> Option<&'static str> {
+        match self {
+            Self::P => Some("P"),
+            Self::R => Some("R"),
+            Self::N => Some("N"),
+            Self::D => Some("D"),
+            Self::S => Some("S"),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+impl Default for GearPrndl {
+    fn default() -> Self {
+        Self::from_raw(0)
+    }
+}
+
+/// Gear (0x4EE), 8 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Gear {
+    /// Prndl [0|15]
+    pub prndl: GearPrndl,
+    /// Torque [-16384|16383.5] "Nm"
+    pub torque: f64,
+}
+
+impl Gear {
+    pub const ID: u32 = 0x4EE;
+    pub const NAME: &'static str = "Gear";
+    pub const LEN: u8 = 8;
+
+    pub const PRNDL_MIN: f64 = 0.0;
+    pub const PRNDL_MAX: f64 = 15.0;
+    pub const PRNDL_UNIT: &'static str = "";
+    const PRNDL_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 4, value_shift: 0 },
+    ];
+
+    pub const TORQUE_MIN: f64 = -16384.0;
+    pub const TORQUE_MAX: f64 = 16383.5;
+    pub const TORQUE_UNIT: &'static str = "Nm";
+    const TORQUE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 3, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::PRNDL_SPANS, &frame.data),
+            extract_spans(Self::TORQUE_SPANS, &frame.data),
+        ];
+        Self {
+            prndl: GearPrndl::from_raw(raw[0] as i64),
+            torque: ((((raw[1] << 48) as i64) >> 48) as f64) * 0.5,
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.prndl;
+        pack_spans(Self::PRNDL_SPANS, &mut frame.data, (value.raw() as u64) & 0xF);
+        let value = self.torque;
+        pack_spans(Self::TORQUE_SPANS, &mut frame.data, ((value / 0.5).round() as i64 as u64) & 0xFFFF);
+        frame
+    }
+}
+
+/// Mixed (0x500), 8 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mixed {
+    /// Page [0|255]
+    pub page: u8,
+    /// Offset [-3276.8|3276.7] "V", multiplexed: None when not in the frame
+    pub offset: Option<f64>,
+    /// Ratio [-1000000|1000000], multiplexed: None when not in the frame
+    pub ratio: Option<f32>,
+}
+
+impl Mixed {
+    pub const ID: u32 = 0x500;
+    pub const NAME: &'static str = "Mixed";
+    pub const LEN: u8 = 8;
+
+    pub const PAGE_MIN: f64 = 0.0;
+    pub const PAGE_MAX: f64 = 255.0;
+    pub const PAGE_UNIT: &'static str = "";
+    const PAGE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    pub const OFFSET_MIN: f64 = -3276.8;
+    pub const OFFSET_MAX: f64 = 3276.7;
+    pub const OFFSET_UNIT: &'static str = "V";
+    const OFFSET_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 8, value_shift: 0 },
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 8 },
+    ];
+
+    pub const RATIO_MIN: f64 = -1000000.0;
+    pub const RATIO_MAX: f64 = 1000000.0;
+    pub const RATIO_UNIT: &'static str = "";
+    const RATIO_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 4, bit_offset: 0, num_bits: 8, value_shift: 0 },
+        BitSpan { byte_index: 5, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 6, bit_offset: 0, num_bits: 8, value_shift: 16 },
+        BitSpan { byte_index: 7, bit_offset: 0, num_bits: 8, value_shift: 24 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::PAGE_SPANS, &frame.data),
+            extract_spans(Self::OFFSET_SPANS, &frame.data),
+            extract_spans(Self::RATIO_SPANS, &frame.data),
+        ];
+        Self {
+            page: raw[0] as u8,
+            offset: if raw[0] == 1 { Some(((((raw[1] << 48) as i64) >> 48) as f64) * 0.1) } else { None },
+            ratio: if raw[0] == 2 { Some(f32::from_bits(raw[2] as u32)) } else { None },
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.page;
+        pack_spans(Self::PAGE_SPANS, &mut frame.data, (value as u64) & 0xFF);
+        if let Some(value) = self.offset {
+            pack_spans(Self::OFFSET_SPANS, &mut frame.data, ((value / 0.1).round() as i64 as u64) & 0xFFFF);
+        }
+        if let Some(value) = self.ratio {
+            pack_spans(Self::RATIO_SPANS, &mut frame.data, value.to_bits() as u64);
+        }
+        frame
+    }
+}
@@ -0,0 +1,279 @@
+// Generated by rocketcan::codegen, do not edit.
+
+use crate::canlog_reader::CanFrame;
+use crate::signal_layout::{extract_spans, pack_spans, BitSpan};
+
+/// FloatsLE (0x300), 8 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FloatsLe {
+    /// Speed [-340000000000000000000000000000000000000|340000000000000000000000000000000000000] "m/s"
+    pub speed: f32,
+    /// Gain [-340000000000000000000000000000000000000|340000000000000000000000000000000000000]
+    pub gain: f64,
+}
+
+impl FloatsLe {
+    pub const ID: u32 = 0x300;
+    pub const NAME: &'static str = "FloatsLE";
+    pub const LEN: u8 = 8;
+
+    pub const SPEED_MIN: f64 = -3.4e38;
+    pub const SPEED_MAX: f64 = 3.4e38;
+    pub const SPEED_UNIT: &'static str = "m/s";
+    const SPEED_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 0, num_bits: 8, value_shift: 0 },
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 16 },
+        BitSpan { byte_index: 3, bit_offset: 0, num_bits: 8, value_shift: 24 },
+    ];
+
+    pub const GAIN_MIN: f64 = -3.4e38;
+    pub const GAIN_MAX: f64 = 3.4e38;
+    pub const GAIN_UNIT: &'static str = "";
+    const GAIN_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 4, bit_offset: 0, num_bits: 8, value_shift: 0 },
+        BitSpan { byte_index: 5, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 6, bit_offset: 0, num_bits: 8, value_shift: 16 },
+        BitSpan { byte_index: 7, bit_offset: 0, num_bits: 8, value_shift: 24 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::SPEED_SPANS, &frame.data),
+            extract_spans(Self::GAIN_SPANS, &frame.data),
+        ];
+        Self {
+            speed: f32::from_bits(raw[0] as u32),
+            gain: (f32::from_bits(raw[1] as u32) as f64) * 2.0 + 10.0,
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.speed;
+        pack_spans(Self::SPEED_SPANS, &mut frame.data, value.to_bits() as u64);
+        let value = self.gain;
+        pack_spans(Self::GAIN_SPANS, &mut frame.data, (((value - 10.0) / 2.0) as f32).to_bits() as u64);
+        frame
+    }
+}
+
+/// FloatsBE (0x301), 8 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FloatsBe {
+    /// Pressure [-340000000000000000000000000000000000000|340000000000000000000000000000000000000] "kPa"
+    pub pressure: f32,
+    /// Ratio [-340000000000000000000000000000000000000|340000000000000000000000000000000000000]
+    pub ratio: f32,
+}
+
+impl FloatsBe {
+    pub const ID: u32 = 0x301;
+    pub const NAME: &'static str = "FloatsBE";
+    pub const LEN: u8 = 8;
+
+    pub const PRESSURE_MIN: f64 = -3.4e38;
+    pub const PRESSURE_MAX: f64 = 3.4e38;
+    pub const PRESSURE_UNIT: &'static str = "kPa";
+    const PRESSURE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 0, num_bits: 8, value_shift: 24 },
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 8, value_shift: 16 },
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 3, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    pub const RATIO_MIN: f64 = -3.4e38;
+    pub const RATIO_MAX: f64 = 3.4e38;
+    pub const RATIO_UNIT: &'static str = "";
+    const RATIO_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 4, bit_offset: 0, num_bits: 8, value_shift: 24 },
+        BitSpan { byte_index: 5, bit_offset: 0, num_bits: 8, value_shift: 16 },
+        BitSpan { byte_index: 6, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 7, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::PRESSURE_SPANS, &frame.data),
+            extract_spans(Self::RATIO_SPANS, &frame.data),
+        ];
+        Self {
+            pressure: f32::from_bits(raw[0] as u32),
+            ratio: f32::from_bits(raw[1] as u32),
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.pressure;
+        pack_spans(Self::PRESSURE_SPANS, &mut frame.data, value.to_bits() as u64);
+        let value = self.ratio;
+        pack_spans(Self::RATIO_SPANS, &mut frame.data, value.to_bits() as u64);
+        frame
+    }
+}
+
+/// DoubleLE (0x302), 8 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DoubleLe {
+    /// Position [-170000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000|170000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000] "m"
+    pub position: f64,
+}
+
+impl DoubleLe {
+    pub const ID: u32 = 0x302;
+    pub const NAME: &'static str = "DoubleLE";
+    pub const LEN: u8 = 8;
+
+    pub const POSITION_MIN: f64 = -1.7e308;
+    pub const POSITION_MAX: f64 = 1.7e308;
+    pub const POSITION_UNIT: &'static str = "m";
+    const POSITION_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 0, num_bits: 8, value_shift: 0 },
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 16 },
+        BitSpan { byte_index: 3, bit_offset: 0, num_bits: 8, value_shift: 24 },
+        BitSpan { byte_index: 4, bit_offset: 0, num_bits: 8, value_shift: 32 },
+        BitSpan { byte_index: 5, bit_offset: 0, num_bits: 8, value_shift: 40 },
+        BitSpan { byte_index: 6, bit_offset: 0, num_bits: 8, value_shift: 48 },
+        BitSpan { byte_index: 7, bit_offset: 0, num_bits: 8, value_shift: 56 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::POSITION_SPANS, &frame.data),
+        ];
+        Self {
+            position: f64::from_bits(raw[0]),
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.position;
+        pack_spans(Self::POSITION_SPANS, &mut frame.data, value.to_bits());
+        frame
+    }
+}
+
+/// DoubleBE (0x303), 8 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DoubleBe {
+    /// Altitude [-170000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000|170000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000] "m"
+    pub altitude: f64,
+}
+
+impl DoubleBe {
+    pub const ID: u32 = 0x303;
+    pub const NAME: &'static str = "DoubleBE";
+    pub const LEN: u8 = 8;
+
+    pub const ALTITUDE_MIN: f64 = -1.7e308;
+    pub const ALTITUDE_MAX: f64 = 1.7e308;
+    pub const ALTITUDE_UNIT: &'static str = "m";
+    const ALTITUDE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 0, num_bits: 8, value_shift: 56 },
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 8, value_shift: 48 },
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 40 },
+        BitSpan { byte_index: 3, bit_offset: 0, num_bits: 8, value_shift: 32 },
+        BitSpan { byte_index: 4, bit_offset: 0, num_bits: 8, value_shift: 24 },
+        BitSpan { byte_index: 5, bit_offset: 0, num_bits: 8, value_shift: 16 },
+        BitSpan { byte_index: 6, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 7, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::ALTITUDE_SPANS, &frame.data),
+        ];
+        Self {
+            altitude: f64::from_bits(raw[0]),
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.altitude;
+        pack_spans(Self::ALTITUDE_SPANS, &mut frame.data, value.to_bits());
+        frame
+    }
+}
+
+/// Counter (0x304), 8 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Counter {
+    /// Ticks [0|4294967295]
+    pub ticks: u32,
+}
+
+impl Counter {
+    pub const ID: u32 = 0x304;
+    pub const NAME: &'static str = "Counter";
+    pub const LEN: u8 = 8;
+
+    pub const TICKS_MIN: f64 = 0.0;
+    pub const TICKS_MAX: f64 = 4294967295.0;
+    pub const TICKS_UNIT: &'static str = "";
+    const TICKS_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 0, num_bits: 8, value_shift: 0 },
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 16 },
+        BitSpan { byte_index: 3, bit_offset: 0, num_bits: 8, value_shift: 24 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::TICKS_SPANS, &frame.data),
+        ];
+        Self {
+            ticks: raw[0] as u32,
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.ticks;
+        pack_spans(Self::TICKS_SPANS, &mut frame.data, (value as u64) & 0xFFFFFFFF);
+        frame
+    }
+}
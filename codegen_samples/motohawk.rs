@@ -0,0 +1,120 @@
+// Generated by rocketcan::codegen, do not edit.
+
+use crate::canlog_reader::CanFrame;
+use crate::signal_layout::{extract_spans, pack_spans, BitSpan};
+
+/// Values of ExampleMessage.Enable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExampleMessageEnable {
+    /// 0 = Disabled
+    Disabled,
+    /// 1 = Enabled
+    Enabled,
+    /// Value without a description
+    Other(i64),
+}
+
+impl ExampleMessageEnable {
+    pub fn from_raw(raw: i64) -> Self {
+        match raw {
+            0 => Self::Disabled,
+            1 => Self::Enabled,
+            other => Self::Other(other),
+        }
+    }
+
+    pub fn raw(&self) -> i64 {
+        match self {
+            Self::Disabled => 0,
+            Self::Enabled => 1,
+            Self::Other(raw) => *raw,
+        }
+    }
+
+    /// Label of the value in the DBC
+    pub fn label(&self) -> Option<&'static str> {
+        match self {
+            Self::Disabled => Some("Disabled"),
+            Self::Enabled => Some("Enabled"),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+impl Default for ExampleMessageEnable {
+    fn default() -> Self {
+        Self::from_raw(0)
+    }
+}
+
+/// ExampleMessage (0x1F0), 8 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExampleMessage {
+    /// Temperature [229.52|270.47] "degK"
+    pub temperature: f64,
+    /// AverageRadius [0|5] "m"
+    pub average_radius: f64,
+    /// Enable [0|0] "-"
+    pub enable: ExampleMessageEnable,
+}
+
+impl ExampleMessage {
+    pub const ID: u32 = 0x1F0;
+    pub const NAME: &'static str = "ExampleMessage";
+    pub const LEN: u8 = 8;
+
+    pub const TEMPERATURE_MIN: f64 = 229.52;
+    pub const TEMPERATURE_MAX: f64 = 270.47;
+    pub const TEMPERATURE_UNIT: &'static str = "degK";
+    const TEMPERATURE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 0, num_bits: 1, value_shift: 11 },
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 8, value_shift: 3 },
+        BitSpan { byte_index: 2, bit_offset: 5, num_bits: 3, value_shift: 0 },
+    ];
+
+    pub const AVERAGE_RADIUS_MIN: f64 = 0.0;
+    pub const AVERAGE_RADIUS_MAX: f64 = 5.0;
+    pub const AVERAGE_RADIUS_UNIT: &'static str = "m";
+    const AVERAGE_RADIUS_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 1, num_bits: 6, value_shift: 0 },
+    ];
+
+    pub const ENABLE_MIN: f64 = 0.0;
+    pub const ENABLE_MAX: f64 = 0.0;
+    pub const ENABLE_UNIT: &'static str = "-";
+    const ENABLE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 7, num_bits: 1, value_shift: 0 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::TEMPERATURE_SPANS, &frame.data),
+            extract_spans(Self::AVERAGE_RADIUS_SPANS, &frame.data),
+            extract_spans(Self::ENABLE_SPANS, &frame.data),
+        ];
+        Self {
+            temperature: ((((raw[0] << 52) as i64) >> 52) as f64) * 0.01 + 250.0,
+            average_radius: (raw[1] as f64) * 0.1,
+            enable: ExampleMessageEnable::from_raw(raw[2] as i64),
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.temperature;
+        pack_spans(Self::TEMPERATURE_SPANS, &mut frame.data, (((value - 250.0) / 0.01).round() as i64 as u64) & 0xFFF);
+        let value = self.average_radius;
+        pack_spans(Self::AVERAGE_RADIUS_SPANS, &mut frame.data, ((value / 0.1).round() as u64) & 0x3F);
+        let value = self.enable;
+        pack_spans(Self::ENABLE_SPANS, &mut frame.data, (value.raw() as u64) & 0x1);
+        frame
+    }
+}
@@ -0,0 +1,384 @@
+// Generated by rocketcan::codegen, do not edit.
+
+use crate::canlog_reader::CanFrame;
+use crate::signal_layout::{extract_spans, pack_spans, BitSpan};
+
+/// Values of VAL_TABLE_ Status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// 0 = Off
+    Off,
+    /// 1 = Init
+    Init,
+    /// 2 = Running
+    Running,
+    /// 15 = Error
+    Error,
+    /// Value without a description
+    Other(i64),
+}
+
+impl Status {
+    pub fn from_raw(raw: i64) -> Self {
+        match raw {
+            0 => Self::Off,
+            1 => Self::Init,
+            2 => Self::Running,
+            15 => Self::Error,
+            other => Self::Other(other),
+        }
+    }
+
+    pub fn raw(&self) -> i64 {
+        match self {
+            Self::Off => 0,
+            Self::Init => 1,
+            Self::Running => 2,
+            Self::Error => 15,
+            Self::Other(raw) => *raw,
+        }
+    }
+
+    /// Label of the value in the DBC
+    pub fn label(&self) -> Option<&'static str> {
+        match self {
+            Self::Off => Some("Off"),
+            Self::Init => Some("Init"),
+            Self::Running => Some("Running"),
+            Self::Error => Some("Error"),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self::from_raw(0)
+    }
+}
+
+/// Values of Diagnostics.Page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticsPage {
+    /// 1 = Version
+    Version,
+    /// 2 = Supply
+    Supply,
+    /// Value without a description
+    Other(i64),
+}
+
+impl DiagnosticsPage {
+    pub fn from_raw(raw: i64) -> Self {
+        match raw {
+            1 => Self::Version,
+            2 => Self::Supply,
+            other => Self::Other(other),
+        }
+    }
+
+    pub fn raw(&self) -> i64 {
+        match self {
+            Self::Version => 1,
+            Self::Supply => 2,
+            Self::Other(raw) => *raw,
+        }
+    }
+
+    /// Label of the value in the DBC
+    pub fn label(&self) -> Option<&'static str> {
+        match self {
+            Self::Version => Some("Version"),
+            Self::Supply => Some("Supply"),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+impl Default for DiagnosticsPage {
+    fn default() -> Self {
+        Self::from_raw(0)
+    }
+}
+
+/// Diagnostics (0x542), 8 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Diagnostics {
+    /// Page [0|255]
+    pub page: DiagnosticsPage,
+    /// Status [0|15]
+    pub status: Status,
+    /// SW_Major [0|255], multiplexed: None when not in the frame
+    pub sw_major: Option<u8>,
+    /// SW_Minor [0|255], multiplexed: None when not in the frame
+    pub sw_minor: Option<u8>,
+    /// Voltage [0|65.535] "V", multiplexed: None when not in the frame
+    pub voltage: Option<f64>,
+    /// Current [-327.68|327.67] "A", multiplexed: None when not in the frame
+    pub current: Option<f64>,
+}
+
+impl Diagnostics {
+    pub const ID: u32 = 0x542;
+    pub const NAME: &'static str = "Diagnostics";
+    pub const LEN: u8 = 8;
+
+    pub const PAGE_MIN: f64 = 0.0;
+    pub const PAGE_MAX: f64 = 255.0;
+    pub const PAGE_UNIT: &'static str = "";
+    const PAGE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    pub const STATUS_MIN: f64 = 0.0;
+    pub const STATUS_MAX: f64 = 15.0;
+    pub const STATUS_UNIT: &'static str = "";
+    const STATUS_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 4, value_shift: 0 },
+    ];
+
+    pub const SW_MAJOR_MIN: f64 = 0.0;
+    pub const SW_MAJOR_MAX: f64 = 255.0;
+    pub const SW_MAJOR_UNIT: &'static str = "";
+    const SW_MAJOR_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    pub const SW_MINOR_MIN: f64 = 0.0;
+    pub const SW_MINOR_MAX: f64 = 255.0;
+    pub const SW_MINOR_UNIT: &'static str = "";
+    const SW_MINOR_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 3, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    pub const VOLTAGE_MIN: f64 = 0.0;
+    pub const VOLTAGE_MAX: f64 = 65.535;
+    pub const VOLTAGE_UNIT: &'static str = "V";
+    const VOLTAGE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 0 },
+        BitSpan { byte_index: 3, bit_offset: 0, num_bits: 8, value_shift: 8 },
+    ];
+
+    pub const CURRENT_MIN: f64 = -327.68;
+    pub const CURRENT_MAX: f64 = 327.67;
+    pub const CURRENT_UNIT: &'static str = "A";
+    const CURRENT_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 4, bit_offset: 0, num_bits: 8, value_shift: 0 },
+        BitSpan { byte_index: 5, bit_offset: 0, num_bits: 8, value_shift: 8 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::PAGE_SPANS, &frame.data),
+            extract_spans(Self::STATUS_SPANS, &frame.data),
+            extract_spans(Self::SW_MAJOR_SPANS, &frame.data),
+            extract_spans(Self::SW_MINOR_SPANS, &frame.data),
+            extract_spans(Self::VOLTAGE_SPANS, &frame.data),
+            extract_spans(Self::CURRENT_SPANS, &frame.data),
+        ];
+        Self {
+            page: DiagnosticsPage::from_raw(raw[0] as i64),
+            status: Status::from_raw(raw[1] as i64),
+            sw_major: if raw[0] == 1 { Some(raw[2] as u8) } else { None },
+            sw_minor: if raw[0] == 1 { Some(raw[3] as u8) } else { None },
+            voltage: if raw[0] == 2 { Some((raw[4] as f64) * 0.001) } else { None },
+            current: if raw[0] == 2 { Some(((((raw[5] << 48) as i64) >> 48) as f64) * 0.01) } else { None },
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.page;
+        pack_spans(Self::PAGE_SPANS, &mut frame.data, (value.raw() as u64) & 0xFF);
+        let value = self.status;
+        pack_spans(Self::STATUS_SPANS, &mut frame.data, (value.raw() as u64) & 0xF);
+        if let Some(value) = self.sw_major {
+            pack_spans(Self::SW_MAJOR_SPANS, &mut frame.data, (value as u64) & 0xFF);
+        }
+        if let Some(value) = self.sw_minor {
+            pack_spans(Self::SW_MINOR_SPANS, &mut frame.data, (value as u64) & 0xFF);
+        }
+        if let Some(value) = self.voltage {
+            pack_spans(Self::VOLTAGE_SPANS, &mut frame.data, ((value / 0.001).round() as u64) & 0xFFFF);
+        }
+        if let Some(value) = self.current {
+            pack_spans(Self::CURRENT_SPANS, &mut frame.data, ((value / 0.01).round() as i64 as u64) & 0xFFFF);
+        }
+        frame
+    }
+}
+
+/// Values of UdsResponse.ResponseCode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdsResponseResponseCode {
+    /// 17 = serviceNotSupported
+    ServiceNotSupported,
+    /// 19 = incorrectMessageLength
+    IncorrectMessageLength,
+    /// 49 = requestOutOfRange
+    RequestOutOfRange,
+    /// Value without a description
+    Other(i64),
+}
+
+impl UdsResponseResponseCode {
+    pub fn from_raw(raw: i64) -> Self {
+        match raw {
+            17 => Self::ServiceNotSupported,
+            19 => Self::IncorrectMessageLength,
+            49 => Self::RequestOutOfRange,
+            other => Self::Other(other),
+        }
+    }
+
+    pub fn raw(&self) -> i64 {
+        match self {
+            Self::ServiceNotSupported => 17,
+            Self::IncorrectMessageLength => 19,
+            Self::RequestOutOfRange => 49,
+            Self::Other(raw) => *raw,
+        }
+    }
+
+    /// Label of the value in the DBC
+    pub fn label(&self) -> Option<&'static str> {
+        match self {
+            Self::ServiceNotSupported => Some("serviceNotSupported"),
+            Self::IncorrectMessageLength => Some("incorrectMessageLength"),
+            Self::RequestOutOfRange => Some("requestOutOfRange"),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+impl Default for UdsResponseResponseCode {
+    fn default() -> Self {
+        Self::from_raw(0)
+    }
+}
+
+/// UdsResponse (0x602 extended), 8 bytes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UdsResponse {
+    /// Service [0|255]
+    pub service: u8,
+    /// NegativeService [0|255], multiplexed: None when not in the frame
+    pub negative_service: Option<u8>,
+    /// ResponseCode [0|255], multiplexed: None when not in the frame
+    pub response_code: Option<UdsResponseResponseCode>,
+    /// Identifier [0|65535], multiplexed: None when not in the frame
+    pub identifier: Option<u16>,
+    /// CoolantTemp [-40|215] "degC", multiplexed: None when not in the frame
+    pub coolant_temp: Option<f64>,
+    /// Odometer [0|429496729.5] "km", multiplexed: None when not in the frame
+    pub odometer: Option<f64>,
+}
+
+impl UdsResponse {
+    pub const ID: u32 = 0x602;
+    pub const NAME: &'static str = "UdsResponse";
+    pub const LEN: u8 = 8;
+
+    pub const SERVICE_MIN: f64 = 0.0;
+    pub const SERVICE_MAX: f64 = 255.0;
+    pub const SERVICE_UNIT: &'static str = "";
+    const SERVICE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 0, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    pub const NEGATIVE_SERVICE_MIN: f64 = 0.0;
+    pub const NEGATIVE_SERVICE_MAX: f64 = 255.0;
+    pub const NEGATIVE_SERVICE_UNIT: &'static str = "";
+    const NEGATIVE_SERVICE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    pub const RESPONSE_CODE_MIN: f64 = 0.0;
+    pub const RESPONSE_CODE_MAX: f64 = 255.0;
+    pub const RESPONSE_CODE_UNIT: &'static str = "";
+    const RESPONSE_CODE_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    pub const IDENTIFIER_MIN: f64 = 0.0;
+    pub const IDENTIFIER_MAX: f64 = 65535.0;
+    pub const IDENTIFIER_UNIT: &'static str = "";
+    const IDENTIFIER_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 1, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 2, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    pub const COOLANT_TEMP_MIN: f64 = -40.0;
+    pub const COOLANT_TEMP_MAX: f64 = 215.0;
+    pub const COOLANT_TEMP_UNIT: &'static str = "degC";
+    const COOLANT_TEMP_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 3, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    pub const ODOMETER_MIN: f64 = 0.0;
+    pub const ODOMETER_MAX: f64 = 429496729.5;
+    pub const ODOMETER_UNIT: &'static str = "km";
+    const ODOMETER_SPANS: &'static [BitSpan] = &[
+        BitSpan { byte_index: 3, bit_offset: 0, num_bits: 8, value_shift: 24 },
+        BitSpan { byte_index: 4, bit_offset: 0, num_bits: 8, value_shift: 16 },
+        BitSpan { byte_index: 5, bit_offset: 0, num_bits: 8, value_shift: 8 },
+        BitSpan { byte_index: 6, bit_offset: 0, num_bits: 8, value_shift: 0 },
+    ];
+
+    /// Decode the signals of a frame of this message
+    pub fn decode(frame: &CanFrame) -> Self {
+        let raw = [
+            extract_spans(Self::SERVICE_SPANS, &frame.data),
+            extract_spans(Self::NEGATIVE_SERVICE_SPANS, &frame.data),
+            extract_spans(Self::RESPONSE_CODE_SPANS, &frame.data),
+            extract_spans(Self::IDENTIFIER_SPANS, &frame.data),
+            extract_spans(Self::COOLANT_TEMP_SPANS, &frame.data),
+            extract_spans(Self::ODOMETER_SPANS, &frame.data),
+        ];
+        Self {
+            service: raw[0] as u8,
+            negative_service: if raw[0] == 127 { Some(raw[1] as u8) } else { None },
+            response_code: if raw[0] == 127 { Some(UdsResponseResponseCode::from_raw(raw[2] as i64)) } else { None },
+            identifier: if raw[0] == 98 { Some(raw[3] as u16) } else { None },
+            coolant_temp: if raw[0] == 98 && raw[3] == 256 { Some((raw[4] as f64) - 40.0) } else { None },
+            odometer: if raw[0] == 98 && (512..=767).contains(&raw[3]) { Some((raw[5] as f64) * 0.1) } else { None },
+        }
+    }
+
+    /// Encode into a frame. Multiplexed fields are only packed when Some,
+    /// the multiplexer fields must select them.
+    pub fn encode(&self) -> CanFrame {
+        let mut frame = CanFrame {
+            id: Self::ID,
+            len: Self::LEN,
+            is_fd: false,
+            ..Default::default()
+        };
+        let value = self.service;
+        pack_spans(Self::SERVICE_SPANS, &mut frame.data, (value as u64) & 0xFF);
+        if let Some(value) = self.negative_service {
+            pack_spans(Self::NEGATIVE_SERVICE_SPANS, &mut frame.data, (value as u64) & 0xFF);
+        }
+        if let Some(value) = self.response_code {
+            pack_spans(Self::RESPONSE_CODE_SPANS, &mut frame.data, (value.raw() as u64) & 0xFF);
+        }
+        if let Some(value) = self.identifier {
+            pack_spans(Self::IDENTIFIER_SPANS, &mut frame.data, (value as u64) & 0xFFFF);
+        }
+        if let Some(value) = self.coolant_temp {
+            pack_spans(Self::COOLANT_TEMP_SPANS, &mut frame.data, ((value + 40.0).round() as u64) & 0xFF);
+        }
+        if let Some(value) = self.odometer {
+            pack_spans(Self::ODOMETER_SPANS, &mut frame.data, ((value / 0.1).round() as u64) & 0xFFFFFFFF);
+        }
+        frame
+    }
+}
@@ -0,0 +1,923 @@
+/*!
+ * AUTOSAR ARXML (4.x) communication matrix import.
+ * ARXML system description -> one can_dbc::Dbc per CAN physical channel
+ *
+ * - CAN-CLUSTER / CAN-PHYSICAL-CHANNEL: an `ArxmlChannel` each
+ * - CAN-FRAME-TRIGGERING: BO_ with the ID and addressing mode of the triggering,
+ *   CAN FD frames get the VFrameFormat attribute
+ * - ECU-INSTANCE frame ports: BU_, transmitters and receivers
+ * - I-SIGNAL-I-PDU: SG_ of its I-signals, at their position in the frame
+ * - COMPU-METHOD: linear scales (factor, offset and range) and text tables (VAL_ labels)
+ * - SW-BASE-TYPE: 2C is signed, IEEE754 is SIG_VALTYPE_ float32/float64
+ * - MULTIPLEXED-I-PDU: the selector field is the multiplexer switch, the signals of each
+ *   dynamic part alternative are m<selector field code>, with SG_MUL_VAL_ ranges for signals
+ *   on several alternatives
+ * - CONTAINER-I-PDU with short or long headers: the contained PDUs are the messages of
+ *   `contained_pdus`, with their header ID as ID. `ContainerFrame` splits and builds the frames.
+ *
+ * ```no_run
+ * use rocketcan::arxml_loader;
+ * use rocketcan::can_database::CanDatabase;
+ * use rocketcan::canlog_reader::CanFrame;
+ *
+ * let channels = arxml_loader::load("system.arxml").unwrap();
+ * let channel = &channels[0];
+ * let frames = CanDatabase::from_dbc(channel.dbc.clone());
+ * let pdus = CanDatabase::from_dbc(channel.contained_pdus.clone());
+ *
+ * let frame = CanFrame::default();
+ * match channel.container(frame.id) {
+ *     Some(container) => {
+ *         for pdu in container.unpack(&frame) {
+ *             println!("{:?}", pdus.decode_frame(&pdu));
+ *         }
+ *     }
+ *     None => println!("{:?}", frames.decode_frame(&frame)),
+ * }
+ * ```
+ */
+
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::{anyhow, bail, Context, Result};
+use can_dbc::{
+    AttributeDefault, AttributeDefinition, AttributeValue, AttributeValueForMessage, AttributeValueType, ByteOrder, Dbc,
+    MessageId, MultiplexIndicator, Signal, SignalExtendedValueType,
+};
+use roxmltree::{Document, Node};
+
+use crate::canlog_reader::CanFrame;
+use crate::dbc_editor::{self, DbcEditor, MessageBuilder, SignalBuilder};
+use crate::xml_util::{descendant, descendants, number, position};
+
+/// Values of the VFrameFormat message attribute, as defined by DBC editors
+const FRAME_FORMATS: &[&str] = &[
+    "StandardCAN", "ExtendedCAN", "reserved", "reserved", "reserved", "reserved", "reserved", "reserved", "reserved",
+    "reserved", "reserved", "reserved", "reserved", "reserved", "StandardCAN_FD", "ExtendedCAN_FD",
+];
+
+/// The frames of a CAN physical channel
+#[derive(Debug, Clone)]
+pub struct ArxmlChannel {
+    pub cluster: String,
+    pub channel: String,
+    /// Frames of the channel. Container frames have no signals, see `containers`.
+    pub dbc: Dbc,
+    /// PDUs carried by the container frames, with their header ID as ID
+    pub contained_pdus: Dbc,
+    pub containers: Vec<ContainerFrame>,
+}
+
+impl ArxmlChannel {
+    /// The container frame with this CAN ID
+    pub fn container(&self, id: u32) -> Option<&ContainerFrame> {
+        self.containers.iter().find(|c| c.id == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerHeader {
+    /// 24 bit header ID and 8 bit length
+    Short,
+    /// 32 bit header ID and 32 bit length
+    Long,
+}
+
+impl ContainerHeader {
+    fn len(self) -> usize {
+        match self {
+            ContainerHeader::Short => 4,
+            ContainerHeader::Long => 8,
+        }
+    }
+}
+
+/// A frame carrying a dynamic list of PDUs, each preceded by a header with its ID and length
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerFrame {
+    pub name: String,
+    pub id: u32,
+    pub len: u8,
+    pub is_fd: bool,
+    pub header: ContainerHeader,
+    /// Byte order of the headers. The ARXML system description doesn't say, big endian
+    /// is the AUTOSAR default.
+    pub header_byte_order: ByteOrder,
+    /// Header IDs of the PDUs the container can carry, with their message names in `contained_pdus`
+    pub pdus: Vec<(u32, String)>,
+}
+
+impl ContainerFrame {
+    /// The PDUs in a frame of this container, as frames with the header ID as ID.
+    /// Stops at the end of the frame, at an all zero header (padding) or at a truncated PDU.
+    pub fn unpack(&self, frame: &CanFrame) -> Vec<CanFrame> {
+        let data = &frame.data[..(frame.len as usize).min(frame.data.len())];
+        let header_len = self.header.len();
+        let field_len = header_len - if self.header == ContainerHeader::Short { 1 } else { 4 };
+        let mut pdus = Vec::new();
+        let mut position = 0;
+        while position + header_len <= data.len() {
+            let header = &data[position..position + header_len];
+            let id = self.read_header_field(&header[..field_len]);
+            let len = self.read_header_field(&header[field_len..]) as usize;
+            if id == 0 && len == 0 {
+                break;
+            }
+            position += header_len;
+            if position + len > data.len() {
+                break;
+            }
+            let mut pdu = CanFrame {
+                timestamp: frame.timestamp,
+                channel: frame.channel.clone(),
+                id: id as u32,
+                is_rx: frame.is_rx,
+                is_fd: frame.is_fd,
+                len: len as u8,
+                ..Default::default()
+            };
+            pdu.data[..len].copy_from_slice(&data[position..position + len]);
+            pdus.push(pdu);
+            position += len;
+        }
+        pdus
+    }
+
+    /// A frame of this container carrying `pdus` (frames with the header ID as ID) in order,
+    /// zero padded. Err if a header ID doesn't fit the header or the PDUs don't fit the frame.
+    pub fn pack(&self, pdus: &[CanFrame]) -> Result<CanFrame> {
+        let mut frame = CanFrame {
+            id: self.id,
+            len: self.len,
+            is_fd: self.is_fd,
+            ..Default::default()
+        };
+        let header_len = self.header.len();
+        let field_len = header_len - if self.header == ContainerHeader::Short { 1 } else { 4 };
+        let mut position = 0;
+        for pdu in pdus {
+            if self.header == ContainerHeader::Short && pdu.id > 0xFF_FFFF {
+                bail!("header ID {:#X} doesn't fit the short header of container {}", pdu.id, self.name);
+            }
+            let len = pdu.len as usize;
+            if position + header_len + len > self.len as usize {
+                bail!("PDU {:#X} doesn't fit in container {}", pdu.id, self.name);
+            }
+            self.write_header_field(&mut frame.data[position..position + field_len], pdu.id as u64);
+            self.write_header_field(&mut frame.data[position + field_len..position + header_len], len as u64);
+            position += header_len;
+            frame.data[position..position + len].copy_from_slice(&pdu.data[..len]);
+            position += len;
+        }
+        Ok(frame)
+    }
+
+    fn read_header_field(&self, bytes: &[u8]) -> u64 {
+        let fold = |value: u64, byte: &u8| (value << 8) | *byte as u64;
+        match self.header_byte_order {
+            ByteOrder::BigEndian => bytes.iter().fold(0, fold),
+            ByteOrder::LittleEndian => bytes.iter().rev().fold(0, fold),
+        }
+    }
+
+    fn write_header_field(&self, bytes: &mut [u8], value: u64) {
+        let len = bytes.len();
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            let shift = match self.header_byte_order {
+                ByteOrder::BigEndian => 8 * (len - 1 - i),
+                ByteOrder::LittleEndian => 8 * i,
+            };
+            *byte = (value >> shift) as u8;
+        }
+    }
+}
+
+/// Load an ARXML file, one `ArxmlChannel` per CAN physical channel
+pub fn load(arxml_path: &str) -> Result<Vec<ArxmlChannel>> {
+    let text = fs::read_to_string(arxml_path).with_context(|| format!("reading {arxml_path}"))?;
+    parse(&text).with_context(|| format!("loading {arxml_path}"))
+}
+
+/// Load an ARXML file, the frames of all channels in one Dbc. Frames triggered on several
+/// channels are added once, container frames are left without their contained PDUs.
+pub fn load_dbc(arxml_path: &str) -> Result<Dbc> {
+    let text = fs::read_to_string(arxml_path).with_context(|| format!("reading {arxml_path}"))?;
+    parse_dbc(&text).with_context(|| format!("loading {arxml_path}"))
+}
+
+/// Parse an ARXML document, one `ArxmlChannel` per CAN physical channel
+pub fn parse(text: &str) -> Result<Vec<ArxmlChannel>> {
+    let document = Document::parse(text)?;
+    let arxml = Arxml::new(&document)?;
+    let mut channels = Vec::new();
+    for (cluster, channel) in arxml.channels() {
+        let mut builder = ChannelBuilder::new();
+        for triggering in descendants(channel, "CAN-FRAME-TRIGGERING") {
+            arxml.add_frame(&mut builder, triggering, true)?;
+        }
+        channels.push(ArxmlChannel {
+            cluster: short_name(cluster).unwrap_or_default().to_owned(),
+            channel: short_name(channel).unwrap_or_default().to_owned(),
+            dbc: builder.frames_dbc(),
+            contained_pdus: builder.contained.into_dbc(),
+            containers: builder.containers,
+        });
+    }
+    Ok(channels)
+}
+
+/// Parse an ARXML document, the frames of all channels in one Dbc
+pub fn parse_dbc(text: &str) -> Result<Dbc> {
+    let document = Document::parse(text)?;
+    let arxml = Arxml::new(&document)?;
+    let mut builder = ChannelBuilder::new();
+    for (_, channel) in arxml.channels() {
+        for triggering in descendants(channel, "CAN-FRAME-TRIGGERING") {
+            arxml.add_frame(&mut builder, triggering, false)?;
+        }
+    }
+    Ok(builder.frames_dbc())
+}
+
+/// What a channel collects while its frame triggerings are read
+struct ChannelBuilder {
+    editor: DbcEditor,
+    contained: DbcEditor,
+    containers: Vec<ContainerFrame>,
+    fd_messages: Vec<MessageId>,
+}
+
+impl ChannelBuilder {
+    fn new() -> Self {
+        Self {
+            editor: DbcEditor::new(),
+            contained: DbcEditor::new(),
+            containers: Vec::new(),
+            fd_messages: Vec::new(),
+        }
+    }
+
+    fn add_node(&mut self, name: &str) {
+        if !self.editor.dbc().nodes.iter().any(|n| n.0 == name) {
+            self.editor.add_node(name).expect("node is not in the DBC");
+        }
+    }
+
+    /// The frames Dbc, with VFrameFormat set for the CAN FD frames
+    fn frames_dbc(&self) -> Dbc {
+        let mut dbc = self.editor.dbc().clone();
+        if self.fd_messages.is_empty() {
+            return dbc;
+        }
+        let formats = FRAME_FORMATS.iter().map(|f| f.to_string()).collect();
+        dbc.attribute_definitions
+            .push(AttributeDefinition::Message("VFrameFormat".to_owned(), AttributeValueType::Enum(formats)));
+        dbc.attribute_defaults.push(AttributeDefault {
+            name: "VFrameFormat".to_owned(),
+            value: AttributeValue::String(FRAME_FORMATS[0].to_owned()),
+        });
+        for id in &self.fd_messages {
+            let format = if matches!(id, MessageId::Extended(_)) { "ExtendedCAN_FD" } else { "StandardCAN_FD" };
+            let index = FRAME_FORMATS.iter().position(|f| *f == format).expect("known frame format");
+            dbc.attribute_values_message.push(AttributeValueForMessage {
+                name: "VFrameFormat".to_owned(),
+                message_id: *id,
+                value: AttributeValue::Uint(index as u64),
+            });
+        }
+        dbc
+    }
+}
+
+/// A signal of a PDU and what goes outside of its SG_ line
+struct ArxmlSignal {
+    signal: Signal,
+    value_type: SignalExtendedValueType,
+    labels: Vec<(i64, String)>,
+    comment: Option<String>,
+    /// Selector field codes of the dynamic part alternatives the signal is on
+    mux_values: Vec<u64>,
+}
+
+impl ArxmlSignal {
+    fn new(signal: Signal) -> Self {
+        Self {
+            signal,
+            value_type: SignalExtendedValueType::SignedOrUnsignedInteger,
+            labels: Vec::new(),
+            comment: None,
+            mux_values: Vec::new(),
+        }
+    }
+
+    /// Same bits and scaling, the same signal on another dynamic part alternative
+    fn same_signal(&self, other: &ArxmlSignal) -> bool {
+        let (a, b) = (&self.signal, &other.signal);
+        (a.start_bit, a.size, a.byte_order, a.value_type, a.factor, a.offset)
+            == (b.start_bit, b.size, b.byte_order, b.value_type, b.factor, b.offset)
+            && self.value_type == other.value_type
+    }
+}
+
+/// Conversion of a COMPU-METHOD from raw to physical values
+struct Conversion {
+    factor: f64,
+    offset: f64,
+    /// Raw range of the linear scale
+    raw_range: Option<(f64, f64)>,
+    labels: Vec<(i64, String)>,
+}
+
+/// The elements of a document by their reference path
+struct Arxml<'a, 'input> {
+    document: &'a Document<'input>,
+    elements: HashMap<String, Node<'a, 'input>>,
+}
+
+impl<'a, 'input> Arxml<'a, 'input> {
+    fn new(document: &'a Document<'input>) -> Result<Self> {
+        let root = document.root_element();
+        if !root.has_tag_name("AUTOSAR") {
+            bail!("not an ARXML document, root element is {}", root.tag_name().name());
+        }
+        let mut elements = HashMap::new();
+        for node in root.descendants().filter(|n| short_name(*n).is_some()) {
+            let mut names: Vec<&str> = node.ancestors().filter_map(short_name).collect();
+            names.reverse();
+            elements.insert(format!("/{}", names.join("/")), node);
+        }
+        Ok(Self { document, elements })
+    }
+
+    /// CAN physical channels and their cluster
+    fn channels(&self) -> Vec<(Node<'a, 'input>, Node<'a, 'input>)> {
+        descendants(self.document.root_element(), "CAN-CLUSTER")
+            .flat_map(|cluster| descendants(cluster, "CAN-PHYSICAL-CHANNEL").map(move |channel| (cluster, channel)))
+            .collect()
+    }
+
+    fn resolve(&self, reference: Node<'a, 'input>) -> Result<Node<'a, 'input>> {
+        let path = reference.text().unwrap_or_default().trim();
+        self.elements
+            .get(path)
+            .copied()
+            .ok_or_else(|| anyhow!("unresolved reference {} at {}", path, position(reference)))
+    }
+
+    /// The element referenced by the first `tag` below `node`
+    fn referenced(&self, node: Node<'a, 'input>, tag: &str) -> Result<Option<Node<'a, 'input>>> {
+        descendant(node, tag).map(|r| self.resolve(r)).transpose()
+    }
+
+    fn add_frame(&self, channel: &mut ChannelBuilder, triggering: Node<'a, 'input>, with_containers: bool) -> Result<()> {
+        let Some(frame) = self.referenced(triggering, "FRAME-REF")? else {
+            return Ok(());
+        };
+        let name = dbc_editor::identifier(short_name(frame).unwrap_or_default());
+        let id = required_number(triggering, "IDENTIFIER")?;
+        let id = u32::try_from(id).map_err(|_| anyhow!("frame {} ID {:#X} out of range", name, id))?;
+        let extended = text(triggering, "CAN-ADDRESSING-MODE") == Some("EXTENDED");
+        let is_fd = ["CAN-FRAME-TX-BEHAVIOR", "CAN-FRAME-RX-BEHAVIOR"]
+            .iter()
+            .any(|tag| text(triggering, tag) == Some("CAN-FD"));
+        let size = number(child_text(frame, "FRAME-LENGTH").unwrap_or("8"))?;
+
+        let mut builder = MessageBuilder::new(&name, id, size);
+        if extended {
+            builder = builder.extended();
+        }
+        let message_id = builder.build().id;
+        if channel.editor.message(&name).is_some_and(|m| m.id == message_id) {
+            // Triggered on another channel already
+            return Ok(());
+        }
+
+        let (transmitters, receivers) = self.frame_ports(triggering)?;
+        for node in transmitters.iter().chain(&receivers) {
+            channel.add_node(node);
+        }
+        let mut signals = Vec::new();
+        let mut container = None;
+        let mut comment = description(frame);
+        for mapping in descendants(frame, "PDU-TO-FRAME-MAPPING") {
+            let Some(pdu) = self.referenced(mapping, "PDU-REF")? else {
+                continue;
+            };
+            comment = comment.or_else(|| description(pdu));
+            if pdu.has_tag_name("CONTAINER-I-PDU") {
+                container = Some(pdu);
+                continue;
+            }
+            let offset = number(text(mapping, "START-POSITION").unwrap_or("0"))?;
+            self.pdu_signals(pdu, offset, &mut signals)?;
+        }
+
+        let mut builder = MessageBuilder::new(&name, id, size);
+        if extended {
+            builder = builder.extended();
+        }
+        if let Some(transmitter) = transmitters.first() {
+            builder = builder.transmitter(transmitter);
+        }
+        for signal in &mut signals {
+            if !receivers.is_empty() {
+                signal.signal.receivers = receivers.clone();
+            }
+            builder = builder.signal(signal.signal.clone());
+        }
+        channel
+            .editor
+            .add_message(builder.build())
+            .with_context(|| format!("frame triggering at {}", position(triggering)))?;
+        apply_signal_details(&mut channel.editor, &name, &signals)?;
+        if let Some(comment) = comment {
+            channel.editor.set_message_comment(&name, &comment)?;
+        }
+        if is_fd {
+            channel.fd_messages.push(message_id);
+        }
+
+        if let (Some(container), true) = (container, with_containers) {
+            self.add_container(channel, &name, id, size, is_fd, container)?;
+        }
+        Ok(())
+    }
+
+    /// Names of the ECUs sending and receiving a frame, from the frame ports of the triggering
+    fn frame_ports(&self, triggering: Node<'a, 'input>) -> Result<(Vec<String>, Vec<String>)> {
+        let mut transmitters = Vec::new();
+        let mut receivers = Vec::new();
+        for reference in descendants(triggering, "FRAME-PORT-REF") {
+            let port = self.resolve(reference)?;
+            let Some(ecu) = port.ancestors().find(|n| n.has_tag_name("ECU-INSTANCE")).and_then(short_name) else {
+                continue;
+            };
+            let ecu = dbc_editor::identifier(ecu);
+            let names = match text(port, "COMMUNICATION-DIRECTION") {
+                Some("OUT") => &mut transmitters,
+                _ => &mut receivers,
+            };
+            if !names.contains(&ecu) {
+                names.push(ecu);
+            }
+        }
+        Ok((transmitters, receivers))
+    }
+
+    /// Collect the signals of a PDU placed `offset` bits into the frame
+    fn pdu_signals(&self, pdu: Node<'a, 'input>, offset: u64, signals: &mut Vec<ArxmlSignal>) -> Result<()> {
+        match pdu.tag_name().name() {
+            "MULTIPLEXED-I-PDU" => self.multiplexed_signals(pdu, offset, signals),
+            "SECURED-I-PDU" => {
+                // The authentic PDU is at the start of the secured one
+                let payload = match self.referenced(pdu, "PAYLOAD-REF")? {
+                    Some(triggering) => self.referenced(triggering, "I-PDU-REF")?,
+                    None => None,
+                };
+                match payload {
+                    Some(payload) => self.pdu_signals(payload, offset, signals),
+                    None => Ok(()),
+                }
+            }
+            _ => {
+                for mapping in descendants(pdu, "I-SIGNAL-TO-I-PDU-MAPPING") {
+                    // Signal group mappings have no position, their signals are mapped on their own
+                    if let Some(i_signal) = self.referenced(mapping, "I-SIGNAL-REF")? {
+                        signals.push(self.signal(mapping, i_signal, offset)?);
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn multiplexed_signals(&self, pdu: Node<'a, 'input>, offset: u64, signals: &mut Vec<ArxmlSignal>) -> Result<()> {
+        let pdu_name = dbc_editor::identifier(short_name(pdu).unwrap_or_default());
+        let start = required_number(pdu, "SELECTOR-FIELD-START-POSITION")?;
+        let length = required_number(pdu, "SELECTOR-FIELD-LENGTH")?;
+        let mut selector = SignalBuilder::new(&format!("{pdu_name}_Selector"), offset + start, length);
+        if text(pdu, "SELECTOR-FIELD-BYTE-ORDER") == Some("MOST-SIGNIFICANT-BYTE-FIRST") {
+            selector = selector.big_endian();
+        }
+        signals.push(ArxmlSignal::new(selector.multiplexor().build()));
+
+        for part in descendants(pdu, "STATIC-PART") {
+            if let Some(part_pdu) = self.referenced(part, "I-PDU-REF")? {
+                self.pdu_signals(part_pdu, offset, signals)?;
+            }
+        }
+        for alternative in descendants(pdu, "DYNAMIC-PART-ALTERNATIVE") {
+            let code = required_number(alternative, "SELECTOR-FIELD-CODE")?;
+            let Some(part_pdu) = self.referenced(alternative, "I-PDU-REF")? else {
+                continue;
+            };
+            let mut page = Vec::new();
+            self.pdu_signals(part_pdu, offset, &mut page)?;
+            for mut signal in page {
+                match signals.iter_mut().find(|s| s.signal.name == signal.signal.name) {
+                    Some(shared) if !shared.mux_values.is_empty() && shared.same_signal(&signal) => {
+                        shared.mux_values.push(code);
+                    }
+                    Some(_) => bail!(
+                        "signal {} of multiplexed PDU {} at {} clashes with another signal of that name",
+                        signal.signal.name,
+                        pdu_name,
+                        position(alternative)
+                    ),
+                    None => {
+                        signal.signal.multiplexer_indicator = MultiplexIndicator::MultiplexedSignal(code);
+                        signal.mux_values.push(code);
+                        signals.push(signal);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// An I-signal at the position given by its I-SIGNAL-TO-I-PDU-MAPPING
+    fn signal(&self, mapping: Node<'a, 'input>, i_signal: Node<'a, 'input>, offset: u64) -> Result<ArxmlSignal> {
+        let name = dbc_editor::identifier(short_name(i_signal).unwrap_or_default());
+        let start = required_number(mapping, "START-POSITION")?;
+        let length = number(child_text(i_signal, "LENGTH").ok_or_else(|| anyhow!("I-signal {} without LENGTH", name))?)?;
+        let system_signal = self.referenced(i_signal, "SYSTEM-SIGNAL-REF")?;
+
+        let mut builder = SignalBuilder::new(&name, offset + start, length);
+        if text(mapping, "PACKING-BYTE-ORDER") == Some("MOST-SIGNIFICANT-BYTE-FIRST") {
+            builder = builder.big_endian();
+        }
+        let mut value_type = SignalExtendedValueType::SignedOrUnsignedInteger;
+        if let Some(base_type) = self.referenced(i_signal, "BASE-TYPE-REF")? {
+            match text(base_type, "BASE-TYPE-ENCODING") {
+                Some("2C") => builder = builder.signed(),
+                Some("IEEE754") if length == 32 => value_type = SignalExtendedValueType::IEEEfloat32Bit,
+                Some("IEEE754") => value_type = SignalExtendedValueType::IEEEdouble64bit,
+                _ => {}
+            }
+        }
+
+        // The network representation of the I-signal, or else the physical props of the system signal
+        let mut compu_method = self.referenced(i_signal, "COMPU-METHOD-REF")?;
+        if let (None, Some(system_signal)) = (compu_method, system_signal) {
+            compu_method = self.referenced(system_signal, "COMPU-METHOD-REF")?;
+        }
+        let mut unit = match compu_method {
+            Some(compu_method) => self.referenced(compu_method, "UNIT-REF")?,
+            None => None,
+        };
+        if let (None, Some(system_signal)) = (unit, system_signal) {
+            unit = self.referenced(system_signal, "UNIT-REF")?;
+        }
+        if let Some(unit) = unit {
+            builder = builder.unit(text(unit, "DISPLAY-NAME").or(short_name(unit)).unwrap_or_default());
+        }
+
+        let conversion = match compu_method {
+            Some(compu_method) => compu_method_conversion(compu_method)?,
+            None => Conversion {
+                factor: 1.0,
+                offset: 0.0,
+                raw_range: None,
+                labels: Vec::new(),
+            },
+        };
+        builder = builder.scale(conversion.factor, conversion.offset);
+        if value_type != SignalExtendedValueType::SignedOrUnsignedInteger {
+            builder = builder.float();
+        } else if let Some((raw_min, raw_max)) = conversion.raw_range {
+            let a = raw_min * conversion.factor + conversion.offset;
+            let b = raw_max * conversion.factor + conversion.offset;
+            builder = builder.range(a.min(b), a.max(b));
+        }
+
+        Ok(ArxmlSignal {
+            signal: builder.build(),
+            value_type,
+            labels: conversion.labels,
+            comment: system_signal.and_then(description),
+            mux_values: Vec::new(),
+        })
+    }
+
+    fn add_container(
+        &self,
+        channel: &mut ChannelBuilder,
+        frame_name: &str,
+        id: u32,
+        len: u64,
+        is_fd: bool,
+        container: Node<'a, 'input>,
+    ) -> Result<()> {
+        let (header, header_tag) = match text(container, "HEADER-TYPE") {
+            Some("SHORT-HEADER") => (ContainerHeader::Short, "HEADER-ID-SHORT-HEADER"),
+            Some("LONG-HEADER") => (ContainerHeader::Long, "HEADER-ID-LONG-HEADER"),
+            // Containers without headers have a static layout, there is nothing to split
+            _ => return Ok(()),
+        };
+        let mut pdus = Vec::new();
+        for reference in descendants(container, "CONTAINED-PDU-TRIGGERING-REF") {
+            let triggering = self.resolve(reference)?;
+            let Some(pdu) = self.referenced(triggering, "I-PDU-REF")? else {
+                continue;
+            };
+            let name = dbc_editor::identifier(short_name(pdu).unwrap_or_default());
+            let header_id = required_number(pdu, header_tag).with_context(|| format!("contained PDU {name}"))?;
+            let header_id = u32::try_from(header_id).map_err(|_| anyhow!("header ID of {} out of range", name))?;
+            let size = number(child_text(pdu, "LENGTH").unwrap_or("0"))?;
+
+            if channel.contained.message(&name).is_none() {
+                let mut signals = Vec::new();
+                self.pdu_signals(pdu, 0, &mut signals)?;
+                let mut builder = MessageBuilder::new(&name, header_id, size);
+                for signal in &signals {
+                    builder = builder.signal(signal.signal.clone());
+                }
+                channel
+                    .contained
+                    .add_message(builder.build())
+                    .with_context(|| format!("contained PDU at {}", position(pdu)))?;
+                apply_signal_details(&mut channel.contained, &name, &signals)?;
+                if let Some(comment) = description(pdu) {
+                    channel.contained.set_message_comment(&name, &comment)?;
+                }
+            }
+            pdus.push((header_id, name));
+        }
+        channel.containers.push(ContainerFrame {
+            name: frame_name.to_owned(),
+            id,
+            len: len as u8,
+            is_fd,
+            header,
+            header_byte_order: ByteOrder::BigEndian,
+            pdus,
+        });
+        Ok(())
+    }
+}
+
+/// Value types, labels, comments and multiplexor values of the signals of a message
+fn apply_signal_details(editor: &mut DbcEditor, message: &str, signals: &[ArxmlSignal]) -> Result<()> {
+    for signal in signals {
+        let signal_name = &signal.signal.name;
+        if signal.value_type != SignalExtendedValueType::SignedOrUnsignedInteger {
+            editor.set_signal_value_type(message, signal_name, signal.value_type)?;
+        }
+        if !signal.labels.is_empty() {
+            let labels: Vec<(i64, &str)> = signal.labels.iter().map(|(v, l)| (*v, l.as_str())).collect();
+            editor.set_value_descriptions(message, signal_name, &labels)?;
+        }
+        if let Some(comment) = &signal.comment {
+            editor.set_signal_comment(message, signal_name, comment)?;
+        }
+        if signal.mux_values.len() > 1 {
+            editor.set_multiplexor_values(message, signal_name, &signal.mux_values)?;
+        }
+    }
+    Ok(())
+}
+
+/// The linear scale and text table of a COMPU-METHOD. The first linear scale is used,
+/// text table entries covering a range of values are skipped.
+fn compu_method_conversion(compu_method: Node) -> Result<Conversion> {
+    let mut conversion = Conversion {
+        factor: 1.0,
+        offset: 0.0,
+        raw_range: None,
+        labels: Vec::new(),
+    };
+    let Some(internal_to_phys) = descendant(compu_method, "COMPU-INTERNAL-TO-PHYS") else {
+        return Ok(conversion);
+    };
+    let mut linear_found = false;
+    for scale in descendants(internal_to_phys, "COMPU-SCALE") {
+        let lower = text(scale, "LOWER-LIMIT").map(parse_f64).transpose()?;
+        let upper = text(scale, "UPPER-LIMIT").map(parse_f64).transpose()?;
+        if let Some(label) = text(scale, "VT") {
+            if let (Some(lower), Some(upper)) = (lower, upper) {
+                if lower == upper {
+                    conversion.labels.push((lower as i64, label.to_owned()));
+                }
+            }
+            continue;
+        }
+        let Some(coefficients) = descendant(scale, "COMPU-RATIONAL-COEFFS") else {
+            continue;
+        };
+        if linear_found {
+            continue;
+        }
+        linear_found = true;
+        let values = |tag: &str| -> Result<Vec<f64>> {
+            match descendant(coefficients, tag) {
+                Some(node) => descendants(node, "V").filter_map(|v| v.text()).map(parse_f64).collect(),
+                None => Ok(Vec::new()),
+            }
+        };
+        let numerator = values("COMPU-NUMERATOR")?;
+        let denominator = values("COMPU-DENOMINATOR")?.first().copied().unwrap_or(1.0);
+        conversion.offset = numerator.first().copied().unwrap_or(0.0) / denominator;
+        conversion.factor = numerator.get(1).copied().unwrap_or(1.0) / denominator;
+        if let (Some(lower), Some(upper)) = (lower, upper) {
+            if lower.is_finite() && upper.is_finite() {
+                conversion.raw_range = Some((lower, upper));
+            }
+        }
+    }
+    Ok(conversion)
+}
+
+fn short_name<'a, 'input>(node: Node<'a, 'input>) -> Option<&'a str> {
+    node.children().find(|c| c.has_tag_name("SHORT-NAME")).and_then(|n| n.text()).map(str::trim)
+}
+
+/// Text of the first `tag` below `node`
+fn text<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<&'a str> {
+    descendant(node, tag).and_then(|n| n.text()).map(str::trim)
+}
+
+/// Text of the `tag` child of `node`, for tags also used by nested elements
+fn child_text<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<&'a str> {
+    node.children().find(|c| c.has_tag_name(tag)).and_then(|n| n.text()).map(str::trim)
+}
+
+/// The English (or first) text of a DESC element
+fn description(node: Node) -> Option<String> {
+    let desc = node.children().find(|c| c.has_tag_name("DESC"))?;
+    let mut texts = desc.children().filter(|c| c.has_tag_name("L-2"));
+    let l2 = texts.clone().find(|t| t.attribute("L") == Some("EN")).or_else(|| texts.next())?;
+    let text = l2.text()?.trim();
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+fn required_number(node: Node, tag: &str) -> Result<u64> {
+    let value = text(node, tag).ok_or_else(|| anyhow!("missing {} at {}", tag, position(node)))?;
+    number(value).with_context(|| format!("{} at {}", tag, position(node)))
+}
+
+fn parse_f64(text: &str) -> Result<f64> {
+    text.trim().parse().map_err(|_| anyhow!("invalid number {:?}", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::can_database::CanDatabase;
+    use crate::can_decoder::decode_message_with_dbc;
+    use crate::can_encoder::CanFrameBuilder;
+    use crate::dbc_writer;
+
+    fn channel() -> ArxmlChannel {
+        let mut channels = load("system.arxml").unwrap();
+        assert_eq!(channels.len(), 1);
+        channels.remove(0)
+    }
+
+    #[test]
+    fn test_frames() {
+        let channel = channel();
+        assert_eq!((channel.cluster.as_str(), channel.channel.as_str()), ("Powertrain", "PT_CAN"));
+        let dbc = &channel.dbc;
+        let names: Vec<_> = dbc.messages.iter().map(|m| (m.name.as_str(), m.id, m.size)).collect();
+        assert_eq!(
+            names,
+            [
+                ("EngineData", MessageId::Standard(0x100), 8),
+                ("StatusFrame", MessageId::Extended(0x18FEF100), 8),
+                ("ContainerFrame", MessageId::Standard(0x400), 64),
+            ]
+        );
+        assert_eq!(dbc.nodes.iter().map(|n| n.0.as_str()).collect::<Vec<_>>(), ["Engine", "Gateway"]);
+
+        let engine = &dbc.messages[0];
+        assert_eq!(engine.transmitter, can_dbc::Transmitter::NodeName("Engine".to_owned()));
+        assert_eq!(engine.signals[0].receivers, ["Gateway"]);
+        assert_eq!(dbc.message_comment(engine.id), Some("Engine state"));
+        assert_eq!(dbc.signal_comment(engine.id, "EngineSpeed"), Some("Crankshaft speed"));
+
+        let speed = &engine.signals[0];
+        assert_eq!((speed.factor, speed.offset, speed.min, speed.max), (0.25, 0.0, 0.0, 16383.75));
+        assert_eq!(speed.unit, "rpm");
+        let oil = &engine.signals[2];
+        assert_eq!((oil.start_bit, oil.byte_order, oil.value_type), (31, ByteOrder::BigEndian, can_dbc::ValueType::Signed));
+        assert_eq!((oil.factor, oil.offset), (0.1, -40.0));
+
+        let frame = CanFrameBuilder::new(engine)
+            .dbc(dbc)
+            .set("EngineSpeed", 2500.0)
+            .unwrap()
+            .set("Gear", "Drive")
+            .unwrap()
+            .set("OilTemp", 92.5)
+            .unwrap()
+            .build();
+        let decoded = decode_message_with_dbc(&frame, engine, dbc);
+        assert_eq!(decoded.values[0], 2500.0);
+        assert_eq!(decoded.labels[1].as_deref(), Some("Drive"));
+        assert!((decoded.values[2] - 92.5).abs() < 1e-9);
+
+        let attributes = &dbc.attribute_values_message;
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0].message_id, MessageId::Standard(0x400));
+        assert_eq!(attributes[0].value, AttributeValue::Uint(14));
+        // The attributes survive a DBC round trip
+        let text = dbc_writer::dbc_to_string(dbc);
+        assert_eq!(&Dbc::try_from(text.as_str()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn test_multiplexed_pdu() {
+        let channel = channel();
+        let status = &channel.dbc.messages[1];
+        let indicators: Vec<_> = status.signals.iter().map(|s| (s.name.as_str(), s.multiplexer_indicator)).collect();
+        assert_eq!(
+            indicators,
+            [
+                ("StatusMux_Selector", MultiplexIndicator::Multiplexor),
+                ("AliveCounter", MultiplexIndicator::Plain),
+                ("VersionMajor", MultiplexIndicator::MultiplexedSignal(1)),
+                ("SupplyVoltage", MultiplexIndicator::MultiplexedSignal(2)),
+            ]
+        );
+        let frame = CanFrameBuilder::new(status).set("SupplyVoltage", 12.345).unwrap().build();
+        assert_eq!(frame.data[0], 2);
+        let decoded = decode_message_with_dbc(&frame, status, &channel.dbc);
+        assert_eq!(decoded.signals, ["StatusMux_Selector", "AliveCounter", "SupplyVoltage"]);
+        assert!((decoded.values[2] - 12.345).abs() < 1e-9);
+
+        // StatusVersion is on selector codes 1 and 3
+        let [version] = &channel.dbc.extended_multiplex[..] else { panic!("one SG_MUL_VAL_") };
+        assert_eq!(version.signal_name, "VersionMajor");
+        assert_eq!(version.multiplexor_signal_name, "StatusMux_Selector");
+        let ranges: Vec<_> = version.mappings.iter().map(|m| (m.min_value, m.max_value)).collect();
+        assert_eq!(ranges, [(1, 1), (3, 3)]);
+        let builder = CanFrameBuilder::new(status).dbc(&channel.dbc);
+        let frame = builder.set("StatusMux_Selector", 3.0).unwrap().set("VersionMajor", 7.0).unwrap().build();
+        let decoded = decode_message_with_dbc(&frame, status, &channel.dbc);
+        assert_eq!(decoded.signals, ["StatusMux_Selector", "AliveCounter", "VersionMajor"]);
+        assert_eq!(decoded.values[2], 7.0);
+    }
+
+    #[test]
+    fn test_container_frames() {
+        let channel = channel();
+        let container = channel.container(0x400).unwrap();
+        assert_eq!(container.header, ContainerHeader::Short);
+        assert!(container.is_fd);
+        assert_eq!(container.pdus, [(0x10, "WheelSpeeds".to_owned()), (0x20, "Temperatures".to_owned())]);
+
+        let pdus = CanDatabase::from_dbc(channel.contained_pdus.clone());
+        let wheels = pdus.message("WheelSpeeds").unwrap();
+        let temperatures = pdus.message("Temperatures").unwrap();
+        let wheel_frame = wheels.builder().set("WheelSpeedFL", 12.5).unwrap().set("WheelSpeedFR", 13.0).unwrap().build();
+        let temperature_frame = temperatures
+            .builder()
+            .dbc(&channel.contained_pdus)
+            .set("Torque", -12.75)
+            .unwrap()
+            .set("AmbientTemp", -7.0)
+            .unwrap()
+            .build();
+
+        let frame = container.pack(&[temperature_frame, wheel_frame]).unwrap();
+        assert_eq!(frame.data[..4], [0, 0, 0x20, 8]);
+        assert_eq!(frame.data[12..16], [0, 0, 0x10, 4]);
+        assert!(frame.data[20..].iter().all(|b| *b == 0));
+
+        let unpacked = container.unpack(&frame);
+        assert_eq!(unpacked.iter().map(|p| p.id).collect::<Vec<_>>(), [0x20, 0x10]);
+        let decoded = pdus.decode_frame(&unpacked[0]).unwrap();
+        assert_eq!(decoded.name, "Temperatures");
+        assert_eq!(decoded.values, [-12.75, -7.0]);
+        let decoded = pdus.decode_frame(&unpacked[1]).unwrap();
+        assert_eq!(decoded.values, [12.5, 13.0]);
+
+        let too_many = vec![unpacked[0].clone(); 6];
+        assert!(container.pack(&too_many).is_err());
+    }
+
+    #[test]
+    fn test_parse_dbc_and_errors() {
+        let text = fs::read_to_string("system.arxml").unwrap();
+        let dbc = parse_dbc(&text).unwrap();
+        assert_eq!(dbc.messages.len(), 3);
+        assert!(dbc.messages[2].signals.is_empty());
+
+        assert!(parse("<Foo/>").unwrap_err().to_string().contains("not an ARXML document"));
+        let broken = text.replace("/Frames/EngineData<", "/Frames/Missing<");
+        let error = parse(&broken).unwrap_err().to_string();
+        assert!(error.starts_with("unresolved reference /Frames/Missing at "), "{error}");
+
+        // Another signal of the same name on an alternative is not merged into the first one
+        let mapping = concat!(
+            "/Signals/SupplyVoltage</I-SIGNAL-REF>\n",
+            "              <PACKING-BYTE-ORDER>MOST-SIGNIFICANT-BYTE-LAST</PACKING-BYTE-ORDER>\n",
+            "              <START-POSITION>8<",
+        );
+        assert!(text.contains(mapping));
+        let clash = text.replace(mapping, &mapping.replace("SupplyVoltage", "VersionMajor").replace(">8<", ">16<"));
+        let error = parse(&clash).unwrap_err().to_string();
+        assert!(error.starts_with("signal VersionMajor of multiplexed PDU StatusMux at "), "{error}");
+    }
+}
@@ -0,0 +1,673 @@
+/*!
+ * Compiled CAN database: messages of one or more DBCs indexed by (channel, ID) and by name,
+ * with the layout, value type, multiplexing and value descriptions of every signal
+ * resolved once, so decoding a log is one hash lookup per frame.
+ * can_dbc::Dbc -> CanDatabase, CanFrame -> DecodedCanMessage
+ */
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use can_dbc::SignalExtendedValueType;
+
+use crate::can_decoder::{self, DecodedCanMessage};
+use crate::can_encoder::{self, CanFrameBuilder};
+use crate::canlog_reader::CanFrame;
+use crate::dbc_attributes;
+use crate::j1939::{self, J1939Id};
+use crate::loader;
+use crate::signal_layout::SignalLayout;
+
+/// IDs above this can only be extended (29-bit) IDs
+const MAX_STANDARD_ID: u32 = 0x7FF;
+
+/// Multiplexer condition of a signal, with the multiplexer as an index into the message signals
+#[derive(Debug, Clone)]
+struct CompiledMux {
+    multiplexor: usize,
+    ranges: Vec<RangeInclusive<u64>>,
+}
+
+/// Everything needed to decode one signal, resolved from the DBC
+#[derive(Debug, Clone)]
+struct CompiledSignal {
+    value_type: SignalExtendedValueType,
+    mux: Option<CompiledMux>,
+    value_descriptions: Vec<can_dbc::ValDescription>,
+}
+
+/// A DBC message with its signals ready to decode and encode
+#[derive(Debug, Clone)]
+pub struct CompiledMessage {
+    dbc: Arc<can_dbc::Dbc>,
+    message_index: usize,
+    id: u32,
+    // 29-bit ID, from the DBC ID or VFrameFormat
+    is_extended: bool,
+    // Parameter group of J1939 messages
+    pgn: Option<u32>,
+    layouts: Vec<SignalLayout>,
+    signals: Vec<CompiledSignal>,
+    signal_indexes: HashMap<String, usize>,
+}
+
+impl CompiledMessage {
+    /// `j1939_database` tells whether the DBC ProtocolType is J1939, see `dbc_attributes::is_j1939_database`
+    fn compile(dbc: Arc<can_dbc::Dbc>, message_index: usize, j1939_database: bool) -> Self {
+        let spec = &dbc.messages[message_index];
+        let id = can_encoder::get_can_id(spec);
+        let frame_format = dbc_attributes::frame_format(&dbc, spec);
+        let is_extended = matches!(spec.id, can_dbc::MessageId::Extended(_))
+            || frame_format.is_some_and(|format| format.is_extended());
+        let is_j1939 = frame_format == Some(dbc_attributes::FrameFormat::J1939) || (j1939_database && is_extended);
+        let signal_indexes: HashMap<String, usize> = spec
+            .signals
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.name.clone(), i))
+            .collect();
+        let layouts = spec.signals.iter().map(SignalLayout::from_spec).collect();
+        let signals = spec
+            .signals
+            .iter()
+            .map(|signal_spec| {
+                let mux = can_decoder::mux_condition(spec, signal_spec, &dbc.extended_multiplex).map(|condition| {
+                    CompiledMux {
+                        multiplexor: signal_indexes[&condition.multiplexor.name],
+                        ranges: condition.ranges,
+                    }
+                });
+                CompiledSignal {
+                    value_type: can_decoder::get_value_type(&dbc, spec, signal_spec),
+                    mux,
+                    value_descriptions: can_decoder::get_value_descriptions(&dbc, spec, signal_spec)
+                        .map(<[_]>::to_vec)
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+        Self {
+            id,
+            is_extended,
+            pgn: is_j1939.then(|| j1939::pgn(id)),
+            dbc,
+            message_index,
+            layouts,
+            signals,
+            signal_indexes,
+        }
+    }
+
+    /// The message as defined in the DBC
+    pub fn spec(&self) -> &can_dbc::Message {
+        &self.dbc.messages[self.message_index]
+    }
+
+    /// The DBC the message comes from
+    pub fn dbc(&self) -> &can_dbc::Dbc {
+        &self.dbc
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.spec().name
+    }
+
+    /// Whether the DBC defines the message with a 29-bit ID, or VFrameFormat says so
+    pub fn is_extended(&self) -> bool {
+        self.is_extended
+    }
+
+    /// J1939 parameter group number, for messages with VFrameFormat J1939PG
+    /// or an extended ID in a DBC with ProtocolType J1939. None for other messages.
+    pub fn pgn(&self) -> Option<u32> {
+        self.pgn
+    }
+
+    /// Retrieve the specification and layout of a signal by name
+    pub fn signal(&self, signal_name: &str) -> Option<(&can_dbc::Signal, &SignalLayout)> {
+        let index = *self.signal_indexes.get(signal_name)?;
+        Some((&self.spec().signals[index], &self.layouts[index]))
+    }
+
+    /// Precomputed layouts, in the order of the DBC signals
+    pub fn layouts(&self) -> &[SignalLayout] {
+        &self.layouts
+    }
+
+    /// Start encoding a frame of this message, with the cached layouts and the DBC
+    /// (multiplexing, float value types and labels)
+    pub fn builder(&self) -> CanFrameBuilder<'_> {
+        CanFrameBuilder::new(self.spec()).dbc(&self.dbc).layouts(&self.layouts)
+    }
+
+    /// Decode the signals of a frame of this message.
+    /// Same result as `can_decoder::decode_message_with_dbc` without any lookup.
+    pub fn decode(&self, can_frame: &CanFrame) -> DecodedCanMessage {
+        let spec = self.spec();
+        let raws: Vec<u64> = self.layouts.iter().map(|layout| layout.extract(&can_frame.data)).collect();
+        let mut decoded = DecodedCanMessage {
+            id: can_frame.id,
+            name: spec.name.clone(),
+            signals: Vec::with_capacity(raws.len()),
+            values: Vec::with_capacity(raws.len()),
+            units: Vec::with_capacity(raws.len()),
+            labels: Vec::with_capacity(raws.len()),
+            source_address: None,
+        };
+        for (i, (signal_spec, signal)) in spec.signals.iter().zip(&self.signals).enumerate() {
+            if !self.is_active(i, &can_frame.data) {
+                continue;
+            }
+            let raw = raws[i];
+            let label = match signal.value_type {
+                SignalExtendedValueType::SignedOrUnsignedInteger if !signal.value_descriptions.is_empty() => {
+                    let raw_value = can_decoder::sign_extend(raw, signal_spec);
+                    signal
+                        .value_descriptions
+                        .iter()
+                        .find(|d| d.id == raw_value)
+                        .map(|d| d.description.clone())
+                }
+                _ => None,
+            };
+            decoded.signals.push(signal_spec.name.clone());
+            decoded.values.push(can_decoder::compute_typed_signal_value(raw, signal_spec, signal.value_type));
+            decoded.units.push(signal_spec.unit.clone());
+            decoded.labels.push(label);
+        }
+        decoded
+    }
+
+    /// Index of a signal in the DBC message, as used by `decode_signal_at`
+    pub fn signal_index(&self, signal_name: &str) -> Option<usize> {
+        self.signal_indexes.get(signal_name).copied()
+    }
+
+    /// Decode a single signal by index, None if the frame's multiplexers don't select it
+    pub fn decode_signal_at(&self, can_frame: &CanFrame, signal_index: usize) -> Option<f64> {
+        if !self.is_active(signal_index, &can_frame.data) {
+            return None;
+        }
+        let raw = self.layouts[signal_index].extract(&can_frame.data);
+        let signal_spec = &self.spec().signals[signal_index];
+        Some(can_decoder::compute_typed_signal_value(raw, signal_spec, self.signals[signal_index].value_type))
+    }
+
+    /// Whether every multiplexer up the tree of a signal selects it
+    fn is_active(&self, signal_index: usize, data: &[u8; 64]) -> bool {
+        let mut index = signal_index;
+        // Guard against multiplexer cycles
+        for _ in 0..=self.signals.len() {
+            let Some(mux) = &self.signals[index].mux else {
+                return true;
+            };
+            let raw = self.layouts[mux.multiplexor].extract(data);
+            if !mux.ranges.iter().any(|range| range.contains(&raw)) {
+                return false;
+            }
+            index = mux.multiplexor;
+        }
+        false
+    }
+}
+
+/// Messages of one or more DBCs, indexed for decoding frames.
+///
+/// A DBC is either added for every channel, or bound to a channel name
+/// (as in `CanFrame::channel`). Channel aliases let one binding cover the names
+/// used by different log formats, e.g. "can0" in candump and "1" in ASC.
+/// When two DBCs define the same ID or name, the one with the highest priority wins,
+/// then messages bound to the frame channel, then the first one added.
+/// With J1939 matching, an exact ID match wins over a PGN match.
+#[derive(Debug, Clone, Default)]
+pub struct CanDatabase {
+    messages: Vec<CompiledMessage>,
+    priorities: Vec<i32>,
+    // Channel each message's DBC is bound to
+    channels: Vec<Option<String>>,
+    by_id: HashMap<u32, usize>,
+    by_channel_id: HashMap<String, HashMap<u32, usize>>,
+    by_name: HashMap<String, usize>,
+    by_any_name: HashMap<String, usize>,
+    by_channel_name: HashMap<String, HashMap<String, usize>>,
+    // Extended ID messages by J1939 PGN, used when `j1939` is set
+    by_pgn: HashMap<u32, usize>,
+    by_channel_pgn: HashMap<String, HashMap<u32, usize>>,
+    channel_aliases: HashMap<String, String>,
+    j1939: bool,
+}
+
+impl CanDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compile a DBC for every channel
+    pub fn from_dbc(dbc: can_dbc::Dbc) -> Self {
+        let mut database = Self::new();
+        database.add_dbc(dbc, None);
+        database
+    }
+
+    /// Load and compile a DBC, KCD, SYM or ARXML file for every channel
+    pub fn load(dbc_path: &str) -> io::Result<Self> {
+        Ok(Self::from_dbc(loader::load(dbc_path)?))
+    }
+
+    /// Add the messages of a DBC, for frames of `channel` only or for every channel when None
+    pub fn add_dbc(&mut self, dbc: can_dbc::Dbc, channel: Option<&str>) {
+        self.add_dbc_with_priority(dbc, channel, 0);
+    }
+
+    /// Add the messages of a DBC, replacing messages of lower priority with the same ID or name
+    pub fn add_dbc_with_priority(&mut self, dbc: can_dbc::Dbc, channel: Option<&str>, priority: i32) {
+        let dbc = Arc::new(dbc);
+        let j1939_database = dbc_attributes::is_j1939_database(&dbc);
+        for message_index in 0..dbc.messages.len() {
+            let message = CompiledMessage::compile(dbc.clone(), message_index, j1939_database);
+            let index = self.messages.len();
+            let (by_id, by_name) = match channel {
+                Some(channel) => (
+                    self.by_channel_id.entry(channel.to_owned()).or_default(),
+                    self.by_channel_name.entry(channel.to_owned()).or_default(),
+                ),
+                None => (&mut self.by_id, &mut self.by_name),
+            };
+            insert_by_priority(by_id, message.id, index, priority, &self.priorities);
+            insert_by_priority(by_name, message.name().to_owned(), index, priority, &self.priorities);
+            insert_by_priority(&mut self.by_any_name, message.name().to_owned(), index, priority, &self.priorities);
+            if let Some(pgn) = message.pgn {
+                let by_pgn = match channel {
+                    Some(channel) => self.by_channel_pgn.entry(channel.to_owned()).or_default(),
+                    None => &mut self.by_pgn,
+                };
+                insert_by_priority(by_pgn, pgn, index, priority, &self.priorities);
+            }
+            self.messages.push(message);
+            self.priorities.push(priority);
+            self.channels.push(channel.map(str::to_owned));
+        }
+    }
+
+    /// Decode frames of channel `alias` as frames of `channel`, e.g. ASC channel "1" as "can0"
+    pub fn add_channel_alias(&mut self, alias: &str, channel: &str) {
+        self.channel_aliases.insert(alias.to_owned(), channel.to_owned());
+    }
+
+    /// Add a DBC for every channel, builder style
+    pub fn with_dbc(mut self, dbc: can_dbc::Dbc) -> Self {
+        self.add_dbc(dbc, None);
+        self
+    }
+
+    /// Add a DBC for the frames of one channel, builder style
+    pub fn with_channel_dbc(mut self, channel: &str, dbc: can_dbc::Dbc) -> Self {
+        self.add_dbc(dbc, Some(channel));
+        self
+    }
+
+    /// Match extended frames without an exact ID match on the PGN of J1939 messages (see `CompiledMessage::pgn`),
+    /// so a message defined with one source address and priority decodes frames of every sender.
+    /// Decoded messages then report the source address.
+    pub fn set_j1939(&mut self, enabled: bool) {
+        self.j1939 = enabled;
+    }
+
+    /// Match extended frames on their J1939 PGN, builder style
+    pub fn with_j1939(mut self) -> Self {
+        self.set_j1939(true);
+        self
+    }
+
+    /// Decode frames of channel `alias` as frames of `channel`, builder style
+    pub fn with_channel_alias(mut self, alias: &str, channel: &str) -> Self {
+        self.add_channel_alias(alias, channel);
+        self
+    }
+
+    /// All compiled messages, in the order of the DBCs
+    pub fn messages(&self) -> &[CompiledMessage] {
+        &self.messages
+    }
+
+    /// Retrieve a message by name, from any channel
+    pub fn message(&self, message_name: &str) -> Option<&CompiledMessage> {
+        self.message_index(message_name).map(|i| &self.messages[i])
+    }
+
+    /// Retrieve a message by name as seen on a channel, when buses reuse message names
+    pub fn message_on(&self, channel: &str, message_name: &str) -> Option<&CompiledMessage> {
+        let bound = self.by_channel_name.get(self.resolve_channel(channel)).and_then(|by_name| by_name.get(message_name));
+        self.pick(bound, self.by_name.get(message_name)).map(|i| &self.messages[i])
+    }
+
+    /// Index in `messages()` of a message by name, from any channel
+    pub fn message_index(&self, message_name: &str) -> Option<usize> {
+        self.by_any_name.get(message_name).copied()
+    }
+
+    /// Channel the DBC of `messages()[index]` is bound to, None for DBCs of every channel
+    pub fn message_channel(&self, index: usize) -> Option<&str> {
+        self.channels.get(index)?.as_deref()
+    }
+
+    /// Index of the message named `message_name` among the DBCs bound to `channel`,
+    /// or among the DBCs of every channel when None
+    pub fn message_index_in(&self, channel: Option<&str>, message_name: &str) -> Option<usize> {
+        let by_name = match channel {
+            Some(channel) => self.by_channel_name.get(channel)?,
+            None => &self.by_name,
+        };
+        by_name.get(message_name).copied()
+    }
+
+    /// Retrieve the message of a frame ID on a channel, see the type documentation for conflicts
+    pub fn message_by_id(&self, channel: &str, id: u32) -> Option<&CompiledMessage> {
+        self.message_index_by_id(channel, id).map(|i| &self.messages[i])
+    }
+
+    /// Index in `messages()` of the message of a frame ID on a channel
+    pub fn message_index_by_id(&self, channel: &str, id: u32) -> Option<usize> {
+        let channel = self.resolve_channel(channel);
+        let bound = self.by_channel_id.get(channel).and_then(|by_id| by_id.get(&id));
+        let exact = self.pick(bound, self.by_id.get(&id));
+        match exact {
+            None if self.j1939 && id > MAX_STANDARD_ID => {
+                let pgn = j1939::pgn(id);
+                let bound = self.by_channel_pgn.get(channel).and_then(|by_pgn| by_pgn.get(&pgn));
+                self.pick(bound, self.by_pgn.get(&pgn))
+            }
+            exact => exact,
+        }
+    }
+
+    /// Channel name after aliases
+    pub fn resolve_channel<'a>(&'a self, channel: &'a str) -> &'a str {
+        self.channel_aliases.get(channel).map_or(channel, String::as_str)
+    }
+
+    /// Channels with DBCs bound to them
+    pub fn channels(&self) -> impl Iterator<Item = &str> {
+        self.by_channel_id.keys().map(String::as_str)
+    }
+
+    /// Choose between the channel-bound and the unbound candidate
+    fn pick(&self, bound: Option<&usize>, unbound: Option<&usize>) -> Option<usize> {
+        match (bound, unbound) {
+            (Some(&b), Some(&u)) if self.priorities[u] > self.priorities[b] => Some(u),
+            (Some(&b), _) => Some(b),
+            (None, u) => u.copied(),
+        }
+    }
+
+    /// Find the message of a frame and decode it, None if the ID is not in the database
+    pub fn decode_frame(&self, can_frame: &CanFrame) -> Option<DecodedCanMessage> {
+        let message = self.message_by_id(&can_frame.channel, can_frame.id)?;
+        let mut decoded = message.decode(can_frame);
+        if self.j1939 && message.pgn.is_some() {
+            decoded.source_address = Some(J1939Id::from_can_id(can_frame.id).source_address);
+        }
+        Some(decoded)
+    }
+}
+
+/// Insert `index` unless the key already maps to a message of higher or equal priority
+fn insert_by_priority<K: Hash + Eq>(
+    map: &mut HashMap<K, usize>,
+    key: K,
+    index: usize,
+    priority: i32,
+    priorities: &[i32],
+) {
+    match map.entry(key) {
+        Entry::Occupied(mut entry) => {
+            if priorities[*entry.get()] < priority {
+                entry.insert(index);
+            }
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::canlog_reader::{self, CanLogParser};
+
+    #[test]
+    fn test_lookup_by_id_and_name() {
+        let database = CanDatabase::load("can_samples/chrysler_cusw.dbc").unwrap();
+        let dbc = can_decoder::load_dbc("can_samples/chrysler_cusw.dbc").unwrap();
+        assert_eq!(database.messages().len(), dbc.messages.len());
+
+        let gear = database.message("GEAR").unwrap();
+        assert_eq!(gear.id(), 1262);
+        assert_eq!(database.message_by_id("vcan0", 1262).unwrap().name(), "GEAR");
+        assert!(database.message("NOT_A_MESSAGE").is_none());
+        assert!(database.message_by_id("vcan0", 0x7FF).is_none());
+
+        let (spec, layout) = gear.signal("PRNDL").unwrap();
+        assert_eq!(spec.size, 4);
+        assert_eq!(layout.signal_size, 4);
+    }
+
+    #[test]
+    fn test_decode_frame_matches_decode_message_with_dbc() {
+        let dbc = can_decoder::load_dbc("can_samples/chrysler_cusw.dbc").unwrap();
+        let database = CanDatabase::from_dbc(dbc.clone());
+        let mut decoded_frames = 0;
+        for frame in CanLogParser::from_file(Path::new("demo-log.log")).unwrap() {
+            let expected = dbc
+                .messages
+                .iter()
+                .find(|m| can_encoder::get_can_id(m) == frame.id)
+                .map(|m| can_decoder::decode_message_with_dbc(&frame, m, &dbc));
+            let decoded = database.decode_frame(&frame);
+            assert_eq!(expected.is_some(), decoded.is_some());
+            if let (Some(expected), Some(decoded)) = (expected, decoded) {
+                assert_eq!(decoded.name, expected.name);
+                assert_eq!(decoded.signals, expected.signals);
+                assert_eq!(decoded.values, expected.values);
+                assert_eq!(decoded.labels, expected.labels);
+                decoded_frames += 1;
+            }
+        }
+        assert!(decoded_frames > 0);
+    }
+
+    #[test]
+    fn test_decode_frame_multiplexed_and_float() {
+        let database = CanDatabase::load("multiplex.dbc").unwrap().with_dbc(can_decoder::load_dbc("floats.dbc").unwrap());
+
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 00000602#6202A500003039").unwrap();
+        let msg = database.decode_frame(&frame).unwrap();
+        assert_eq!(msg.signals, ["Service", "Identifier", "Odometer"]);
+        assert_eq!(msg.values, [98.0, 677.0, 1234.5]);
+
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 542#0202D430FEFF0000").unwrap();
+        let msg = database.decode_frame(&frame).unwrap();
+        assert_eq!(msg.signals, ["Page", "Status", "Voltage", "Current"]);
+        assert_eq!(msg.labels[1].as_deref(), Some("Running"));
+
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 303#C0C1486E147AE148").unwrap();
+        assert_eq!(database.decode_frame(&frame).unwrap().values, [-8848.86]);
+    }
+
+    #[test]
+    fn test_decode_frame_empty_signed_signal() {
+        let dbc = can_decoder::dbc_from_str(concat!(
+            "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: X\n\n",
+            "BO_ 100 M: 1 X\n SG_ Empty : 8|0@1- (1,0) [0|0] \"\" X\n SG_ Byte : 0|8@1- (1,0) [0|0] \"\" X\n\n",
+            "VAL_ 100 Empty 0 \"None\" ;\n",
+        ))
+        .unwrap();
+        let database = CanDatabase::from_dbc(dbc);
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 064#FF").unwrap();
+        let msg = database.decode_frame(&frame).unwrap();
+        assert_eq!(msg.values, [0.0, -1.0]);
+        assert_eq!(msg.labels[0].as_deref(), Some("None"));
+    }
+
+    #[test]
+    fn test_channel_bound_dbc() {
+        let database = CanDatabase::new()
+            .with_channel_dbc("can1", can_decoder::load_dbc("floats.dbc").unwrap())
+            .with_dbc(can_decoder::load_dbc("motohawk.dbc").unwrap());
+
+        let mut frame = canlog_reader::parse_candump_line("(0.0) can1 300#00004841000050C0").unwrap();
+        assert_eq!(database.decode_frame(&frame).unwrap().name, "FloatsLE");
+        frame.channel = "can0".to_owned();
+        assert!(database.decode_frame(&frame).is_none());
+
+        // Unbound DBCs apply to every channel
+        let frame = canlog_reader::parse_candump_line("(0.0) can1 1F0#A5B6D90000000000").unwrap();
+        assert_eq!(database.decode_frame(&frame).unwrap().name, "ExampleMessage");
+    }
+
+    #[test]
+    fn test_colliding_ids_across_channels() {
+        let motohawk = can_decoder::load_dbc("motohawk.dbc").unwrap();
+        let mut body = motohawk.clone();
+        body.messages[0].name = "BodyMessage".to_owned();
+        let database = CanDatabase::new()
+            .with_channel_dbc("can0", motohawk)
+            .with_channel_dbc("can1", body)
+            .with_channel_alias("1", "can0")
+            .with_channel_alias("2", "can1");
+
+        let mut frame = canlog_reader::parse_candump_line("(0.0) can0 1F0#A5B6D90000000000").unwrap();
+        assert_eq!(database.decode_frame(&frame).unwrap().name, "ExampleMessage");
+        frame.channel = "can1".to_owned();
+        assert_eq!(database.decode_frame(&frame).unwrap().name, "BodyMessage");
+        // ASC channel numbers
+        frame.channel = "1".to_owned();
+        assert_eq!(database.decode_frame(&frame).unwrap().name, "ExampleMessage");
+        frame.channel = "2".to_owned();
+        assert_eq!(database.decode_frame(&frame).unwrap().name, "BodyMessage");
+        frame.channel = "can2".to_owned();
+        assert!(database.decode_frame(&frame).is_none());
+
+        assert_eq!(database.message_on("2", "BodyMessage").unwrap().id(), 0x1F0);
+        assert!(database.message_on("can0", "BodyMessage").is_none());
+        let mut channels: Vec<_> = database.channels().collect();
+        channels.sort();
+        assert_eq!(channels, ["can0", "can1"]);
+    }
+
+    #[test]
+    fn test_priority() {
+        let motohawk = can_decoder::load_dbc("motohawk.dbc").unwrap();
+        let mut other = motohawk.clone();
+        other.messages[0].name = "OtherMessage".to_owned();
+        let frame = canlog_reader::parse_candump_line("(0.0) can0 1F0#A5B6D90000000000").unwrap();
+
+        // Same priority: first added wins
+        let mut database = CanDatabase::new();
+        database.add_dbc(motohawk.clone(), None);
+        database.add_dbc(other.clone(), None);
+        assert_eq!(database.decode_frame(&frame).unwrap().name, "ExampleMessage");
+
+        // Higher priority replaces
+        database.add_dbc_with_priority(other.clone(), None, 1);
+        assert_eq!(database.decode_frame(&frame).unwrap().name, "OtherMessage");
+        assert_eq!(database.message("OtherMessage").unwrap().id(), 0x1F0);
+
+        // Channel-bound DBCs win ties, a higher priority unbound DBC wins over them
+        let mut database = CanDatabase::new();
+        database.add_dbc_with_priority(other.clone(), Some("can0"), 0);
+        database.add_dbc_with_priority(motohawk.clone(), None, 0);
+        assert_eq!(database.decode_frame(&frame).unwrap().name, "OtherMessage");
+        let mut database = CanDatabase::new();
+        database.add_dbc_with_priority(other, Some("can0"), -1);
+        database.add_dbc_with_priority(motohawk, None, 0);
+        assert_eq!(database.decode_frame(&frame).unwrap().name, "ExampleMessage");
+        assert_eq!(database.message_on("can0", "OtherMessage").unwrap().name(), "OtherMessage");
+    }
+
+    const J1939_DBC: &str = concat!(
+        "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: Engine\n\n",
+        "BO_ 2364539904 EEC1: 8 Engine\n",
+        " SG_ EngineSpeed : 24|16@1+ (0.125,0) [0|8031.875] \"rpm\" Vector__XXX\n\n",
+        "BO_ 2348875534 TSC1: 8 Engine\n",
+        " SG_ RequestedSpeed : 8|16@1+ (0.125,0) [0|8031.875] \"rpm\" Vector__XXX\n\n",
+        "BO_ 1024 Plain: 8 Engine\n",
+        " SG_ Value : 0|8@1+ (1,0) [0|255] \"\" Vector__XXX\n\n",
+        "BA_DEF_  \"ProtocolType\" STRING ;\n",
+        "BA_DEF_DEF_  \"ProtocolType\" \"\";\n",
+        "BA_ \"ProtocolType\" \"J1939\";\n",
+    );
+
+    #[test]
+    fn test_j1939_pgn_matching() {
+        let dbc = can_decoder::dbc_from_str(J1939_DBC).unwrap();
+        // EEC1 from source address 0x21 with priority 6
+        let frame = canlog_reader::parse_candump_line("(0.0) can0 18F00421#0000002003000000").unwrap();
+        assert!(CanDatabase::from_dbc(dbc.clone()).decode_frame(&frame).is_none());
+
+        let database = CanDatabase::from_dbc(dbc).with_j1939();
+        let decoded = database.decode_frame(&frame).unwrap();
+        assert_eq!(decoded.name, "EEC1");
+        assert_eq!(decoded.id, 0x18F00421);
+        assert_eq!(decoded.source_address, Some(0x21));
+        assert_eq!(decoded.values, [100.0]);
+
+        // PDU1: the destination address is not part of the PGN
+        let frame = canlog_reader::parse_candump_line("(0.0) can0 0C001703#0020030000000000").unwrap();
+        let decoded = database.decode_frame(&frame).unwrap();
+        assert_eq!(decoded.name, "TSC1");
+        assert_eq!(decoded.source_address, Some(0x03));
+
+        // Standard IDs match exactly
+        let frame = canlog_reader::parse_candump_line("(0.0) can0 400#0100000000000000").unwrap();
+        assert_eq!(database.decode_frame(&frame).unwrap().source_address, None);
+        let frame = canlog_reader::parse_candump_line("(0.0) can0 00040100#0100000000000000").unwrap();
+        assert!(database.decode_frame(&frame).is_none());
+        assert_eq!(database.message("EEC1").unwrap().pgn(), Some(0xF004));
+        assert_eq!(database.message("Plain").unwrap().pgn(), None);
+
+        // Extended messages of other DBCs only match their exact ID
+        let dbc = can_decoder::dbc_from_str(J1939_DBC.split("BA_DEF_").next().unwrap()).unwrap();
+        let database = CanDatabase::from_dbc(dbc).with_j1939();
+        assert!(database.message("EEC1").unwrap().is_extended());
+        assert_eq!(database.message("EEC1").unwrap().pgn(), None);
+        let frame = canlog_reader::parse_candump_line("(0.0) can0 18F00421#0000002003000000").unwrap();
+        assert!(database.decode_frame(&frame).is_none());
+        let frame = canlog_reader::parse_candump_line("(0.0) can0 0CF00400#0000002003000000").unwrap();
+        assert_eq!(database.decode_frame(&frame).unwrap().source_address, None);
+
+        // Unless VFrameFormat says they are J1939 parameter groups
+        let j1939_pg = format!(
+            "{}{}{}",
+            J1939_DBC.split("BA_DEF_").next().unwrap(),
+            "BA_DEF_ BO_  \"VFrameFormat\" ENUM  \"StandardCAN\",\"ExtendedCAN\",\"reserved\",\"J1939PG\";\n",
+            "BA_DEF_DEF_  \"VFrameFormat\" \"StandardCAN\";\nBA_ \"VFrameFormat\" BO_ 2364539904 3;\n",
+        );
+        let database = CanDatabase::from_dbc(can_decoder::dbc_from_str(&j1939_pg).unwrap()).with_j1939();
+        assert_eq!(database.message("EEC1").unwrap().pgn(), Some(0xF004));
+        assert_eq!(database.message("TSC1").unwrap().pgn(), None);
+    }
+
+    #[test]
+    fn test_builder_uses_cached_layouts() {
+        let dbc = can_decoder::load_dbc("can_samples/chrysler_cusw.dbc").unwrap();
+        let database = CanDatabase::from_dbc(dbc.clone());
+        let message = database.message("GEAR").unwrap();
+
+        let frame = message.builder().set("PRNDL", "D").unwrap().build();
+        let expected = can_encoder::encode_message(can_decoder::get_message_spec(&dbc, "GEAR").unwrap(), &[("PRNDL", 4.0)]).unwrap();
+        assert_eq!(frame.data, expected.data);
+        assert_eq!(message.decode(&frame).labels, [Some("D".to_string())]);
+    }
+}
@@ -4,13 +4,18 @@
  */
 
 use crate::canlog_reader::CanFrame;
+use crate::dbc_loader;
+use crate::signal_layout::SignalLayout;
 use can_dbc::Dbc;
+use can_dbc::MultiplexIndicator;
+use can_dbc::SignalExtendedValueType;
 use rand::prelude::*;
 use std::collections::HashMap;
-use std::{fmt, fs};
+use std::fmt;
 use std::fs::File;
 use std::hint::black_box;
 use std::io::{self, Read};
+use std::ops::RangeInclusive;
 use std::time::Instant;
 
 pub struct SignalsMap {
@@ -39,17 +44,35 @@ impl fmt::Display for SignalsMap {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct DecodedCanMessage {
     pub id: u32,
     pub name: String,
     pub signals: Vec<String>,
     pub values: Vec<f64>,
     pub units: Vec<String>,
+    /// Enumeration label of each value, from the DBC value descriptions (VAL_)
+    pub labels: Vec<Option<String>>,
+    /// J1939 source address of the frame, when decoded with J1939 matching
+    pub source_address: Option<u8>,
+}
+
+impl DecodedCanMessage {
+    /// Return the label of a signal if it has one, else its value
+    fn value_text(&self, index: usize) -> String {
+        match self.labels.get(index) {
+            Some(Some(label)) => label.clone(),
+            _ => self.values[index].to_string(),
+        }
+    }
 }
 
 impl fmt::Display for DecodedCanMessage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} : {:#X} ", self.name, self.id)?;
+        if let Some(source_address) = self.source_address {
+            write!(f, "SA {source_address:#04X} ")?;
+        }
         write!(f, "{{\n")?;
         //Alternate form prints horizontally
         if f.alternate() {
@@ -57,14 +80,14 @@ impl fmt::Display for DecodedCanMessage {
                 write!(f, "{signal_name} \t")?;
             }
             write!(f, "\n")?;
-            for value in self.values.iter() {
-                write!(f, "{value} \t")?;
+            for i in 0..self.values.len() {
+                write!(f, "{} \t", self.value_text(i))?;
             }
         }
         //Standard form prints vertically
         else {
-            for (signal_name, value) in self.signals.iter().zip(self.values.iter()) {
-                write!(f, "{}: {},\n", signal_name, value)?;
+            for (i, signal_name) in self.signals.iter().enumerate() {
+                write!(f, "{}: {},\n", signal_name, self.value_text(i))?;
             }
         }
 
@@ -74,11 +97,32 @@ impl fmt::Display for DecodedCanMessage {
 }
 
 /// Decode all the signal values from a given message
+/// For multiplexed messages only the switch, plain signals and the signals of the
+/// page selected by the switch are decoded.
+/// The frame channel is not looked at, see `CanDatabase` to decode logs of several buses.
+///
+/// Every signal is read as an integer: the message spec doesn't carry the SIG_VALTYPE_ of the DBC,
+/// so float signals need `decode_message_with_dbc` (or `CanDatabase`) to decode as floats.
 pub fn decode_message(can_frame: &CanFrame, message_spec: &can_dbc::Message) -> DecodedCanMessage {
+    decode_message_with_mux(can_frame, message_spec, &[])
+}
+
+/// Decode all the signal values from a given message, with the extended multiplexing
+/// (`SG_MUL_VAL_`) rules of the DBC, usually `&dbc.extended_multiplex`.
+/// Signals are decoded only when every multiplexer up their tree reads a value in range.
+/// Float signals read as integers, as in `decode_message`.
+pub fn decode_message_with_mux(
+    can_frame: &CanFrame,
+    message_spec: &can_dbc::Message,
+    extended_multiplex: &[can_dbc::ExtendedMultiplex],
+) -> DecodedCanMessage {
     let mut values = Vec::with_capacity(message_spec.signals.len());
     let mut names = Vec::with_capacity(message_spec.signals.len());
     let mut units = Vec::with_capacity(message_spec.signals.len());
     for signal_spec in &message_spec.signals {
+        if !is_signal_active_with_mux(can_frame, message_spec, signal_spec, extended_multiplex) {
+            continue;
+        }
         names.push(signal_spec.name.clone());
         values.push(decode_signal(&can_frame, &signal_spec));
         units.push(signal_spec.unit.to_owned());
@@ -87,10 +131,215 @@ pub fn decode_message(can_frame: &CanFrame, message_spec: &can_dbc::Message) ->
     return DecodedCanMessage {
         id: can_frame.id,
         name: message_spec.name.clone(),
+        labels: vec![None; names.len()],
         signals: names,
         values: values,
         units: units,
+        source_address: None,
+    };
+}
+
+/// Decode a message with everything the DBC knows about it:
+/// extended multiplexing, float value types (SIG_VALTYPE_) and enumeration labels of the values.
+pub fn decode_message_with_dbc(
+    can_frame: &CanFrame,
+    message_spec: &can_dbc::Message,
+    dbc: &Dbc,
+) -> DecodedCanMessage {
+    let mut decoded = decode_message_with_mux(can_frame, message_spec, &dbc.extended_multiplex);
+    for (i, signal_name) in decoded.signals.iter().enumerate() {
+        let Some(signal_spec) = get_signal_spec(message_spec, signal_name) else {
+            continue;
+        };
+        let value_type = get_value_type(dbc, message_spec, signal_spec);
+        if value_type != SignalExtendedValueType::SignedOrUnsignedInteger {
+            let raw = SignalLayout::from_spec(signal_spec).extract(&can_frame.data);
+            decoded.values[i] = compute_typed_signal_value(raw, signal_spec, value_type);
+            // Value descriptions are for integer values only
+            continue;
+        }
+        let raw_value = decode_raw_signal(can_frame, signal_spec);
+        decoded.labels[i] = signal_label(dbc, message_spec, signal_spec, raw_value).map(str::to_owned);
+    }
+    decoded
+}
+
+/// Value type of a signal declared with SIG_VALTYPE_, integer when not declared
+pub fn get_value_type(
+    dbc: &Dbc,
+    message_spec: &can_dbc::Message,
+    signal_spec: &can_dbc::Signal,
+) -> SignalExtendedValueType {
+    dbc.extended_value_type_for_signal(message_spec.id, &signal_spec.name)
+        .copied()
+        .unwrap_or(SignalExtendedValueType::SignedOrUnsignedInteger)
+}
+
+/// Compute the physical value of a signal from its raw bits, according to its value type:
+/// IEEE float signals hold the bits of an f32 (in the lower 32 bits) or an f64,
+/// factor and offset apply on top of the float value.
+pub fn compute_typed_signal_value(
+    raw_value: u64,
+    signal_spec: &can_dbc::Signal,
+    value_type: SignalExtendedValueType,
+) -> f64 {
+    let value = match value_type {
+        SignalExtendedValueType::SignedOrUnsignedInteger => return compute_signal_value(raw_value, signal_spec),
+        SignalExtendedValueType::IEEEfloat32Bit => f32::from_bits(raw_value as u32) as f64,
+        SignalExtendedValueType::IEEEdouble64bit => f64::from_bits(raw_value),
     };
+    value * signal_spec.factor + signal_spec.offset
+}
+
+/// Extract the raw value of a signal, sign extended for signed signals.
+/// This is the value that value descriptions (VAL_) refer to.
+pub fn decode_raw_signal(can_frame: &CanFrame, signal_spec: &can_dbc::Signal) -> i64 {
+    sign_extend(SignalLayout::from_spec(signal_spec).extract(&can_frame.data), signal_spec)
+}
+
+/// Raw bits of a signal as an integer, sign extended for signed signals. A 0-bit signal is 0.
+pub(crate) fn sign_extend(raw: u64, signal_spec: &can_dbc::Signal) -> i64 {
+    match (signal_spec.value_type, signal_spec.size) {
+        (can_dbc::ValueType::Signed, 0) => 0,
+        (can_dbc::ValueType::Signed, size @ 1..64) => {
+            // Shift left to place the MSB at the top of the i64, shift right to extend the sign
+            let shift_len = 64 - size;
+            ((raw as i64) << shift_len) >> shift_len
+        }
+        _ => raw as i64,
+    }
+}
+
+/// Retrieve the value descriptions of a signal: its VAL_ entry, or else
+/// the global VAL_TABLE_ named after the signal
+pub fn get_value_descriptions<'a>(
+    dbc: &'a Dbc,
+    message_spec: &can_dbc::Message,
+    signal_spec: &can_dbc::Signal,
+) -> Option<&'a [can_dbc::ValDescription]> {
+    dbc.value_descriptions_for_signal(message_spec.id, &signal_spec.name)
+        .or_else(|| {
+            dbc.value_tables
+                .iter()
+                .find(|table| table.name == signal_spec.name)
+                .map(|table| table.descriptions.as_slice())
+        })
+}
+
+/// Return the enumeration label of a raw signal value, None if the value has no description
+pub fn signal_label<'a>(
+    dbc: &'a Dbc,
+    message_spec: &can_dbc::Message,
+    signal_spec: &can_dbc::Signal,
+    raw_value: i64,
+) -> Option<&'a str> {
+    get_value_descriptions(dbc, message_spec, signal_spec)?
+        .iter()
+        .find(|d| d.id == raw_value)
+        .map(|d| d.description.as_str())
+}
+
+/// Return the raw value of an enumeration label, None if the signal has no such label
+pub fn label_raw_value(
+    dbc: &Dbc,
+    message_spec: &can_dbc::Message,
+    signal_spec: &can_dbc::Signal,
+    label: &str,
+) -> Option<i64> {
+    get_value_descriptions(dbc, message_spec, signal_spec)?
+        .iter()
+        .find(|d| d.description == label)
+        .map(|d| d.id)
+}
+
+/// Return the multiplexer switch signal (`M` in the DBC), None if the message is not multiplexed
+pub fn get_multiplexor_spec(message_spec: &can_dbc::Message) -> Option<&can_dbc::Signal> {
+    message_spec
+        .signals
+        .iter()
+        .find(|s| s.multiplexer_indicator == MultiplexIndicator::Multiplexor)
+}
+
+/// Read the raw value of the multiplexer switch from the frame.
+/// Multiplexed signals are selected by the raw switch value, not the physical one.
+pub fn multiplexor_value(can_frame: &CanFrame, message_spec: &can_dbc::Message) -> Option<u64> {
+    let switch_spec = get_multiplexor_spec(message_spec)?;
+    Some(SignalLayout::from_spec(switch_spec).extract(&can_frame.data))
+}
+
+/// Whether a signal is present in a frame whose multiplexer switch reads `mux_value`
+/// Plain signals and the switch itself are always present.
+pub fn is_signal_active(signal_spec: &can_dbc::Signal, mux_value: Option<u64>) -> bool {
+    match signal_spec.multiplexer_indicator {
+        MultiplexIndicator::MultiplexedSignal(page)
+        | MultiplexIndicator::MultiplexorAndMultiplexedSignal(page) => mux_value == Some(page),
+        MultiplexIndicator::Multiplexor | MultiplexIndicator::Plain => true,
+    }
+}
+
+/// Condition for a multiplexed signal to be present in a frame:
+/// the multiplexer signal is present and its raw value is in one of the ranges.
+pub struct MuxCondition<'a> {
+    pub multiplexor: &'a can_dbc::Signal,
+    pub ranges: Vec<RangeInclusive<u64>>,
+}
+
+impl MuxCondition<'_> {
+    pub fn matches(&self, raw_value: u64) -> bool {
+        self.ranges.iter().any(|range| range.contains(&raw_value))
+    }
+}
+
+/// Return the condition for a signal to be present, None if the signal is always present.
+/// `SG_MUL_VAL_` entries take precedence over the `m<n>` page of the signal, which
+/// is relative to the message switch (`M`).
+pub fn mux_condition<'a>(
+    message_spec: &'a can_dbc::Message,
+    signal_spec: &can_dbc::Signal,
+    extended_multiplex: &[can_dbc::ExtendedMultiplex],
+) -> Option<MuxCondition<'a>> {
+    let extended = extended_multiplex
+        .iter()
+        .find(|e| e.message_id == message_spec.id && e.signal_name == signal_spec.name);
+    if let Some(extended) = extended {
+        let multiplexor = get_signal_spec(message_spec, &extended.multiplexor_signal_name)?;
+        let ranges = extended
+            .mappings
+            .iter()
+            .map(|m| m.min_value..=m.max_value)
+            .collect();
+        return Some(MuxCondition { multiplexor, ranges });
+    }
+    match signal_spec.multiplexer_indicator {
+        MultiplexIndicator::MultiplexedSignal(page)
+        | MultiplexIndicator::MultiplexorAndMultiplexedSignal(page) => Some(MuxCondition {
+            multiplexor: get_multiplexor_spec(message_spec)?,
+            ranges: vec![page..=page],
+        }),
+        MultiplexIndicator::Multiplexor | MultiplexIndicator::Plain => None,
+    }
+}
+
+/// Whether a signal is present in a frame, walking up the multiplexer tree
+pub fn is_signal_active_with_mux(
+    can_frame: &CanFrame,
+    message_spec: &can_dbc::Message,
+    signal_spec: &can_dbc::Signal,
+    extended_multiplex: &[can_dbc::ExtendedMultiplex],
+) -> bool {
+    let mut signal_spec = signal_spec;
+    // A well formed tree is at most as deep as the number of signals, this guards against cycles
+    for _ in 0..=message_spec.signals.len() {
+        let Some(condition) = mux_condition(message_spec, signal_spec, extended_multiplex) else {
+            return true;
+        };
+        let raw_value = SignalLayout::from_spec(condition.multiplexor).extract(&can_frame.data);
+        if !condition.matches(raw_value) {
+            return false;
+        }
+        signal_spec = condition.multiplexor;
+    }
+    false
 }
 
 /*
@@ -158,13 +407,7 @@ fn compute_signal_value(decoded_value: u64, signal_spec: &can_dbc::Signal) -> f6
     //conversion from raw_signal to real value
     let raw_value = match signal_spec.value_type {
         //Sign extend if the value is signed
-        can_dbc::ValueType::Signed => {
-            let shift_len = 64 - signal_spec.size;
-            //Sign extend operation: shift left to place MSB into top of u64, shift right to get sign extension.
-            let sign_extended = ((decoded_value as i64) << shift_len) >> shift_len;
-            //println!("shift len {shift_len} sign_extended {sign_extended}");
-            sign_extended as f64
-        }
+        can_dbc::ValueType::Signed => sign_extend(decoded_value, signal_spec) as f64,
         can_dbc::ValueType::Unsigned => decoded_value as f64,
     };
     return raw_value * signal_spec.factor + signal_spec.offset;
@@ -240,6 +483,7 @@ pub fn decode_signal_by_bytes(can_frame: &CanFrame, signal_spec: &can_dbc::Signa
 ///     which fail on dbc comments with messages. See here for dbc spec
 ///     https://github.com/stefanhoelzl/CANpy/blob/master/docs/DBC_Specification.md#cm_
 ///     TODO: Open bug on can-dbc-pest
+#[deprecated(note = "dbc_loader repairs the comments instead of dropping them")]
 pub fn strip_dbc_comments(data: &str) -> String {
     let stripped: String = data
             .lines()
@@ -249,33 +493,22 @@ pub fn strip_dbc_comments(data: &str) -> String {
     return stripped;
 }
 
-/// Load a dbc from the file path
+/// Load a dbc from the file path.
+/// Parse errors report their line and column, see `dbc_loader::load` for the warnings.
 pub fn load_dbc(dbc_path: &str) -> io::Result<can_dbc::Dbc> {
-    let data = fs::read_to_string(dbc_path).expect("Unable to read input file");
-    return dbc_from_str(data.as_str());
+    Ok(dbc_loader::load(dbc_path)?.dbc)
 }
 
 /// Create a dbc from a str of the dbc contents
 pub fn dbc_from_str(data: &str) -> io::Result<can_dbc::Dbc> {
-    let maybe_dbc = can_dbc::Dbc::try_from(data);
-
-    if let Ok(dbc) = maybe_dbc {
-        return Ok(dbc);
-    }
-
-    // Retry again without DBC comments
-    let stripped_dbc = strip_dbc_comments(data);
-    can_dbc::Dbc::try_from(stripped_dbc.as_str())
-        .map_err(|_| io::Error::new(io::ErrorKind::Other, "Error loading dbc"))
+    let loaded = dbc_loader::parse(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(loaded.dbc)
 }
 
-/// Create a dbc from a slice of utf8 bytes
+/// Create a dbc from a slice of UTF-8 or Windows-1252 bytes
 pub fn dbc_from_slice(slice: &[u8]) -> io::Result<can_dbc::Dbc> {
-    let data = str::from_utf8(slice).or_else(|e| {
-            let io_error = io::Error::new(io::ErrorKind::InvalidData,format!("Invalid UTF-8 sequence: {}", e));
-            Err(io_error)
-        })?;
-    return dbc_from_str(data);
+    let loaded = dbc_loader::from_bytes(slice).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(loaded.dbc)
 }
 
 /// Retreive specification of  the message as read from the CAN Dbc
@@ -411,14 +644,8 @@ mod tests {
     }
     #[test]
     fn test_load_dbc() {
-        let dbc = load_dbc("motohawk.dbc").unwrap();
-        let dbc = load_dbc("signed.dbc").unwrap();
-        //let dbc = load_dbc("abs.dbc").unwrap();
-        for message in dbc.messages.iter() {
-            for s in message.signals.iter() {
-                println!("{:?}", s);
-            }
-            //println!("{:?}", message);
+        for (path, messages) in [("motohawk.dbc", 1), ("signed.dbc", 11), ("abs.dbc", 18)] {
+            assert_eq!(load_dbc(path).unwrap().messages.len(), messages, "{path}");
         }
     }
     #[test]
@@ -574,6 +801,134 @@ mod tests {
 
         println!("{msg}");
     }
+
+    #[test]
+    fn test_decode_multiplexed_message() {
+        let dbc = load_dbc("multiplex.dbc").unwrap();
+        let msg_spec = get_message_spec(&dbc, "Diagnostics").unwrap();
+
+        // Page 1: software version
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 542#0105020AFFFF0000").unwrap();
+        assert_eq!(multiplexor_value(&frame, msg_spec), Some(1));
+        let msg = decode_message(&frame, msg_spec);
+        assert_eq!(msg.signals, ["Page", "Status", "SW_Major", "SW_Minor"]);
+        assert_eq!(msg.values, [1.0, 5.0, 2.0, 10.0]);
+
+        // Page 2: supply, same bytes read as voltage and current
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 542#0205D430FEFF0000").unwrap();
+        let msg = decode_message(&frame, msg_spec);
+        assert_eq!(msg.signals, ["Page", "Status", "Voltage", "Current"]);
+        assert_eq!(msg.values, [2.0, 5.0, 12.5, -0.02]);
+
+        // Unknown page: only the switch and plain signals
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 542#0705000000000000").unwrap();
+        let msg = decode_message(&frame, msg_spec);
+        assert_eq!(msg.signals, ["Page", "Status"]);
+
+        // Non multiplexed messages are untouched
+        let motohawk = load_dbc("motohawk.dbc").unwrap();
+        let example = get_message_spec(&motohawk, "ExampleMessage").unwrap();
+        assert_eq!(multiplexor_value(&frame, example), None);
+        assert_eq!(decode_message(&frame, example).signals.len(), 3);
+    }
+
+    #[test]
+    fn test_decode_value_labels() {
+        let dbc = load_dbc("can_samples/chrysler_cusw.dbc").unwrap();
+        let gear = get_message_spec(&dbc, "GEAR").unwrap();
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 4EE#0004000000000000").unwrap();
+        let msg = decode_message_with_dbc(&frame, gear, &dbc);
+        assert_eq!(msg.values, [4.0]);
+        assert_eq!(msg.labels, [Some("D".to_string())]);
+        assert_eq!(format!("{msg}"), "GEAR : 0x4EE {\nPRNDL: D,\n}\n");
+
+        // Values without a description keep the number
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 4EE#0009000000000000").unwrap();
+        let msg = decode_message_with_dbc(&frame, gear, &dbc);
+        assert_eq!(msg.labels, [None]);
+        assert_eq!(format!("{msg}"), "GEAR : 0x4EE {\nPRNDL: 9,\n}\n");
+
+        // Plain decode_message doesn't look labels up
+        assert_eq!(decode_message(&frame, gear).labels, [None]);
+
+        // VAL_ of the switch and global VAL_TABLE_ named after the signal
+        let dbc = load_dbc("multiplex.dbc").unwrap();
+        let diagnostics = get_message_spec(&dbc, "Diagnostics").unwrap();
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 542#0202D430FEFF0000").unwrap();
+        let msg = decode_message_with_dbc(&frame, diagnostics, &dbc);
+        assert_eq!(msg.signals, ["Page", "Status", "Voltage", "Current"]);
+        assert_eq!(
+            msg.labels,
+            [Some("Supply".to_string()), Some("Running".to_string()), None, None]
+        );
+        assert_eq!(format!("{msg:#}"), "Diagnostics : 0x542 {\nPage \tStatus \tVoltage \tCurrent \t\nSupply \tRunning \t12.5 \t-0.02 \t}\n");
+    }
+
+    #[test]
+    fn test_decode_float_signals() {
+        let dbc = load_dbc("floats.dbc").unwrap();
+        let decode = |msg_name: &str, line: &str| {
+            let frame = canlog_reader::parse_candump_line(line).unwrap();
+            decode_message_with_dbc(&frame, get_message_spec(&dbc, msg_name).unwrap(), &dbc)
+        };
+
+        let msg = decode("FloatsLE", "(0.0) vcan0 300#00004841000050C0");
+        assert_eq!(msg.values, [12.5, 3.5]);
+        let msg = decode("FloatsBE", "(0.0) vcan0 301#42CAA666BE000000");
+        assert_eq!(msg.values, [101.325f32 as f64, -0.125]);
+        let msg = decode("DoubleLE", "(0.0) vcan0 302#ADFA5C6D454A9340");
+        assert_eq!(msg.values, [1234.5678]);
+        let msg = decode("DoubleBE", "(0.0) vcan0 303#C0C1486E147AE148");
+        assert_eq!(msg.values, [-8848.86]);
+
+        // Without the DBC value types the bits read as integers
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 300#00004841000050C0").unwrap();
+        let msg = decode_message(&frame, get_message_spec(&dbc, "FloatsLE").unwrap());
+        assert_eq!(msg.values[0], 0x41480000 as f64);
+    }
+
+    #[test]
+    fn test_decode_empty_signed_signal() {
+        let dbc = dbc_from_str(concat!(
+            "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: X\n\n",
+            "BO_ 100 M: 2 X\n SG_ Empty : 8|0@1- (1,5) [0|0] \"\" X\n SG_ Byte : 0|8@1- (1,0) [0|0] \"\" X\n",
+        ))
+        .unwrap();
+        let spec = get_message_spec(&dbc, "M").unwrap();
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 064#FFFF").unwrap();
+        assert_eq!(decode_raw_signal(&frame, &spec.signals[0]), 0);
+        assert_eq!(decode_raw_signal(&frame, &spec.signals[1]), -1);
+        assert_eq!(decode_message(&frame, spec).values, [5.0, -1.0]);
+    }
+
+    #[test]
+    fn test_decode_extended_multiplexed_message() {
+        let dbc = load_dbc("multiplex.dbc").unwrap();
+        let msg_spec = get_message_spec(&dbc, "UdsResponse").unwrap();
+        let decode = |line: &str| {
+            let frame = canlog_reader::parse_candump_line(line).unwrap();
+            decode_message_with_mux(&frame, msg_spec, &dbc.extended_multiplex)
+        };
+
+        // ReadDataByIdentifier response, identifier 0x0100: coolant temperature
+        let msg = decode("(0.0) vcan0 00000602#62010078000000");
+        assert_eq!(msg.signals, ["Service", "Identifier", "CoolantTemp"]);
+        assert_eq!(msg.values, [98.0, 256.0, 80.0]);
+
+        // Identifier range 0x0200-0x02FF: odometer
+        let msg = decode("(0.0) vcan0 00000602#6202A500003039");
+        assert_eq!(msg.signals, ["Service", "Identifier", "Odometer"]);
+        assert_eq!(msg.values, [98.0, 677.0, 1234.5]);
+
+        // Identifier outside of every range
+        let msg = decode("(0.0) vcan0 00000602#62030000000000");
+        assert_eq!(msg.signals, ["Service", "Identifier"]);
+
+        // Negative response, the identifier bytes are not an identifier anymore
+        let msg = decode("(0.0) vcan0 00000602#7F2231");
+        assert_eq!(msg.signals, ["Service", "NegativeService", "ResponseCode"]);
+        assert_eq!(msg.values, [127.0, 34.0, 49.0]);
+    }
 }
 
 /*
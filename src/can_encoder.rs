@@ -9,8 +9,38 @@ use anyhow::{anyhow, Result};
 
 use crate::can_decoder;
 use crate::canlog_reader::CanFrame;
+use crate::dbc_attributes;
+use crate::frame_protection::Protection;
+use crate::j1939::J1939Id;
 use crate::signal_layout::SignalLayout;
 
+/// What to do with physical values that don't fit their signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RangePolicy {
+    /// Fail when the value is outside the DBC `[min|max]` (when the DBC gives a range)
+    /// or can't be represented in the signal size
+    Error,
+    /// Clamp to the smallest / largest raw value of the signal size
+    SaturateRaw,
+    /// Clamp to the DBC `[min|max]` when the DBC gives a range, then to the raw range
+    SaturateDbc,
+    /// Keep the low `size` bits of the raw value (two's complement)
+    Wrap,
+    /// Convert as `compute_raw_value` does: negative values of unsigned signals saturate to 0,
+    /// then the low `size` bits of the raw value are kept
+    #[default]
+    Cast,
+}
+
+/// A physical value that didn't fit its signal and was changed by the range policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClampedValue {
+    pub signal: String,
+    pub requested: f64,
+    /// Physical value of the raw value in the frame
+    pub encoded: f64,
+}
+
 /// Convert a physical (engineering) value to the raw unsigned integer
 /// that gets packed into the CAN frame data.
 ///
@@ -18,30 +48,131 @@ use crate::signal_layout::SignalLayout;
 ///   physical = raw * factor + offset
 ///   raw = (physical - offset) / factor
 ///
-/// For signed signals, the result is truncated to `signal_size` bits
-/// (two's complement representation stored as u64).
+/// Negative values of unsigned signals saturate to 0, the result is then truncated
+/// to `signal_size` bits (two's complement representation stored as u64).
+/// See `compute_raw_value_with_policy` for the other range policies.
 pub fn compute_raw_value(physical: f64, spec: &can_dbc::Signal) -> u64 {
-    let raw_f64 = (physical - spec.offset) / spec.factor;
+    cast_raw(((physical - spec.offset) / spec.factor).round(), spec)
+}
 
-    match spec.value_type {
-        can_dbc::ValueType::Signed => {
-            let raw_i64 = raw_f64.round() as i64;
-            // Mask to signal_size bits to get the unsigned two's complement representation
-            if spec.size >= 64 {
-                raw_i64 as u64
-            } else {
-                (raw_i64 as u64) & ((1u64 << spec.size) - 1)
-            }
+/// Raw value as an `as` cast to i64 / u64 gives it, truncated to the signal size
+fn cast_raw(raw: f64, spec: &can_dbc::Signal) -> u64 {
+    let raw = match spec.value_type {
+        can_dbc::ValueType::Signed => raw as i64 as u64,
+        can_dbc::ValueType::Unsigned => raw as u64,
+    };
+    if spec.size >= 64 {
+        raw
+    } else {
+        raw & ((1u64 << spec.size) - 1)
+    }
+}
+
+/// Convert a physical value to the raw value of an integer signal, applying `policy`
+/// to values outside the DBC range or the raw range of the signal.
+/// Returns the raw value and whether the value was changed to fit.
+///
+/// Returns Err with `RangePolicy::Error` when the value doesn't fit,
+/// and for NaN and infinite values whatever the policy.
+pub fn compute_raw_value_with_policy(physical: f64, spec: &can_dbc::Signal, policy: RangePolicy) -> Result<(u64, bool)> {
+    let raw = ((physical - spec.offset) / spec.factor).round();
+    if !raw.is_finite() {
+        return Err(anyhow!("signal {}: can't encode {}", spec.name, physical));
+    }
+    let (raw_min, raw_max) = raw_range(spec);
+    let (raw_min, raw_max) = (raw_min as f64, raw_max as f64);
+    let fitted = match policy {
+        RangePolicy::Cast => return Ok((cast_raw(raw, spec), raw < raw_min || raw > raw_max)),
+        RangePolicy::Wrap => raw,
+        RangePolicy::SaturateRaw => raw.clamp(raw_min, raw_max),
+        RangePolicy::SaturateDbc => {
+            let in_dbc_range = dbc_raw_range(spec).map_or(raw, |(min, max)| raw.clamp(min, max));
+            in_dbc_range.clamp(raw_min, raw_max)
         }
-        can_dbc::ValueType::Unsigned => {
-            let raw_u64 = raw_f64.round() as u64;
-            // Clamp to representable range
-            if spec.size >= 64 {
-                raw_u64
-            } else {
-                raw_u64 & ((1u64 << spec.size) - 1)
+        RangePolicy::Error => {
+            if dbc_raw_range(spec).is_some_and(|(min, max)| raw < min || raw > max) {
+                return Err(anyhow!("signal {}: {} is outside [{}|{}]", spec.name, physical, spec.min, spec.max));
             }
+            if raw < raw_min || raw > raw_max {
+                return Err(anyhow!("signal {}: {} doesn't fit in {} bits", spec.name, physical, spec.size));
+            }
+            raw
+        }
+    };
+    let changed = fitted != raw || fitted < raw_min || fitted > raw_max;
+    // Two's complement truncated to the signal size
+    let raw = fitted as i128 as u64;
+    if spec.size >= 64 {
+        Ok((raw, changed))
+    } else {
+        Ok((raw & ((1u64 << spec.size) - 1), changed))
+    }
+}
+
+/// Smallest and largest raw value of an integer signal
+fn raw_range(spec: &can_dbc::Signal) -> (i128, i128) {
+    let size = spec.size.min(64) as u32;
+    if size == 0 {
+        return (0, 0);
+    }
+    match spec.value_type {
+        can_dbc::ValueType::Signed => (-(1i128 << (size - 1)), (1i128 << (size - 1)) - 1),
+        can_dbc::ValueType::Unsigned => (0, (1i128 << size) - 1),
+    }
+}
+
+/// DBC `[min|max]` as raw values, None when the DBC gives no range (min >= max)
+fn dbc_raw_range(spec: &can_dbc::Signal) -> Option<(f64, f64)> {
+    if spec.min >= spec.max {
+        return None;
+    }
+    let min = ((spec.min - spec.offset) / spec.factor).round();
+    let max = ((spec.max - spec.offset) / spec.factor).round();
+    Some((min.min(max), min.max(max)))
+}
+
+/// Convert a physical value to the raw bits of a signal according to its value type.
+///
+/// Integer signals go through `compute_raw_value`. IEEE float signals store
+/// `(physical - offset) / factor` as the bits of an f32 or f64, without rounding.
+pub fn compute_typed_raw_value(
+    physical: f64,
+    spec: &can_dbc::Signal,
+    value_type: can_dbc::SignalExtendedValueType,
+) -> u64 {
+    match value_type {
+        can_dbc::SignalExtendedValueType::SignedOrUnsignedInteger => compute_raw_value(physical, spec),
+        _ => float_raw((physical - spec.offset) / spec.factor, value_type),
+    }
+}
+
+/// Same as `compute_typed_raw_value`, applying `policy` like `compute_raw_value_with_policy`.
+/// Float signals have no raw range, only the DBC range is checked or saturated to.
+pub fn compute_typed_raw_value_with_policy(
+    physical: f64,
+    spec: &can_dbc::Signal,
+    value_type: can_dbc::SignalExtendedValueType,
+    policy: RangePolicy,
+) -> Result<(u64, bool)> {
+    if value_type == can_dbc::SignalExtendedValueType::SignedOrUnsignedInteger {
+        return compute_raw_value_with_policy(physical, spec, policy);
+    }
+    let in_range = spec.min >= spec.max || (spec.min..=spec.max).contains(&physical);
+    let fitted = match policy {
+        RangePolicy::Error if !in_range => {
+            return Err(anyhow!("signal {}: {} is outside [{}|{}]", spec.name, physical, spec.min, spec.max))
         }
+        RangePolicy::SaturateDbc if !in_range => physical.clamp(spec.min, spec.max),
+        _ => physical,
+    };
+    Ok((float_raw((fitted - spec.offset) / spec.factor, value_type), fitted != physical))
+}
+
+/// Bits of an f32 or f64 float signal
+fn float_raw(value: f64, value_type: can_dbc::SignalExtendedValueType) -> u64 {
+    match value_type {
+        can_dbc::SignalExtendedValueType::IEEEfloat32Bit => (value as f32).to_bits() as u64,
+        _ => value.to_bits(),
     }
 }
 
@@ -61,28 +192,80 @@ pub fn get_can_id(message_spec: &can_dbc::Message) -> u32{
 /// Encode a full message from signal name/value pairs into a `CanFrame`.
 ///
 /// Looks up each signal by name in `message_spec`, computes the raw value,
-/// and packs it into the frame data using `SignalLayout`. Unspecified signals
-/// are left as zero.
+/// and packs it into the frame data using `SignalLayout`. Values that don't fit are
+/// converted as by `compute_raw_value`, see `encode_message_with_policy`. Unspecified signals
+/// are left as zero. For multiplexed messages the switch is set to the page of
+/// the given signals.
 ///
-/// Returns an error if any signal name is not found in the message spec.
+/// Returns an error if any signal name is not found in the message spec,
+/// or if the signals belong to different multiplexer pages.
 pub fn encode_message(
     message_spec: &can_dbc::Message,
     signals: &[(&str, f64)]
 ) -> Result<CanFrame> {
-    let mut frame = CanFrame::default();
-    //frame.id = message_id;
-    frame.id = get_can_id(message_spec);
-    frame.len = message_spec.size as u8;
+    encode_message_with_mux(message_spec, signals, &[])
+}
 
+/// Same as `encode_message`, applying `policy` to values that don't fit their signal.
+/// Returns the frame and the values that were changed to fit.
+pub fn encode_message_with_policy(
+    message_spec: &can_dbc::Message,
+    signals: &[(&str, f64)],
+    policy: RangePolicy,
+) -> Result<(CanFrame, Vec<ClampedValue>)> {
+    let mut builder = CanFrameBuilder::new(message_spec).range_policy(policy);
     for (signal_name, physical_value) in signals {
-        let spec = can_decoder::get_signal_spec(message_spec, signal_name)
-            .ok_or_else(|| anyhow!("unknown signal: {}", signal_name))?;
-        let layout = SignalLayout::from_spec(spec);
-        let raw = compute_raw_value(*physical_value, spec);
-        layout.pack(&mut frame.data, raw);
+        builder = builder.set(signal_name, *physical_value)?;
+    }
+    Ok(builder.build_with_report())
+}
+
+/// Same as `encode_message`, with the extended multiplexing (`SG_MUL_VAL_`) rules
+/// of the DBC. Every multiplexer up the tree of the given signals is set.
+pub fn encode_message_with_mux(
+    message_spec: &can_dbc::Message,
+    signals: &[(&str, f64)],
+    extended_multiplex: &[can_dbc::ExtendedMultiplex],
+) -> Result<CanFrame> {
+    let mut builder = CanFrameBuilder::new(message_spec).extended_multiplex(extended_multiplex);
+    for (signal_name, physical_value) in signals {
+        builder = builder.set(signal_name, *physical_value)?;
+    }
+    Ok(builder.build())
+}
+
+/// Value given to `CanFrameBuilder::set`: a physical value or an enumeration label
+/// from the DBC value descriptions (VAL_ / VAL_TABLE_).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignalValue<'v> {
+    Physical(f64),
+    Label(&'v str),
+}
+
+impl From<f64> for SignalValue<'_> {
+    fn from(value: f64) -> Self {
+        SignalValue::Physical(value)
+    }
+}
+
+impl<'v> From<&'v str> for SignalValue<'v> {
+    fn from(label: &'v str) -> Self {
+        SignalValue::Label(label)
     }
+}
 
-    Ok(frame)
+/// Same as `encode_message`, with everything the DBC defines about the message:
+/// extended multiplexing and float value types (SIG_VALTYPE_).
+pub fn encode_message_with_dbc(
+    message_spec: &can_dbc::Message,
+    signals: &[(&str, f64)],
+    dbc: &can_dbc::Dbc,
+) -> Result<CanFrame> {
+    let mut builder = CanFrameBuilder::new(message_spec).dbc(dbc);
+    for (signal_name, physical_value) in signals {
+        builder = builder.set(signal_name, *physical_value)?;
+    }
+    Ok(builder.build())
 }
 
 /// Builder for constructing encoded CAN frames signal-by-signal.
@@ -92,6 +275,29 @@ pub fn encode_message(
 pub struct CanFrameBuilder<'a> {
     message_spec: &'a can_dbc::Message,
     frame: CanFrame,
+    dbc: Option<&'a can_dbc::Dbc>,
+    extended_multiplex: &'a [can_dbc::ExtendedMultiplex],
+    // Multiplexer values required by the signals set so far
+    mux_values: Vec<(&'a str, u64)>,
+    // Precomputed layouts of the message signals, in the same order
+    layouts: Option<&'a [SignalLayout]>,
+    // 29-bit ID, from the DBC ID or VFrameFormat
+    is_extended: bool,
+    // J1939 ID fields applied by build() to extended IDs
+    source_address: Option<u8>,
+    priority: Option<u8>,
+    destination_address: Option<u8>,
+    // Counters and checksums filled in by build()
+    protection: Option<&'a mut Protection>,
+    // Names of the signals set with set()
+    set_signals: Vec<&'a str>,
+    // Started from an existing frame, whose bits are kept unless a signal is set
+    from_frame: bool,
+    range_policy: RangePolicy,
+    // Values changed by the range policy
+    clamped: Vec<ClampedValue>,
+    // GenSigStartValue raw values, packed by build() into the signals that were not set
+    start_values: Vec<(&'a can_dbc::Signal, u64)>,
 }
 
 impl<'a> CanFrameBuilder<'a> {
@@ -99,19 +305,238 @@ impl<'a> CanFrameBuilder<'a> {
         let mut frame = CanFrame::default();
         frame.id = get_can_id(message_spec);
         frame.len = message_spec.size as u8;
-        Self { message_spec, frame }
+        Self {
+            message_spec,
+            frame,
+            dbc: None,
+            extended_multiplex: &[],
+            mux_values: Vec::new(),
+            layouts: None,
+            is_extended: matches!(message_spec.id, can_dbc::MessageId::Extended(_)),
+            source_address: None,
+            priority: None,
+            destination_address: None,
+            protection: None,
+            set_signals: Vec::new(),
+            from_frame: false,
+            range_policy: RangePolicy::Cast,
+            clamped: Vec::new(),
+            start_values: Vec::new(),
+        }
+    }
+
+    /// Start from an existing frame of the message, e.g. to modify and resend it.
+    /// Only the signals set (and the multiplexers they need) change, every other bit,
+    /// including bits no signal owns (see `signal_layout::unowned_bits`), stays as in `frame`.
+    /// The ID, length, timestamp and channel are those of `frame`.
+    pub fn from_frame(message_spec: &'a can_dbc::Message, frame: &CanFrame) -> Self {
+        let mut builder = Self::new(message_spec);
+        builder.frame = frame.clone();
+        builder.is_extended |= frame.id > 0x7FF;
+        builder.from_frame = true;
+        builder
+    }
+
+    /// Use the extended multiplexing (`SG_MUL_VAL_`) rules of the DBC, usually `&dbc.extended_multiplex`
+    pub fn extended_multiplex(mut self, extended_multiplex: &'a [can_dbc::ExtendedMultiplex]) -> Self {
+        self.extended_multiplex = extended_multiplex;
+        self
+    }
+
+    /// Use everything the DBC of the message defines: extended multiplexing,
+    /// float value types and value descriptions, so that signals can be set by label.
+    /// Signals that are always present and not `set()` get their GenSigStartValue (a raw value)
+    /// on `build()`, whether `.dbc()` is called before or after `.set()`.
+    /// VFrameFormat decides whether the frame is CAN FD, and an extended VFrameFormat makes
+    /// the J1939 setters (`.source_address()`, ...) apply to the ID, whatever the call order.
+    /// Builders started `from_frame` keep the values and format of the frame.
+    pub fn dbc(mut self, dbc: &'a can_dbc::Dbc) -> Self {
+        self.dbc = Some(dbc);
+        self.extended_multiplex = &dbc.extended_multiplex;
+        if self.from_frame {
+            return self;
+        }
+        if let Some(format) = dbc_attributes::frame_format(dbc, self.message_spec) {
+            self.frame.is_fd = format.is_fd();
+            self.is_extended |= format.is_extended();
+        }
+        let message_spec = self.message_spec;
+        for spec in &message_spec.signals {
+            if can_decoder::mux_condition(message_spec, spec, self.extended_multiplex).is_some() {
+                continue;
+            }
+            let Some(start_value) = dbc_attributes::start_value(dbc, message_spec, spec).filter(|v| *v != 0.0) else {
+                continue;
+            };
+            let raw = match can_decoder::get_value_type(dbc, message_spec, spec) {
+                can_dbc::SignalExtendedValueType::SignedOrUnsignedInteger if spec.size >= 64 => start_value as i64 as u64,
+                can_dbc::SignalExtendedValueType::SignedOrUnsignedInteger => {
+                    (start_value as i64 as u64) & ((1u64 << spec.size) - 1)
+                }
+                can_dbc::SignalExtendedValueType::IEEEfloat32Bit => (start_value as f32).to_bits() as u64,
+                can_dbc::SignalExtendedValueType::IEEEdouble64bit => start_value.to_bits(),
+            };
+            self.start_values.push((spec, raw));
+        }
+        self
+    }
+
+    /// Use precomputed layouts of the message signals (same order as `message_spec.signals`)
+    /// instead of building them on every `set()`
+    pub fn layouts(mut self, layouts: &'a [SignalLayout]) -> Self {
+        self.layouts = Some(layouts);
+        self
     }
 
-    /// Set a signal by name. Returns Err if the signal name is not found.
-    pub fn set(mut self, signal_name: &str, physical_value: f64) -> Result<Self> {
+    /// Fill in the counter and checksum signals of the message declared in `protection` on `build()`.
+    /// The counter follows the previous frame built with it, unless set explicitly.
+    pub fn protection(mut self, protection: &'a mut Protection) -> Self {
+        self.protection = Some(protection);
+        self
+    }
+
+    /// Choose what `set()` does with physical values that don't fit their signal,
+    /// `RangePolicy::Cast` by default. Labels are never changed.
+    pub fn range_policy(mut self, policy: RangePolicy) -> Self {
+        self.range_policy = policy;
+        self
+    }
+
+    /// Values set so far that were changed by the range policy
+    pub fn clamped(&self) -> &[ClampedValue] {
+        &self.clamped
+    }
+
+    /// Set a signal by name, to a physical value or a label (e.g. `.set("PRNDL", "D")`).
+    /// Returns Err if the signal name is not found, the label is unknown, or the value
+    /// doesn't fit with `RangePolicy::Error`.
+    /// Labels are looked up in the DBC given with `.dbc()`.
+    ///
+    /// Setting a multiplexed signal also sets the multiplexers above it to select it,
+    /// to the start of the range when not set yet; set a multiplexer first to choose its value.
+    /// Returns Err if the signal is on a different page than the signals already set.
+    pub fn set<'v>(mut self, signal_name: &str, value: impl Into<SignalValue<'v>>) -> Result<Self> {
         let spec = can_decoder::get_signal_spec(self.message_spec, signal_name)
             .ok_or_else(|| anyhow!("unknown signal: {}", signal_name))?;
-        let layout = SignalLayout::from_spec(spec);
-        let raw = compute_raw_value(physical_value, spec);
-        layout.pack(&mut self.frame.data, raw);
+        let raw = match value.into() {
+            SignalValue::Physical(physical_value) => {
+                let value_type = match self.dbc {
+                    Some(dbc) => can_decoder::get_value_type(dbc, self.message_spec, spec),
+                    None => can_dbc::SignalExtendedValueType::SignedOrUnsignedInteger,
+                };
+                let (raw, changed) =
+                    compute_typed_raw_value_with_policy(physical_value, spec, value_type, self.range_policy)?;
+                if changed {
+                    self.clamped.push(ClampedValue {
+                        signal: spec.name.clone(),
+                        requested: physical_value,
+                        // Empty signals always decode to their offset
+                        encoded: match spec.size {
+                            0 => spec.offset,
+                            _ => can_decoder::compute_typed_signal_value(raw, spec, value_type),
+                        },
+                    });
+                }
+                raw
+            }
+            SignalValue::Label(label) => {
+                let dbc = self
+                    .dbc
+                    .ok_or_else(|| anyhow!("can't set {} to label {}: builder has no dbc", signal_name, label))?;
+                let raw = can_decoder::label_raw_value(dbc, self.message_spec, spec, label)
+                    .ok_or_else(|| anyhow!("unknown label {} for signal {}", label, signal_name))?;
+                // Two's complement truncated to the signal size, like compute_raw_value
+                if spec.size >= 64 {
+                    raw as u64
+                } else {
+                    (raw as u64) & ((1u64 << spec.size) - 1)
+                }
+            }
+        };
+        if matches!(
+            spec.multiplexer_indicator,
+            can_dbc::MultiplexIndicator::Multiplexor | can_dbc::MultiplexIndicator::MultiplexorAndMultiplexedSignal(_)
+        ) || self.extended_multiplex.iter().any(|e| e.multiplexor_signal_name == spec.name)
+        {
+            self.lock_mux_value(spec, raw)?;
+        }
+        self.select_signal(spec)?;
+        self.pack(spec, raw);
+        self.set_signals.push(spec.name.as_str());
         Ok(self)
     }
 
+    /// Set the multiplexers up the tree of a signal so that it is present in the frame
+    fn select_signal(&mut self, spec: &'a can_dbc::Signal) -> Result<()> {
+        let mut spec = spec;
+        for _ in 0..=self.message_spec.signals.len() {
+            let Some(condition) = can_decoder::mux_condition(self.message_spec, spec, self.extended_multiplex) else {
+                return Ok(());
+            };
+            // Keep the value already required by other signals if it selects this one too
+            let raw = match self.mux_values.iter().find(|(name, _)| *name == condition.multiplexor.name) {
+                Some((_, current)) if condition.matches(*current) => *current,
+                Some((_, current)) => {
+                    return Err(anyhow!(
+                        "signal {} needs multiplexer {} in {:?}, but the frame already has {} = {}",
+                        spec.name,
+                        condition.multiplexor.name,
+                        condition.ranges,
+                        condition.multiplexor.name,
+                        current
+                    ))
+                }
+                // A frame we started from keeps its value when it selects this signal
+                None if self.from_frame && condition.matches(self.extract(condition.multiplexor)) => {
+                    let raw = self.extract(condition.multiplexor);
+                    self.mux_values.push((condition.multiplexor.name.as_str(), raw));
+                    raw
+                }
+                None => {
+                    let raw = condition.ranges.first().map_or(0, |range| *range.start());
+                    self.mux_values.push((condition.multiplexor.name.as_str(), raw));
+                    raw
+                }
+            };
+            self.pack(condition.multiplexor, raw);
+            spec = condition.multiplexor;
+        }
+        Err(anyhow!("multiplexer loop in message {}", self.message_spec.name))
+    }
+
+    /// Pack a raw value with the precomputed layout of the signal if there is one
+    fn pack(&mut self, spec: &can_dbc::Signal, raw: u64) {
+        let cached = self.layouts.and_then(|layouts| {
+            let index = self.message_spec.signals.iter().position(|s| std::ptr::eq(s, spec))?;
+            layouts.get(index)
+        });
+        match cached {
+            Some(layout) => layout.pack(&mut self.frame.data, raw),
+            None => SignalLayout::from_spec(spec).pack(&mut self.frame.data, raw),
+        }
+    }
+
+    fn extract(&self, spec: &can_dbc::Signal) -> u64 {
+        SignalLayout::from_spec(spec).extract(&self.frame.data)
+    }
+
+    /// Set a multiplexer directly, Err if other signals of the frame need another value
+    fn lock_mux_value(&mut self, spec: &'a can_dbc::Signal, raw: u64) -> Result<()> {
+        match self.mux_values.iter().find(|(name, _)| *name == spec.name) {
+            Some((_, current)) if *current != raw => Err(anyhow!(
+                "multiplexer {} is already set to {} by other signals of the frame, can't set it to {}",
+                spec.name,
+                current,
+                raw
+            )),
+            Some(_) => Ok(()),
+            None => {
+                self.mux_values.push((spec.name.as_str(), raw));
+                Ok(())
+            }
+        }
+    }
+
     pub fn timestamp(mut self, ts: f64) -> Self {
         self.frame.timestamp = ts;
         self
@@ -122,8 +547,68 @@ impl<'a> CanFrameBuilder<'a> {
         self
     }
 
+    /// Send a J1939 message from another source address than the DBC ID.
+    /// No effect on standard ID messages (see `.dbc()` for VFrameFormat).
+    pub fn source_address(mut self, source_address: u8) -> Self {
+        self.source_address = Some(source_address);
+        self
+    }
+
+    /// Send a J1939 message with another priority (0 to 7) than the DBC ID.
+    /// No effect on standard ID messages.
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Send a destination specific (PDU1) J1939 message to another address than the DBC ID.
+    /// No effect on PDU2 or standard ID messages.
+    pub fn destination_address(mut self, destination_address: u8) -> Self {
+        self.destination_address = Some(destination_address);
+        self
+    }
+
+    /// Apply the J1939 setters, once `.dbc()` had its say on the ID format
+    fn apply_j1939_overrides(&mut self) {
+        if !self.is_extended {
+            return;
+        }
+        let mut id = J1939Id::from_can_id(self.frame.id);
+        if let Some(source_address) = self.source_address {
+            id = id.with_source_address(source_address);
+        }
+        if let Some(priority) = self.priority {
+            id = id.with_priority(priority);
+        }
+        if let Some(destination_address) = self.destination_address {
+            id = id.with_destination_address(destination_address);
+        }
+        self.frame.id = id.to_can_id();
+    }
+
+    /// Consume the builder and produce the finished frame, with the values that were
+    /// changed by the range policy.
+    pub fn build_with_report(mut self) -> (CanFrame, Vec<ClampedValue>) {
+        let clamped = std::mem::take(&mut self.clamped);
+        (self.build(), clamped)
+    }
+
     /// Consume the builder and produce the finished frame.
-    pub fn build(self) -> CanFrame {
+    pub fn build(mut self) -> CanFrame {
+        self.apply_j1939_overrides();
+        for (spec, raw) in std::mem::take(&mut self.start_values) {
+            let name = spec.name.as_str();
+            if !self.set_signals.contains(&name) && !self.mux_values.iter().any(|(mux, _)| *mux == name) {
+                self.pack(spec, raw);
+            }
+        }
+        if let Some(protection) = self.protection.take() {
+            let counter_set = protection
+                .message(&self.message_spec.name)
+                .and_then(|message| message.counter.as_deref())
+                .is_some_and(|counter| self.set_signals.contains(&counter));
+            protection.protect(&mut self.frame, self.message_spec, counter_set);
+        }
         self.frame
     }
 }
@@ -176,6 +661,76 @@ mod tests {
         assert_eq!(raw, 1);
     }
 
+    #[test]
+    fn test_compute_raw_range_policies() {
+        // AverageRadius: factor=0.1, unsigned 6-bit (raw 0..63), DBC [0|5] (raw 0..50)
+        let dbc = can_decoder::load_dbc("motohawk.dbc").unwrap();
+        let msg = can_decoder::get_message_spec(&dbc, "ExampleMessage").unwrap();
+        let signal = can_decoder::get_signal_spec(msg, "AverageRadius").unwrap();
+
+        // (physical, policy, expected raw and changed, None for Err)
+        type Case = (f64, RangePolicy, Option<(u64, bool)>);
+        let cases: &[Case] = &[
+            (4.0, RangePolicy::Error, Some((40, false))),
+            (5.5, RangePolicy::Error, None),
+            (7.0, RangePolicy::Wrap, Some((6, true))),
+            (-0.1, RangePolicy::Wrap, Some((63, true))),
+            (5.5, RangePolicy::Wrap, Some((55, false))),
+            (7.0, RangePolicy::SaturateRaw, Some((63, true))),
+            (-0.1, RangePolicy::SaturateRaw, Some((0, true))),
+            (5.5, RangePolicy::SaturateDbc, Some((50, true))),
+            (-3.0, RangePolicy::SaturateDbc, Some((0, true))),
+            (7.0, RangePolicy::Cast, Some((6, true))),
+            (-0.1, RangePolicy::Cast, Some((0, true))),
+            (5.5, RangePolicy::Cast, Some((55, false))),
+            (f64::NAN, RangePolicy::Wrap, None),
+        ];
+        for (physical, policy, expected) in cases {
+            let result = compute_raw_value_with_policy(*physical, signal, *policy).ok();
+            assert_eq!(result, *expected, "{physical} with {policy:?}");
+        }
+        assert_eq!(compute_raw_value(7.0, signal), 6);
+        // Negative values of unsigned signals saturate unless wrapping is asked for
+        assert_eq!(compute_raw_value(-0.1, signal), 0);
+        let layout = SignalLayout::from_spec(signal);
+        let frame = CanFrameBuilder::new(msg).set("AverageRadius", -0.1).unwrap().build();
+        assert_eq!(layout.extract(&frame.data), 0);
+        let builder = CanFrameBuilder::new(msg).range_policy(RangePolicy::Wrap);
+        assert_eq!(layout.extract(&builder.set("AverageRadius", -0.1).unwrap().build().data), 63);
+
+        // Temperature: signed 12-bit, raw -2048..2047
+        let signal = can_decoder::get_signal_spec(msg, "Temperature").unwrap();
+        assert_eq!(compute_raw_value_with_policy(300.0, signal, RangePolicy::SaturateRaw).unwrap(), (0x7FF, true));
+        assert_eq!(compute_raw_value_with_policy(200.0, signal, RangePolicy::SaturateRaw).unwrap(), (0x800, true));
+
+        // Enable has no DBC range ([0|0]), only the raw range applies
+        let signal = can_decoder::get_signal_spec(msg, "Enable").unwrap();
+        assert_eq!(compute_raw_value_with_policy(1.0, signal, RangePolicy::Error).unwrap(), (1, false));
+        assert_eq!(compute_raw_value_with_policy(2.0, signal, RangePolicy::SaturateDbc).unwrap(), (1, true));
+        assert!(compute_raw_value_with_policy(2.0, signal, RangePolicy::Error).is_err());
+    }
+
+    #[test]
+    fn test_compute_raw_empty_signed_signal() {
+        // can-dbc accepts 0-bit signals, they only ever hold 0
+        let dbc = can_decoder::dbc_from_str(concat!(
+            "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: Ecu\n\n",
+            "BO_ 100 Empty: 8 Ecu\n",
+            " SG_ Nothing : 0|0@1- (1,0) [0|0] \"\" Vector__XXX\n",
+            " SG_ Value : 8|8@1+ (1,0) [0|255] \"\" Vector__XXX\n",
+        ))
+        .unwrap();
+        let msg = can_decoder::get_message_spec(&dbc, "Empty").unwrap();
+        let signal = can_decoder::get_signal_spec(msg, "Nothing").unwrap();
+        assert_eq!(compute_raw_value_with_policy(0.0, signal, RangePolicy::Error).unwrap(), (0, false));
+        assert_eq!(compute_raw_value_with_policy(3.0, signal, RangePolicy::SaturateRaw).unwrap(), (0, true));
+        assert!(compute_raw_value_with_policy(3.0, signal, RangePolicy::Error).is_err());
+
+        let (frame, clamped) = encode_message_with_policy(msg, &[("Nothing", 3.0), ("Value", 7.0)], RangePolicy::Wrap).unwrap();
+        assert_eq!(frame.data[..2], [0, 7]);
+        assert_eq!(clamped, [ClampedValue { signal: "Nothing".into(), requested: 3.0, encoded: 0.0 }]);
+    }
+
     // ---------------------------------------------------------------
     // encode_message tests
     // ---------------------------------------------------------------
@@ -190,7 +745,8 @@ mod tests {
         // Note: The golden frame A5B6D9... has residual bits in byte 2
         // that don't belong to any signal. Encoding from scratch leaves
         // those bits as zero, so we verify signal-level correctness
-        // rather than raw byte equality for partially-covered bytes.
+        // rather than raw byte equality for partially-covered bytes
+        // (see test_builder_from_frame for encoding onto the golden frame).
         let dbc = can_decoder::load_dbc("motohawk.dbc").unwrap();
         let msg = can_decoder::get_message_spec(&dbc, "ExampleMessage").unwrap();
 
@@ -530,4 +1086,277 @@ mod tests {
             }
         }
     }
+
+    // ---------------------------------------------------------------
+    // Multiplexed messages
+    // ---------------------------------------------------------------
+
+    #[test]
+    fn test_encode_multiplexed_sets_switch() {
+        let dbc = can_decoder::load_dbc("multiplex.dbc").unwrap();
+        let msg = can_decoder::get_message_spec(&dbc, "Diagnostics").unwrap();
+
+        let frame = encode_message(msg, &[("Status", 3.0), ("Voltage", 12.5), ("Current", -1.5)]).unwrap();
+        assert_eq!(can_decoder::multiplexor_value(&frame, msg), Some(2));
+
+        let decoded = can_decoder::decode_message(&frame, msg);
+        assert_eq!(decoded.signals, ["Page", "Status", "Voltage", "Current"]);
+        assert_eq!(decoded.values, [2.0, 3.0, 12.5, -1.5]);
+    }
+
+    #[test]
+    fn test_encode_multiplexed_rejects_other_page() {
+        let dbc = can_decoder::load_dbc("multiplex.dbc").unwrap();
+        let msg = can_decoder::get_message_spec(&dbc, "Diagnostics").unwrap();
+
+        assert!(encode_message(msg, &[("SW_Major", 1.0), ("Voltage", 12.5)]).is_err());
+        assert!(CanFrameBuilder::new(msg).set("Page", 1.0).unwrap().set("Voltage", 12.5).is_err());
+        assert!(CanFrameBuilder::new(msg).set("SW_Minor", 4.0).unwrap().set("Page", 2.0).is_err());
+
+        let frame = CanFrameBuilder::new(msg)
+            .set("Page", 1.0)
+            .unwrap()
+            .set("SW_Major", 3.0)
+            .unwrap()
+            .set("SW_Minor", 14.0)
+            .unwrap()
+            .build();
+        assert_eq!(&frame.data[..4], &[1, 0, 3, 14]);
+    }
+
+    #[test]
+    fn test_encode_extended_multiplexed() {
+        let dbc = can_decoder::load_dbc("multiplex.dbc").unwrap();
+        let msg = can_decoder::get_message_spec(&dbc, "UdsResponse").unwrap();
+        let mux = &dbc.extended_multiplex;
+
+        // Setting a leaf signal selects the whole branch, using the start of each range
+        let frame = encode_message_with_mux(msg, &[("Odometer", 1234.5)], mux).unwrap();
+        assert_eq!(&frame.data[..7], &[98, 0x02, 0x00, 0x00, 0x00, 0x30, 0x39]);
+
+        // An identifier set beforehand is kept when it is in range
+        let frame = encode_message_with_mux(msg, &[("Identifier", 677.0), ("Odometer", 1234.5)], mux).unwrap();
+        let decoded = can_decoder::decode_message_with_mux(&frame, msg, mux);
+        assert_eq!(decoded.signals, ["Service", "Identifier", "Odometer"]);
+        assert_eq!(decoded.values, [98.0, 677.0, 1234.5]);
+
+        // Signals from different branches of the tree
+        assert!(encode_message_with_mux(msg, &[("CoolantTemp", 80.0), ("Odometer", 1.0)], mux).is_err());
+        assert!(encode_message_with_mux(msg, &[("ResponseCode", 49.0), ("CoolantTemp", 80.0)], mux).is_err());
+        assert!(encode_message_with_mux(msg, &[("Identifier", 256.0), ("Odometer", 1.0)], mux).is_err());
+
+        let frame = CanFrameBuilder::new(msg)
+            .extended_multiplex(mux)
+            .set("NegativeService", 34.0)
+            .unwrap()
+            .set("ResponseCode", 49.0)
+            .unwrap()
+            .build();
+        assert_eq!(&frame.data[..3], &[0x7F, 0x22, 0x31]);
+    }
+
+    #[test]
+    fn test_builder_j1939_addresses() {
+        let dbc = can_decoder::dbc_from_str(concat!(
+            "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: Engine\n\n",
+            "BO_ 2364539904 EEC1: 8 Engine\n",
+            " SG_ EngineSpeed : 24|16@1+ (0.125,0) [0|8031.875] \"rpm\" Vector__XXX\n\n",
+            "BO_ 2348875534 TSC1: 8 Engine\n",
+            " SG_ RequestedSpeed : 8|16@1+ (0.125,0) [0|8031.875] \"rpm\" Vector__XXX\n\n",
+        ))
+        .unwrap();
+        let eec1 = can_decoder::get_message_spec(&dbc, "EEC1").unwrap();
+        let frame = CanFrameBuilder::new(eec1).source_address(0x21).priority(6).set("EngineSpeed", 100.0).unwrap().build();
+        assert_eq!(frame.id, 0x18F00421);
+        assert_eq!(frame.data[3..5], [0x20, 0x03]);
+        // PDU2 messages have no destination
+        assert_eq!(CanFrameBuilder::new(eec1).destination_address(0x17).build().id, 0x0CF00400);
+
+        let tsc1 = can_decoder::get_message_spec(&dbc, "TSC1").unwrap();
+        assert_eq!(CanFrameBuilder::new(tsc1).destination_address(0x17).build().id, 0x0C00170E);
+
+        let motohawk = can_decoder::load_dbc("motohawk.dbc").unwrap();
+        let example = can_decoder::get_message_spec(&motohawk, "ExampleMessage").unwrap();
+        assert_eq!(CanFrameBuilder::new(example).source_address(0x21).build().id, 0x1F0);
+    }
+
+    #[test]
+    fn test_builder_attribute_defaults() {
+        let dbc = can_decoder::dbc_from_str(concat!(
+            "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: Engine\n\n",
+            "BO_ 100 Cyclic: 8 Engine\n",
+            " SG_ Counter : 0|4@1+ (1,0) [0|15] \"\" Vector__XXX\n",
+            " SG_ Speed : 8|16@1+ (0.5,0) [0|1000] \"km/h\" Vector__XXX\n",
+            " SG_ Offset : 24|8@1- (1,0) [-128|127] \"\" Vector__XXX\n\n",
+            "BO_ 2147484648 Fd: 16 Engine\n",
+            " SG_ Value : 0|8@1+ (1,0) [0|255] \"\" Vector__XXX\n\n",
+            "BO_ 1001 Extended: 8 Engine\n\n",
+            "BA_DEF_ BO_  \"VFrameFormat\" ENUM  \"StandardCAN\",\"ExtendedCAN\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"StandardCAN_FD\",\"ExtendedCAN_FD\";\n",
+            "BA_DEF_ SG_  \"GenSigStartValue\" INT -1000 100000;\n",
+            "BA_DEF_DEF_  \"VFrameFormat\" \"StandardCAN\";\n",
+            "BA_DEF_DEF_  \"GenSigStartValue\" 0;\n",
+            "BA_ \"VFrameFormat\" BO_ 2147484648 15;\n",
+            "BA_ \"VFrameFormat\" BO_ 1001 1;\n",
+            "BA_ \"GenSigStartValue\" SG_ 100 Speed 200;\n",
+            "BA_ \"GenSigStartValue\" SG_ 100 Offset -2;\n",
+        ))
+        .unwrap();
+        let cyclic = can_decoder::get_message_spec(&dbc, "Cyclic").unwrap();
+
+        // Start values are raw values, and only apply with the DBC
+        let frame = CanFrameBuilder::new(cyclic).dbc(&dbc).set("Counter", 3.0).unwrap().build();
+        assert_eq!(frame.data[..4], [3, 200, 0, 0xFE]);
+        assert!(!frame.is_fd);
+        let frame = CanFrameBuilder::new(cyclic).dbc(&dbc).set("Speed", 50.0).unwrap().build();
+        assert_eq!(frame.data[..4], [0, 100, 0, 0xFE]);
+        assert_eq!(CanFrameBuilder::new(cyclic).build().data[..4], [0, 0, 0, 0]);
+        // Values set before the DBC is given are kept
+        let frame = CanFrameBuilder::new(cyclic).set("Speed", 10.0).unwrap().dbc(&dbc).build();
+        assert_eq!(frame.data[..4], [0, 20, 0, 0xFE]);
+
+        let fd = can_decoder::get_message_spec(&dbc, "Fd").unwrap();
+        let frame = CanFrameBuilder::new(fd).dbc(&dbc).source_address(0x21).build();
+        assert!(frame.is_fd);
+        assert_eq!(frame.len, 16);
+        assert_eq!(frame.id, 0x321);
+        // A standard DBC ID with an extended VFrameFormat, the J1939 setters wait for the DBC
+        let extended = can_decoder::get_message_spec(&dbc, "Extended").unwrap();
+        assert_eq!(CanFrameBuilder::new(extended).source_address(0x21).dbc(&dbc).build().id, 0x321);
+        assert_eq!(CanFrameBuilder::new(extended).dbc(&dbc).source_address(0x21).build().id, 0x321);
+        assert_eq!(CanFrameBuilder::new(extended).source_address(0x21).build().id, 0x3E9);
+    }
+
+    #[test]
+    fn test_builder_set_label() {
+        let dbc = can_decoder::load_dbc("can_samples/chrysler_cusw.dbc").unwrap();
+        let gear = can_decoder::get_message_spec(&dbc, "GEAR").unwrap();
+
+        let frame = CanFrameBuilder::new(gear).dbc(&dbc).set("PRNDL", "D").unwrap().build();
+        let by_value = CanFrameBuilder::new(gear).set("PRNDL", 4.0).unwrap().build();
+        assert_eq!(frame.data, by_value.data);
+        assert_eq!(frame.data[1], 0x04);
+
+        assert!(CanFrameBuilder::new(gear).dbc(&dbc).set("PRNDL", "Q").is_err());
+        // No DBC to look the label up in
+        assert!(CanFrameBuilder::new(gear).set("PRNDL", "D").is_err());
+
+        // Labels of a switch select the page, VAL_TABLE_ labels work as well
+        let dbc = can_decoder::load_dbc("multiplex.dbc").unwrap();
+        let diagnostics = can_decoder::get_message_spec(&dbc, "Diagnostics").unwrap();
+        let frame = CanFrameBuilder::new(diagnostics)
+            .dbc(&dbc)
+            .set("Page", "Supply")
+            .unwrap()
+            .set("Status", "Error")
+            .unwrap()
+            .set("Voltage", 12.5)
+            .unwrap()
+            .build();
+        assert_eq!(&frame.data[..4], &[0x02, 0x0F, 0xD4, 0x30]);
+        assert!(CanFrameBuilder::new(diagnostics).dbc(&dbc).set("Page", "Version").unwrap().set("Voltage", 1.0).is_err());
+    }
+
+    #[test]
+    fn test_encode_float_signals() {
+        let dbc = can_decoder::load_dbc("floats.dbc").unwrap();
+        // (message, signals, expected frame)
+        type Case<'a> = (&'a str, &'a [(&'a str, f64)], &'a str);
+        let cases: &[Case] = &[
+            ("FloatsLE", &[("Speed", 12.5), ("Gain", 3.5)], "(0.0) vcan0 300#00004841000050C0"),
+            ("FloatsBE", &[("Pressure", 101.325), ("Ratio", -0.125)], "(0.0) vcan0 301#42CAA666BE000000"),
+            ("DoubleLE", &[("Position", 1234.5678)], "(0.0) vcan0 302#ADFA5C6D454A9340"),
+            ("DoubleBE", &[("Altitude", -8848.86)], "(0.0) vcan0 303#C0C1486E147AE148"),
+        ];
+        for (msg_name, signals, line) in cases {
+            let msg = can_decoder::get_message_spec(&dbc, msg_name).unwrap();
+            let expected = canlog_reader::parse_candump_line(line).unwrap();
+            let frame = encode_message_with_dbc(msg, signals, &dbc).unwrap();
+            assert_eq!(&frame.data[..8], &expected.data[..8], "{msg_name}");
+        }
+
+        // Float values are not rounded to integers
+        let msg = can_decoder::get_message_spec(&dbc, "FloatsLE").unwrap();
+        let speed = can_decoder::get_signal_spec(msg, "Speed").unwrap();
+        let raw = compute_typed_raw_value(0.25, speed, can_dbc::SignalExtendedValueType::IEEEfloat32Bit);
+        assert_eq!(raw, 0.25f32.to_bits() as u64);
+        assert_eq!(compute_typed_raw_value(0.25, speed, can_dbc::SignalExtendedValueType::SignedOrUnsignedInteger), 0);
+    }
+
+    #[test]
+    fn test_builder_from_frame() {
+        // Residual bits of the golden frame survive, only Enable changes
+        let golden = canlog_reader::parse_candump_line("(1.5) vcan1 1F0#A5B6D90000000000").unwrap();
+        let dbc = can_decoder::load_dbc("motohawk.dbc").unwrap();
+        let msg = can_decoder::get_message_spec(&dbc, "ExampleMessage").unwrap();
+        let frame = CanFrameBuilder::from_frame(msg, &golden).dbc(&dbc).set("Enable", 0.0).unwrap().build();
+        assert_eq!(frame.data[..8], [0x25, 0xB6, 0xD9, 0, 0, 0, 0, 0]);
+        assert_eq!((frame.timestamp, frame.channel.as_str()), (1.5, "vcan1"));
+
+        // Setting every signal to its decoded value gives the same bytes back
+        let mut builder = CanFrameBuilder::from_frame(msg, &golden);
+        for signal in &msg.signals {
+            builder = builder.set(&signal.name, SignalLayout::from_spec(signal).decode(&golden, signal)).unwrap();
+        }
+        assert_eq!(builder.build(), golden);
+
+        // Bits outside of the message signals are never touched
+        let noisy = canlog_reader::parse_candump_line("(0.0) vcan0 1F0#FFFFFFFFFFFFFFFF").unwrap();
+        let frame = CanFrameBuilder::from_frame(msg, &noisy).set("Temperature", 250.0).unwrap().build();
+        let unowned = crate::signal_layout::unowned_bits(msg);
+        for (i, mask) in unowned.iter().enumerate() {
+            assert_eq!(frame.data[i] & mask, *mask, "byte {i}");
+        }
+        assert_eq!(frame.data[..3], [0xFE, 0x00, 0x1F]);
+    }
+
+    #[test]
+    fn test_builder_from_frame_keeps_mux_in_range() {
+        // Identifier 677 selects Odometer like the start of its range would, so it stays
+        let dbc = can_decoder::load_dbc("multiplex.dbc").unwrap();
+        let msg = can_decoder::get_message_spec(&dbc, "UdsResponse").unwrap();
+        let base = encode_message_with_mux(msg, &[("Identifier", 677.0), ("Odometer", 1.0)], &dbc.extended_multiplex).unwrap();
+        let frame = CanFrameBuilder::from_frame(msg, &base).dbc(&dbc).set("Odometer", 1234.5).unwrap().build();
+        let decoded = can_decoder::decode_message_with_mux(&frame, msg, &dbc.extended_multiplex);
+        assert_eq!(decoded.values, [98.0, 677.0, 1234.5]);
+        assert_eq!(frame.data[..3], base.data[..3]);
+    }
+
+    #[test]
+    fn test_encode_range_report() {
+        let dbc = can_decoder::load_dbc("motohawk.dbc").unwrap();
+        let msg = can_decoder::get_message_spec(&dbc, "ExampleMessage").unwrap();
+        let signals = [("AverageRadius", 7.0), ("Enable", 1.0), ("Temperature", 244.14)];
+
+        let (frame, clamped) = encode_message_with_policy(msg, &signals, RangePolicy::SaturateDbc).unwrap();
+        assert_eq!(clamped, [ClampedValue { signal: "AverageRadius".into(), requested: 7.0, encoded: 5.0 }]);
+        let radius = can_decoder::get_signal_spec(msg, "AverageRadius").unwrap();
+        assert_eq!(SignalLayout::from_spec(radius).decode(&frame, radius), 5.0);
+
+        let (_, clamped) = encode_message_with_policy(msg, &signals, RangePolicy::Wrap).unwrap();
+        assert_eq!(clamped.len(), 1);
+        assert!((clamped[0].encoded - 0.6).abs() < 1e-9);
+        assert!(encode_message_with_policy(msg, &signals, RangePolicy::Error).is_err());
+
+        let builder = CanFrameBuilder::new(msg).range_policy(RangePolicy::Error);
+        assert!(builder.set("AverageRadius", 5.5).is_err());
+
+        // Float signals are checked against the DBC range only
+        let dbc = can_decoder::dbc_from_str(concat!(
+            "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: Imu\n\n",
+            "BO_ 768 Floats: 8 Imu\n",
+            " SG_ Speed : 0|32@1- (1,0) [-100|100] \"m/s\" Vector__XXX\n\n",
+            "SIG_VALTYPE_ 768 Speed : 1;\n",
+        ))
+        .unwrap();
+        let floats = can_decoder::get_message_spec(&dbc, "Floats").unwrap();
+        let builder = CanFrameBuilder::new(floats).dbc(&dbc).range_policy(RangePolicy::SaturateDbc);
+        let builder = builder.set("Speed", 150.5).unwrap();
+        assert_eq!(builder.clamped()[0].encoded, 100.0);
+        assert_eq!(builder.build().data[..4], 100.0f32.to_le_bytes());
+        let builder = CanFrameBuilder::new(floats).dbc(&dbc).range_policy(RangePolicy::Error);
+        assert!(builder.set("Speed", -150.0).is_err());
+        let builder = CanFrameBuilder::new(floats).dbc(&dbc).range_policy(RangePolicy::SaturateRaw);
+        let (_, clamped) = builder.set("Speed", 150.5).unwrap().build_with_report();
+        assert!(clamped.is_empty());
+    }
 }
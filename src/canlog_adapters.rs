@@ -0,0 +1,424 @@
+/*!
+ * Composable CanWriter adapters: tee to several writers, filter, map and buffer frames.
+ *
+ * ```
+ * use rocketcan::canlog_adapters::{self, CanWriterExt, TeeWriter};
+ * use rocketcan::canlog_writer::CandumpWriter;
+ *
+ * let raw = CandumpWriter::from_writer(std::io::sink());
+ * let powertrain = CandumpWriter::from_writer(std::io::sink())
+ *     .filter(canlog_adapters::id_ranges(&[0x100..=0x1FF, 0x400..=0x41F]));
+ * let mut tee = TeeWriter::new().with(raw).with(powertrain);
+ * ```
+ */
+
+use std::collections::VecDeque;
+use std::io;
+use std::ops::{RangeBounds, RangeInclusive};
+
+use crate::canlog_reader::CanFrame;
+use crate::canlog_writer::CanWriter;
+
+/// Write every frame to all inner writers.
+///
+/// A failing writer does not stop the frame from reaching the others,
+/// the first error is returned once all writers were tried.
+#[derive(Default)]
+pub struct TeeWriter {
+    writers: Vec<Box<dyn CanWriter>>,
+}
+
+impl TeeWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a writer, consuming-self so tees can be built in one expression
+    pub fn with<W: CanWriter + 'static>(mut self, writer: W) -> Self {
+        self.writers.push(Box::new(writer));
+        self
+    }
+
+    pub fn push(&mut self, writer: Box<dyn CanWriter>) {
+        self.writers.push(writer);
+    }
+
+    pub fn len(&self) -> usize {
+        self.writers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writers.is_empty()
+    }
+}
+
+impl CanWriter for TeeWriter {
+    fn write(&mut self, frame: &CanFrame) -> io::Result<()> {
+        let mut result = Ok(());
+        for writer in self.writers.iter_mut() {
+            if let Err(e) = writer.write(frame) {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+        for writer in self.writers.iter_mut() {
+            if let Err(e) = writer.flush() {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+}
+
+/// Only pass frames for which `predicate` returns true to the inner writer
+pub struct FilterWriter<W, F> {
+    inner: W,
+    predicate: F,
+}
+
+impl<W, F> FilterWriter<W, F> {
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: CanWriter, F: FnMut(&CanFrame) -> bool> CanWriter for FilterWriter<W, F> {
+    fn write(&mut self, frame: &CanFrame) -> io::Result<()> {
+        if (self.predicate)(frame) {
+            self.inner.write(frame)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Transform each frame before it reaches the inner writer
+pub struct MapWriter<W, F> {
+    inner: W,
+    map: F,
+}
+
+impl<W, F> MapWriter<W, F> {
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: CanWriter, F: FnMut(CanFrame) -> CanFrame> CanWriter for MapWriter<W, F> {
+    fn write(&mut self, frame: &CanFrame) -> io::Result<()> {
+        let mapped = (self.map)(frame.clone());
+        self.inner.write(&mapped)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// What a `BufferedFrameWriter` does with a new frame when its buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the oldest buffered frame to make room
+    DropOldest,
+    /// Discard the incoming frame
+    DropNewest,
+    /// Write the buffered frames through to the inner writer
+    FlushWhenFull,
+}
+
+/// Hold up to `capacity` frames in memory, handing them to the inner writer on `flush`.
+///
+/// Meant to sit in front of writers that can fall behind (e.g. a CAN device):
+/// if the inner writer fails, the frame that failed stays at the front of the buffer.
+pub struct BufferedFrameWriter<W> {
+    inner: W,
+    buffer: VecDeque<CanFrame>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: u64,
+}
+
+impl<W: CanWriter> BufferedFrameWriter<W> {
+    pub fn new(inner: W, capacity: usize, policy: OverflowPolicy) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            inner,
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+            policy,
+            dropped: 0,
+        }
+    }
+
+    /// Number of frames discarded due to overflow
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Number of frames waiting to be written
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Hand buffered frames to the inner writer, oldest first
+    fn drain(&mut self) -> io::Result<()> {
+        while let Some(frame) = self.buffer.front() {
+            self.inner.write(frame)?;
+            self.buffer.pop_front();
+        }
+        Ok(())
+    }
+}
+
+impl<W: CanWriter> CanWriter for BufferedFrameWriter<W> {
+    fn write(&mut self, frame: &CanFrame) -> io::Result<()> {
+        if self.buffer.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    self.buffer.pop_front();
+                    self.dropped += 1;
+                }
+                OverflowPolicy::DropNewest => {
+                    self.dropped += 1;
+                    return Ok(());
+                }
+                OverflowPolicy::FlushWhenFull => self.drain()?,
+            }
+        }
+        self.buffer.push_back(frame.clone());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.drain()?;
+        self.inner.flush()
+    }
+}
+
+/// Collect frames in memory
+impl CanWriter for Vec<CanFrame> {
+    fn write(&mut self, frame: &CanFrame) -> io::Result<()> {
+        self.push(frame.clone());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Adapter methods available on every `CanWriter`
+pub trait CanWriterExt: CanWriter + Sized {
+    /// Only write frames for which `predicate` returns true
+    fn filter<F: FnMut(&CanFrame) -> bool>(self, predicate: F) -> FilterWriter<Self, F> {
+        FilterWriter { inner: self, predicate }
+    }
+
+    /// Transform frames before writing them
+    fn map<F: FnMut(CanFrame) -> CanFrame>(self, map: F) -> MapWriter<Self, F> {
+        MapWriter { inner: self, map }
+    }
+
+    /// Buffer up to `capacity` frames until flushed
+    fn buffered(self, capacity: usize, policy: OverflowPolicy) -> BufferedFrameWriter<Self> {
+        BufferedFrameWriter::new(self, capacity, policy)
+    }
+}
+
+impl<W: CanWriter> CanWriterExt for W {}
+
+// Common predicates for `CanWriterExt::filter`
+
+/// Frames whose ID is inside `range`
+pub fn id_in<R: RangeBounds<u32>>(range: R) -> impl Fn(&CanFrame) -> bool {
+    move |frame| range.contains(&frame.id)
+}
+
+/// Frames whose ID is inside any of `ranges`
+pub fn id_ranges(ranges: &[RangeInclusive<u32>]) -> impl Fn(&CanFrame) -> bool {
+    let ranges = ranges.to_vec();
+    move |frame| ranges.iter().any(|range| range.contains(&frame.id))
+}
+
+/// Frames seen on `channel`
+pub fn channel_is(channel: &str) -> impl Fn(&CanFrame) -> bool {
+    let channel = channel.to_owned();
+    move |frame| frame.channel == channel
+}
+
+/// CAN FD frames only
+pub fn fd_only() -> impl Fn(&CanFrame) -> bool {
+    |frame| frame.is_fd
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use tempfile::NamedTempFile;
+
+    use super::*;
+    use crate::canlog_reader::{self, CanLogParser};
+    use crate::canlog_writer::CandumpWriter;
+
+    fn candump_frames() -> Vec<CanFrame> {
+        CanLogParser::from_file(Path::new("candump.log")).unwrap().collect()
+    }
+
+    /// CanWriter that fails every write
+    struct FailingWriter;
+
+    impl CanWriter for FailingWriter {
+        fn write(&mut self, _frame: &CanFrame) -> io::Result<()> {
+            Err(io::Error::other("write failed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tee_candump_with_filter() {
+        let frames = candump_frames();
+        let raw_file = NamedTempFile::new().unwrap();
+        let filtered_file = NamedTempFile::new().unwrap();
+
+        let mut tee = TeeWriter::new()
+            .with(CandumpWriter::from_path(raw_file.path()).unwrap())
+            .with(CandumpWriter::from_path(filtered_file.path()).unwrap().filter(id_in(0x100..0x200)));
+        assert_eq!(tee.len(), 2);
+        for frame in &frames {
+            tee.write(frame).unwrap();
+        }
+        tee.flush().unwrap();
+
+        let raw: Vec<_> = CanLogParser::from_file(raw_file.path()).unwrap().collect();
+        assert_eq!(frames, raw);
+
+        let filtered: Vec<_> = CanLogParser::from_file(filtered_file.path()).unwrap().collect();
+        let expected: Vec<_> = frames.iter().filter(|f| (0x100..0x200).contains(&f.id)).cloned().collect();
+        assert!(!expected.is_empty());
+        assert_eq!(expected, filtered);
+    }
+
+    #[test]
+    fn test_tee_continues_after_error() {
+        let file = NamedTempFile::new().unwrap();
+        let mut tee = TeeWriter::new()
+            .with(FailingWriter)
+            .with(CandumpWriter::from_path(file.path()).unwrap());
+        assert!(tee.write(&CanFrame::default()).is_err());
+        tee.flush().unwrap();
+        assert_eq!(fs::read_to_string(file.path()).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let mut frame = canlog_reader::parse_candump_line("(0.0) vcan1 123##41122").unwrap();
+        assert!(id_ranges(&[0x0..=0x10, 0x120..=0x130])(&frame));
+        assert!(!id_ranges(&[0x0..=0x10])(&frame));
+        assert!(channel_is("vcan1")(&frame));
+        assert!(!channel_is("vcan0")(&frame));
+        assert!(fd_only()(&frame));
+        frame.is_fd = false;
+        assert!(!fd_only()(&frame));
+    }
+
+    #[test]
+    fn test_map_writer() {
+        let frames = candump_frames();
+        let mut writer = Vec::new().map(|mut frame: CanFrame| {
+            frame.channel = "can9".to_string();
+            frame
+        });
+        for frame in &frames {
+            writer.write(frame).unwrap();
+        }
+        let mapped = writer.into_inner();
+        assert_eq!(mapped.len(), frames.len());
+        assert!(mapped.iter().all(|frame| frame.channel == "can9"));
+        assert_eq!(mapped[0].data, frames[0].data);
+    }
+
+    #[test]
+    fn test_buffered_drop_oldest() {
+        let frames = candump_frames();
+        let mut writer = Vec::new().buffered(3, OverflowPolicy::DropOldest);
+        for frame in &frames[..5] {
+            writer.write(frame).unwrap();
+        }
+        assert_eq!(writer.pending(), 3);
+        assert_eq!(writer.dropped(), 2);
+        assert!(writer.get_ref().is_empty());
+
+        writer.flush().unwrap();
+        assert_eq!(writer.get_ref(), &frames[2..5]);
+    }
+
+    #[test]
+    fn test_buffered_drop_newest_and_flush_when_full() {
+        let frames = candump_frames();
+        let mut drop_newest = Vec::new().buffered(3, OverflowPolicy::DropNewest);
+        let mut flush_when_full = Vec::new().buffered(3, OverflowPolicy::FlushWhenFull);
+        for frame in &frames[..5] {
+            drop_newest.write(frame).unwrap();
+            flush_when_full.write(frame).unwrap();
+        }
+        drop_newest.flush().unwrap();
+        assert_eq!(drop_newest.get_ref(), &frames[..3]);
+        assert_eq!(drop_newest.dropped(), 2);
+
+        // First 3 frames were written through when the 4th arrived
+        assert_eq!(flush_when_full.get_ref(), &frames[..3]);
+        flush_when_full.flush().unwrap();
+        assert_eq!(flush_when_full.get_ref(), &frames[..5]);
+        assert_eq!(flush_when_full.dropped(), 0);
+    }
+
+    #[test]
+    fn test_buffered_keeps_frames_on_error() {
+        let mut writer = FailingWriter.buffered(4, OverflowPolicy::DropOldest);
+        writer.write(&CanFrame::default()).unwrap();
+        writer.write(&CanFrame::default()).unwrap();
+        assert!(writer.flush().is_err());
+        assert_eq!(writer.pending(), 2);
+    }
+
+    #[test]
+    fn test_chained_candump_output() {
+        let frames = candump_frames();
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = CandumpWriter::from_path(file.path())
+            .unwrap()
+            .filter(id_in(..0x200))
+            .map(|mut frame: CanFrame| {
+                frame.timestamp = 0.0;
+                frame
+            });
+        for frame in &frames {
+            writer.write(frame).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let contents = fs::read_to_string(file.path()).unwrap();
+        assert!(contents.lines().all(|line| line.starts_with("(0.000000)")));
+        assert_eq!(
+            contents.lines().count(),
+            frames.iter().filter(|f| f.id < 0x200).count()
+        );
+    }
+}
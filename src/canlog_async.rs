@@ -0,0 +1,308 @@
+/*!
+ * Async adapters over the synchronous log readers and writers.
+ * AsyncBufRead -> Stream<Item = CanFrame>, CanWriter -> Sink<CanFrame>
+ *
+ * Only built with the `async` cargo feature.
+ */
+
+use std::future::Future;
+use std::io::{self, Cursor};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, Chain, Lines};
+use tokio::task::JoinHandle;
+
+use crate::canlog_format::{self, CanLogFormat, CandumpFormat, LineParser, SNIFF_LEN};
+use crate::canlog_reader::CanFrame;
+use crate::canlog_writer::CanWriter;
+
+/// Stream of CanFrames read from any line based log format over an `AsyncBufRead`.
+///
+/// Unless given explicitly, the format is sniffed from the start of the input
+/// with the `canlog_format` registry, falling back to candump. The start is read
+/// until a format recognizes its complete lines, `SNIFF_LEN` bytes or the end of the input,
+/// so short reads don't pick the wrong format.
+/// Like `CanLogParser`, lines that fail to parse are skipped and an io error
+/// ends the stream.
+pub struct AsyncCanLogReader<R> {
+    // The bytes read while sniffing are parsed again from the cursor
+    lines: Lines<Chain<Cursor<Vec<u8>>, R>>,
+    parser: Option<Box<dyn LineParser>>, // None until the format is sniffed
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncCanLogReader<R> {
+    /// Create a reader, format is detected from the log contents
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: Cursor::new(Vec::new()).chain(reader).lines(),
+            parser: None,
+        }
+    }
+
+    /// Create a reader for a known format.
+    /// Errors if the format is not line based.
+    pub fn with_format(reader: R, format: &dyn CanLogFormat) -> io::Result<Self> {
+        let parser = format.line_parser().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported, format!("{} is not a line based format", format.name()))
+        })?;
+        Ok(Self {
+            lines: Cursor::new(Vec::new()).chain(reader).lines(),
+            parser: Some(parser),
+        })
+    }
+
+    /// Read the start of the input into the cursor until the format is known
+    fn poll_sniff(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Arc<dyn CanLogFormat>>> {
+        let registry = canlog_format::registry();
+        let (head, reader) = self.lines.get_mut().get_mut();
+        let head = head.get_mut();
+        loop {
+            // Partial lines could be mistaken for another format
+            let complete = head.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+            if let Some(format) = registry.sniff(&head[..complete]) {
+                return Poll::Ready(Ok(format));
+            }
+            if head.len() >= SNIFF_LEN {
+                break;
+            }
+            let chunk = ready!(Pin::new(&mut *reader).poll_fill_buf(cx))?;
+            if chunk.is_empty() {
+                break;
+            }
+            let len = chunk.len().min(SNIFF_LEN - head.len());
+            head.extend_from_slice(&chunk[..len]);
+            Pin::new(&mut *reader).consume(len);
+        }
+        Poll::Ready(Ok(registry.sniff(head).unwrap_or_else(|| Arc::new(CandumpFormat))))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for AsyncCanLogReader<R> {
+    type Item = CanFrame;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let parser = match &mut this.parser {
+            Some(parser) => parser,
+            None => {
+                let format = match this.poll_sniff(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(format)) => format,
+                    Poll::Ready(Err(_)) => return Poll::Ready(None),
+                };
+                let Some(parser) = format.line_parser() else {
+                    return Poll::Ready(None);
+                };
+                this.parser.insert(parser)
+            }
+        };
+        loop {
+            let line = match Pin::new(&mut this.lines).poll_next_line(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(Some(line))) => line,
+                Poll::Ready(Ok(None)) | Poll::Ready(Err(_)) => return Poll::Ready(None),
+            };
+            if let Some(frame) = parser.parse_line(&line) {
+                return Poll::Ready(Some(frame));
+            }
+        }
+    }
+}
+
+/// Sink that forwards CanFrames to any synchronous `CanWriter`.
+///
+/// Frames are buffered by `start_send`. The inner writer does its (blocking) I/O on
+/// tokio's blocking thread pool on `poll_flush`/`poll_close`, or when the buffer is full,
+/// so the sink needs a tokio runtime and never blocks the task polling it.
+pub struct CanWriterSink<W> {
+    // None while a flush holds it on the blocking pool, or after it panicked
+    writer: Option<W>,
+    buffer: Vec<CanFrame>,
+    capacity: usize,
+    flushing: Option<JoinHandle<(W, io::Result<()>)>>,
+}
+
+/// Frames buffered by `CanWriterSink` before it flushes them on its own
+const DEFAULT_SINK_CAPACITY: usize = 256;
+
+impl<W: CanWriter + Send + 'static> CanWriterSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_capacity(writer, DEFAULT_SINK_CAPACITY)
+    }
+
+    /// Create a sink that buffers up to `capacity` frames between writes
+    pub fn with_capacity(writer: W, capacity: usize) -> Self {
+        Self {
+            writer: Some(writer),
+            buffer: Vec::new(),
+            capacity: capacity.max(1),
+            flushing: None,
+        }
+    }
+
+    /// The inner writer, None while a flush is in progress
+    pub fn get_ref(&self) -> Option<&W> {
+        self.writer.as_ref()
+    }
+
+    /// The inner writer, None while a flush is in progress
+    pub fn get_mut(&mut self) -> Option<&mut W> {
+        self.writer.as_mut()
+    }
+
+    /// Consume the sink and return the inner writer, None while a flush is in progress.
+    /// Frames not flushed yet are dropped.
+    pub fn into_inner(self) -> Option<W> {
+        self.writer
+    }
+}
+
+impl<W: CanWriter + Send + Unpin + 'static> Sink<CanFrame> for CanWriterSink<W> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.buffer.len() >= self.capacity {
+            return self.poll_flush(cx);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, frame: CanFrame) -> io::Result<()> {
+        self.get_mut().buffer.push(frame);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(flushing) = &mut this.flushing {
+                let joined = ready!(Pin::new(flushing).poll(cx));
+                this.flushing = None;
+                let (writer, result) = joined.map_err(io::Error::other)?;
+                this.writer = Some(writer);
+                result?;
+            }
+            // Nothing to hand to the blocking pool, the previous flush already flushed the writer
+            if this.buffer.is_empty() && this.writer.is_some() {
+                return Poll::Ready(Ok(()));
+            }
+            let Some(mut writer) = this.writer.take() else {
+                return Poll::Ready(Err(io::Error::other("writer was lost in a failed flush")));
+            };
+            let frames = std::mem::take(&mut this.buffer);
+            this.flushing = Some(tokio::task::spawn_blocking(move || {
+                let result = frames.iter().try_for_each(|frame| writer.write(frame)).and_then(|()| writer.flush());
+                (writer, result)
+            }));
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use futures_util::{FutureExt, SinkExt, StreamExt};
+    use tempfile::NamedTempFile;
+    use tokio::io::BufReader;
+
+    use super::*;
+    use crate::canlog_format::VectorAsciiFormat;
+    use crate::canlog_reader::CanLogParser;
+    use crate::canlog_writer::CandumpWriter;
+
+    async fn read_async(filename: &str) -> Vec<CanFrame> {
+        let file = tokio::fs::File::open(filename).await.unwrap();
+        AsyncCanLogReader::new(BufReader::new(file)).collect().await
+    }
+
+    #[tokio::test]
+    async fn test_async_candump_matches_sync() {
+        let filename = "candump.log";
+        let sync_frames: Vec<_> = CanLogParser::from_file(Path::new(filename)).unwrap().collect();
+        let async_frames = read_async(filename).await;
+        assert!(!async_frames.is_empty());
+        assert_eq!(sync_frames, async_frames);
+    }
+
+    #[tokio::test]
+    async fn test_async_ascii_matches_sync() {
+        for filename in ["candump.asc", "v2asc-fd-test.asc"] {
+            let sync_frames: Vec<_> = CanLogParser::from_file(Path::new(filename)).unwrap().collect();
+            let async_frames = read_async(filename).await;
+            assert!(!async_frames.is_empty(), "no frames read from {filename}");
+            assert_eq!(sync_frames, async_frames, "mismatch reading {filename}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_ascii_dec_base() {
+        let log = "date Fri Jan 23 23:04:02 2026\nbase dec  timestamps absolute\n0.400291 1  150       Rx   d 8 11 22 33 44 55 66 77 88\n";
+        let frames: Vec<_> = AsyncCanLogReader::new(log.as_bytes()).collect().await;
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].id, 150);
+        assert_eq!(frames[0].data[0], 11);
+
+        let with_format: Vec<_> = AsyncCanLogReader::with_format(log.as_bytes(), &VectorAsciiFormat)
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(frames, with_format);
+    }
+
+    #[tokio::test]
+    async fn test_async_ascii_short_reads() {
+        // One byte per read, the header is only recognized once its lines are complete
+        let log = concat!(
+            "date Fri Jan 23 23:04:02 2026\nbase dec  timestamps absolute\n",
+            "0.400291 1  150       Rx   d 8 11 22 33 44 55 66 77 88\n",
+        );
+        let frames: Vec<_> = AsyncCanLogReader::new(BufReader::with_capacity(1, log.as_bytes())).collect().await;
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].id, 150);
+
+        let candump = fs::read_to_string("candump.log").unwrap();
+        let frames: Vec<_> = AsyncCanLogReader::new(BufReader::with_capacity(3, candump.as_bytes())).collect().await;
+        assert_eq!(frames, read_async("candump.log").await);
+    }
+
+    #[tokio::test]
+    async fn test_sink_roundtrip() {
+        let file = NamedTempFile::new().unwrap();
+        let input_frames = read_async("candump.log").await;
+
+        // Small buffer so that frames are also flushed while sending
+        let mut sink = CanWriterSink::with_capacity(CandumpWriter::from_path(file.path()).unwrap(), 16);
+        for frame in input_frames.iter().cloned() {
+            sink.feed(frame).await.unwrap();
+        }
+        sink.close().await.unwrap();
+        assert!(sink.get_ref().is_some());
+
+        let written = fs::read_to_string(file.path()).unwrap();
+        let expected = fs::read_to_string("candump.log").unwrap();
+        assert_eq!(written.lines().count(), input_frames.len());
+        for (written_line, expected_line) in written.lines().zip(expected.lines()) {
+            assert_eq!(written_line, expected_line.trim());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sink_empty_flush() {
+        let file = NamedTempFile::new().unwrap();
+        let mut sink = CanWriterSink::new(CandumpWriter::from_path(file.path()).unwrap());
+        // Completes without a round trip through the blocking pool
+        assert!(sink.flush().now_or_never().unwrap().is_ok());
+        assert!(sink.close().now_or_never().unwrap().is_ok());
+        assert!(sink.get_ref().is_some());
+    }
+}
@@ -0,0 +1,459 @@
+/*!
+ * Pluggable CAN log formats.
+ *
+ * Each format implements `CanLogFormat` and is looked up through a `FormatRegistry`
+ * by name, file extension or by sniffing the first bytes of the log.
+ * The process wide registry starts with the built-in candump and Vector ASCII formats,
+ * downstream crates can add their own with `register_format`.
+ */
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::canlog_reader::{self, AsciiBase, CanFrame};
+use crate::canlog_writer::{CanWriter, CandumpWriter};
+
+/// Number of bytes handed to `CanLogFormat::sniff` when detecting a format
+pub const SNIFF_LEN: usize = 512;
+
+/// Stateful parser for line based text formats, fed one line at a time.
+/// Returns None for lines that do not hold a CAN frame (headers, events, parse errors).
+pub trait LineParser: Send {
+    fn parse_line(&mut self, line: &str) -> Option<CanFrame>;
+}
+
+/// A CAN log file format that can be read and/or written.
+///
+/// Line based formats only need to provide `line_parser`, the default `reader`
+/// drives it over the lines of the input.
+pub trait CanLogFormat: Send + Sync {
+    /// Short unique name of the format, e.g. "candump"
+    fn name(&self) -> &str;
+
+    /// File extensions (without the dot) used by this format
+    fn extensions(&self) -> &[&str];
+
+    /// Return true if `head`, the start of a log, looks like this format
+    fn sniff(&self, _head: &[u8]) -> bool {
+        false
+    }
+
+    /// Create a parser for line based formats. None for binary formats.
+    fn line_parser(&self) -> Option<Box<dyn LineParser>> {
+        None
+    }
+
+    /// Create an iterator of frames read from `reader`
+    fn reader(&self, reader: Box<dyn BufRead>) -> io::Result<Box<dyn Iterator<Item = CanFrame>>> {
+        let parser = self.line_parser().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported, format!("reading {} logs is not supported", self.name()))
+        })?;
+        Ok(Box::new(LineFrames::new(reader, parser)))
+    }
+
+    /// Create a writer that outputs frames in this format to `writer`
+    fn writer(&self, _writer: Box<dyn Write>) -> io::Result<Box<dyn CanWriter>> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, format!("writing {} logs is not supported", self.name())))
+    }
+}
+
+/// Iterator of frames from a `BufRead`, one `LineParser` call per line.
+/// Ends on EOF or io error, lines that don't parse are skipped.
+pub struct LineFrames<R> {
+    reader: R,
+    buf: String, // local buf to re-use so we don't keep allocating
+    parser: Box<dyn LineParser>,
+}
+
+impl<R: BufRead> LineFrames<R> {
+    pub fn new(reader: R, parser: Box<dyn LineParser>) -> Self {
+        Self {
+            reader,
+            buf: String::new(),
+            parser,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LineFrames<R> {
+    type Item = CanFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => {
+                    if let Some(frame) = self.parser.parse_line(&self.buf) {
+                        return Some(frame);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Format output by logs of can-utils candump application. End in .log.
+pub struct CandumpFormat;
+
+struct CandumpLineParser;
+
+impl LineParser for CandumpLineParser {
+    fn parse_line(&mut self, line: &str) -> Option<CanFrame> {
+        canlog_reader::parse_candump_line(line).ok()
+    }
+}
+
+impl CanLogFormat for CandumpFormat {
+    fn name(&self) -> &str {
+        "candump"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["log"]
+    }
+
+    /// First non-empty line looks like "(1436509053.850870) vcan0 1A0#..."
+    fn sniff(&self, head: &[u8]) -> bool {
+        let text = String::from_utf8_lossy(head);
+        match text.lines().map(str::trim).find(|line| !line.is_empty()) {
+            Some(line) => line.starts_with('(') && line.contains('#'),
+            None => false,
+        }
+    }
+
+    fn line_parser(&self) -> Option<Box<dyn LineParser>> {
+        Some(Box::new(CandumpLineParser))
+    }
+
+    fn writer(&self, writer: Box<dyn Write>) -> io::Result<Box<dyn CanWriter>> {
+        Ok(Box::new(CandumpWriter::from_writer(writer)))
+    }
+}
+
+/// Format output by Vector sw tools. End in .asc.
+pub struct VectorAsciiFormat;
+
+/// Vector ascii logs start with a header giving the base of numbers in the log:
+///     date Fri Jan 23 23:04:02 2026
+///     base hex  timestamps absolute
+/// Lines before the base line are skipped.
+struct VectorAsciiLineParser {
+    base: Option<AsciiBase>,
+}
+
+impl LineParser for VectorAsciiLineParser {
+    fn parse_line(&mut self, line: &str) -> Option<CanFrame> {
+        match &self.base {
+            Some(base) => canlog_reader::parse_ascii_line(line, base.clone()).ok(),
+            None => {
+                let mut splits = line.split_whitespace();
+                if splits.next() == Some("base") {
+                    self.base = match splits.next() {
+                        Some("hex") => Some(AsciiBase::Hex),
+                        Some("dec") => Some(AsciiBase::Dec),
+                        _ => None,
+                    };
+                }
+                None
+            }
+        }
+    }
+}
+
+impl CanLogFormat for VectorAsciiFormat {
+    fn name(&self) -> &str {
+        "vector-ascii"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["asc"]
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        canlog_reader::get_ascii_base(head).is_ok()
+    }
+
+    fn line_parser(&self) -> Option<Box<dyn LineParser>> {
+        Some(Box::new(VectorAsciiLineParser { base: None }))
+    }
+
+    /// Same as the default line reader, but rejects logs without a valid header
+    fn reader(&self, mut reader: Box<dyn BufRead>) -> io::Result<Box<dyn Iterator<Item = CanFrame>>> {
+        if canlog_reader::get_ascii_base(reader.fill_buf()?).is_err() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid ascii header"));
+        }
+        Ok(Box::new(LineFrames::new(reader, Box::new(VectorAsciiLineParser { base: None }))))
+    }
+}
+
+/// Set of known log formats.
+///
+/// Lookups search the most recently registered format first, so a format
+/// registered later can take over an extension from a built-in one.
+#[derive(Clone, Default)]
+pub struct FormatRegistry {
+    formats: Vec<Arc<dyn CanLogFormat>>,
+}
+
+impl FormatRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry holding the formats rocketcan supports out of the box
+    pub fn with_builtin_formats() -> Self {
+        let mut registry = Self::new();
+        registry.register(CandumpFormat);
+        registry.register(VectorAsciiFormat);
+        registry
+    }
+
+    pub fn register<F: CanLogFormat + 'static>(&mut self, format: F) {
+        self.formats.push(Arc::new(format));
+    }
+
+    /// Remove the formats named `name`, returns false if there was none
+    pub fn unregister(&mut self, name: &str) -> bool {
+        let count = self.formats.len();
+        self.formats.retain(|format| !format.name().eq_ignore_ascii_case(name));
+        self.formats.len() != count
+    }
+
+    /// All registered formats, in registration order
+    pub fn formats(&self) -> impl Iterator<Item = &Arc<dyn CanLogFormat>> {
+        self.formats.iter()
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<Arc<dyn CanLogFormat>> {
+        self.formats
+            .iter()
+            .rev()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    pub fn by_extension(&self, extension: &str) -> Option<Arc<dyn CanLogFormat>> {
+        self.formats
+            .iter()
+            .rev()
+            .find(|format| format.extensions().iter().any(|ext| ext.eq_ignore_ascii_case(extension)))
+            .cloned()
+    }
+
+    /// Find the format from the extension of `path`
+    pub fn for_path(&self, path: &Path) -> Option<Arc<dyn CanLogFormat>> {
+        let extension = path.extension()?.to_str()?;
+        self.by_extension(extension)
+    }
+
+    /// Find the first format that recognizes the start of a log
+    pub fn sniff(&self, head: &[u8]) -> Option<Arc<dyn CanLogFormat>> {
+        let head = &head[..head.len().min(SNIFF_LEN)];
+        self.formats.iter().rev().find(|format| format.sniff(head)).cloned()
+    }
+
+    /// Open a log file for reading.
+    /// The format is picked by extension, falling back to sniffing the file contents.
+    pub fn open(&self, path: &Path) -> io::Result<Box<dyn Iterator<Item = CanFrame>>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let format = match self.for_path(path) {
+            Some(format) => format,
+            None => self.sniff(reader.fill_buf()?).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidFilename,
+                    format!("CAN Log file format not supported for {}", path.display()),
+                )
+            })?,
+        };
+        format.reader(Box::new(reader))
+    }
+
+    /// Create (or truncate) a log file for writing, format is picked by extension
+    pub fn create(&self, path: &Path) -> io::Result<Box<dyn CanWriter>> {
+        let format = self.for_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidFilename,
+                format!("CAN Log file extension not supported for {}", path.display()),
+            )
+        })?;
+        format.writer(Box::new(File::create(path)?))
+    }
+}
+
+fn global_registry() -> &'static RwLock<FormatRegistry> {
+    static REGISTRY: OnceLock<RwLock<FormatRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(FormatRegistry::with_builtin_formats()))
+}
+
+/// Snapshot of the process wide registry used by `CanLogParser` and the other readers/writers.
+/// Formats registered afterwards are not in the snapshot.
+pub fn registry() -> FormatRegistry {
+    global_registry().read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
+
+/// Add a format to the process wide registry
+pub fn register_format<F: CanLogFormat + 'static>(format: F) {
+    global_registry()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .register(format);
+}
+
+/// Remove a format from the process wide registry, returns false if it was not registered
+pub fn unregister_format(name: &str) -> bool {
+    global_registry().write().unwrap_or_else(|poisoned| poisoned.into_inner()).unregister(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Toy format: "<id hex> <data hex>" lines
+    struct SpaceFormat;
+
+    struct SpaceLineParser;
+
+    impl LineParser for SpaceLineParser {
+        fn parse_line(&mut self, line: &str) -> Option<CanFrame> {
+            let mut splits = line.split_whitespace();
+            let mut frame = CanFrame {
+                id: u32::from_str_radix(splits.next()?, 16).ok()?,
+                ..Default::default()
+            };
+            let data = splits.next()?;
+            frame.data = canlog_reader::candump_hex_to_bytes(data).ok()?;
+            frame.len = (data.len() / 2) as u8;
+            Some(frame)
+        }
+    }
+
+    impl CanLogFormat for SpaceFormat {
+        fn name(&self) -> &str {
+            "space"
+        }
+        fn extensions(&self) -> &[&str] {
+            &["spc"]
+        }
+        fn sniff(&self, head: &[u8]) -> bool {
+            head.starts_with(b"SPC")
+        }
+        fn line_parser(&self) -> Option<Box<dyn LineParser>> {
+            Some(Box::new(SpaceLineParser))
+        }
+    }
+
+    #[test]
+    fn test_builtin_lookup() {
+        let registry = FormatRegistry::with_builtin_formats();
+        assert_eq!(registry.by_extension("log").unwrap().name(), "candump");
+        assert_eq!(registry.by_extension("ASC").unwrap().name(), "vector-ascii");
+        assert_eq!(registry.by_name("Candump").unwrap().name(), "candump");
+        assert!(registry.by_extension("blf").is_none());
+        assert_eq!(registry.for_path(Path::new("dir/run.asc")).unwrap().name(), "vector-ascii");
+    }
+
+    #[test]
+    fn test_sniff() {
+        let registry = FormatRegistry::with_builtin_formats();
+        let candump = b"\n(1436509052.249713) vcan0 044#2A366C2BBA\n";
+        assert_eq!(registry.sniff(candump).unwrap().name(), "candump");
+        let ascii = b"date Fri Jan 23 23:04:02 2026\nbase hex  timestamps absolute\n";
+        assert_eq!(registry.sniff(ascii).unwrap().name(), "vector-ascii");
+        assert!(registry.sniff(b"garbage").is_none());
+    }
+
+    #[test]
+    fn test_ascii_header_dec_base() {
+        let log = "date Fri Jan 23 23:04:02 2026\nbase dec  timestamps absolute\n0.400291 1  150       Rx   d 8 11 22 33 44 55 66 77 88\n";
+        let frames: Vec<_> = VectorAsciiFormat.reader(Box::new(Cursor::new(log))).unwrap().collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].id, 150);
+        assert_eq!(frames[0].data[0], 11);
+
+        assert!(VectorAsciiFormat.reader(Box::new(Cursor::new("no header\n"))).is_err());
+    }
+
+    #[test]
+    fn test_custom_format() {
+        let mut registry = FormatRegistry::with_builtin_formats();
+        registry.register(SpaceFormat);
+        assert_eq!(registry.sniff(b"SPC\n1A0 0102").unwrap().name(), "space");
+
+        let format = registry.by_extension("spc").unwrap();
+        let frames: Vec<_> = format.reader(Box::new(Cursor::new("SPC\n1A0 0102\n7FF AA\n"))).unwrap().collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].id, 0x1A0);
+        assert_eq!(frames[0].len, 2);
+        assert_eq!(frames[1].data[0], 0xAA);
+
+        // No writer was provided
+        assert!(format.writer(Box::new(io::sink())).is_err());
+    }
+
+    /// Space format under a name and extension no other test uses, so it can be in the global registry
+    struct GlobalSpaceFormat;
+
+    impl CanLogFormat for GlobalSpaceFormat {
+        fn name(&self) -> &str {
+            "global-space-test"
+        }
+        fn extensions(&self) -> &[&str] {
+            &["globalspc"]
+        }
+        fn line_parser(&self) -> Option<Box<dyn LineParser>> {
+            Some(Box::new(SpaceLineParser))
+        }
+    }
+
+    /// Removes the format from the global registry at the end of the test, even when it fails
+    struct Unregister(&'static str);
+
+    impl Drop for Unregister {
+        fn drop(&mut self) {
+            unregister_format(self.0);
+        }
+    }
+
+    #[test]
+    fn test_global_registration() {
+        // Tools reading through the global registry pick up new formats,
+        // registering while holding a snapshot doesn't wait on it
+        let snapshot = registry();
+        register_format(GlobalSpaceFormat);
+        let _unregister = Unregister("global-space-test");
+        assert!(snapshot.by_name("candump").is_some());
+        assert!(snapshot.by_name("global-space-test").is_none());
+        let mut file = tempfile::NamedTempFile::with_suffix(".globalspc").unwrap();
+        writeln!(file, "1A0 0102").unwrap();
+        let frames: Vec<_> = crate::canlog_reader::CanLogParser::from_file(file.path()).unwrap().collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].id, 0x1A0);
+
+        assert!(unregister_format("global-space-test"));
+        assert!(registry().by_extension("globalspc").is_none());
+        assert!(!unregister_format("global-space-test"));
+    }
+
+    #[test]
+    fn test_candump_writer_from_format() {
+        let file = tempfile::NamedTempFile::with_suffix(".log").unwrap();
+        let registry = FormatRegistry::with_builtin_formats();
+        let frames: Vec<_> = registry.open(Path::new("candump.log")).unwrap().collect();
+
+        let mut writer = crate::canlog_writer::writer_from_path(file.path()).unwrap();
+        for frame in &frames {
+            writer.write(frame).unwrap();
+        }
+        writer.flush().unwrap();
+        drop(writer);
+
+        let read_back: Vec<_> = registry.open(file.path()).unwrap().collect();
+        assert_eq!(frames, read_back);
+    }
+}
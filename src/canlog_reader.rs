@@ -5,8 +5,11 @@ use std::fs::File;
 use std::io::Cursor;
 use std::io::{self, BufRead, BufReader};
 use std::num::ParseIntError;
+use std::sync::Arc;
 use std::time::Instant;
 
+use crate::canlog_format::{self, CanLogFormat, CandumpFormat, LineParser};
+
 const DEFAULT_FRAME_PAYLOAD_LEN: usize = 64;
 #[derive(Debug,PartialEq, PartialOrd,Clone)]
 pub struct CanFrame {
@@ -146,7 +149,7 @@ fn parse_can_id(item: &str, radix: u32) -> Result<u32,ParseIntError> {
 }
 
 /// Get the base (radix) from the Vector ascii header
-fn get_ascii_base(mut reader: impl BufRead) -> anyhow::Result<AsciiBase> {
+pub(crate) fn get_ascii_base(mut reader: impl BufRead) -> anyhow::Result<AsciiBase> {
     /* Ascii Header Format
         date Fri Jan 23 23:04:02 2026
         base hex  timestamps absolute
@@ -260,93 +263,37 @@ fn parse_ascii_can_fd(splits: Vec<&str>, radix: u32) -> anyhow::Result<CanFrame>
     Ok(frame)
 }
 
-#[derive(PartialEq)]
-enum CanLogFormat {
-    /// Format output by logs of can-utils candump application. End in .log.
-    Candump,
-    /// Format output by Vector sw tools. End in .asc.
-    VectorAscii,
-}
-pub struct CanLogParser/*<R>*/{
-    //reader: R,
-    reader: Box<dyn BufRead>,
-    buf: String, // local buf to re-use so we don't keep allocating
-    format: CanLogFormat,
-    ascii_base: Option<AsciiBase>, // For vector ascii only
+/// Reads CanFrames from a log file in any format known to the `canlog_format` registry.
+pub struct CanLogParser {
+    frames: Box<dyn Iterator<Item = CanFrame>>,
 }
 
 impl CanLogParser {
 
-    /// Create CanLogParser from a file path
+    /// Create CanLogParser from a file path.
+    /// Format is picked by file extension, falling back to sniffing the file contents.
     pub fn from_file(path: &std::path::Path) -> io::Result<Self> {
-        let file = File::open(path)?;
-        
-        let extension = path.extension().ok_or_else(|| {
-            io::Error::new(io::ErrorKind::InvalidFilename, "CAN Log file extension not supported for {path}")
-        })?;
-        let format;
-        let mut ascii_base= None;
-        //println!("{:?}",extension);
-        if extension == "log" {
-            format =  CanLogFormat::Candump;
-        } else if extension == "asc" {
-            let file2 = File::open(path)?;
-            format = CanLogFormat::VectorAscii;
-            let reader = BufReader::new(file2);
-            if let Ok(base) = get_ascii_base(reader) {
-                //println!("Base {:?}",base);
-                ascii_base = Some(base);
-            } else {
-                return Err(io::Error::new(io::ErrorKind::InvalidInput,"Invalid ascii header"));
-            }
-            ascii_base = Some(AsciiBase::Hex);
-        } else {
-            return Err(io::Error::new(io::ErrorKind::InvalidFilename, "CAN Log file extension not supported for {path}"));
-        }
-        
-        Ok( CanLogParser { 
-            reader: Box::new(BufReader::new(file)), 
-            buf: String::new(),
-            format: format,
-            ascii_base: ascii_base
-        })
-        
+        let frames = canlog_format::registry().open(path)?;
+        Ok(CanLogParser { frames })
     }
 
-    /// Create CanLogParser from raw bytes
-    pub fn from_bytes(bytes: Vec<u8>) -> Self {
-        let cursor = Cursor::new(&bytes);
-        let reader = BufReader::new(cursor);
-        let mut ascii_base = None;
-        let mut format = CanLogFormat::Candump;
-        if let Ok(base) = get_ascii_base(reader) {
-            ascii_base = Some(base);
-            format = CanLogFormat::VectorAscii;
-        } else {
-            //TODO return error
-            println!("Error calling CanLogParser::from_bytes");
-            //return Err(io::Error::new(io::ErrorKind::InvalidInput,"Invalid ascii header"));
-        }
-        CanLogParser { 
-            reader: Box::new(Cursor::new(bytes)), 
-            buf: String::new(),
-            format: format,
-            ascii_base: ascii_base,
-        }
+    /// Create CanLogParser from raw bytes, format is sniffed from the contents.
+    /// Defaults to candump if no format recognizes the bytes.
+    /// Returns Err if the format can't be read, e.g. a Vector ASCII log without a valid header.
+    /// Breaking change: this used to return `Self`, callers now need `?` or `unwrap()`.
+    pub fn from_bytes(bytes: Vec<u8>) -> io::Result<Self> {
+        let format = canlog_format::registry()
+            .sniff(&bytes)
+            .unwrap_or_else(|| Arc::new(CandumpFormat));
+        let frames = format.reader(Box::new(Cursor::new(bytes)))?;
+        Ok(CanLogParser { frames })
     }
 
-    /*/// Create CanLogParser from any type that implements the BufRead trait
-    pub fn from_reader<R: BufRead + 'static>(reader: R, format: CanLogFormat) -> Self {
-        if format == CanLogFormat::VectorAscii {
-            panic!("Error CanLogParser::from_reader does not support VectorAscii");
-        }
-        Self {
-            reader: Box::new(reader),
-            buf: String::new(),
-            format: format,
-            ascii_base: None,
-        }
-    }*/
+    /// Create CanLogParser from any type that implements the BufRead trait
+    pub fn from_reader<R: BufRead + 'static>(reader: R, format: &dyn CanLogFormat) -> io::Result<Self> {
+        let frames = format.reader(Box::new(reader))?;
+        Ok(CanLogParser { frames })
+    }
 
 }
 
@@ -354,51 +301,28 @@ impl Iterator for CanLogParser {
     type Item = CanFrame;
 
     fn next(&mut self) -> Option<Self::Item>{
-        self.buf.clear();
-        match self.format {
-            CanLogFormat::Candump => {
-                match self.reader.read_line(&mut self.buf) {
-                    Ok(0) => None,
-                    Ok(_) => {
-                        //TODO: Should this cause an error? 
-                        //Consider returning Option<Result<CanFrame>> to indicate parse failure on line to user
-                        parse_candump_line(&self.buf).ok() //throw away parsing failures here...
-                    }
-                    Err(_) => None,
-                }
-            },
-            CanLogFormat::VectorAscii => {
-                // Vector ASCII has varying begin/end blocks
-                // And could contain unsupported frames in the middle (error, ETH, Flexray)
-                // Read until either EOF,error, or we get a CAN frame
-                loop {
-                    self.buf.clear();
-                    match self.reader.read_line(&mut self.buf) {
-                        Ok(0) => {
-                            //dbg!(&self.buf);
-                            return None;
-                        },
-                        Ok(_) => {
-                            //dbg!(&self.buf);
-                            if let Ok(frame) = parse_ascii_line(&self.buf, self.ascii_base.as_ref().cloned().unwrap()) {
-                                return Some(frame);
-                            }
-                        },
-                        Err(_) => {
-                            return None;
-                        },
-                    }
-                }
-            }
-        }
-        
+        self.frames.next()
     }
 }
+
+/// Legacy reader over an iterator of lines.
+/// Uses the line parser of a registered text format, candump by default.
 pub struct CanLogReader<T>
 where
     T: Iterator,
 {
     iterable: T,
+    parser: Box<dyn LineParser>,
+}
+
+impl<T> CanLogReader<T>
+where
+    T: Iterator<Item = std::io::Result<String>>,
+{
+    /// Create a reader over lines of a log in the given text format
+    pub fn from_lines(lines: T, format: &dyn CanLogFormat) -> Option<Self> {
+        Some(CanLogReader { iterable: lines, parser: format.line_parser()? })
+    }
 }
 
 impl<T> Iterator for CanLogReader<T>
@@ -408,25 +332,31 @@ where
 {
     type Item = CanFrame;
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(line) = self.iterable.next() {
-            //println!("{}", line.unwrap());
-            //return Some(parse_candump_line(&line.unwrap()));
-            return parse_candump_line(&line.unwrap()).ok();
+        for line in self.iterable.by_ref() {
+            let Ok(line) = line else {
+                return None;
+            };
+            if let Some(frame) = self.parser.parse_line(&line) {
+                return Some(frame);
+            }
         }
-        return None;
+        None
     }
 }
 
 type LinesFileBufReader = std::io::Lines<BufReader<File>>;
 impl CanLogReader<LinesFileBufReader> {
-    pub fn from_file(filename: &str) -> CanLogReader<LinesFileBufReader> {
-        let Ok(f) = File::open(filename) else {
-            panic!("Unable to open file named {filename}");
-        };
-        let buf_reader = BufReader::new(f);
-        let lines = buf_reader.lines();
-        let reader = CanLogReader { iterable: lines };
-        return reader;
+    /// Open a line based log, format is picked by extension (candump if unknown).
+    /// Returns Err if the file can't be opened or the format is not line based.
+    pub fn from_file(filename: &str) -> io::Result<CanLogReader<LinesFileBufReader>> {
+        let f = File::open(filename)?;
+        let format = canlog_format::registry()
+            .for_path(std::path::Path::new(filename))
+            .unwrap_or_else(|| Arc::new(CandumpFormat));
+        let lines = BufReader::new(f).lines();
+        CanLogReader::from_lines(lines, format.as_ref()).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported, format!("{} is not a line based log format", format.name()))
+        })
     }
 }
 
@@ -455,8 +385,8 @@ mod tests {
         let t = buf_reader.lines();
         //let next = t.next();
         //let mut cr = t.to_canlog_reader();
-        let mut cr = CanLogReader { iterable: t };
-        let cr = CanLogReader::from_file(filename);
+        let mut cr = CanLogReader::from_lines(t, &CandumpFormat).unwrap();
+        let cr = CanLogReader::from_file(filename).unwrap();
         let mut can_reader_collection = Vec::new();
         for can_frame in cr {
             println!("{:?}", can_frame);
@@ -474,13 +404,15 @@ mod tests {
         for i in 0..can_parser_collection.len() {
             assert_eq!(can_parser_collection[i], can_reader_collection[i]);
         }
+
+        assert!(CanLogReader::from_file("missing.log").is_err_and(|e| e.kind() == io::ErrorKind::NotFound));
     }
 
     #[test]
     fn benchmark_reading() {
         //let filename = "candump.log";
         let filename = "can_samples/aphryx-canx-nissan-leaf/demo_meet_200k_revised.log";
-        let reader = CanLogReader::from_file(filename);
+        let reader = CanLogReader::from_file(filename).unwrap();
         let parser = CanLogParser::from_file(std::path::Path::new(filename)).unwrap();
         let parser2 = CanLogParser::from_file(std::path::Path::new(filename)).unwrap();
 
@@ -782,7 +714,7 @@ mod tests {
         let mut bytes = Vec::new();
         let read_size = File::open(ascii_filename).unwrap().read_to_end(&mut bytes).unwrap();
         assert!(read_size > 1);
-        let frames_from_bytes: Vec<_> = CanLogParser::from_bytes(bytes).collect();
+        let frames_from_bytes: Vec<_> = CanLogParser::from_bytes(bytes).unwrap().collect();
         for (frame1, frame2) in ascii_frames.iter().zip(frames_from_bytes.iter()){
             assert_eq!(frame1,frame2);
         }
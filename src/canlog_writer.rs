@@ -2,11 +2,18 @@
  * Interfaces to write CAN frames to output log files
  */
 
-use std::fmt::Write as FmtWrite; use std::fs::File;
+use std::fmt::Write as FmtWrite; use std::fs::{self, File};
 //for write! on Strings. Just need trait in scope
 use std::io::{self, BufWriter};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::canlog_format::{self, CanLogFormat};
 use crate::canlog_reader::CanFrame;
 
 /// Convert a CanFrame to an ascii candump line
@@ -47,6 +54,26 @@ pub trait CanWriter {
     fn flush(&mut self) -> io::Result<()>;
 }
 
+impl<W: CanWriter + ?Sized> CanWriter for Box<W> {
+    fn write(&mut self, frame: &CanFrame) -> io::Result<()> {
+        (**self).write(frame)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+impl<W: CanWriter + ?Sized> CanWriter for &mut W {
+    fn write(&mut self, frame: &CanFrame) -> io::Result<()> {
+        (**self).write(frame)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
 /// Write CanFrames to a log file in candump (linux can-utils) format
 pub struct CandumpWriter<W: io::Write> {
     writer: BufWriter<W>,
@@ -72,6 +99,16 @@ impl<W: io::Write> CandumpWriter<W> {
     }
 }
 
+impl<W: io::Write> CanWriter for CandumpWriter<W> {
+    fn write(&mut self, frame: &CanFrame) -> io::Result<()> {
+        CandumpWriter::write(self, frame)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        CandumpWriter::flush(self)
+    }
+}
+
 impl CandumpWriter<File> {
     /// Create a new writer to a file.
     /// Creates a new file if one does not exist, 
@@ -85,13 +122,232 @@ impl CandumpWriter<File> {
 }
 
 /// Create a writer that auto-detects format from file extension.
-/// .log -> CandumpWriter, .asc -> AsciiWriter
-/*pub fn writer_from_path(path: &Path) -> io::Result<Box<dyn CanWriter>> {
-    let extension = path.extension()
-    if path.extension().ok_or_else == ".log" {
-        CandumpWriter::from_path(path)
+/// .log -> CandumpWriter, other extensions as registered in `canlog_format`
+pub fn writer_from_path<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn CanWriter>> {
+    canlog_format::registry().create(path.as_ref())
+}
+
+/// Limits on a single `RotatingWriter` segment. Limits left as None are not checked.
+#[derive(Debug, Clone, Default)]
+pub struct RotationPolicy {
+    /// Start a new segment before a frame would push the file past this size.
+    /// The frame is measured as the first frame of a fresh file, so formats with headers rotate early.
+    pub max_bytes: Option<u64>,
+    /// Number of frames per segment
+    pub max_frames: Option<u64>,
+    /// Wall-clock time a segment stays open
+    pub max_duration: Option<Duration>,
+    /// gzip closed segments, session_000.log -> session_000.log.gz
+    pub compress: bool,
+}
+
+/// In-memory output of the per-segment format writer.
+/// Lets `RotatingWriter` see the exact bytes of each frame before they reach a file.
+#[derive(Clone, Default)]
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuf {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Write CanFrames into numbered segment files, session.log -> session_000.log, session_001.log, ...
+///
+/// Works with any registered `CanLogFormat`: each segment gets a fresh format writer,
+/// so per-file headers are repeated, and a frame is never split across two files.
+/// Call `close` at the end to finish (and compress) the last segment and get its errors,
+/// dropping the writer does the same on a best-effort basis.
+pub struct RotatingWriter {
+    format: Arc<dyn CanLogFormat>,
+    policy: RotationPolicy,
+    base_path: PathBuf,
+    segments: Vec<PathBuf>, // All segment paths, closed segments point to the compressed file
+    file: BufWriter<File>,
+    formatter: Option<Box<dyn CanWriter>>, // None once the segment is finished
+    scratch: SharedBuf,
+    segment_bytes: u64,
+    segment_frames: u64,
+    segment_start: Instant,
+    closed: bool,
+}
+
+impl RotatingWriter {
+    /// Create a rotating writer, format is picked by the extension of `base_path`
+    pub fn new<P: AsRef<Path>>(base_path: P, policy: RotationPolicy) -> io::Result<Self> {
+        let base_path = base_path.as_ref();
+        let format = canlog_format::registry().for_path(base_path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidFilename,
+                format!("CAN Log file extension not supported for {}", base_path.display()),
+            )
+        })?;
+        Self::with_format(base_path, format, policy)
+    }
+
+    /// Create a rotating writer that outputs segments in `format`
+    pub fn with_format<P: AsRef<Path>>(
+        base_path: P,
+        format: Arc<dyn CanLogFormat>,
+        policy: RotationPolicy,
+    ) -> io::Result<Self> {
+        let base_path = base_path.as_ref().to_path_buf();
+        let scratch = SharedBuf::default();
+        let path = segment_path(&base_path, 0);
+        let file = BufWriter::new(File::create(&path)?);
+        let formatter = format.writer(Box::new(scratch.clone()))?;
+        Ok(Self {
+            format,
+            policy,
+            base_path,
+            segments: vec![path],
+            file,
+            formatter: Some(formatter),
+            scratch,
+            segment_bytes: 0,
+            segment_frames: 0,
+            segment_start: Instant::now(),
+            closed: false,
+        })
+    }
+
+    /// Paths of all segments written so far, including the open one
+    pub fn segment_paths(&self) -> &[PathBuf] {
+        &self.segments
+    }
+
+    /// Finish the current segment and return the paths of all segments
+    pub fn close(mut self) -> io::Result<Vec<PathBuf>> {
+        // Drop must not finish the segment a second time, even after an error
+        self.closed = true;
+        self.finish_segment()?;
+        Ok(std::mem::take(&mut self.segments))
+    }
+
+    /// Run the frame through the segment's format writer and return its bytes
+    fn serialize(&mut self, frame: &CanFrame) -> io::Result<Vec<u8>> {
+        let formatter = self.formatter.as_mut().ok_or_else(segment_finished)?;
+        formatter.write(frame)?;
+        formatter.flush()?;
+        Ok(self.scratch.take())
+    }
+
+    /// Size of the frame in a fresh segment, header included.
+    /// Uses a throwaway format writer so the segment's writer only ever sees the frames it keeps.
+    fn estimate_size(&self, frame: &CanFrame) -> io::Result<u64> {
+        let probe = SharedBuf::default();
+        let mut formatter = self.format.writer(Box::new(probe.clone()))?;
+        formatter.write(frame)?;
+        formatter.flush()?;
+        Ok(probe.take().len() as u64)
     }
-}*/
+
+    /// Flush everything to the current segment file, compressing it if enabled.
+    /// The format writer is dropped first so any footer it writes ends up in this segment.
+    fn finish_segment(&mut self) -> io::Result<()> {
+        if let Some(mut formatter) = self.formatter.take() {
+            formatter.flush()?;
+        }
+        self.file.write_all(&self.scratch.take())?;
+        self.file.flush()?;
+        if self.policy.compress {
+            if let Some(path) = self.segments.last_mut() {
+                *path = gzip_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.finish_segment()?;
+        let path = segment_path(&self.base_path, self.segments.len());
+        self.file = BufWriter::new(File::create(&path)?);
+        self.formatter = Some(self.format.writer(Box::new(self.scratch.clone()))?);
+        self.segments.push(path);
+        self.segment_bytes = 0;
+        self.segment_frames = 0;
+        self.segment_start = Instant::now();
+        Ok(())
+    }
+}
+
+fn segment_finished() -> io::Error {
+    io::Error::other("RotatingWriter segment already finished")
+}
+
+impl CanWriter for RotatingWriter {
+    fn write(&mut self, frame: &CanFrame) -> io::Result<()> {
+        // Every segment holds at least one frame, even if that frame alone breaks a limit
+        if self.segment_frames > 0 {
+            let frames_full = self.policy.max_frames.is_some_and(|max| self.segment_frames >= max);
+            let time_up = self.policy.max_duration.is_some_and(|max| self.segment_start.elapsed() >= max);
+            let bytes_full = match self.policy.max_bytes {
+                Some(max) if !(frames_full || time_up) => self.segment_bytes + self.estimate_size(frame)? > max,
+                _ => false,
+            };
+            if frames_full || time_up || bytes_full {
+                self.rotate()?;
+            }
+        }
+
+        let bytes = self.serialize(frame)?;
+        self.file.write_all(&bytes)?;
+        self.segment_bytes += bytes.len() as u64;
+        self.segment_frames += 1;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.formatter.as_mut().ok_or_else(segment_finished)?.flush()?;
+        let bytes = self.scratch.take();
+        self.segment_bytes += bytes.len() as u64;
+        self.file.write_all(&bytes)?;
+        self.file.flush()
+    }
+}
+
+impl Drop for RotatingWriter {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = self.finish_segment();
+        }
+    }
+}
+
+/// Path of segment `index`: dir/session.log -> dir/session_003.log
+fn segment_path(base_path: &Path, index: usize) -> PathBuf {
+    let stem = base_path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match base_path.extension() {
+        Some(extension) => format!("{stem}_{index:03}.{}", extension.to_string_lossy()),
+        None => format!("{stem}_{index:03}"),
+    };
+    base_path.with_file_name(name)
+}
+
+/// Compress `path` to `path`.gz and remove the original
+fn gzip_file(path: &Path) -> io::Result<PathBuf> {
+    let mut gz_path = path.as_os_str().to_owned();
+    gz_path.push(".gz");
+    let gz_path = PathBuf::from(gz_path);
+
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(BufWriter::new(File::create(&gz_path)?), Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.flush()?;
+    fs::remove_file(path)?;
+    Ok(gz_path)
+}
 
 #[cfg(test)]
 mod tests {
@@ -165,5 +421,172 @@ mod tests {
     }
     //test_vector_ascii_write
 
+    // ---------------------------------------------------------------
+    // RotatingWriter tests
+    // ---------------------------------------------------------------
 
+    /// Write every frame of candump.log through a RotatingWriter, return input frames and segment paths
+    fn write_rotating(base_path: &Path, policy: RotationPolicy) -> (Vec<CanFrame>, Vec<PathBuf>) {
+        let frames: Vec<_> = canlog_reader::CanLogParser::from_file(Path::new("candump.log")).unwrap().collect();
+        let mut writer = RotatingWriter::new(base_path, policy).unwrap();
+        for frame in &frames {
+            writer.write(frame).unwrap();
+        }
+        (frames, writer.close().unwrap())
+    }
+
+    fn read_segments(paths: &[PathBuf]) -> Vec<CanFrame> {
+        paths
+            .iter()
+            .flat_map(|path| canlog_reader::CanLogParser::from_file(path).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rotate_max_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = RotationPolicy { max_frames: Some(3), ..Default::default() };
+        let (frames, paths) = write_rotating(&dir.path().join("session.log"), policy);
+
+        assert_eq!(paths.len(), frames.len().div_ceil(3));
+        assert_eq!(paths[0], dir.path().join("session_000.log"));
+        assert_eq!(paths[1], dir.path().join("session_001.log"));
+        for path in &paths[..paths.len() - 1] {
+            assert_eq!(canlog_reader::CanLogParser::from_file(path).unwrap().count(), 3);
+        }
+        assert_eq!(frames, read_segments(&paths));
+    }
+
+    #[test]
+    fn test_rotate_max_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let max_bytes = 150;
+        let policy = RotationPolicy { max_bytes: Some(max_bytes), ..Default::default() };
+        let (frames, paths) = write_rotating(&dir.path().join("session.log"), policy);
+
+        assert!(paths.len() > 1);
+        for path in &paths {
+            let contents = fs::read_to_string(path).unwrap();
+            assert!(contents.len() as u64 <= max_bytes, "{} is {} bytes", path.display(), contents.len());
+            // Frames are never split, every segment ends on a full line
+            assert!(contents.ends_with('\n'));
+        }
+        assert_eq!(frames, read_segments(&paths));
+    }
+
+    #[test]
+    fn test_rotate_duration() {
+        // A zero duration starts a new segment for every frame
+        let dir = tempfile::tempdir().unwrap();
+        let policy = RotationPolicy { max_duration: Some(Duration::ZERO), ..Default::default() };
+        let (frames, paths) = write_rotating(&dir.path().join("session.log"), policy);
+        assert_eq!(paths.len(), frames.len());
+    }
+
+    #[test]
+    fn test_rotate_compress() {
+        use std::io::Read;
+
+        let dir = tempfile::tempdir().unwrap();
+        let policy = RotationPolicy { max_frames: Some(4), compress: true, ..Default::default() };
+        let (frames, paths) = write_rotating(&dir.path().join("session.log"), policy);
+
+        assert_eq!(paths[0], dir.path().join("session_000.log.gz"));
+        assert!(!dir.path().join("session_000.log").exists());
+        let mut decompressed = String::new();
+        for path in &paths {
+            let mut decoder = flate2::read::GzDecoder::new(File::open(path).unwrap());
+            decoder.read_to_string(&mut decompressed).unwrap();
+        }
+        let read_back: Vec<_> = decompressed
+            .lines()
+            .map(|line| canlog_reader::parse_candump_line(line).unwrap())
+            .collect();
+        assert_eq!(frames, read_back);
+    }
+
+    #[test]
+    fn test_rotate_drop_finishes_segment() {
+        use std::io::Read;
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<_> = canlog_reader::CanLogParser::from_file(Path::new("candump.log")).unwrap().collect();
+        let policy = RotationPolicy { compress: true, ..Default::default() };
+        {
+            let mut writer = RotatingWriter::new(dir.path().join("session.log"), policy).unwrap();
+            for frame in &frames {
+                writer.write(frame).unwrap();
+            }
+        }
+
+        assert!(!dir.path().join("session_000.log").exists());
+        let mut decoder = flate2::read::GzDecoder::new(File::open(dir.path().join("session_000.log.gz")).unwrap());
+        let mut decompressed = String::new();
+        decoder.read_to_string(&mut decompressed).unwrap();
+        assert_eq!(decompressed.lines().count(), frames.len());
+    }
+
+    /// Format whose writer numbers its frames and writes a footer with the frame count when dropped
+    struct CountingFormat;
+
+    struct CountingWriter {
+        writer: Box<dyn Write>,
+        frames: usize,
+    }
+
+    impl CanLogFormat for CountingFormat {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["cnt"]
+        }
+
+        fn writer(&self, writer: Box<dyn Write>) -> io::Result<Box<dyn CanWriter>> {
+            Ok(Box::new(CountingWriter { writer, frames: 0 }))
+        }
+    }
+
+    impl CanWriter for CountingWriter {
+        fn write(&mut self, frame: &CanFrame) -> io::Result<()> {
+            self.frames += 1;
+            writeln!(self.writer, "frame {:03X}", frame.id)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.writer.flush()
+        }
+    }
+
+    impl Drop for CountingWriter {
+        fn drop(&mut self) {
+            let _ = writeln!(self.writer, "end {}", self.frames);
+        }
+    }
+
+    #[test]
+    fn test_rotate_max_bytes_stateful_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<_> = canlog_reader::CanLogParser::from_file(Path::new("candump.log")).unwrap().collect();
+        let policy = RotationPolicy { max_bytes: Some(30), ..Default::default() };
+        let mut writer = RotatingWriter::with_format(dir.path().join("session.cnt"), Arc::new(CountingFormat), policy)
+            .unwrap();
+        for frame in &frames {
+            writer.write(frame).unwrap();
+        }
+        let segments = writer.close().unwrap();
+
+        assert!(segments.len() > 1);
+        let mut written = 0;
+        for path in &segments {
+            // The footer counts exactly the frames of its own segment
+            let contents = fs::read_to_string(path).unwrap();
+            let (body, footer) = contents.trim_end().rsplit_once('\n').unwrap();
+            let count = body.lines().count();
+            assert_eq!(footer, format!("end {count}"), "{}", path.display());
+            written += count;
+        }
+        assert_eq!(written, frames.len());
+    }
 }
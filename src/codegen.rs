@@ -0,0 +1,699 @@
+/*!
+ * Generates typed Rust code from a DBC: one struct per message with a field per signal,
+ * enums for value descriptions, and `encode`/`decode` methods packing the signals with
+ * the `SignalLayout` segments computed at generation time.
+ * can_dbc::Dbc -> Rust source
+ *
+ * From a build script:
+ * ```no_run
+ * // build.rs
+ * rocketcan::codegen::generate_to_out_dir("can_samples/chrysler_cusw.dbc", "chrysler.rs").unwrap();
+ * ```
+ * and in the crate:
+ * ```ignore
+ * #[allow(clippy::all)]
+ * mod chrysler {
+ *     include!(concat!(env!("OUT_DIR"), "/chrysler.rs"));
+ * }
+ * let frame = chrysler::Gear { prndl: chrysler::GearPrndl::D }.encode();
+ * ```
+ */
+
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use can_dbc::{SignalExtendedValueType, ValueType};
+
+use crate::can_decoder;
+use crate::can_encoder;
+use crate::signal_layout::SignalLayout;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static", "struct",
+    "trait", "true", "type", "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "gen",
+    "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// Rust code generator for the messages of a DBC
+pub struct Codegen<'a> {
+    dbc: &'a can_dbc::Dbc,
+    crate_path: String,
+}
+
+/// Rust type of a generated signal field
+#[derive(Debug, Clone, PartialEq)]
+enum FieldType {
+    Bool,
+    Int { signed: bool, bits: u64 },
+    F32,
+    F64,
+    Enum(String),
+}
+
+impl FieldType {
+    fn rust_type(&self) -> String {
+        match self {
+            FieldType::Bool => "bool".to_owned(),
+            FieldType::Int { signed: true, bits } => format!("i{bits}"),
+            FieldType::Int { signed: false, bits } => format!("u{bits}"),
+            FieldType::F32 => "f32".to_owned(),
+            FieldType::F64 => "f64".to_owned(),
+            FieldType::Enum(name) => name.clone(),
+        }
+    }
+}
+
+/// Everything needed to emit one signal
+struct FieldPlan<'s> {
+    spec: &'s can_dbc::Signal,
+    field: String,
+    const_prefix: String,
+    field_type: FieldType,
+    value_type: SignalExtendedValueType,
+    /// Expression that is true when the multiplexers select the signal, None if always present
+    condition: Option<String>,
+}
+
+impl<'a> Codegen<'a> {
+    pub fn new(dbc: &'a can_dbc::Dbc) -> Self {
+        Self {
+            dbc,
+            crate_path: "rocketcan".to_owned(),
+        }
+    }
+
+    /// Path of the rocketcan crate in the generated code, "rocketcan" by default
+    pub fn crate_path(mut self, crate_path: &str) -> Self {
+        self.crate_path = crate_path.to_owned();
+        self
+    }
+
+    /// Generate the Rust source for all messages of the DBC
+    pub fn generate(&self) -> String {
+        let mut out = String::new();
+        let krate = &self.crate_path;
+        out.push_str("// Generated by rocketcan::codegen, do not edit.\n\n");
+        let _ = writeln!(out, "use {krate}::canlog_reader::CanFrame;");
+        let _ = writeln!(out, "use {krate}::signal_layout::{{extract_spans, pack_spans, BitSpan}};");
+
+        // Enums of global value tables, shared by the signals named after them
+        let mut emitted_enums = HashSet::new();
+        for table in &self.dbc.value_tables {
+            let name = to_camel_case(&table.name);
+            if emitted_enums.insert(name.clone()) {
+                write_enum(&mut out, &name, &format!("VAL_TABLE_ {}", table.name), &table.descriptions);
+            }
+        }
+
+        for message in &self.dbc.messages {
+            let struct_name = to_camel_case(&message.name);
+            let fields = self.plan_fields(message, &struct_name);
+            for field in &fields {
+                let FieldType::Enum(enum_name) = &field.field_type else {
+                    continue;
+                };
+                if emitted_enums.insert(enum_name.clone()) {
+                    let descriptions = can_decoder::get_value_descriptions(self.dbc, message, field.spec).unwrap_or_default();
+                    let doc = format!("{}.{}", message.name, field.spec.name);
+                    write_enum(&mut out, enum_name, &doc, descriptions);
+                }
+            }
+            write_message(&mut out, message, &struct_name, &fields);
+        }
+        out
+    }
+
+    fn plan_fields<'s>(&self, message: &'s can_dbc::Message, struct_name: &str) -> Vec<FieldPlan<'s>> {
+        let mut used_fields = HashSet::new();
+        let mut used_consts = HashSet::from(["ID".to_owned(), "NAME".to_owned(), "LEN".to_owned()]);
+        let mut fields: Vec<FieldPlan> = Vec::with_capacity(message.signals.len());
+        for spec in &message.signals {
+            let field = unique_name(to_field_name(&spec.name), &mut used_fields);
+            let const_prefix = unique_name(to_const_name(&spec.name), &mut used_consts);
+            let value_type = can_decoder::get_value_type(self.dbc, message, spec);
+            let field_type = self.field_type(message, spec, value_type, struct_name);
+            fields.push(FieldPlan {
+                spec,
+                field,
+                const_prefix,
+                field_type,
+                value_type,
+                condition: None,
+            });
+        }
+        // Conditions reference the raw values of the multiplexers by index
+        for field in fields.iter_mut() {
+            let mut terms = Vec::new();
+            let mut spec = field.spec;
+            for _ in 0..=message.signals.len() {
+                let Some(condition) = can_decoder::mux_condition(message, spec, &self.dbc.extended_multiplex) else {
+                    break;
+                };
+                let Some(index) = message.signals.iter().position(|s| s.name == condition.multiplexor.name) else {
+                    break;
+                };
+                let ranges: Vec<String> = condition
+                    .ranges
+                    .iter()
+                    .map(|range| match range.start() == range.end() {
+                        true => format!("raw[{index}] == {}", range.start()),
+                        false => format!("({}..={}).contains(&raw[{index}])", range.start(), range.end()),
+                    })
+                    .collect();
+                terms.push(match ranges.len() {
+                    1 => ranges[0].clone(),
+                    _ => format!("({})", ranges.join(" || ")),
+                });
+                spec = condition.multiplexor;
+            }
+            if !terms.is_empty() {
+                terms.reverse();
+                field.condition = Some(terms.join(" && "));
+            }
+        }
+        fields
+    }
+
+    fn field_type(
+        &self,
+        message: &can_dbc::Message,
+        spec: &can_dbc::Signal,
+        value_type: SignalExtendedValueType,
+        struct_name: &str,
+    ) -> FieldType {
+        let scaled = spec.factor != 1.0 || spec.offset != 0.0;
+        match value_type {
+            SignalExtendedValueType::IEEEfloat32Bit if !scaled => return FieldType::F32,
+            SignalExtendedValueType::IEEEfloat32Bit | SignalExtendedValueType::IEEEdouble64bit => {
+                return FieldType::F64
+            }
+            SignalExtendedValueType::SignedOrUnsignedInteger => {}
+        }
+        let has_own_descriptions = self.dbc.value_descriptions_for_signal(message.id, &spec.name).is_some();
+        if has_own_descriptions {
+            return FieldType::Enum(format!("{struct_name}{}", to_camel_case(&spec.name)));
+        }
+        if self.dbc.value_tables.iter().any(|table| table.name == spec.name) {
+            return FieldType::Enum(to_camel_case(&spec.name));
+        }
+        let signed = spec.value_type == ValueType::Signed;
+        match (scaled, signed, spec.size) {
+            (true, _, _) => FieldType::F64,
+            (false, false, 1) => FieldType::Bool,
+            (false, signed, size) => FieldType::Int {
+                signed,
+                bits: [8, 16, 32, 64].into_iter().find(|bits| size <= *bits).unwrap_or(64),
+            },
+        }
+    }
+}
+
+/// Generate the Rust source for a DBC file, with the default "rocketcan" crate path
+pub fn generate_from_file(dbc_path: &str) -> io::Result<String> {
+    let dbc = can_decoder::load_dbc(dbc_path)?;
+    Ok(Codegen::new(&dbc).generate())
+}
+
+/// Build script helper: generate the code of a DBC into `$OUT_DIR/out_name`
+/// and have cargo rerun the build script when the DBC changes.
+/// Returns the path of the generated file, to `include!` in the crate.
+pub fn generate_to_out_dir<P: AsRef<Path>>(dbc_path: P, out_name: &str) -> io::Result<PathBuf> {
+    let dbc_path = dbc_path.as_ref();
+    let out_dir = env::var_os("OUT_DIR")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set, call from build.rs"))?;
+    let dbc_str = dbc_path
+        .to_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "DBC path is not valid UTF-8"))?;
+    println!("cargo:rerun-if-changed={dbc_str}");
+    let out_path = Path::new(&out_dir).join(out_name);
+    fs::write(&out_path, generate_from_file(dbc_str)?)?;
+    Ok(out_path)
+}
+
+fn write_enum(out: &mut String, name: &str, doc: &str, descriptions: &[can_dbc::ValDescription]) {
+    let mut seen_ids = HashSet::new();
+    let mut used_variants = HashSet::from(["Other".to_owned()]);
+    let mut variants = Vec::new();
+    for description in descriptions {
+        if !seen_ids.insert(description.id) {
+            continue;
+        }
+        let mut variant = to_camel_case(&description.description);
+        if used_variants.contains(&variant) {
+            variant = format!("{variant}{}", description.id.to_string().replace('-', "Neg"));
+        }
+        let variant = unique_name(variant, &mut used_variants);
+        variants.push((variant, description));
+    }
+
+    let _ = writeln!(out, "\n/// Values of {doc}");
+    out.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq)]\n");
+    let _ = writeln!(out, "pub enum {name} {{");
+    for (variant, description) in &variants {
+        let _ = writeln!(out, "    /// {} = {}", description.id, doc_text(&description.description));
+        let _ = writeln!(out, "    {variant},");
+    }
+    out.push_str("    /// Value without a description\n    Other(i64),\n}\n\n");
+
+    let _ = writeln!(out, "impl {name} {{");
+    out.push_str("    pub fn from_raw(raw: i64) -> Self {\n        match raw {\n");
+    for (variant, description) in &variants {
+        let _ = writeln!(out, "            {} => Self::{variant},", description.id);
+    }
+    out.push_str("            other => Self::Other(other),\n        }\n    }\n\n");
+    out.push_str("    pub fn raw(&self) -> i64 {\n        match self {\n");
+    for (variant, description) in &variants {
+        let _ = writeln!(out, "            Self::{variant} => {},", description.id);
+    }
+    out.push_str("            Self::Other(raw) => *raw,\n        }\n    }\n\n");
+    out.push_str("    /// Label of the value in the DBC\n");
+    out.push_str("    pub fn label(&self) -> Option<&'static str> {\n        match self {\n");
+    for (variant, description) in &variants {
+        let _ = writeln!(out, "            Self::{variant} => Some({:?}),", description.description);
+    }
+    out.push_str("            Self::Other(_) => None,\n        }\n    }\n}\n\n");
+
+    let _ = writeln!(out, "impl Default for {name} {{");
+    out.push_str("    fn default() -> Self {\n        Self::from_raw(0)\n    }\n}\n");
+}
+
+fn write_message(out: &mut String, message: &can_dbc::Message, struct_name: &str, fields: &[FieldPlan]) {
+    let id = can_encoder::get_can_id(message);
+    let extended = match message.id {
+        can_dbc::MessageId::Standard(_) => "",
+        can_dbc::MessageId::Extended(_) => " extended",
+    };
+    let _ = writeln!(out, "\n/// {} ({:#X}{extended}), {} bytes", message.name, id, message.size);
+    out.push_str("#[derive(Debug, Clone, PartialEq, Default)]\n");
+    let _ = writeln!(out, "pub struct {struct_name} {{");
+    for field in fields {
+        let spec = field.spec;
+        let mut doc = format!("{} [{}|{}]", spec.name, spec.min, spec.max);
+        if !spec.unit.is_empty() {
+            let _ = write!(doc, " \"{}\"", spec.unit);
+        }
+        if field.condition.is_some() {
+            doc.push_str(", multiplexed: None when not in the frame");
+        }
+        let _ = writeln!(out, "    /// {}", doc_text(&doc));
+        let rust_type = field.field_type.rust_type();
+        match field.condition {
+            Some(_) => {
+                let _ = writeln!(out, "    pub {}: Option<{rust_type}>,", field.field);
+            }
+            None => {
+                let _ = writeln!(out, "    pub {}: {rust_type},", field.field);
+            }
+        }
+    }
+    out.push_str("}\n\n");
+
+    let _ = writeln!(out, "impl {struct_name} {{");
+    let _ = writeln!(out, "    pub const ID: u32 = {id:#X};");
+    let _ = writeln!(out, "    pub const NAME: &'static str = {:?};", message.name);
+    let _ = writeln!(out, "    pub const LEN: u8 = {};", message.size);
+    for field in fields {
+        let spec = field.spec;
+        let prefix = &field.const_prefix;
+        let _ = writeln!(out, "\n    pub const {prefix}_MIN: f64 = {};", float_literal(spec.min));
+        let _ = writeln!(out, "    pub const {prefix}_MAX: f64 = {};", float_literal(spec.max));
+        let _ = writeln!(out, "    pub const {prefix}_UNIT: &'static str = {:?};", spec.unit);
+        let layout = SignalLayout::from_spec(spec);
+        let _ = writeln!(out, "    const {prefix}_SPANS: &'static [BitSpan] = &[");
+        for span in &layout.segments {
+            let _ = writeln!(
+                out,
+                "        BitSpan {{ byte_index: {}, bit_offset: {}, num_bits: {}, value_shift: {} }},",
+                span.byte_index, span.bit_offset, span.num_bits, span.value_shift
+            );
+        }
+        out.push_str("    ];\n");
+    }
+
+    // decode
+    out.push_str("\n    /// Decode the signals of a frame of this message\n");
+    if fields.is_empty() {
+        out.push_str("    pub fn decode(_frame: &CanFrame) -> Self {\n        Self {}\n    }\n");
+    } else {
+        out.push_str("    pub fn decode(frame: &CanFrame) -> Self {\n");
+        out.push_str("        let raw = [\n");
+        for field in fields {
+            let _ = writeln!(out, "            extract_spans(Self::{}_SPANS, &frame.data),", field.const_prefix);
+        }
+        out.push_str("        ];\n        Self {\n");
+        for (i, field) in fields.iter().enumerate() {
+            let value = decode_expr(field, &format!("raw[{i}]"));
+            match &field.condition {
+                Some(condition) => {
+                    let _ = writeln!(out, "            {}: if {condition} {{ Some({value}) }} else {{ None }},", field.field);
+                }
+                None => {
+                    let _ = writeln!(out, "            {}: {value},", field.field);
+                }
+            }
+        }
+        out.push_str("        }\n    }\n");
+    }
+
+    // encode
+    out.push_str("\n    /// Encode into a frame. Multiplexed fields are only packed when Some,\n");
+    out.push_str("    /// the multiplexer fields must select them.\n");
+    out.push_str("    pub fn encode(&self) -> CanFrame {\n");
+    let mutability = if fields.is_empty() { "" } else { "mut " };
+    let _ = writeln!(out, "        let {mutability}frame = CanFrame {{");
+    out.push_str("            id: Self::ID,\n            len: Self::LEN,\n");
+    let _ = writeln!(out, "            is_fd: {},", message.size > 8);
+    out.push_str("            ..Default::default()\n        };\n");
+    for field in fields {
+        let raw = encode_expr(field, "value");
+        let pack = format!("pack_spans(Self::{}_SPANS, &mut frame.data, {raw});", field.const_prefix);
+        match field.condition {
+            Some(_) => {
+                let _ = writeln!(out, "        if let Some(value) = self.{} {{\n            {pack}\n        }}", field.field);
+            }
+            None => {
+                let _ = writeln!(out, "        let value = self.{};\n        {pack}", field.field);
+            }
+        }
+    }
+    out.push_str("        frame\n    }\n}\n");
+}
+
+/// Expression turning the raw u64 `raw` into the field value
+fn decode_expr(field: &FieldPlan, raw: &str) -> String {
+    let spec = field.spec;
+    let signed_raw = || match spec.size {
+        // Shifting by 64 doesn't compile, a 0-bit signal is always 0
+        0 => "0i64".to_owned(),
+        64.. => format!("({raw} as i64)"),
+        size => format!("((({raw} << {shift}) as i64) >> {shift})", shift = 64 - size),
+    };
+    let scale = |value: String| scale_expr(value, spec.factor, spec.offset);
+    match (&field.field_type, field.value_type) {
+        (FieldType::F32, _) => format!("f32::from_bits({raw} as u32)"),
+        (FieldType::F64, SignalExtendedValueType::IEEEfloat32Bit) => {
+            scale(format!("(f32::from_bits({raw} as u32) as f64)"))
+        }
+        (FieldType::F64, SignalExtendedValueType::IEEEdouble64bit) => scale(format!("f64::from_bits({raw})")),
+        (FieldType::F64, _) => match spec.value_type {
+            ValueType::Signed => scale(format!("({} as f64)", signed_raw())),
+            ValueType::Unsigned => scale(format!("({raw} as f64)")),
+        },
+        (FieldType::Bool, _) => format!("{raw} != 0"),
+        (FieldType::Int { signed: false, bits: 64 }, _) => raw.to_owned(),
+        (FieldType::Int { signed: false, bits }, _) => format!("{raw} as u{bits}"),
+        (FieldType::Int { signed: true, bits: 64 }, _) => signed_raw(),
+        (FieldType::Int { signed: true, bits }, _) => format!("{} as i{bits}", signed_raw()),
+        (FieldType::Enum(name), _) => match spec.value_type {
+            ValueType::Signed => format!("{name}::from_raw({})", signed_raw()),
+            ValueType::Unsigned => format!("{name}::from_raw({raw} as i64)"),
+        },
+    }
+}
+
+/// Expression turning the field value `value` into the raw u64 to pack
+fn encode_expr(field: &FieldPlan, value: &str) -> String {
+    let spec = field.spec;
+    let mask = |expr: String| match spec.size {
+        64.. => expr,
+        size => format!("{expr} & {:#X}", (1u64 << size) - 1),
+    };
+    let unscale = || unscale_expr(value, spec.factor, spec.offset);
+    match (&field.field_type, field.value_type) {
+        (FieldType::F32, _) => format!("{value}.to_bits() as u64"),
+        (FieldType::F64, SignalExtendedValueType::IEEEfloat32Bit) => format!("({} as f32).to_bits() as u64", unscale()),
+        (FieldType::F64, SignalExtendedValueType::IEEEdouble64bit) => format!("{}.to_bits()", unscale()),
+        (FieldType::F64, _) => match spec.value_type {
+            ValueType::Signed => mask(format!("({}.round() as i64 as u64)", unscale())),
+            ValueType::Unsigned => mask(format!("({}.round() as u64)", unscale())),
+        },
+        (FieldType::Bool, _) => format!("{value} as u64"),
+        (FieldType::Int { signed: false, bits: 64 }, _) => value.to_owned(),
+        (FieldType::Int { signed: false, .. }, _) => mask(format!("({value} as u64)")),
+        (FieldType::Int { signed: true, bits: 64 }, _) => mask(format!("({value} as u64)")),
+        (FieldType::Int { signed: true, .. }, _) => mask(format!("({value} as i64 as u64)")),
+        (FieldType::Enum(_), _) => mask(format!("({value}.raw() as u64)")),
+    }
+}
+
+/// `value * factor + offset`, leaving out identity operations
+fn scale_expr(value: String, factor: f64, offset: f64) -> String {
+    let scaled = match factor == 1.0 {
+        true => value,
+        false => format!("{value} * {}", float_literal(factor)),
+    };
+    if offset == 0.0 {
+        scaled
+    } else if offset < 0.0 {
+        format!("{scaled} - {}", float_literal(-offset))
+    } else {
+        format!("{scaled} + {}", float_literal(offset))
+    }
+}
+
+/// `(value - offset) / factor`, leaving out identity operations
+fn unscale_expr(value: &str, factor: f64, offset: f64) -> String {
+    let shifted = if offset == 0.0 {
+        value.to_owned()
+    } else if offset < 0.0 {
+        format!("({value} + {})", float_literal(-offset))
+    } else {
+        format!("({value} - {})", float_literal(offset))
+    };
+    match factor == 1.0 {
+        true => shifted,
+        false => format!("({shifted} / {})", float_literal(factor)),
+    }
+}
+
+fn float_literal(value: f64) -> String {
+    if value.is_nan() {
+        "f64::NAN".to_owned()
+    } else if value.is_infinite() {
+        if value > 0.0 { "f64::INFINITY" } else { "f64::NEG_INFINITY" }.to_owned()
+    } else {
+        format!("{value:?}")
+    }
+}
+
+/// Single line text for a doc comment
+fn doc_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Append a number to `name` until it is not in `used`
+fn unique_name(name: String, used: &mut HashSet<String>) -> String {
+    let mut candidate = name.clone();
+    let mut n = 2;
+    while used.contains(&candidate) {
+        candidate = format!("{name}_{n}");
+        n += 1;
+    }
+    used.insert(candidate.clone());
+    candidate
+}
+
+/// Split a DBC name into words: on non alphanumerics and at lower to upper case changes
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && prev_lower && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        prev_lower = c.is_ascii_lowercase();
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// "WHEEL_SPEEDS_REAR" -> "WheelSpeedsRear", "active faults" -> "ActiveFaults"
+fn to_camel_case(name: &str) -> String {
+    let mut camel = String::new();
+    for word in split_words(name) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel.push(first.to_ascii_uppercase());
+            camel.push_str(&chars.as_str().to_ascii_lowercase());
+        }
+    }
+    match camel.chars().next() {
+        None => "Value".to_owned(),
+        Some(c) if c.is_ascii_digit() => format!("V{camel}"),
+        Some(_) if camel == "Self" => "Self_".to_owned(),
+        Some(_) => camel,
+    }
+}
+
+/// "STEER_ANGLE" -> "steer_angle", "AverageRadius" -> "average_radius"
+fn to_field_name(name: &str) -> String {
+    let snake = split_words(name).join("_").to_ascii_lowercase();
+    match snake.as_str() {
+        "" => "signal".to_owned(),
+        "self" | "super" | "crate" => format!("{snake}_"),
+        s if RUST_KEYWORDS.contains(&s) => format!("r#{snake}"),
+        s if s.starts_with(|c: char| c.is_ascii_digit()) => format!("_{snake}"),
+        _ => snake,
+    }
+}
+
+/// "STEER_ANGLE" -> "STEER_ANGLE", "AverageRadius" -> "AVERAGE_RADIUS"
+fn to_const_name(name: &str) -> String {
+    let constant = split_words(name).join("_").to_ascii_uppercase();
+    match constant.chars().next() {
+        None => "SIGNAL".to_owned(),
+        Some(c) if c.is_ascii_digit() => format!("_{constant}"),
+        Some(_) => constant,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canlog_reader;
+
+    // Snapshots of the generated code, compiled as part of the tests.
+    // After changing the generator, regenerate them with
+    //   cargo run -- dbc codegen --crate-path crate <dbc> -o codegen_samples/<name>.rs
+    #[allow(clippy::all, dead_code)]
+    mod motohawk {
+        include!("../codegen_samples/motohawk.rs");
+    }
+    #[allow(clippy::all, dead_code)]
+    mod multiplex {
+        include!("../codegen_samples/multiplex.rs");
+    }
+    #[allow(clippy::all, dead_code)]
+    mod floats {
+        include!("../codegen_samples/floats.rs");
+    }
+    #[allow(clippy::all, dead_code)]
+    mod codegen {
+        include!("../codegen_samples/codegen.rs");
+    }
+
+    fn generate(dbc_path: &str) -> String {
+        let dbc = can_decoder::load_dbc(dbc_path).unwrap();
+        Codegen::new(&dbc).crate_path("crate").generate()
+    }
+
+    #[test]
+    fn test_snapshots_up_to_date() {
+        for (dbc_path, snapshot) in [
+            ("motohawk.dbc", include_str!("../codegen_samples/motohawk.rs")),
+            ("multiplex.dbc", include_str!("../codegen_samples/multiplex.rs")),
+            ("floats.dbc", include_str!("../codegen_samples/floats.rs")),
+            ("codegen.dbc", include_str!("../codegen_samples/codegen.rs")),
+        ] {
+            assert_eq!(generate(dbc_path), snapshot, "codegen_samples snapshot of {dbc_path} is out of date");
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(to_camel_case("WHEEL_SPEEDS_REAR"), "WheelSpeedsRear");
+        assert_eq!(to_camel_case("ExampleMessage"), "ExampleMessage");
+        assert_eq!(to_camel_case("active faults stored"), "ActiveFaultsStored");
+        assert_eq!(to_camel_case("4WD"), "V4wd");
+        assert_eq!(to_field_name("AverageRadius"), "average_radius");
+        assert_eq!(to_field_name("STEER_ANGLE"), "steer_angle");
+        assert_eq!(to_field_name("Type"), "r#type");
+        assert_eq!(to_field_name("s10big"), "s10big");
+        assert_eq!(to_const_name("AverageRadius"), "AVERAGE_RADIUS");
+    }
+
+    #[test]
+    fn test_generated_motohawk_matches_runtime_decode() {
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 1F0#A5B6D90000000000").unwrap();
+        let message = motohawk::ExampleMessage::decode(&frame);
+        assert_eq!(message.temperature, 244.14);
+        assert_eq!(message.average_radius, 1.8);
+        assert_eq!(message.enable, motohawk::ExampleMessageEnable::Enabled);
+        assert_eq!(message.enable.label(), Some("Enabled"));
+        assert_eq!(motohawk::ExampleMessage::TEMPERATURE_UNIT, "degK");
+
+        let encoded = message.encode();
+        assert_eq!(encoded.id, 0x1F0);
+        assert_eq!(&encoded.data[..2], &frame.data[..2]);
+        assert_eq!(motohawk::ExampleMessage::decode(&encoded), message);
+    }
+
+    #[test]
+    fn test_generated_enums_and_multiplexing() {
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 542#0202D430FEFF0000").unwrap();
+        let diagnostics = multiplex::Diagnostics::decode(&frame);
+        assert_eq!(diagnostics.page, multiplex::DiagnosticsPage::Supply);
+        assert_eq!(diagnostics.status, multiplex::Status::Running);
+        assert_eq!(diagnostics.voltage, Some(12.5));
+        assert_eq!(diagnostics.current, Some(-0.02));
+        assert_eq!(diagnostics.sw_major, None);
+        assert_eq!(diagnostics.encode().data, frame.data);
+
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 00000602#6202A500003039").unwrap();
+        let response = multiplex::UdsResponse::decode(&frame);
+        assert_eq!(response.identifier, Some(677));
+        assert_eq!(response.odometer, Some(1234.5));
+        assert_eq!(response.coolant_temp, None);
+        assert_eq!(response.response_code, None);
+
+        let gear = codegen::Gear { prndl: codegen::GearPrndl::D, torque: -10.5 }.encode();
+        assert_eq!(gear.id, 0x4EE);
+        assert_eq!(gear.data[1..4], [0x04, 0xFF, 0xEB]);
+        assert_eq!(codegen::Gear::decode(&gear).torque, -10.5);
+        assert_eq!(codegen::GearPrndl::from_raw(4).label(), Some("D"));
+        assert_eq!(codegen::GearPrndl::from_raw(9), codegen::GearPrndl::Other(9));
+    }
+
+    #[test]
+    fn test_generated_floats() {
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 300#00004841000050C0").unwrap();
+        let floats = floats::FloatsLe::decode(&frame);
+        assert_eq!(floats.speed, 12.5f32);
+        assert_eq!(floats.gain, 3.5);
+        assert_eq!(floats.encode().data, frame.data);
+
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 303#C0C1486E147AE148").unwrap();
+        assert_eq!(floats::DoubleBe::decode(&frame).altitude, -8848.86);
+        assert_eq!(floats::DoubleBe { altitude: -8848.86 }.encode().data, frame.data);
+
+        // A multiplexed float32
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 500#020000000000C03F").unwrap();
+        let mixed = codegen::Mixed::decode(&frame);
+        assert_eq!((mixed.offset, mixed.ratio), (None, Some(1.5)));
+        assert_eq!(mixed.encode().data, frame.data);
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 500#01F6FF0000000000").unwrap();
+        assert_eq!(codegen::Mixed::decode(&frame).offset, Some(-1.0));
+    }
+
+    #[test]
+    fn test_generated_empty_signed_signal() {
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 400#F6FF").unwrap();
+        let signed = codegen::Signed::decode(&frame);
+        assert_eq!((signed.reserved, signed.temperature), (0, -10));
+        assert_eq!(signed.encode().data[..2], [0xF6, 0x00]);
+    }
+
+    #[test]
+    fn test_generate_to_out_dir_needs_out_dir() {
+        // Only meaningful from a build script
+        if env::var_os("OUT_DIR").is_none() {
+            assert!(generate_to_out_dir("motohawk.dbc", "motohawk.rs").is_err());
+        }
+    }
+}
@@ -0,0 +1,295 @@
+/*!
+ * Typed access to DBC attributes (BA_), falling back to the BA_DEF_DEF_ default,
+ * and the attributes rocketcan uses: GenMsgCycleTime, GenMsgSendType,
+ * GenSigStartValue and VFrameFormat.
+ * (can_dbc::Dbc, object, attribute name) -> Attribute
+ *
+ * ```no_run
+ * use rocketcan::{can_decoder, dbc_attributes};
+ *
+ * let dbc = can_decoder::load_dbc("abs.dbc").unwrap();
+ * for message in &dbc.messages {
+ *     let cycle_time = dbc_attributes::cycle_time(&dbc, message);
+ *     let format = dbc_attributes::frame_format(&dbc, message);
+ *     println!("{}: {cycle_time:?} ms, {format:?}", message.name);
+ * }
+ * ```
+ */
+
+use can_dbc::{AttributeDefinition, AttributeValue, AttributeValueType, Dbc};
+
+/// An attribute value, typed by its BA_DEF_ definition
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+    /// INT and HEX attributes
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// ENUM attributes: the label and its index in the definition
+    Enum(String, usize),
+}
+
+impl Attribute {
+    /// Numeric value, the index for enums, None for strings
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Attribute::Int(value) => Some(*value as f64),
+            Attribute::Float(value) => Some(*value),
+            Attribute::Enum(_, index) => Some(*index as f64),
+            Attribute::String(_) => None,
+        }
+    }
+
+    /// Integer value, the index for enums, None for strings and non-integer floats
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Attribute::Int(value) => Some(*value),
+            Attribute::Float(value) if value.fract() == 0.0 => Some(*value as i64),
+            Attribute::Enum(_, index) => Some(*index as i64),
+            _ => None,
+        }
+    }
+
+    /// Text of strings, label of enums
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Attribute::String(value) | Attribute::Enum(value, _) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Kind of object an attribute is defined for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ObjectKind {
+    Database,
+    Node,
+    Message,
+    Signal,
+}
+
+/// Attribute of the whole database (BA_ without object)
+pub fn database_attribute(dbc: &Dbc, name: &str) -> Option<Attribute> {
+    let value = dbc.attribute_values_database.iter().find(|v| v.name == name).map(|v| &v.value);
+    resolve(dbc, ObjectKind::Database, name, value)
+}
+
+/// Attribute of a node (BA_ BU_)
+pub fn node_attribute(dbc: &Dbc, node_name: &str, name: &str) -> Option<Attribute> {
+    let value = dbc
+        .attribute_values_node
+        .iter()
+        .find(|v| v.name == name && v.node_name == node_name)
+        .map(|v| &v.value);
+    resolve(dbc, ObjectKind::Node, name, value)
+}
+
+/// Attribute of a message (BA_ BO_)
+pub fn message_attribute(dbc: &Dbc, message_spec: &can_dbc::Message, name: &str) -> Option<Attribute> {
+    let value = dbc
+        .attribute_values_message
+        .iter()
+        .find(|v| v.name == name && v.message_id == message_spec.id)
+        .map(|v| &v.value);
+    resolve(dbc, ObjectKind::Message, name, value)
+}
+
+/// Attribute of a signal (BA_ SG_)
+pub fn signal_attribute(
+    dbc: &Dbc,
+    message_spec: &can_dbc::Message,
+    signal_spec: &can_dbc::Signal,
+    name: &str,
+) -> Option<Attribute> {
+    let value = dbc
+        .attribute_values_signal
+        .iter()
+        .find(|v| v.name == name && v.message_id == message_spec.id && v.signal_name == signal_spec.name)
+        .map(|v| &v.value);
+    resolve(dbc, ObjectKind::Signal, name, value)
+}
+
+/// Type the value set on the object, or the default when there is none.
+/// Without a definition, values keep the type they were written with.
+fn resolve(dbc: &Dbc, kind: ObjectKind, name: &str, value: Option<&AttributeValue>) -> Option<Attribute> {
+    let definitions: Vec<(Option<ObjectKind>, &AttributeValueType)> = dbc
+        .attribute_definitions
+        .iter()
+        .filter_map(|definition| {
+            let (definition_kind, definition_name, value_type) = match definition {
+                AttributeDefinition::Plain(n, t) => (Some(ObjectKind::Database), n, t),
+                AttributeDefinition::Node(n, t) => (Some(ObjectKind::Node), n, t),
+                AttributeDefinition::Message(n, t) => (Some(ObjectKind::Message), n, t),
+                AttributeDefinition::Signal(n, t) => (Some(ObjectKind::Signal), n, t),
+                AttributeDefinition::EnvironmentVariable(n, t) => (None, n, t),
+            };
+            (definition_name == name).then_some((definition_kind, value_type))
+        })
+        .collect();
+    let value_type = definitions.iter().find(|(k, _)| *k == Some(kind)).map(|(_, t)| *t);
+    // Defined for other objects only
+    if value_type.is_none() && !definitions.is_empty() {
+        return None;
+    }
+    let default = || dbc.attribute_defaults.iter().find(|d| d.name == name).map(|d| &d.value);
+    // Values that don't fit the definition (e.g. an enum default of "") fall back to the default
+    value
+        .and_then(|value| typed(value, value_type))
+        .or_else(|| default().and_then(|value| typed(value, value_type)))
+}
+
+fn typed(value: &AttributeValue, value_type: Option<&AttributeValueType>) -> Option<Attribute> {
+    let number = match value {
+        AttributeValue::Uint(v) => Some(*v as f64),
+        AttributeValue::Int(v) => Some(*v as f64),
+        AttributeValue::Double(v) => Some(*v),
+        AttributeValue::String(_) => None,
+    };
+    match (value_type, value) {
+        (Some(AttributeValueType::Enum(labels)), AttributeValue::String(label)) => {
+            let index = labels.iter().position(|l| l == label)?;
+            Some(Attribute::Enum(label.clone(), index))
+        }
+        (Some(AttributeValueType::Enum(labels)), _) => {
+            let index = number.filter(|n| *n >= 0.0)? as usize;
+            Some(Attribute::Enum(labels.get(index)?.clone(), index))
+        }
+        (Some(AttributeValueType::Int(..) | AttributeValueType::Hex(..)), _) => Some(Attribute::Int(number? as i64)),
+        (Some(AttributeValueType::Float(..)), _) => Some(Attribute::Float(number?)),
+        (Some(AttributeValueType::String), AttributeValue::String(text)) => Some(Attribute::String(text.clone())),
+        (Some(AttributeValueType::String), _) => None,
+        (None, AttributeValue::Uint(v)) => Some(Attribute::Int(*v as i64)),
+        (None, AttributeValue::Int(v)) => Some(Attribute::Int(*v)),
+        (None, AttributeValue::Double(v)) => Some(Attribute::Float(*v)),
+        (None, AttributeValue::String(text)) => Some(Attribute::String(text.clone())),
+    }
+}
+
+/// Frame type from the VFrameFormat message attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    StandardCan,
+    ExtendedCan,
+    StandardCanFd,
+    ExtendedCanFd,
+    /// J1939 parameter group, extended ID
+    J1939,
+}
+
+impl FrameFormat {
+    pub fn is_fd(&self) -> bool {
+        matches!(self, FrameFormat::StandardCanFd | FrameFormat::ExtendedCanFd)
+    }
+
+    pub fn is_extended(&self) -> bool {
+        matches!(self, FrameFormat::ExtendedCan | FrameFormat::ExtendedCanFd | FrameFormat::J1939)
+    }
+}
+
+/// Whether the ProtocolType database attribute marks the DBC as a J1939 database
+pub fn is_j1939_database(dbc: &Dbc) -> bool {
+    database_attribute(dbc, "ProtocolType").is_some_and(|protocol| protocol.as_str() == Some("J1939"))
+}
+
+/// Frame type of a message from VFrameFormat, None when not set or unknown
+pub fn frame_format(dbc: &Dbc, message_spec: &can_dbc::Message) -> Option<FrameFormat> {
+    match message_attribute(dbc, message_spec, "VFrameFormat")?.as_str()? {
+        "StandardCAN" => Some(FrameFormat::StandardCan),
+        "ExtendedCAN" => Some(FrameFormat::ExtendedCan),
+        "StandardCAN_FD" => Some(FrameFormat::StandardCanFd),
+        "ExtendedCAN_FD" => Some(FrameFormat::ExtendedCanFd),
+        "J1939PG" => Some(FrameFormat::J1939),
+        _ => None,
+    }
+}
+
+/// Period of a cyclic message in ms, from GenMsgCycleTime
+pub fn cycle_time(dbc: &Dbc, message_spec: &can_dbc::Message) -> Option<u64> {
+    message_attribute(dbc, message_spec, "GenMsgCycleTime")?
+        .as_i64()
+        .and_then(|ms| u64::try_from(ms).ok())
+}
+
+/// Send type of a message from GenMsgSendType, e.g. "Cyclic" or "OnChange"
+pub fn send_type(dbc: &Dbc, message_spec: &can_dbc::Message) -> Option<String> {
+    message_attribute(dbc, message_spec, "GenMsgSendType")?.as_str().map(str::to_owned)
+}
+
+/// Raw value a signal has before the application sets it, from GenSigStartValue
+pub fn start_value(dbc: &Dbc, message_spec: &can_dbc::Message, signal_spec: &can_dbc::Signal) -> Option<f64> {
+    signal_attribute(dbc, message_spec, signal_spec, "GenSigStartValue")?.as_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::can_decoder;
+
+    const DBC: &str = concat!(
+        "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: Engine Gateway\n\n",
+        "BO_ 100 Cyclic: 8 Engine\n",
+        " SG_ Counter : 0|4@1+ (1,0) [0|15] \"\" Gateway\n",
+        " SG_ Speed : 8|16@1+ (0.5,0) [0|1000] \"km/h\" Gateway\n\n",
+        "BO_ 200 Fd: 16 Engine\n",
+        " SG_ Value : 0|8@1+ (1,0) [0|255] \"\" Gateway\n\n",
+        "BA_DEF_  \"BusType\" STRING ;\n",
+        "BA_DEF_ BU_  \"NodeAddress\" HEX 0 255;\n",
+        "BA_DEF_ BO_  \"GenMsgCycleTime\" INT 0 10000;\n",
+        "BA_DEF_ BO_  \"GenMsgSendType\" ENUM  \"Cyclic\",\"OnChange\";\n",
+        "BA_DEF_ BO_  \"VFrameFormat\" ENUM  \"StandardCAN\",\"ExtendedCAN\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"reserved\",\"StandardCAN_FD\",\"ExtendedCAN_FD\";\n",
+        "BA_DEF_ SG_  \"GenSigStartValue\" FLOAT 0 100000;\n",
+        "BA_DEF_DEF_  \"BusType\" \"CAN\";\n",
+        "BA_DEF_DEF_  \"NodeAddress\" 0;\n",
+        "BA_DEF_DEF_  \"GenMsgCycleTime\" 100;\n",
+        "BA_DEF_DEF_  \"GenMsgSendType\" \"Cyclic\";\n",
+        "BA_DEF_DEF_  \"VFrameFormat\" \"\";\n",
+        "BA_DEF_DEF_  \"GenSigStartValue\" 0;\n",
+        "BA_ \"BusType\" \"CAN FD\";\n",
+        "BA_ \"NodeAddress\" BU_ Gateway 23;\n",
+        "BA_ \"GenMsgCycleTime\" BO_ 100 20;\n",
+        "BA_ \"GenMsgSendType\" BO_ 200 1;\n",
+        "BA_ \"VFrameFormat\" BO_ 100 0;\n",
+        "BA_ \"VFrameFormat\" BO_ 200 14;\n",
+        "BA_ \"GenSigStartValue\" SG_ 100 Speed 200;\n",
+    );
+
+    #[test]
+    fn test_values_and_defaults() {
+        let dbc = can_decoder::dbc_from_str(DBC).unwrap();
+        let cyclic = can_decoder::get_message_spec(&dbc, "Cyclic").unwrap();
+        let fd = can_decoder::get_message_spec(&dbc, "Fd").unwrap();
+
+        assert_eq!(database_attribute(&dbc, "BusType"), Some(Attribute::String("CAN FD".to_owned())));
+        assert_eq!(node_attribute(&dbc, "Gateway", "NodeAddress"), Some(Attribute::Int(23)));
+        assert_eq!(node_attribute(&dbc, "Engine", "NodeAddress"), Some(Attribute::Int(0)));
+        assert_eq!(message_attribute(&dbc, cyclic, "GenMsgCycleTime"), Some(Attribute::Int(20)));
+        assert_eq!(message_attribute(&dbc, fd, "GenMsgSendType"), Some(Attribute::Enum("OnChange".to_owned(), 1)));
+        assert_eq!(message_attribute(&dbc, cyclic, "Unknown"), None);
+        // Attributes are looked up by object kind
+        assert_eq!(message_attribute(&dbc, cyclic, "NodeAddress").and_then(|a| a.as_i64()), None);
+
+        assert_eq!(cycle_time(&dbc, cyclic), Some(20));
+        assert_eq!(cycle_time(&dbc, fd), Some(100));
+        assert_eq!(send_type(&dbc, cyclic).as_deref(), Some("Cyclic"));
+        assert_eq!(frame_format(&dbc, cyclic), Some(FrameFormat::StandardCan));
+        assert_eq!(frame_format(&dbc, fd), Some(FrameFormat::StandardCanFd));
+
+        let speed = can_decoder::get_signal_spec(cyclic, "Speed").unwrap();
+        let counter = can_decoder::get_signal_spec(cyclic, "Counter").unwrap();
+        assert_eq!(start_value(&dbc, cyclic, speed), Some(200.0));
+        assert_eq!(start_value(&dbc, cyclic, counter), Some(0.0));
+    }
+
+    #[test]
+    fn test_invalid_enum_default() {
+        // abs.dbc has VFrameFormat "" as default and 0 on every message
+        let dbc = can_decoder::load_dbc("abs.dbc").unwrap();
+        for message in &dbc.messages {
+            assert_eq!(frame_format(&dbc, message), Some(FrameFormat::StandardCan), "{}", message.name);
+        }
+        let mut without_values = dbc.clone();
+        without_values.attribute_values_message.clear();
+        assert_eq!(frame_format(&without_values, &dbc.messages[0]), None);
+        assert_eq!(cycle_time(&without_values, &dbc.messages[0]), Some(10));
+    }
+}
@@ -0,0 +1,457 @@
+/*!
+ * Semantic diff of two DBCs, and the decoding impact of the changes on a log.
+ * (old Dbc, new Dbc) -> Vec<DbcChange>
+ * (old CanDatabase, new CanDatabase, Iterator<CanFrame>) -> ImpactReport
+ *
+ * Messages are matched by name, then the remaining ones by ID (reported as renamed).
+ * Signals are matched by name within matched messages.
+ *
+ * ```no_run
+ * use std::path::Path;
+ * use rocketcan::can_database::CanDatabase;
+ * use rocketcan::canlog_reader::CanLogParser;
+ * use rocketcan::{can_decoder, dbc_diff};
+ *
+ * let old = can_decoder::load_dbc("v2.2.dbc").unwrap();
+ * let new = can_decoder::load_dbc("v2.3.dbc").unwrap();
+ * for change in dbc_diff::diff(&old, &new) {
+ *     println!("{change}");
+ * }
+ *
+ * let frames = CanLogParser::from_file(Path::new("candump.log")).unwrap();
+ * let report = dbc_diff::replay_impact(&CanDatabase::from_dbc(old), &CanDatabase::from_dbc(new), frames);
+ * print!("{report}");
+ * ```
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use can_dbc::{ByteOrder, Dbc, MultiplexIndicator, ValueType};
+
+use crate::can_database::CanDatabase;
+use crate::can_decoder;
+use crate::can_encoder;
+use crate::canlog_reader::CanFrame;
+
+/// One difference between the old and the new DBC
+#[derive(Debug, Clone, PartialEq)]
+pub enum DbcChange {
+    AddedMessage { message: String, id: u32 },
+    RemovedMessage { message: String, id: u32 },
+    /// Same ID, different name
+    RenamedMessage { old: String, new: String },
+    MessageId { message: String, old: u32, new: u32 },
+    MessageSize { message: String, old: u64, new: u64 },
+    AddedSignal { message: String, signal: String },
+    RemovedSignal { message: String, signal: String },
+    /// Start bit, size, byte order or signedness, as `start|size@order sign`
+    Layout { message: String, signal: String, old: String, new: String },
+    /// (factor, offset)
+    Scaling { message: String, signal: String, old: (f64, f64), new: (f64, f64) },
+    /// (min, max)
+    Range { message: String, signal: String, old: (f64, f64), new: (f64, f64) },
+    Unit { message: String, signal: String, old: String, new: String },
+    /// Multiplexer indicator, as in the DBC (M, m<n>, m<n>M or empty)
+    Multiplexing { message: String, signal: String, old: String, new: String },
+    /// Value descriptions added, removed or relabeled, as (raw value, old label, new label)
+    ValueTable { message: String, signal: String, changes: Vec<(i64, Option<String>, Option<String>)> },
+}
+
+impl fmt::Display for DbcChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbcChange::AddedMessage { message, id } => write!(f, "+ message {message} ({id:#X})"),
+            DbcChange::RemovedMessage { message, id } => write!(f, "- message {message} ({id:#X})"),
+            DbcChange::RenamedMessage { old, new } => write!(f, "~ message {old}: renamed to {new}"),
+            DbcChange::MessageId { message, old, new } => write!(f, "~ message {message}: ID {old:#X} -> {new:#X}"),
+            DbcChange::MessageSize { message, old, new } => {
+                write!(f, "~ message {message}: size {old} -> {new} bytes")
+            }
+            DbcChange::AddedSignal { message, signal } => write!(f, "+ signal {message}.{signal}"),
+            DbcChange::RemovedSignal { message, signal } => write!(f, "- signal {message}.{signal}"),
+            DbcChange::Layout { message, signal, old, new } => write!(f, "~ {message}.{signal}: layout {old} -> {new}"),
+            DbcChange::Scaling { message, signal, old, new } => {
+                write!(f, "~ {message}.{signal}: scaling ({},{}) -> ({},{})", old.0, old.1, new.0, new.1)
+            }
+            DbcChange::Range { message, signal, old, new } => {
+                write!(f, "~ {message}.{signal}: range [{}|{}] -> [{}|{}]", old.0, old.1, new.0, new.1)
+            }
+            DbcChange::Unit { message, signal, old, new } => {
+                write!(f, "~ {message}.{signal}: unit \"{old}\" -> \"{new}\"")
+            }
+            DbcChange::Multiplexing { message, signal, old, new } => {
+                write!(f, "~ {message}.{signal}: multiplexing \"{old}\" -> \"{new}\"")
+            }
+            DbcChange::ValueTable { message, signal, changes } => {
+                write!(f, "~ {message}.{signal}: value table")?;
+                // Words rather than +/- signs, which would read as the sign of the value
+                let mut separator = " ";
+                for (value, old, new) in changes {
+                    match (old, new) {
+                        (None, Some(new)) => write!(f, "{separator}added {value}=\"{new}\"")?,
+                        (Some(old), None) => write!(f, "{separator}removed {value}=\"{old}\"")?,
+                        (Some(old), Some(new)) => write!(f, "{separator}{value}=\"{old}\"->\"{new}\"")?,
+                        (None, None) => continue,
+                    }
+                    separator = ", ";
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Differences between two DBCs, removed messages first, then changed and added
+/// messages in the order of the new DBC
+pub fn diff(old: &Dbc, new: &Dbc) -> Vec<DbcChange> {
+    let new_by_name: HashMap<&str, usize> = new.messages.iter().enumerate().map(|(i, m)| (m.name.as_str(), i)).collect();
+    let mut pairs: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+    let mut new_matched = vec![false; new.messages.len()];
+    let mut old_unmatched = Vec::new();
+    for (i, message) in old.messages.iter().enumerate() {
+        match new_by_name.get(message.name.as_str()) {
+            Some(&j) if !new_matched[j] => {
+                new_matched[j] = true;
+                pairs.push((Some(i), Some(j)));
+            }
+            _ => old_unmatched.push(i),
+        }
+    }
+    for i in old_unmatched {
+        let id = can_encoder::get_can_id(&old.messages[i]);
+        let renamed = new
+            .messages
+            .iter()
+            .enumerate()
+            .find(|(j, m)| !new_matched[*j] && can_encoder::get_can_id(m) == id)
+            .map(|(j, _)| j);
+        if let Some(j) = renamed {
+            new_matched[j] = true;
+        }
+        pairs.push((Some(i), renamed));
+    }
+    pairs.extend(new_matched.iter().enumerate().filter(|(_, matched)| !**matched).map(|(j, _)| (None, Some(j))));
+    // Removed first, then in the order of the new DBC
+    pairs.sort_by_key(|&(i, j)| (j.is_some(), j, i));
+
+    let mut changes = Vec::new();
+    for pair in pairs {
+        match pair {
+            (Some(i), None) => {
+                let message = &old.messages[i];
+                changes.push(DbcChange::RemovedMessage { message: message.name.clone(), id: can_encoder::get_can_id(message) });
+            }
+            (None, Some(j)) => {
+                let message = &new.messages[j];
+                changes.push(DbcChange::AddedMessage { message: message.name.clone(), id: can_encoder::get_can_id(message) });
+            }
+            (Some(i), Some(j)) => diff_message(old, &old.messages[i], new, &new.messages[j], &mut changes),
+            (None, None) => {}
+        }
+    }
+    changes
+}
+
+fn diff_message(
+    old_dbc: &Dbc,
+    old: &can_dbc::Message,
+    new_dbc: &Dbc,
+    new: &can_dbc::Message,
+    changes: &mut Vec<DbcChange>,
+) {
+    let message = new.name.clone();
+    if old.name != new.name {
+        changes.push(DbcChange::RenamedMessage { old: old.name.clone(), new: new.name.clone() });
+    }
+    let (old_id, new_id) = (can_encoder::get_can_id(old), can_encoder::get_can_id(new));
+    if old_id != new_id {
+        changes.push(DbcChange::MessageId { message: message.clone(), old: old_id, new: new_id });
+    }
+    if old.size != new.size {
+        changes.push(DbcChange::MessageSize { message: message.clone(), old: old.size, new: new.size });
+    }
+
+    for signal in &old.signals {
+        if !new.signals.iter().any(|s| s.name == signal.name) {
+            changes.push(DbcChange::RemovedSignal { message: message.clone(), signal: signal.name.clone() });
+        }
+    }
+    for new_signal in &new.signals {
+        let Some(old_signal) = old.signals.iter().find(|s| s.name == new_signal.name) else {
+            changes.push(DbcChange::AddedSignal { message: message.clone(), signal: new_signal.name.clone() });
+            continue;
+        };
+        let signal = new_signal.name.clone();
+        let (old_layout, new_layout) = (layout(old_signal), layout(new_signal));
+        if old_layout != new_layout {
+            changes.push(DbcChange::Layout { message: message.clone(), signal: signal.clone(), old: old_layout, new: new_layout });
+        }
+        let (old_scaling, new_scaling) = ((old_signal.factor, old_signal.offset), (new_signal.factor, new_signal.offset));
+        if old_scaling != new_scaling {
+            changes.push(DbcChange::Scaling { message: message.clone(), signal: signal.clone(), old: old_scaling, new: new_scaling });
+        }
+        let (old_range, new_range) = ((old_signal.min, old_signal.max), (new_signal.min, new_signal.max));
+        if old_range != new_range {
+            changes.push(DbcChange::Range { message: message.clone(), signal: signal.clone(), old: old_range, new: new_range });
+        }
+        if old_signal.unit != new_signal.unit {
+            changes.push(DbcChange::Unit {
+                message: message.clone(),
+                signal: signal.clone(),
+                old: old_signal.unit.clone(),
+                new: new_signal.unit.clone(),
+            });
+        }
+        let (old_mux, new_mux) = (multiplexing(&old_signal.multiplexer_indicator), multiplexing(&new_signal.multiplexer_indicator));
+        if old_mux != new_mux {
+            changes.push(DbcChange::Multiplexing { message: message.clone(), signal: signal.clone(), old: old_mux, new: new_mux });
+        }
+
+        let old_values = value_table(old_dbc, old, old_signal);
+        let new_values = value_table(new_dbc, new, new_signal);
+        let mut value_changes = Vec::new();
+        for value in old_values.keys().chain(new_values.keys().filter(|v| !old_values.contains_key(v))) {
+            let (old_label, new_label) = (old_values.get(value), new_values.get(value));
+            if old_label != new_label {
+                value_changes.push((*value, old_label.cloned(), new_label.cloned()));
+            }
+        }
+        if !value_changes.is_empty() {
+            value_changes.sort_by_key(|(value, _, _)| *value);
+            changes.push(DbcChange::ValueTable { message: message.clone(), signal, changes: value_changes });
+        }
+    }
+}
+
+/// `start|size@order sign`, as in the DBC SG_ line
+fn layout(signal: &can_dbc::Signal) -> String {
+    let order = match signal.byte_order {
+        ByteOrder::LittleEndian => 1,
+        ByteOrder::BigEndian => 0,
+    };
+    let sign = match signal.value_type {
+        ValueType::Signed => '-',
+        ValueType::Unsigned => '+',
+    };
+    format!("{}|{}@{order}{sign}", signal.start_bit, signal.size)
+}
+
+fn multiplexing(indicator: &MultiplexIndicator) -> String {
+    match indicator {
+        MultiplexIndicator::Plain => String::new(),
+        MultiplexIndicator::Multiplexor => "M".to_owned(),
+        MultiplexIndicator::MultiplexedSignal(page) => format!("m{page}"),
+        MultiplexIndicator::MultiplexorAndMultiplexedSignal(page) => format!("m{page}M"),
+    }
+}
+
+fn value_table(dbc: &Dbc, message: &can_dbc::Message, signal: &can_dbc::Signal) -> BTreeMap<i64, String> {
+    can_decoder::get_value_descriptions(dbc, message, signal)
+        .unwrap_or_default()
+        .iter()
+        .map(|d| (d.id, d.description.clone()))
+        .collect()
+}
+
+/// How one signal decoded differently in a log
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SignalImpact {
+    /// Frames where both versions decoded the signal
+    pub compared: u64,
+    /// Of the compared frames, those where the value or label differ
+    pub differing: u64,
+    /// Frames where only the old or only the new version decoded the signal
+    pub only_old: u64,
+    pub only_new: u64,
+    /// Timestamp, old and new value of the first difference
+    pub first_difference: Option<(f64, Option<f64>, Option<f64>)>,
+}
+
+impl SignalImpact {
+    pub fn is_changed(&self) -> bool {
+        self.differing > 0 || self.only_old > 0 || self.only_new > 0
+    }
+}
+
+/// Result of replaying a log through two versions of a database
+#[derive(Debug, Clone, Default)]
+pub struct ImpactReport {
+    pub frames: u64,
+    /// Per (message, signal), with the new message name when both versions decode the frame
+    pub signals: BTreeMap<(String, String), SignalImpact>,
+}
+
+impl ImpactReport {
+    /// Signals that decoded differently in at least one frame
+    pub fn changed(&self) -> impl Iterator<Item = (&(String, String), &SignalImpact)> {
+        self.signals.iter().filter(|(_, impact)| impact.is_changed())
+    }
+}
+
+impl fmt::Display for ImpactReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let changed = self.changed().count();
+        writeln!(f, "{} frames, {changed} of {} signals decode differently", self.frames, self.signals.len())?;
+        let value = |v: &Option<f64>| v.map_or("-".to_owned(), |v| v.to_string());
+        for ((message, signal), impact) in self.changed() {
+            write!(
+                f,
+                "  {message}.{signal}: {} of {} differ, {} old only, {} new only",
+                impact.differing, impact.compared, impact.only_old, impact.only_new
+            )?;
+            if let Some((timestamp, old, new)) = &impact.first_difference {
+                write!(f, ", first at {timestamp:.6}: {} -> {}", value(old), value(new))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode every frame with both databases and compare the signals by name
+pub fn replay_impact<I>(old: &CanDatabase, new: &CanDatabase, frames: I) -> ImpactReport
+where
+    I: IntoIterator<Item = CanFrame>,
+{
+    let mut report = ImpactReport::default();
+    for frame in frames {
+        report.frames += 1;
+        let old_message = old.decode_frame(&frame);
+        let new_message = new.decode_frame(&frame);
+        let Some(name) = new_message.as_ref().or(old_message.as_ref()).map(|m| m.name.clone()) else {
+            continue;
+        };
+        let value = |message: &Option<can_decoder::DecodedCanMessage>, signal: &str| {
+            let message = message.as_ref()?;
+            let index = message.signals.iter().position(|s| s == signal)?;
+            Some((message.values[index], message.labels[index].clone()))
+        };
+        let signals = old_message
+            .iter()
+            .chain(&new_message)
+            .flat_map(|m| m.signals.iter())
+            .fold(Vec::new(), |mut names: Vec<&String>, signal| {
+                if !names.contains(&signal) {
+                    names.push(signal);
+                }
+                names
+            });
+        for signal in signals {
+            let impact = report.signals.entry((name.clone(), signal.clone())).or_default();
+            let (old_value, new_value) = (value(&old_message, signal), value(&new_message, signal));
+            let differs = match (&old_value, &new_value) {
+                (Some(old_value), Some(new_value)) => {
+                    impact.compared += 1;
+                    let same = old_value.1 == new_value.1
+                        && (old_value.0 == new_value.0 || (old_value.0.is_nan() && new_value.0.is_nan()));
+                    if !same {
+                        impact.differing += 1;
+                    }
+                    !same
+                }
+                (Some(_), None) => {
+                    impact.only_old += 1;
+                    true
+                }
+                (None, _) => {
+                    impact.only_new += 1;
+                    true
+                }
+            };
+            if differs && impact.first_difference.is_none() {
+                impact.first_difference =
+                    Some((frame.timestamp, old_value.map(|(v, _)| v), new_value.map(|(v, _)| v)));
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::canlog_reader::{self, CanLogParser};
+    use crate::dbc_editor::DbcEditor;
+
+    #[test]
+    fn test_identical() {
+        let dbc = can_decoder::load_dbc("can_samples/chrysler_cusw.dbc").unwrap();
+        assert!(diff(&dbc, &dbc).is_empty());
+
+        let database = CanDatabase::from_dbc(dbc);
+        let frames = CanLogParser::from_file(Path::new("demo-log.log")).unwrap();
+        let report = replay_impact(&database, &database, frames);
+        assert!(report.frames > 0);
+        assert!(!report.signals.is_empty());
+        assert_eq!(report.changed().count(), 0);
+    }
+
+    #[test]
+    fn test_changes() {
+        let old = can_decoder::load_dbc("motohawk.dbc").unwrap();
+        let mut new = old.clone();
+        new.messages[0].name = "Example".to_owned();
+        new.messages[0].size = 7;
+        let signals = &mut new.messages[0].signals;
+        let temperature = signals.iter_mut().find(|s| s.name == "Temperature").unwrap();
+        temperature.factor = 0.02;
+        temperature.unit = "degF".to_owned();
+        temperature.start_bit = 1;
+        signals.retain(|s| s.name != "AverageRadius");
+        let mut signal = signals[0].clone();
+        signal.name = "Added".to_owned();
+        signals.push(signal);
+        new.messages.push(can_dbc::Message {
+            id: can_dbc::MessageId::Standard(0x123),
+            name: "NewMessage".to_owned(),
+            ..new.messages[0].clone()
+        });
+
+        let changes: Vec<String> = diff(&old, &new).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            changes,
+            [
+                "~ message ExampleMessage: renamed to Example",
+                "~ message Example: size 8 -> 7 bytes",
+                "- signal Example.AverageRadius",
+                "~ Example.Temperature: layout 0|12@0- -> 1|12@0-",
+                "~ Example.Temperature: scaling (0.01,250) -> (0.02,250)",
+                "~ Example.Temperature: unit \"degK\" -> \"degF\"",
+                "+ signal Example.Added",
+                "+ message NewMessage (0x123)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_value_tables_and_impact() {
+        let old = can_decoder::load_dbc("motohawk.dbc").unwrap();
+        let mut editor = DbcEditor::from_dbc(old.clone());
+        editor.set_value_descriptions("ExampleMessage", "Enable", &[(0, "Off"), (2, "Boost")]).unwrap();
+        let message = editor.message_mut("ExampleMessage").unwrap();
+        message.signals.iter_mut().find(|s| s.name == "Temperature").unwrap().offset = 200.0;
+        let new = editor.into_dbc();
+
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes.last().unwrap().to_string(),
+            "~ ExampleMessage.Enable: value table 0=\"Disabled\"->\"Off\", removed 1=\"Enabled\", added 2=\"Boost\""
+        );
+
+        let frames = ["(1.0) can0 1F0#A5B6D90000000000", "(2.0) can0 1F0#0000000000000000"]
+            .map(|line| canlog_reader::parse_candump_line(line).unwrap());
+        let report = replay_impact(&CanDatabase::from_dbc(old), &CanDatabase::from_dbc(new), frames);
+        assert_eq!(report.frames, 2);
+        let changed: Vec<_> = report.changed().map(|((_, signal), impact)| (signal.as_str(), impact.differing)).collect();
+        assert_eq!(changed, [("Enable", 2), ("Temperature", 2)]);
+        let temperature = &report.signals[&("ExampleMessage".to_owned(), "Temperature".to_owned())];
+        assert_eq!(temperature.compared, 2);
+        let (timestamp, old_value, new_value) = temperature.first_difference.unwrap();
+        assert_eq!(timestamp, 1.0);
+        assert!((old_value.unwrap() - 244.14).abs() < 1e-9);
+        assert!((new_value.unwrap() - 194.14).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,1029 @@
+/*!
+ * Programmatic editing of DBCs: add, rename or remove nodes, messages and signals while
+ * keeping the comments, attributes, value descriptions and multiplexing entries that
+ * reference them consistent. Save the result with `dbc_writer`.
+ *
+ * ```no_run
+ * use rocketcan::dbc_editor::{DbcEditor, MessageBuilder, SignalBuilder};
+ *
+ * let mut editor = DbcEditor::load("can_samples/chrysler_cusw.dbc").unwrap();
+ * editor.remove_message("BRAKE_1").unwrap();
+ * editor.rename_signal("STEERING", "STEER_ANGLE", "STEERING_ANGLE").unwrap();
+ * editor
+ *     .add_message(
+ *         MessageBuilder::new("UNKNOWN_2A0", 0x2A0, 8)
+ *             .signal(SignalBuilder::new("COUNTER", 0, 4).build())
+ *             .signal(SignalBuilder::new("SPEED", 15, 16).big_endian().scale(0.01, 0.0).unit("km/h").build())
+ *             .build(),
+ *     )
+ *     .unwrap();
+ * editor.set_message_comment("UNKNOWN_2A0", "Found while reverse engineering").unwrap();
+ * editor.save("chrysler_edited.dbc").unwrap();
+ * ```
+ */
+
+use std::io;
+
+use anyhow::{anyhow, bail, Result};
+use can_dbc::{
+    AccessNode, AttributeValueForRelationType, ByteOrder, Comment, Dbc, ExtendedMultiplex, ExtendedMultiplexMapping,
+    Message, MessageId, MultiplexIndicator, Node, Signal, SignalExtendedValueType, SignalExtendedValueTypeList, Symbol,
+    Transmitter, ValDescription, ValueDescription, ValueTable, ValueType, Version,
+};
+
+use crate::dbc_writer;
+use crate::loader;
+
+/// Symbols listed in NS_ of new DBCs, as emitted by common DBC editors
+const DEFAULT_NEW_SYMBOLS: &[&str] = &[
+    "NS_DESC_", "CM_", "BA_DEF_", "BA_", "VAL_", "CAT_DEF_", "CAT_", "FILTER", "BA_DEF_DEF_", "EV_DATA_",
+    "ENVVAR_DATA_", "SGTYPE_", "SGTYPE_VAL_", "BA_DEF_SGTYPE_", "BA_SGTYPE_", "SIG_TYPE_REF_", "VAL_TABLE_",
+    "SIG_GROUP_", "SIG_VALTYPE_", "SIGTYPE_VALTYPE_", "BO_TX_BU_", "BA_DEF_REL_", "BA_REL_", "BA_DEF_DEF_REL_",
+    "BU_SG_REL_", "BU_EV_REL_", "BU_BO_REL_", "SG_MUL_VAL_",
+];
+
+/// Message ID from a raw ID: standard up to 0x7FF, extended above
+pub fn message_id(id: u32, extended: bool) -> MessageId {
+    match u16::try_from(id) {
+        Ok(standard) if !extended && standard <= 0x7FF => MessageId::Standard(standard),
+        _ => MessageId::Extended(id & 0x1FFF_FFFF),
+    }
+}
+
+/// Name made a valid DBC identifier, other characters become `_`
+pub(crate) fn identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    if !identifier.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+/// DBC start bit of a big endian signal whose MSB is numbered from the most significant
+/// bit of byte 0, as in KCD and SYM files
+pub(crate) fn msb_first_start_bit(bit: u64) -> u64 {
+    8 * (bit / 8) + 7 - bit % 8
+}
+
+/// Builds a `can_dbc::Signal`. Defaults: little endian, unsigned, factor 1, offset 0,
+/// range 0..=raw max, no unit, received by Vector__XXX.
+pub struct SignalBuilder {
+    signal: Signal,
+    range_set: bool,
+    float: bool,
+}
+
+impl SignalBuilder {
+    pub fn new(name: &str, start_bit: u64, size: u64) -> Self {
+        Self {
+            signal: Signal {
+                name: name.to_owned(),
+                multiplexer_indicator: MultiplexIndicator::Plain,
+                start_bit,
+                size,
+                byte_order: ByteOrder::LittleEndian,
+                value_type: ValueType::Unsigned,
+                factor: 1.0,
+                offset: 0.0,
+                min: 0.0,
+                max: 0.0,
+                unit: String::new(),
+                receivers: vec!["Vector__XXX".to_owned()],
+            },
+            range_set: false,
+            float: false,
+        }
+    }
+
+    /// IEEE float or double signal (its SIG_VALTYPE_ is set on the `DbcEditor`).
+    /// The raw integer range means nothing for floats, so without `range()` it is [0|0].
+    pub fn float(mut self) -> Self {
+        self.float = true;
+        self
+    }
+
+    /// Motorola byte order, `start_bit` is then the MSB as in the DBC
+    pub fn big_endian(mut self) -> Self {
+        self.signal.byte_order = ByteOrder::BigEndian;
+        self
+    }
+
+    pub fn signed(mut self) -> Self {
+        self.signal.value_type = ValueType::Signed;
+        self
+    }
+
+    /// physical = raw * factor + offset
+    pub fn scale(mut self, factor: f64, offset: f64) -> Self {
+        self.signal.factor = factor;
+        self.signal.offset = offset;
+        self
+    }
+
+    /// Physical range, computed from the raw range and scale when not given
+    pub fn range(mut self, min: f64, max: f64) -> Self {
+        self.signal.min = min;
+        self.signal.max = max;
+        self.range_set = true;
+        self
+    }
+
+    pub fn unit(mut self, unit: &str) -> Self {
+        self.signal.unit = unit.to_owned();
+        self
+    }
+
+    /// Add a receiving node
+    pub fn receiver(mut self, node: &str) -> Self {
+        self.signal.receivers.retain(|r| r != "Vector__XXX");
+        self.signal.receivers.push(node.to_owned());
+        self
+    }
+
+    /// The multiplexer switch of the message (M)
+    pub fn multiplexor(mut self) -> Self {
+        self.signal.multiplexer_indicator = match self.signal.multiplexer_indicator {
+            MultiplexIndicator::MultiplexedSignal(value) => MultiplexIndicator::MultiplexorAndMultiplexedSignal(value),
+            MultiplexIndicator::Plain => MultiplexIndicator::Multiplexor,
+            other => other,
+        };
+        self
+    }
+
+    /// Only present when the multiplexer switch is `value` (m<value>)
+    pub fn multiplexed(mut self, value: u64) -> Self {
+        self.signal.multiplexer_indicator = match self.signal.multiplexer_indicator {
+            MultiplexIndicator::Multiplexor | MultiplexIndicator::MultiplexorAndMultiplexedSignal(_) => {
+                MultiplexIndicator::MultiplexorAndMultiplexedSignal(value)
+            }
+            _ => MultiplexIndicator::MultiplexedSignal(value),
+        };
+        self
+    }
+
+    pub fn build(mut self) -> Signal {
+        if !self.range_set && !self.float {
+            let size = self.signal.size.clamp(1, 64) as u32;
+            let (raw_min, raw_max) = match self.signal.value_type {
+                ValueType::Unsigned => (0.0, (u64::MAX >> (64 - size)) as f64),
+                ValueType::Signed => (-((1u128 << (size - 1)) as f64), ((1u128 << (size - 1)) - 1) as f64),
+            };
+            let a = raw_min * self.signal.factor + self.signal.offset;
+            let b = raw_max * self.signal.factor + self.signal.offset;
+            self.signal.min = a.min(b);
+            self.signal.max = a.max(b);
+        }
+        self.signal
+    }
+}
+
+/// Builds a `can_dbc::Message`, sent by Vector__XXX unless a transmitter is given
+pub struct MessageBuilder {
+    message: Message,
+}
+
+impl MessageBuilder {
+    /// IDs above 0x7FF are extended
+    pub fn new(name: &str, id: u32, size: u64) -> Self {
+        Self {
+            message: Message {
+                id: message_id(id, false),
+                name: name.to_owned(),
+                size,
+                transmitter: Transmitter::VectorXXX,
+                signals: Vec::new(),
+            },
+        }
+    }
+
+    /// Use a 29 bit ID even if it fits in 11 bits
+    pub fn extended(mut self) -> Self {
+        self.message.id = message_id(self.message.id.raw() & 0x1FFF_FFFF, true);
+        self
+    }
+
+    pub fn transmitter(mut self, node: &str) -> Self {
+        self.message.transmitter = Transmitter::NodeName(node.to_owned());
+        self
+    }
+
+    pub fn signal(mut self, signal: Signal) -> Self {
+        self.message.signals.push(signal);
+        self
+    }
+
+    pub fn build(self) -> Message {
+        self.message
+    }
+}
+
+/// Owns a DBC and edits it, keeping cross references consistent
+#[derive(Debug, Clone)]
+pub struct DbcEditor {
+    dbc: Dbc,
+}
+
+impl Default for DbcEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DbcEditor {
+    /// An empty DBC, with the usual NS_ and BS_ sections
+    pub fn new() -> Self {
+        Self {
+            dbc: Dbc {
+                version: Version(String::new()),
+                new_symbols: DEFAULT_NEW_SYMBOLS.iter().map(|s| Symbol(s.to_string())).collect(),
+                bit_timing: Some(Vec::new()),
+                nodes: Vec::new(),
+                value_tables: Vec::new(),
+                messages: Vec::new(),
+                message_transmitters: Vec::new(),
+                environment_variables: Vec::new(),
+                environment_variable_data: Vec::new(),
+                signal_types: Vec::new(),
+                comments: Vec::new(),
+                attribute_definitions: Vec::new(),
+                relation_attribute_definitions: Vec::new(),
+                attribute_defaults: Vec::new(),
+                relation_attribute_defaults: Vec::new(),
+                relation_attribute_values: Vec::new(),
+                attribute_values_database: Vec::new(),
+                attribute_values_node: Vec::new(),
+                attribute_values_message: Vec::new(),
+                attribute_values_signal: Vec::new(),
+                attribute_values_env: Vec::new(),
+                value_descriptions: Vec::new(),
+                signal_type_refs: Vec::new(),
+                signal_groups: Vec::new(),
+                signal_extended_value_type_list: Vec::new(),
+                extended_multiplex: Vec::new(),
+            },
+        }
+    }
+
+    pub fn from_dbc(dbc: Dbc) -> Self {
+        Self { dbc }
+    }
+
+    /// Load a DBC, or a KCD, SYM or ARXML file converted to a DBC
+    pub fn load(dbc_path: &str) -> io::Result<Self> {
+        Ok(Self::from_dbc(loader::load(dbc_path)?))
+    }
+
+    pub fn dbc(&self) -> &Dbc {
+        &self.dbc
+    }
+
+    pub fn into_dbc(self) -> Dbc {
+        self.dbc
+    }
+
+    /// DBC text of the edited database
+    pub fn to_dbc_string(&self) -> String {
+        dbc_writer::dbc_to_string(&self.dbc)
+    }
+
+    pub fn save(&self, dbc_path: &str) -> io::Result<()> {
+        dbc_writer::save_dbc(&self.dbc, dbc_path)
+    }
+
+    pub fn message(&self, name: &str) -> Option<&Message> {
+        self.dbc.messages.iter().find(|m| m.name == name)
+    }
+
+    /// Direct access to a message. Renaming signals or changing the ID through it
+    /// leaves references stale, use the editor methods for that.
+    pub fn message_mut(&mut self, name: &str) -> Option<&mut Message> {
+        self.dbc.messages.iter_mut().find(|m| m.name == name)
+    }
+
+    fn message_index(&self, name: &str) -> Result<usize> {
+        self.dbc
+            .messages
+            .iter()
+            .position(|m| m.name == name)
+            .ok_or_else(|| anyhow!("unknown message: {}", name))
+    }
+
+    fn signal_index(&self, message: &str, signal: &str) -> Result<(usize, usize)> {
+        let message_index = self.message_index(message)?;
+        let signal_index = self.dbc.messages[message_index]
+            .signals
+            .iter()
+            .position(|s| s.name == signal)
+            .ok_or_else(|| anyhow!("unknown signal: {}.{}", message, signal))?;
+        Ok((message_index, signal_index))
+    }
+
+    pub fn add_node(&mut self, name: &str) -> Result<()> {
+        if self.dbc.nodes.iter().any(|n| n.0 == name) {
+            bail!("node {} already exists", name);
+        }
+        self.dbc.nodes.push(Node(name.to_owned()));
+        Ok(())
+    }
+
+    /// Rename a node, including as transmitter, receiver, and in comments and attributes
+    pub fn rename_node(&mut self, name: &str, new_name: &str) -> Result<()> {
+        if self.dbc.nodes.iter().any(|n| n.0 == new_name) {
+            bail!("node {} already exists", new_name);
+        }
+        let node = self.dbc.nodes.iter_mut().find(|n| n.0 == name).ok_or_else(|| anyhow!("unknown node: {}", name))?;
+        node.0 = new_name.to_owned();
+        self.for_each_node_ref(|node_name| {
+            if node_name == name {
+                *node_name = new_name.to_owned();
+            }
+        });
+        Ok(())
+    }
+
+    /// Remove a node and everything referencing it.
+    /// Its messages are then sent by Vector__XXX, and signals it alone received by Vector__XXX.
+    pub fn remove_node(&mut self, name: &str) -> Result<()> {
+        let index =
+            self.dbc.nodes.iter().position(|n| n.0 == name).ok_or_else(|| anyhow!("unknown node: {}", name))?;
+        self.dbc.nodes.remove(index);
+        let dbc = &mut self.dbc;
+        for message in &mut dbc.messages {
+            if matches!(&message.transmitter, Transmitter::NodeName(node) if node == name) {
+                message.transmitter = Transmitter::VectorXXX;
+            }
+            for signal in &mut message.signals {
+                if signal.receivers.iter().any(|r| r == name) {
+                    signal.receivers.retain(|r| r != name);
+                    if signal.receivers.is_empty() {
+                        signal.receivers.push("Vector__XXX".to_owned());
+                    }
+                }
+            }
+        }
+        for transmitters in &mut dbc.message_transmitters {
+            transmitters.transmitter.retain(|t| !matches!(t, Transmitter::NodeName(node) if node == name));
+        }
+        dbc.message_transmitters.retain(|t| !t.transmitter.is_empty());
+        dbc.comments.retain(|c| !matches!(c, Comment::Node { name: node, .. } if node == name));
+        dbc.attribute_values_node.retain(|a| a.node_name != name);
+        dbc.relation_attribute_values.retain(|a| match &a.details {
+            AttributeValueForRelationType::NodeToSignal { node_name, .. }
+            | AttributeValueForRelationType::NodeToMessage { node_name, .. } => node_name != name,
+        });
+        for variable in &mut dbc.environment_variables {
+            if variable.access_nodes.iter().any(|n| matches!(n, AccessNode::Name(node) if node == name)) {
+                variable.access_nodes.retain(|n| !matches!(n, AccessNode::Name(node) if node == name));
+                if variable.access_nodes.is_empty() {
+                    variable.access_nodes.push(AccessNode::VectorXXX);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the nodes that no message or signal references as transmitter or receiver,
+    /// e.g. after removing messages. Returns the names of the removed nodes.
+    pub fn remove_unused_nodes(&mut self) -> Vec<String> {
+        let mut used = std::collections::HashSet::new();
+        for message in &self.dbc.messages {
+            if let Transmitter::NodeName(node) = &message.transmitter {
+                used.insert(node.clone());
+            }
+            for signal in &message.signals {
+                used.extend(signal.receivers.iter().cloned());
+            }
+        }
+        for transmitters in &self.dbc.message_transmitters {
+            for transmitter in &transmitters.transmitter {
+                if let Transmitter::NodeName(node) = transmitter {
+                    used.insert(node.clone());
+                }
+            }
+        }
+        let unused: Vec<String> =
+            self.dbc.nodes.iter().filter(|n| !used.contains(&n.0)).map(|n| n.0.clone()).collect();
+        for node in &unused {
+            // The node was just found, remove_node can't fail
+            let _ = self.remove_node(node);
+        }
+        unused
+    }
+
+    /// Add a message, its ID and name must not be in use
+    pub fn add_message(&mut self, message: Message) -> Result<()> {
+        if let Some(existing) = self.dbc.messages.iter().find(|m| m.id == message.id || m.name == message.name) {
+            bail!("message {} ({:#X}) clashes with {}", message.name, message.id.raw(), existing.name);
+        }
+        self.dbc.messages.push(message);
+        Ok(())
+    }
+
+    /// Remove a message and everything referencing it.
+    /// Its transmitter and receiver nodes stay in BU_, see `remove_unused_nodes`.
+    pub fn remove_message(&mut self, name: &str) -> Result<Message> {
+        let index = self.message_index(name)?;
+        let message = self.dbc.messages.remove(index);
+        self.remove_message_refs(message.id);
+        Ok(message)
+    }
+
+    /// Keep the messages for which `keep` is true, e.g. to strip private messages before
+    /// sharing a DBC. Returns the removed messages.
+    pub fn retain_messages<F: FnMut(&Message) -> bool>(&mut self, mut keep: F) -> Vec<Message> {
+        let (kept, removed): (Vec<Message>, Vec<Message>) = self.dbc.messages.drain(..).partition(|m| keep(m));
+        self.dbc.messages = kept;
+        for message in &removed {
+            self.remove_message_refs(message.id);
+        }
+        removed
+    }
+
+    pub fn rename_message(&mut self, name: &str, new_name: &str) -> Result<()> {
+        if self.message(new_name).is_some() {
+            bail!("message {} already exists", new_name);
+        }
+        let index = self.message_index(name)?;
+        self.dbc.messages[index].name = new_name.to_owned();
+        Ok(())
+    }
+
+    /// Change the ID of a message, moving everything that references the old ID
+    pub fn set_message_id(&mut self, name: &str, id: MessageId) -> Result<()> {
+        let index = self.message_index(name)?;
+        if let Some(other) = self.dbc.messages.iter().find(|m| m.id == id && m.name != name) {
+            bail!("ID {:#X} is already used by {}", id.raw(), other.name);
+        }
+        let old_id = std::mem::replace(&mut self.dbc.messages[index].id, id);
+        self.for_each_message_ref(|ref_id| {
+            if *ref_id == old_id {
+                *ref_id = id;
+            }
+        });
+        Ok(())
+    }
+
+    pub fn add_signal(&mut self, message: &str, signal: Signal) -> Result<()> {
+        let index = self.message_index(message)?;
+        let message = &mut self.dbc.messages[index];
+        if message.signals.iter().any(|s| s.name == signal.name) {
+            bail!("signal {}.{} already exists", message.name, signal.name);
+        }
+        message.signals.push(signal);
+        Ok(())
+    }
+
+    /// Remove a signal and everything referencing it
+    pub fn remove_signal(&mut self, message: &str, signal: &str) -> Result<Signal> {
+        let (message_index, signal_index) = self.signal_index(message, signal)?;
+        let id = self.dbc.messages[message_index].id;
+        let removed = self.dbc.messages[message_index].signals.remove(signal_index);
+        let dbc = &mut self.dbc;
+        let is_signal = |message_id: &MessageId, name: &str| *message_id == id && name == signal;
+        dbc.comments.retain(|c| !matches!(c, Comment::Signal { message_id, name, .. } if is_signal(message_id, name)));
+        dbc.attribute_values_signal.retain(|a| !is_signal(&a.message_id, &a.signal_name));
+        dbc.relation_attribute_values.retain(|a| match &a.details {
+            AttributeValueForRelationType::NodeToSignal { message_id, signal_name, .. } => {
+                !is_signal(message_id, signal_name)
+            }
+            AttributeValueForRelationType::NodeToMessage { .. } => true,
+        });
+        dbc.value_descriptions.retain(|v| match v {
+            ValueDescription::Signal { message_id, name, .. } => !is_signal(message_id, name),
+            ValueDescription::EnvironmentVariable { .. } => true,
+        });
+        dbc.signal_type_refs.retain(|r| !is_signal(&r.message_id, &r.signal_name));
+        for group in dbc.signal_groups.iter_mut().filter(|g| g.message_id == id) {
+            group.signal_names.retain(|s| s != signal);
+        }
+        dbc.signal_extended_value_type_list.retain(|v| !is_signal(&v.message_id, &v.signal_name));
+        dbc.extended_multiplex
+            .retain(|m| !is_signal(&m.message_id, &m.signal_name) && !is_signal(&m.message_id, &m.multiplexor_signal_name));
+        Ok(removed)
+    }
+
+    /// Rename a signal, including in comments, attributes, value descriptions and multiplexing
+    pub fn rename_signal(&mut self, message: &str, signal: &str, new_name: &str) -> Result<()> {
+        let (message_index, signal_index) = self.signal_index(message, signal)?;
+        if self.dbc.messages[message_index].signals.iter().any(|s| s.name == new_name) {
+            bail!("signal {}.{} already exists", message, new_name);
+        }
+        let id = self.dbc.messages[message_index].id;
+        self.dbc.messages[message_index].signals[signal_index].name = new_name.to_owned();
+        let rename = |message_id: &MessageId, name: &mut String| {
+            if *message_id == id && name == signal {
+                *name = new_name.to_owned();
+            }
+        };
+        let dbc = &mut self.dbc;
+        for comment in &mut dbc.comments {
+            if let Comment::Signal { message_id, name, .. } = comment {
+                rename(message_id, name);
+            }
+        }
+        for value in &mut dbc.attribute_values_signal {
+            rename(&value.message_id, &mut value.signal_name);
+        }
+        for value in &mut dbc.relation_attribute_values {
+            if let AttributeValueForRelationType::NodeToSignal { message_id, signal_name, .. } = &mut value.details {
+                rename(message_id, signal_name);
+            }
+        }
+        for description in &mut dbc.value_descriptions {
+            if let ValueDescription::Signal { message_id, name, .. } = description {
+                rename(message_id, name);
+            }
+        }
+        for type_ref in &mut dbc.signal_type_refs {
+            rename(&type_ref.message_id, &mut type_ref.signal_name);
+        }
+        for group in &mut dbc.signal_groups {
+            for name in &mut group.signal_names {
+                rename(&group.message_id, name);
+            }
+        }
+        for value_type in &mut dbc.signal_extended_value_type_list {
+            rename(&value_type.message_id, &mut value_type.signal_name);
+        }
+        for multiplex in &mut dbc.extended_multiplex {
+            rename(&multiplex.message_id, &mut multiplex.signal_name);
+            rename(&multiplex.message_id, &mut multiplex.multiplexor_signal_name);
+        }
+        Ok(())
+    }
+
+    /// Set the comment of a message, replacing any previous one
+    pub fn set_message_comment(&mut self, message: &str, comment: &str) -> Result<()> {
+        let id = self.dbc.messages[self.message_index(message)?].id;
+        self.dbc.comments.retain(|c| !matches!(c, Comment::Message { id: message_id, .. } if *message_id == id));
+        self.dbc.comments.push(Comment::Message {
+            id,
+            comment: comment.to_owned(),
+        });
+        Ok(())
+    }
+
+    /// Set the comment of a signal, replacing any previous one
+    pub fn set_signal_comment(&mut self, message: &str, signal: &str, comment: &str) -> Result<()> {
+        let (message_index, _) = self.signal_index(message, signal)?;
+        let id = self.dbc.messages[message_index].id;
+        self.dbc.comments.retain(
+            |c| !matches!(c, Comment::Signal { message_id, name, .. } if *message_id == id && name == signal),
+        );
+        self.dbc.comments.push(Comment::Signal {
+            message_id: id,
+            name: signal.to_owned(),
+            comment: comment.to_owned(),
+        });
+        Ok(())
+    }
+
+    /// Set the VAL_ labels of a signal, an empty list removes them
+    pub fn set_value_descriptions(&mut self, message: &str, signal: &str, labels: &[(i64, &str)]) -> Result<()> {
+        let (message_index, _) = self.signal_index(message, signal)?;
+        let id = self.dbc.messages[message_index].id;
+        let position = self.dbc.value_descriptions.iter().position(
+            |v| matches!(v, ValueDescription::Signal { message_id, name, .. } if *message_id == id && name == signal),
+        );
+        let value_descriptions = to_descriptions(labels);
+        match (position, labels.is_empty()) {
+            (Some(position), true) => {
+                self.dbc.value_descriptions.remove(position);
+            }
+            (Some(position), false) => {
+                self.dbc.value_descriptions[position] = ValueDescription::Signal {
+                    message_id: id,
+                    name: signal.to_owned(),
+                    value_descriptions,
+                };
+            }
+            (None, true) => {}
+            (None, false) => self.dbc.value_descriptions.push(ValueDescription::Signal {
+                message_id: id,
+                name: signal.to_owned(),
+                value_descriptions,
+            }),
+        }
+        Ok(())
+    }
+
+    /// Add or replace a global VAL_TABLE_
+    pub fn set_value_table(&mut self, name: &str, labels: &[(i64, &str)]) {
+        let table = ValueTable {
+            name: name.to_owned(),
+            descriptions: to_descriptions(labels),
+        };
+        match self.dbc.value_tables.iter_mut().find(|t| t.name == name) {
+            Some(existing) => *existing = table,
+            None => self.dbc.value_tables.push(table),
+        }
+    }
+
+    /// Declare a signal as float32/float64 (SIG_VALTYPE_), or back to integer
+    pub fn set_signal_value_type(
+        &mut self,
+        message: &str,
+        signal: &str,
+        value_type: SignalExtendedValueType,
+    ) -> Result<()> {
+        let (message_index, _) = self.signal_index(message, signal)?;
+        let id = self.dbc.messages[message_index].id;
+        let list = &mut self.dbc.signal_extended_value_type_list;
+        list.retain(|v| !(v.message_id == id && v.signal_name == signal));
+        if value_type != SignalExtendedValueType::SignedOrUnsignedInteger {
+            list.push(SignalExtendedValueTypeList {
+                message_id: id,
+                signal_name: signal.to_owned(),
+                signal_extended_value_type: value_type,
+            });
+        }
+        Ok(())
+    }
+
+    /// Put a signal on the pages of its message with these multiplexor values: m<first value>,
+    /// and SG_MUL_VAL_ ranges when there are several or the multiplexor is itself multiplexed.
+    /// With several multiplexors the one already in SG_MUL_VAL_ for the signal is kept, otherwise it's an error.
+    pub fn set_multiplexor_values(&mut self, message: &str, signal: &str, values: &[u64]) -> Result<()> {
+        let (message_index, signal_index) = self.signal_index(message, signal)?;
+        let spec = &mut self.dbc.messages[message_index];
+        let id = spec.id;
+        let switches: Vec<&Signal> = spec
+            .signals
+            .iter()
+            .filter(|s| s.name != signal)
+            .filter(|s| {
+                matches!(
+                    s.multiplexer_indicator,
+                    MultiplexIndicator::Multiplexor | MultiplexIndicator::MultiplexorAndMultiplexedSignal(_)
+                )
+            })
+            .collect();
+        let current = self
+            .dbc
+            .extended_multiplex
+            .iter()
+            .find(|m| m.message_id == id && m.signal_name == signal)
+            .map(|m| m.multiplexor_signal_name.as_str());
+        let multiplexor = match (switches.as_slice(), current) {
+            ([], _) => bail!("message {} has no multiplexor", message),
+            ([only], _) => *only,
+            (_, Some(current)) => switches
+                .iter()
+                .find(|s| s.name == current)
+                .copied()
+                .ok_or_else(|| anyhow!("unknown multiplexor {}.{}", message, current))?,
+            _ => bail!("message {} has several multiplexors, none selected for {}", message, signal),
+        };
+        let nested = switches.len() > 1 || multiplexor.multiplexer_indicator != MultiplexIndicator::Multiplexor;
+        let multiplexor = multiplexor.name.clone();
+        let mappings = value_ranges(values);
+        let first = mappings.first().ok_or_else(|| anyhow!("no multiplexor value for {}.{}", message, signal))?;
+        let indicator = &mut spec.signals[signal_index].multiplexer_indicator;
+        *indicator = match indicator {
+            // A multiplexor keeps selecting its own signals
+            MultiplexIndicator::Multiplexor | MultiplexIndicator::MultiplexorAndMultiplexedSignal(_) => {
+                MultiplexIndicator::MultiplexorAndMultiplexedSignal(first.min_value)
+            }
+            _ => MultiplexIndicator::MultiplexedSignal(first.min_value),
+        };
+
+        let list = &mut self.dbc.extended_multiplex;
+        list.retain(|m| !(m.message_id == id && m.signal_name == signal));
+        if nested || mappings.len() > 1 || first.min_value != first.max_value {
+            list.push(ExtendedMultiplex {
+                message_id: id,
+                signal_name: signal.to_owned(),
+                multiplexor_signal_name: multiplexor,
+                mappings,
+            });
+        }
+        Ok(())
+    }
+
+    /// Apply `f` to every node name referenced by messages, signals, comments, attributes and environment variables
+    fn for_each_node_ref<F: FnMut(&mut String)>(&mut self, mut f: F) {
+        let dbc = &mut self.dbc;
+        for message in &mut dbc.messages {
+            if let Transmitter::NodeName(node) = &mut message.transmitter {
+                f(node);
+            }
+            for signal in &mut message.signals {
+                signal.receivers.iter_mut().for_each(&mut f);
+            }
+        }
+        for transmitters in &mut dbc.message_transmitters {
+            for transmitter in &mut transmitters.transmitter {
+                if let Transmitter::NodeName(node) = transmitter {
+                    f(node);
+                }
+            }
+        }
+        for comment in &mut dbc.comments {
+            if let Comment::Node { name, .. } = comment {
+                f(name);
+            }
+        }
+        dbc.attribute_values_node.iter_mut().for_each(|a| f(&mut a.node_name));
+        for value in &mut dbc.relation_attribute_values {
+            match &mut value.details {
+                AttributeValueForRelationType::NodeToSignal { node_name, .. }
+                | AttributeValueForRelationType::NodeToMessage { node_name, .. } => f(node_name),
+            }
+        }
+        for variable in &mut dbc.environment_variables {
+            for node in &mut variable.access_nodes {
+                if let AccessNode::Name(name) = node {
+                    f(name);
+                }
+            }
+        }
+    }
+
+    /// Apply `f` to every message ID stored outside of the messages themselves
+    fn for_each_message_ref<F: FnMut(&mut MessageId)>(&mut self, mut f: F) {
+        let dbc = &mut self.dbc;
+        dbc.message_transmitters.iter_mut().for_each(|t| f(&mut t.message_id));
+        for comment in &mut dbc.comments {
+            match comment {
+                Comment::Message { id, .. } => f(id),
+                Comment::Signal { message_id, .. } => f(message_id),
+                _ => {}
+            }
+        }
+        dbc.attribute_values_message.iter_mut().for_each(|a| f(&mut a.message_id));
+        dbc.attribute_values_signal.iter_mut().for_each(|a| f(&mut a.message_id));
+        for value in &mut dbc.relation_attribute_values {
+            match &mut value.details {
+                AttributeValueForRelationType::NodeToSignal { message_id, .. }
+                | AttributeValueForRelationType::NodeToMessage { message_id, .. } => f(message_id),
+            }
+        }
+        for description in &mut dbc.value_descriptions {
+            if let ValueDescription::Signal { message_id, .. } = description {
+                f(message_id);
+            }
+        }
+        dbc.signal_type_refs.iter_mut().for_each(|r| f(&mut r.message_id));
+        dbc.signal_groups.iter_mut().for_each(|g| f(&mut g.message_id));
+        dbc.signal_extended_value_type_list.iter_mut().for_each(|v| f(&mut v.message_id));
+        dbc.extended_multiplex.iter_mut().for_each(|m| f(&mut m.message_id));
+    }
+
+    fn remove_message_refs(&mut self, id: MessageId) {
+        let dbc = &mut self.dbc;
+        dbc.message_transmitters.retain(|t| t.message_id != id);
+        dbc.comments.retain(|c| match c {
+            Comment::Message { id: message_id, .. } | Comment::Signal { message_id, .. } => *message_id != id,
+            _ => true,
+        });
+        dbc.attribute_values_message.retain(|a| a.message_id != id);
+        dbc.attribute_values_signal.retain(|a| a.message_id != id);
+        dbc.relation_attribute_values.retain(|a| match &a.details {
+            AttributeValueForRelationType::NodeToSignal { message_id, .. }
+            | AttributeValueForRelationType::NodeToMessage { message_id, .. } => *message_id != id,
+        });
+        dbc.value_descriptions.retain(|v| match v {
+            ValueDescription::Signal { message_id, .. } => *message_id != id,
+            ValueDescription::EnvironmentVariable { .. } => true,
+        });
+        dbc.signal_type_refs.retain(|r| r.message_id != id);
+        dbc.signal_groups.retain(|g| g.message_id != id);
+        dbc.signal_extended_value_type_list.retain(|v| v.message_id != id);
+        dbc.extended_multiplex.retain(|m| m.message_id != id);
+    }
+}
+
+/// Multiplexor values as ranges, consecutive values merged
+fn value_ranges(values: &[u64]) -> Vec<ExtendedMultiplexMapping> {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    let mut ranges: Vec<ExtendedMultiplexMapping> = Vec::new();
+    for value in sorted {
+        match ranges.last_mut() {
+            Some(range) if range.max_value + 1 == value => range.max_value = value,
+            _ => ranges.push(ExtendedMultiplexMapping {
+                min_value: value,
+                max_value: value,
+            }),
+        }
+    }
+    ranges
+}
+
+fn to_descriptions(labels: &[(i64, &str)]) -> Vec<ValDescription> {
+    labels
+        .iter()
+        .map(|(id, description)| ValDescription {
+            id: *id,
+            description: description.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::can_database::CanDatabase;
+    use crate::can_decoder;
+    use crate::canlog_reader;
+
+    fn reparse(editor: &DbcEditor) -> Dbc {
+        can_dbc::Dbc::try_from(editor.to_dbc_string().as_str()).unwrap()
+    }
+
+    #[test]
+    fn test_build_new_dbc() {
+        let mut editor = DbcEditor::new();
+        editor.add_node("ECU").unwrap();
+        assert!(editor.add_node("ECU").is_err());
+        editor
+            .add_message(
+                MessageBuilder::new("Status", 0x123, 8)
+                    .transmitter("ECU")
+                    .signal(SignalBuilder::new("Mode", 0, 2).multiplexor().build())
+                    .signal(SignalBuilder::new("Temp", 8, 8).signed().scale(0.5, -10.0).unit("degC").multiplexed(1).build())
+                    .signal(SignalBuilder::new("Speed", 23, 16).big_endian().scale(0.01, 0.0).receiver("ECU").build())
+                    .build(),
+            )
+            .unwrap();
+        editor.add_message(MessageBuilder::new("Ext", 0x1, 4).extended().build()).unwrap();
+        assert!(editor.add_message(MessageBuilder::new("Status", 0x124, 8).build()).is_err());
+        editor.set_value_descriptions("Status", "Mode", &[(0, "Off"), (1, "On")]).unwrap();
+        editor.set_signal_comment("Status", "Speed", "Vehicle speed").unwrap();
+        editor.set_signal_comment("Status", "Speed", "Wheel speed").unwrap();
+
+        let dbc = reparse(&editor);
+        assert_eq!(&dbc, editor.dbc());
+        assert_eq!(dbc.messages[1].id, MessageId::Extended(1));
+        let temp = &dbc.messages[0].signals[1];
+        assert_eq!((temp.min, temp.max), (-74.0, 53.5));
+        assert_eq!(dbc.messages[0].signals[2].receivers, ["ECU"]);
+        assert_eq!(dbc.signal_comment(MessageId::Standard(0x123), "Speed"), Some("Wheel speed"));
+
+        // The written DBC decodes like the model
+        let database = CanDatabase::from_dbc(dbc);
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 123#0114000A00000000").unwrap();
+        let decoded = database.decode_frame(&frame).unwrap();
+        assert_eq!(decoded.values, [1.0, 0.0, 0.1]);
+        assert_eq!(decoded.labels[0].as_deref(), Some("On"));
+    }
+
+    #[test]
+    fn test_rename_signal_updates_references() {
+        let mut editor = DbcEditor::load("multiplex.dbc").unwrap();
+        editor.rename_signal("UdsResponse", "Identifier", "DataIdentifier").unwrap();
+        editor.rename_signal("UdsResponse", "ResponseCode", "Nrc").unwrap();
+        assert!(editor.rename_signal("UdsResponse", "Service", "Nrc").is_err());
+        assert!(editor.rename_signal("UdsResponse", "Nope", "X").is_err());
+
+        let dbc = reparse(&editor);
+        let id = editor.message("UdsResponse").unwrap().id;
+        assert!(dbc.extended_multiplex.iter().any(|m| m.multiplexor_signal_name == "DataIdentifier"));
+        assert!(!dbc.extended_multiplex.iter().any(|m| m.signal_name == "Identifier"));
+        assert!(dbc.value_descriptions_for_signal(id, "Nrc").is_some());
+        assert!(dbc.value_descriptions_for_signal(id, "ResponseCode").is_none());
+
+        // Multiplexing still resolves through the renamed multiplexer
+        let database = CanDatabase::from_dbc(dbc);
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 00000602#6202A500003039").unwrap();
+        let decoded = database.decode_frame(&frame).unwrap();
+        assert_eq!(decoded.signals, ["Service", "DataIdentifier", "Odometer"]);
+    }
+
+    #[test]
+    fn test_remove_and_retain_messages() {
+        let mut editor = DbcEditor::load("can_samples/chrysler_cusw.dbc").unwrap();
+        let count = editor.dbc().messages.len();
+        let removed = editor.remove_message("GEAR").unwrap();
+        assert!(editor.remove_message("GEAR").is_err());
+        let dbc = reparse(&editor);
+        assert_eq!(dbc.messages.len(), count - 1);
+        assert!(dbc.value_descriptions_for_signal(removed.id, "PRNDL").is_none());
+
+        // Strip everything above 0x300
+        let removed = editor.retain_messages(|m| m.id.raw() <= 0x300);
+        assert!(!removed.is_empty());
+        let dbc = reparse(&editor);
+        assert!(dbc.messages.iter().all(|m| m.id.raw() <= 0x300));
+        // Comments of the removed messages go with them, the DBC has orphans for 498 and 1492
+        let removed_ids: Vec<MessageId> = removed.iter().map(|m| m.id).collect();
+        assert!(dbc.comments.iter().all(|c| match c {
+            Comment::Message { id, .. } | Comment::Signal { message_id: id, .. } => !removed_ids.contains(id),
+            _ => true,
+        }));
+        assert!(dbc.comments.iter().any(|c| matches!(c, Comment::Signal { message_id, .. } if message_id.raw() == 1492)));
+    }
+
+    #[test]
+    fn test_set_message_id_and_value_type() {
+        let mut editor = DbcEditor::load("floats.dbc").unwrap();
+        editor.rename_message("FloatsLE", "Floats").unwrap();
+        assert!(editor.set_message_id("Floats", MessageId::Standard(769)).is_err());
+        editor.set_message_id("Floats", MessageId::Standard(0x310)).unwrap();
+        editor
+            .set_signal_value_type("Counter", "Ticks", SignalExtendedValueType::IEEEfloat32Bit)
+            .unwrap();
+        let dbc = reparse(&editor);
+        let new_id = MessageId::Standard(0x310);
+        assert_eq!(
+            dbc.extended_value_type_for_signal(new_id, "Speed"),
+            Some(&SignalExtendedValueType::IEEEfloat32Bit)
+        );
+        assert!(dbc.extended_value_type_for_signal(MessageId::Standard(768), "Speed").is_none());
+        assert_eq!(
+            dbc.extended_value_type_for_signal(MessageId::Standard(772), "Ticks"),
+            Some(&SignalExtendedValueType::IEEEfloat32Bit)
+        );
+
+        editor.remove_signal("Floats", "Gain").unwrap();
+        let dbc = reparse(&editor);
+        assert!(dbc.extended_value_type_for_signal(new_id, "Gain").is_none());
+        assert_eq!(dbc.messages[0].signals.len(), 1);
+    }
+
+    #[test]
+    fn test_rename_and_remove_nodes() {
+        let dbc = can_decoder::dbc_from_str(concat!(
+            "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: ECU Gateway Logger\n\n",
+            "BO_ 100 Status: 8 ECU\n",
+            " SG_ Speed : 0|16@1+ (1,0) [0|65535] \"\" Gateway,Logger\n",
+            " SG_ Mode : 16|8@1+ (1,0) [0|255] \"\" Gateway\n\n",
+            "BO_ 200 Request: 8 Gateway\n",
+            " SG_ Command : 0|8@1+ (1,0) [0|255] \"\" ECU\n\n",
+            "BO_TX_BU_ 100 : ECU,Gateway;\n",
+            "CM_ BU_ Gateway \"Central gateway\";\n",
+            "BA_DEF_ BU_  \"NodeLayer\" INT 0 10;\n",
+            "BA_DEF_DEF_  \"NodeLayer\" 0;\n",
+            "BA_ \"NodeLayer\" BU_ Gateway 2;\n",
+        ))
+        .unwrap();
+        let mut editor = DbcEditor::from_dbc(dbc);
+        assert!(editor.rename_node("Gateway", "ECU").is_err());
+        assert!(editor.rename_node("Nope", "X").is_err());
+        editor.rename_node("Gateway", "Gw").unwrap();
+        let dbc = reparse(&editor);
+        assert_eq!(dbc.nodes.iter().map(|n| n.0.as_str()).collect::<Vec<_>>(), ["ECU", "Gw", "Logger"]);
+        assert_eq!(dbc.messages[0].signals[0].receivers, ["Gw", "Logger"]);
+        assert_eq!(dbc.messages[1].transmitter, Transmitter::NodeName("Gw".to_owned()));
+        assert_eq!(dbc.message_transmitters[0].transmitter[1], Transmitter::NodeName("Gw".to_owned()));
+        assert!(dbc.comments.iter().any(|c| matches!(c, Comment::Node { name, .. } if name == "Gw")));
+        assert_eq!(dbc.attribute_values_node[0].node_name, "Gw");
+
+        editor.remove_node("Gw").unwrap();
+        assert!(editor.remove_node("Gw").is_err());
+        let dbc = reparse(&editor);
+        assert_eq!(dbc.messages[0].signals[0].receivers, ["Logger"]);
+        assert_eq!(dbc.messages[0].signals[1].receivers, ["Vector__XXX"]);
+        assert_eq!(dbc.messages[1].transmitter, Transmitter::VectorXXX);
+        assert_eq!(dbc.message_transmitters[0].transmitter, [Transmitter::NodeName("ECU".to_owned())]);
+        assert!(dbc.comments.is_empty());
+        assert!(dbc.attribute_values_node.is_empty());
+
+        // Logger receives a signal of Status, ECU only sent it and received Request
+        editor.remove_message("Status").unwrap();
+        assert_eq!(editor.remove_unused_nodes(), ["Logger"]);
+        assert_eq!(editor.dbc().nodes, [Node("ECU".to_owned())]);
+    }
+
+    #[test]
+    fn test_set_multiplexor_values_extended() {
+        let mut editor = DbcEditor::load("multiplex.dbc").unwrap();
+        // A multiplexed multiplexor keeps its role, the other switch selects it
+        editor.set_multiplexor_values("UdsResponse", "Identifier", &[98, 99]).unwrap();
+        // Odometer keeps the multiplexor of its SG_MUL_VAL_
+        editor.set_multiplexor_values("UdsResponse", "Odometer", &[512, 600]).unwrap();
+        editor.add_signal("UdsResponse", SignalBuilder::new("Extra", 40, 8).build()).unwrap();
+        assert!(editor.set_multiplexor_values("UdsResponse", "Extra", &[1]).is_err());
+        editor.remove_signal("UdsResponse", "Extra").unwrap();
+        editor.set_multiplexor_values("Diagnostics", "Status", &[3]).unwrap();
+
+        let dbc = reparse(&editor);
+        let uds = &dbc.messages[1];
+        assert_eq!(uds.signals[3].multiplexer_indicator, MultiplexIndicator::MultiplexorAndMultiplexedSignal(98));
+        let switch = |signal: &str| {
+            dbc.extended_multiplex.iter().find(|m| m.signal_name == signal).map(|m| m.multiplexor_signal_name.as_str())
+        };
+        assert_eq!(switch("Identifier"), Some("Service"));
+        assert_eq!(switch("Odometer"), Some("Identifier"));
+        assert_eq!(dbc.messages[0].signals[1].multiplexer_indicator, MultiplexIndicator::MultiplexedSignal(3));
+        assert_eq!(switch("Status"), None);
+
+        let database = CanDatabase::from_dbc(dbc);
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 00000602#63025800003039").unwrap();
+        let decoded = database.decode_frame(&frame).unwrap();
+        assert_eq!(decoded.signals, ["Service", "Identifier", "Odometer"]);
+    }
+
+    #[test]
+    fn test_message_id() {
+        assert_eq!(message_id(0x7FF, false), MessageId::Standard(0x7FF));
+        assert_eq!(message_id(0x800, false), MessageId::Extended(0x800));
+        assert_eq!(message_id(0x10, true), MessageId::Extended(0x10));
+        assert_eq!(message_id(0x8000_0602, false), MessageId::Extended(0x602));
+    }
+}
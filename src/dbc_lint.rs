@@ -0,0 +1,353 @@
+/*!
+ * DBC validation: finds signal layouts and definitions that decode wrong or not at all.
+ * can_dbc::Dbc -> Vec<LintIssue>
+ *
+ * Bit positions come from `SignalLayout::from_spec`, so the checks see exactly the bits
+ * the decoder and encoder use. Signals on exclusive multiplexer pages may share bits.
+ *
+ * ```no_run
+ * use rocketcan::{can_decoder, dbc_lint};
+ *
+ * let dbc = can_decoder::load_dbc("motohawk.dbc").unwrap();
+ * for issue in dbc_lint::lint(&dbc) {
+ *     println!("{issue}");
+ * }
+ * ```
+ */
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use can_dbc::{Dbc, MultiplexIndicator, SignalExtendedValueType};
+
+use crate::can_decoder;
+use crate::can_encoder;
+use crate::signal_layout::SignalLayout;
+
+/// How bad an issue is, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Suspicious but decodes as written
+    Warning,
+    /// Decodes wrong values, or the signal or message can't be decoded
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// What is wrong
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintKind {
+    /// Another message has the same ID
+    DuplicateId { first: String },
+    /// Size is 0 or more than 64 bits
+    InvalidSize { size: u64 },
+    /// Float signal whose size is not 32 or 64 bits
+    InvalidFloatSize { size: u64 },
+    /// Bits after the end of the message, `end` is the number of bytes the signal needs
+    BeyondDlc { dlc: u64, end: u64 },
+    /// Shares `bits` bits with another signal present in the same frames
+    Overlap { other: String, bits: u32 },
+    /// Factor 0, every raw value decodes to the offset
+    ZeroFactor,
+    /// min is greater than max
+    MinAboveMax { min: f64, max: f64 },
+    /// min or max is outside the physical values the raw bits can hold
+    RangeNotRepresentable { min: f64, max: f64, lowest: f64, highest: f64 },
+    /// Multiplexed signal without a multiplexer to select it
+    MuxWithoutSwitch,
+}
+
+/// One problem found in a DBC
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintIssue {
+    pub severity: Severity,
+    pub message: String,
+    /// None for issues about the whole message
+    pub signal: Option<String>,
+    pub kind: LintKind,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)?;
+        if let Some(signal) = &self.signal {
+            write!(f, ".{signal}")?;
+        }
+        write!(f, ": ")?;
+        match &self.kind {
+            LintKind::DuplicateId { first } => write!(f, "same ID as {first}"),
+            LintKind::InvalidSize { size } => write!(f, "invalid size of {size} bits"),
+            LintKind::InvalidFloatSize { size } => write!(f, "float signal of {size} bits, expected 32 or 64"),
+            LintKind::BeyondDlc { dlc, end } => write!(f, "needs {end} bytes but the message has {dlc}"),
+            LintKind::Overlap { other, bits } => write!(f, "overlaps {other} on {bits} bits"),
+            LintKind::ZeroFactor => write!(f, "factor is 0"),
+            LintKind::MinAboveMax { min, max } => write!(f, "min {min} is greater than max {max}"),
+            LintKind::RangeNotRepresentable { min, max, lowest, highest } => {
+                write!(f, "range [{min}|{max}] outside of the representable [{lowest}|{highest}]")
+            }
+            LintKind::MuxWithoutSwitch => write!(f, "multiplexed signal without a multiplexer"),
+        }
+    }
+}
+
+/// Check every message of a DBC, issues are in message order
+pub fn lint(dbc: &Dbc) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut ids: HashMap<u32, &str> = HashMap::new();
+    for message in &dbc.messages {
+        let id = can_encoder::get_can_id(message);
+        match ids.get(&id) {
+            Some(first) => issues.push(LintIssue {
+                severity: Severity::Error,
+                message: message.name.clone(),
+                signal: None,
+                kind: LintKind::DuplicateId { first: first.to_string() },
+            }),
+            None => {
+                ids.insert(id, &message.name);
+            }
+        }
+        lint_message(dbc, message, &mut issues);
+    }
+    issues
+}
+
+/// Bits of a signal, indexed by byte * 8 + bit, up to 64 bytes
+type Bits = [u64; 8];
+
+/// Multiplexer name and the raw values selecting a signal
+type MuxConditions = Vec<(String, Vec<RangeInclusive<u64>>)>;
+
+fn lint_message(dbc: &Dbc, message: &can_dbc::Message, issues: &mut Vec<LintIssue>) {
+    let mut issue = |severity, signal: &can_dbc::Signal, kind| {
+        issues.push(LintIssue { severity, message: message.name.clone(), signal: Some(signal.name.clone()), kind });
+    };
+    // Bits and multiplexer conditions of the signals with a valid size
+    let mut placed: Vec<(&can_dbc::Signal, Bits, MuxConditions)> = Vec::new();
+    for signal in &message.signals {
+        if signal.size == 0 || signal.size > 64 {
+            issue(Severity::Error, signal, LintKind::InvalidSize { size: signal.size });
+            continue;
+        }
+        let value_type = can_decoder::get_value_type(dbc, message, signal);
+        let is_float = value_type != SignalExtendedValueType::SignedOrUnsignedInteger;
+        let float_size = match value_type {
+            SignalExtendedValueType::IEEEfloat32Bit => 32,
+            SignalExtendedValueType::IEEEdouble64bit => 64,
+            SignalExtendedValueType::SignedOrUnsignedInteger => signal.size,
+        };
+        if signal.size != float_size {
+            issue(Severity::Error, signal, LintKind::InvalidFloatSize { size: signal.size });
+        }
+
+        let layout = SignalLayout::from_spec(signal);
+        let end = layout.segments.iter().map(|s| s.byte_index as u64 + 1).max().unwrap_or(0);
+        if end > message.size {
+            issue(Severity::Error, signal, LintKind::BeyondDlc { dlc: message.size, end });
+        }
+
+        if signal.factor == 0.0 {
+            issue(Severity::Error, signal, LintKind::ZeroFactor);
+        } else if signal.min > signal.max {
+            issue(Severity::Warning, signal, LintKind::MinAboveMax { min: signal.min, max: signal.max });
+        } else if !is_float && (signal.min != 0.0 || signal.max != 0.0) {
+            // [0|0] means no range in DBC files
+            let (lowest, highest) = physical_range(signal);
+            let tolerance = signal.factor.abs() / 2.0;
+            if signal.min < lowest - tolerance || signal.max > highest + tolerance {
+                issue(
+                    Severity::Warning,
+                    signal,
+                    LintKind::RangeNotRepresentable { min: signal.min, max: signal.max, lowest, highest },
+                );
+            }
+        }
+
+        let is_multiplexed = matches!(
+            signal.multiplexer_indicator,
+            MultiplexIndicator::MultiplexedSignal(_) | MultiplexIndicator::MultiplexorAndMultiplexedSignal(_)
+        ) || dbc.extended_multiplex.iter().any(|e| e.message_id == message.id && e.signal_name == signal.name);
+        let conditions = mux_conditions(dbc, message, signal);
+        if is_multiplexed && conditions.is_empty() {
+            issue(Severity::Error, signal, LintKind::MuxWithoutSwitch);
+        }
+
+        let mut bits = [0u64; 8];
+        for span in &layout.segments {
+            for bit in span.bit_offset..span.bit_offset + span.num_bits {
+                let index = span.byte_index * 8 + bit as usize;
+                if index < 512 {
+                    bits[index / 64] |= 1 << (index % 64);
+                }
+            }
+        }
+        placed.push((signal, bits, conditions));
+    }
+
+    for (i, (signal, bits, conditions)) in placed.iter().enumerate() {
+        for (other, other_bits, other_conditions) in &placed[..i] {
+            let shared: u32 = bits.iter().zip(other_bits).map(|(a, b)| (a & b).count_ones()).sum();
+            if shared > 0 && !exclusive(conditions, other_conditions) {
+                issue(Severity::Error, signal, LintKind::Overlap { other: other.name.clone(), bits: shared });
+            }
+        }
+    }
+}
+
+/// Lowest and highest physical values of the raw bits of an integer signal
+fn physical_range(signal: &can_dbc::Signal) -> (f64, f64) {
+    let (raw_min, raw_max) = match signal.value_type {
+        can_dbc::ValueType::Signed => (-(2f64.powi(signal.size as i32 - 1)), 2f64.powi(signal.size as i32 - 1) - 1.0),
+        can_dbc::ValueType::Unsigned => (0.0, 2f64.powi(signal.size as i32) - 1.0),
+    };
+    let a = raw_min * signal.factor + signal.offset;
+    let b = raw_max * signal.factor + signal.offset;
+    (a.min(b), a.max(b))
+}
+
+/// Multiplexer conditions from the signal up to the top of the multiplexer tree,
+/// as (multiplexer name, selecting raw values)
+fn mux_conditions(
+    dbc: &Dbc,
+    message: &can_dbc::Message,
+    signal: &can_dbc::Signal,
+) -> MuxConditions {
+    let mut conditions = MuxConditions::new();
+    let mut current = signal;
+    while let Some(condition) = can_decoder::mux_condition(message, current, &dbc.extended_multiplex) {
+        // Guard against multiplexer cycles
+        if condition.multiplexor.name == signal.name || conditions.iter().any(|(name, _)| *name == condition.multiplexor.name) {
+            break;
+        }
+        conditions.push((condition.multiplexor.name.clone(), condition.ranges));
+        current = condition.multiplexor;
+    }
+    conditions
+}
+
+/// Whether two signals are never in the same frame: one multiplexer selects them on disjoint values
+fn exclusive(a: &MuxConditions, b: &MuxConditions) -> bool {
+    a.iter().any(|(mux, ranges)| {
+        b.iter().any(|(other_mux, other_ranges)| {
+            mux == other_mux
+                && ranges.iter().all(|r| other_ranges.iter().all(|o| r.end() < o.start() || o.end() < r.start()))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEAD: &str = "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: A\n\n";
+
+    fn lint_str(body: &str) -> Vec<LintIssue> {
+        lint(&can_decoder::dbc_from_str(&format!("{HEAD}{body}")).unwrap())
+    }
+
+    #[test]
+    fn test_sample_dbcs_are_clean() {
+        for path in ["motohawk.dbc", "multiplex.dbc", "floats.dbc"] {
+            let dbc = can_decoder::load_dbc(path).unwrap();
+            let errors: Vec<_> = lint(&dbc).into_iter().filter(|i| i.severity == Severity::Error).collect();
+            assert!(errors.is_empty(), "{path}: {errors:?}");
+        }
+    }
+
+    #[test]
+    fn test_layout_issues() {
+        let issues = lint_str(concat!(
+            "BO_ 100 M: 4 A\n",
+            " SG_ A : 0|12@1+ (1,0) [0|4095] \"\" A\n",
+            " SG_ B : 8|8@1+ (1,0) [0|255] \"\" A\n",
+            " SG_ C : 23|16@0+ (1,0) [0|65535] \"\" A\n",
+            " SG_ D : 24|0@1+ (1,0) [0|0] \"\" A\n\n",
+        ));
+        let kinds: Vec<_> = issues.iter().map(|i| (i.signal.as_deref().unwrap(), &i.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("D", &LintKind::InvalidSize { size: 0 }),
+                ("B", &LintKind::Overlap { other: "A".to_owned(), bits: 4 }),
+            ]
+        );
+
+        let issues = lint_str("BO_ 100 M: 2 A\n SG_ C : 7|16@0+ (1,0) [0|65535] \"\" A\n SG_ E : 16|8@1+ (1,0) [0|255] \"\" A\n\n");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, LintKind::BeyondDlc { dlc: 2, end: 3 });
+        assert_eq!(issues[0].to_string(), "error: M.E: needs 3 bytes but the message has 2");
+    }
+
+    #[test]
+    fn test_value_issues() {
+        let issues = lint_str(concat!(
+            "BO_ 100 M: 8 A\n",
+            " SG_ Zero : 0|8@1+ (0,0) [0|255] \"\" A\n",
+            " SG_ TooHigh : 8|8@1+ (0.5,0) [0|200] \"\" A\n",
+            " SG_ Signed : 16|8@1- (1,0) [-128|127] \"\" A\n",
+            " SG_ Negative : 24|8@1+ (1,0) [-1|255] \"\" A\n",
+            " SG_ Swapped : 32|8@1+ (1,0) [10|0] \"\" A\n",
+            " SG_ Rounded : 40|8@1+ (0.1,0) [0|25.5] \"\" A\n\n",
+        ));
+        let kinds: Vec<_> = issues.iter().map(|i| (i.signal.as_deref().unwrap(), i.severity, &i.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("Zero", Severity::Error, &LintKind::ZeroFactor),
+                (
+                    "TooHigh",
+                    Severity::Warning,
+                    &LintKind::RangeNotRepresentable { min: 0.0, max: 200.0, lowest: 0.0, highest: 127.5 }
+                ),
+                (
+                    "Negative",
+                    Severity::Warning,
+                    &LintKind::RangeNotRepresentable { min: -1.0, max: 255.0, lowest: 0.0, highest: 255.0 }
+                ),
+                ("Swapped", Severity::Warning, &LintKind::MinAboveMax { min: 10.0, max: 0.0 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_ids_and_multiplexing() {
+        let issues = lint_str(concat!(
+            "BO_ 100 First: 8 A\n",
+            " SG_ Mux M : 0|8@1+ (1,0) [0|0] \"\" A\n",
+            " SG_ PageA m1 : 8|16@1+ (1,0) [0|0] \"\" A\n",
+            " SG_ PageB m2 : 8|8@1+ (1,0) [0|0] \"\" A\n",
+            " SG_ Plain : 16|8@1+ (1,0) [0|0] \"\" A\n\n",
+            "BO_ 100 Second: 8 A\n",
+            " SG_ Orphan m1 : 0|8@1+ (1,0) [0|0] \"\" A\n\n",
+        ));
+        let found: Vec<_> = issues.iter().map(|i| (i.message.as_str(), i.signal.as_deref(), &i.kind)).collect();
+        assert_eq!(
+            found,
+            [
+                ("First", Some("Plain"), &LintKind::Overlap { other: "PageA".to_owned(), bits: 8 }),
+                ("Second", None, &LintKind::DuplicateId { first: "First".to_owned() }),
+                ("Second", Some("Orphan"), &LintKind::MuxWithoutSwitch),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_float_size() {
+        let issues = lint_str(concat!(
+            "BO_ 100 M: 8 A\n",
+            " SG_ F : 0|16@1+ (1,0) [0|0] \"\" A\n\n",
+            "SIG_VALTYPE_ 100 F : 1;\n",
+        ));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, LintKind::InvalidFloatSize { size: 16 });
+    }
+}
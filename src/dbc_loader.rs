@@ -0,0 +1,413 @@
+/*!
+ * DBC loading with diagnostics.
+ * DBC file / bytes -> can_dbc::Dbc + warnings, or an error with the line and column
+ *
+ * Besides parsing, the loader
+ * - decodes files that are not UTF-8 as Windows-1252, as written by Windows DBC editors
+ * - repairs CM_ strings ending in a backslash, e.g. "C:\logs\", which can-dbc reads as
+ *   an escaped quote running into the rest of the file. The comment text is kept as is.
+ * - warns about entries referencing messages or signals the DBC does not define
+ *
+ * ```no_run
+ * use rocketcan::dbc_loader;
+ *
+ * match dbc_loader::load("abs.dbc") {
+ *     Ok(loaded) => {
+ *         for warning in &loaded.warnings {
+ *             eprintln!("warning: {warning}");
+ *         }
+ *         println!("{} messages", loaded.dbc.messages.len());
+ *     }
+ *     Err(e) => eprintln!("{e}"),
+ * }
+ * ```
+ */
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+
+use can_dbc::{AttributeValueForRelationType, Comment, Dbc, DbcError, MessageId, ValueDescription};
+use pest::error::LineColLocation;
+
+/// Appended to repaired comment strings so the parser sees `\` followed by a plain
+/// character, removed again once parsed
+const BACKSLASH_MARKER: char = '\u{E000}';
+
+/// A DBC and the recoverable problems found while loading it
+#[derive(Debug, Clone)]
+pub struct LoadedDbc {
+    pub dbc: Dbc,
+    pub warnings: Vec<DbcWarning>,
+}
+
+/// Recoverable problems found while loading a DBC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbcWarning {
+    /// The file is not UTF-8 and was decoded as Windows-1252
+    Windows1252,
+    /// A CM_ string on this line ends with a backslash and was repaired
+    CommentBackslash { line: usize },
+    /// Two messages share an ID, lookups by ID find the first one
+    DuplicateMessageId { id: u32, first: String, second: String },
+    /// A section entry references a message ID that is not defined
+    UnknownMessage { section: &'static str, id: u32 },
+    /// A section entry references a signal that is not in its message
+    UnknownSignal { section: &'static str, message: String, signal: String },
+}
+
+impl fmt::Display for DbcWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbcWarning::Windows1252 => write!(f, "not valid UTF-8, decoded as Windows-1252"),
+            DbcWarning::CommentBackslash { line } => {
+                write!(f, "line {line}: comment ending with a backslash, read as a literal backslash")
+            }
+            DbcWarning::DuplicateMessageId { id, first, second } => {
+                write!(f, "messages {first} and {second} share the ID {id:#X}")
+            }
+            DbcWarning::UnknownMessage { section, id } => write!(f, "{section} references unknown message {id:#X}"),
+            DbcWarning::UnknownSignal { section, message, signal } => {
+                write!(f, "{section} references unknown signal {message}.{signal}")
+            }
+        }
+    }
+}
+
+/// Why a DBC could not be loaded
+#[derive(Debug)]
+pub enum DbcLoadError {
+    Io { path: String, source: io::Error },
+    Parse(DbcParseError),
+}
+
+/// A parse failure, with its location when the parser reports one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbcParseError {
+    /// File the DBC was read from, None when parsed from memory
+    pub path: Option<String>,
+    /// 1-based line and column
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub reason: String,
+    /// Text of the failing line
+    pub line_text: Option<String>,
+}
+
+impl fmt::Display for DbcParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{path}:")?;
+        }
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{line}:{column}: ")?,
+            (Some(line), None) => write!(f, "{line}: ")?,
+            _ => {}
+        }
+        write!(f, "{}", self.reason)?;
+        if let (Some(text), Some(column)) = (&self.line_text, self.column) {
+            write!(f, "\n    {text}\n    {:>width$}", "^", width = column)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for DbcParseError {}
+
+impl fmt::Display for DbcLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbcLoadError::Io { path, source } => write!(f, "{path}: {source}"),
+            DbcLoadError::Parse(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl error::Error for DbcLoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            DbcLoadError::Io { source, .. } => Some(source),
+            DbcLoadError::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<DbcLoadError> for io::Error {
+    fn from(e: DbcLoadError) -> Self {
+        match e {
+            DbcLoadError::Io { path, source } => io::Error::new(source.kind(), format!("{path}: {source}")),
+            DbcLoadError::Parse(e) => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+/// Load a DBC file
+pub fn load(dbc_path: &str) -> Result<LoadedDbc, DbcLoadError> {
+    let bytes = fs::read(dbc_path).map_err(|source| DbcLoadError::Io {
+        path: dbc_path.to_owned(),
+        source,
+    })?;
+    from_bytes(&bytes).map_err(|mut e| {
+        e.path = Some(dbc_path.to_owned());
+        DbcLoadError::Parse(e)
+    })
+}
+
+/// Parse DBC file contents, UTF-8 or Windows-1252
+pub fn from_bytes(bytes: &[u8]) -> Result<LoadedDbc, DbcParseError> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => parse(text),
+        Err(_) => {
+            // Every byte is defined in Windows-1252, decoding can't fail
+            let text = can_dbc::decode_cp1252(bytes).unwrap_or_default();
+            let mut loaded = parse(&text)?;
+            loaded.warnings.insert(0, DbcWarning::Windows1252);
+            Ok(loaded)
+        }
+    }
+}
+
+/// Parse DBC text
+pub fn parse(text: &str) -> Result<LoadedDbc, DbcParseError> {
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+    let mut warnings = Vec::new();
+    let dbc = match can_dbc::Dbc::try_from(text) {
+        Ok(dbc) => dbc,
+        Err(first_error) => {
+            let (repaired, lines) = repair_comment_backslashes(text);
+            if lines.is_empty() {
+                return Err(parse_error(text, &first_error));
+            }
+            let mut dbc = can_dbc::Dbc::try_from(repaired.as_str()).map_err(|e| parse_error(&repaired, &e))?;
+            for comment in &mut dbc.comments {
+                let text = match comment {
+                    Comment::Plain { comment }
+                    | Comment::Node { comment, .. }
+                    | Comment::Message { comment, .. }
+                    | Comment::Signal { comment, .. }
+                    | Comment::EnvVar { comment, .. } => comment,
+                };
+                if text.ends_with(BACKSLASH_MARKER) {
+                    text.pop();
+                }
+            }
+            warnings.extend(lines.into_iter().map(|line| DbcWarning::CommentBackslash { line }));
+            dbc
+        }
+    };
+    warnings.extend(check_references(&dbc));
+    Ok(LoadedDbc { dbc, warnings })
+}
+
+/// Insert a marker between a backslash and the closing quote of CM_ strings.
+/// Returns the repaired text and the 1-based lines that were changed.
+fn repair_comment_backslashes(text: &str) -> (String, Vec<usize>) {
+    let mut repaired = String::with_capacity(text.len());
+    let mut lines = Vec::new();
+    let mut in_comment = false;
+    let mut quotes = 0;
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        if !in_comment && line.trim_start().starts_with("CM_") {
+            in_comment = true;
+            quotes = 0;
+        }
+        quotes += line.matches('"').count();
+        // A comment statement ends with the closing quote and an optional ';'
+        let body = line.trim_end();
+        let string_end = body.strip_suffix(';').map(str::trim_end).unwrap_or(body);
+        if in_comment && quotes >= 2 && string_end.ends_with('"') {
+            in_comment = false;
+            if string_end.ends_with("\\\"") {
+                let quote = string_end.len() - 1;
+                repaired.push_str(&line[..quote]);
+                repaired.push(BACKSLASH_MARKER);
+                repaired.push_str(&line[quote..]);
+                lines.push(index + 1);
+                continue;
+            }
+        }
+        repaired.push_str(line);
+    }
+    (repaired, lines)
+}
+
+fn parse_error(text: &str, error: &DbcError) -> DbcParseError {
+    match error {
+        DbcError::Pest(pest_error) => {
+            let (line, column) = match pest_error.line_col {
+                LineColLocation::Pos((line, column)) | LineColLocation::Span((line, column), _) => (line, column),
+            };
+            DbcParseError {
+                path: None,
+                line: Some(line),
+                column: Some(column),
+                reason: pest_error.variant.message().into_owned(),
+                line_text: text.lines().nth(line - 1).map(|l| l.trim_end().to_owned()),
+            }
+        }
+        other => DbcParseError {
+            path: None,
+            line: None,
+            column: None,
+            reason: other.to_string(),
+            line_text: None,
+        },
+    }
+}
+
+/// Entries of the other sections that point at messages or signals that don't exist
+fn check_references(dbc: &Dbc) -> Vec<DbcWarning> {
+    let mut warnings = Vec::new();
+    let mut by_id: HashMap<MessageId, &can_dbc::Message> = HashMap::new();
+    for message in &dbc.messages {
+        if let Some(first) = by_id.get(&message.id) {
+            warnings.push(DbcWarning::DuplicateMessageId {
+                id: message.id.raw(),
+                first: first.name.clone(),
+                second: message.name.clone(),
+            });
+        } else {
+            by_id.insert(message.id, message);
+        }
+    }
+
+    let mut check = |section: &'static str, id: &MessageId, signal: Option<&str>| {
+        let Some(message) = by_id.get(id) else {
+            let warning = DbcWarning::UnknownMessage { section, id: id.raw() };
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+            return;
+        };
+        if let Some(signal) = signal {
+            if !message.signals.iter().any(|s| s.name == signal) {
+                warnings.push(DbcWarning::UnknownSignal {
+                    section,
+                    message: message.name.clone(),
+                    signal: signal.to_owned(),
+                });
+            }
+        }
+    };
+    for comment in &dbc.comments {
+        match comment {
+            Comment::Message { id, .. } => check("CM_", id, None),
+            Comment::Signal { message_id, name, .. } => check("CM_", message_id, Some(name)),
+            _ => {}
+        }
+    }
+    for value in &dbc.attribute_values_message {
+        check("BA_", &value.message_id, None);
+    }
+    for value in &dbc.attribute_values_signal {
+        check("BA_", &value.message_id, Some(&value.signal_name));
+    }
+    for value in &dbc.relation_attribute_values {
+        match &value.details {
+            AttributeValueForRelationType::NodeToSignal { message_id, signal_name, .. } => {
+                check("BA_REL_", message_id, Some(signal_name))
+            }
+            AttributeValueForRelationType::NodeToMessage { message_id, .. } => check("BA_REL_", message_id, None),
+        }
+    }
+    for description in &dbc.value_descriptions {
+        if let ValueDescription::Signal { message_id, name, .. } = description {
+            check("VAL_", message_id, Some(name));
+        }
+    }
+    for transmitters in &dbc.message_transmitters {
+        check("BO_TX_BU_", &transmitters.message_id, None);
+    }
+    for group in &dbc.signal_groups {
+        for signal in &group.signal_names {
+            check("SIG_GROUP_", &group.message_id, Some(signal));
+        }
+    }
+    for value_type in &dbc.signal_extended_value_type_list {
+        check("SIG_VALTYPE_", &value_type.message_id, Some(&value_type.signal_name));
+    }
+    for multiplex in &dbc.extended_multiplex {
+        check("SG_MUL_VAL_", &multiplex.message_id, Some(&multiplex.signal_name));
+        check("SG_MUL_VAL_", &multiplex.message_id, Some(&multiplex.multiplexor_signal_name));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEAD: &str = "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: A\n\nBO_ 100 M: 8 A\n SG_ S : 0|8@1+ (1,0) [0|255] \"\" A\n\n";
+
+    #[test]
+    fn test_comment_ending_with_backslash_is_kept() {
+        let text = fs::read_to_string("comment_backslash.dbc").unwrap();
+        assert!(can_dbc::Dbc::try_from(text.as_str()).is_err());
+        let loaded = parse(&text).unwrap();
+        assert_eq!(loaded.dbc.message_comment(MessageId::Standard(100)), Some("Logged to C:\\logs\\"));
+        assert_eq!(loaded.dbc.comments.len(), 3);
+        assert!(matches!(&loaded.dbc.comments[1], Comment::Signal { comment, .. } if comment == "multi\nline \\"));
+        assert_eq!(
+            loaded.warnings,
+            [DbcWarning::CommentBackslash { line: 12 }, DbcWarning::CommentBackslash { line: 14 }]
+        );
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let text = format!("{HEAD}BO_ 200 Broken: 8 A\n SG_ X : 0|8@1+ (1,0) [0|255 \"\" A\n");
+        let error = parse(&text).unwrap_err();
+        assert_eq!(error.line, Some(13));
+        assert_eq!(error.column, Some(23));
+        assert_eq!(error.line_text.as_deref(), Some(" SG_ X : 0|8@1+ (1,0) [0|255 \"\" A"));
+        assert!(error.to_string().starts_with("13:23: expected"), "{error}");
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let error = load("no_such_file.dbc").unwrap_err();
+        assert!(matches!(&error, DbcLoadError::Io { source, .. } if source.kind() == io::ErrorKind::NotFound));
+        assert!(error.to_string().starts_with("no_such_file.dbc: "));
+
+        let io_error: io::Error = DbcLoadError::Parse(parse("BO_ x").unwrap_err()).into();
+        assert_eq!(io_error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_windows_1252() {
+        let loaded = load("abs.dbc").unwrap();
+        assert_eq!(loaded.warnings[0], DbcWarning::Windows1252);
+        assert!(!loaded.dbc.messages.is_empty());
+        // The degree sign is 0xB0 in Windows-1252
+        assert!(loaded.dbc.messages.iter().flat_map(|m| &m.signals).any(|s| s.unit.contains('\u{B0}')));
+    }
+
+    #[test]
+    fn test_reference_warnings() {
+        let text = format!(
+            "{HEAD}BO_ 100 Twin: 8 A\n\nCM_ SG_ 100 Nope \"x\";\nCM_ BO_ 300 \"y\";\nVAL_ 300 S 0 \"Off\" ;\n"
+        );
+        let loaded = parse(&text).unwrap();
+        assert_eq!(
+            loaded.warnings,
+            [
+                DbcWarning::DuplicateMessageId { id: 100, first: "M".into(), second: "Twin".into() },
+                DbcWarning::UnknownSignal { section: "CM_", message: "M".into(), signal: "Nope".into() },
+                DbcWarning::UnknownMessage { section: "CM_", id: 300 },
+                DbcWarning::UnknownMessage { section: "VAL_", id: 300 },
+            ]
+        );
+        // The chrysler DBC has comments for messages it doesn't define
+        let loaded = load("can_samples/chrysler_cusw.dbc").unwrap();
+        assert!(loaded.warnings.contains(&DbcWarning::UnknownMessage { section: "CM_", id: 1492 }));
+        assert!(load("motohawk.dbc").unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_byte_order_mark() {
+        let text = format!("\u{FEFF}{HEAD}");
+        assert!(parse(&text).unwrap().warnings.is_empty());
+    }
+}
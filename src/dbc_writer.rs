@@ -0,0 +1,425 @@
+/*!
+ * Serializes a `can_dbc::Dbc` back to DBC text.
+ * can_dbc::Dbc -> DBC file
+ *
+ * Sections are written in the usual order (VERSION, NS_, BS_, BU_, VAL_TABLE_, BO_, ...,
+ * SG_MUL_VAL_) so parsing the output gives back an equal `Dbc`. Strings are written as
+ * stored, can_dbc keeps them unescaped. In free text (comments, value labels and string
+ * attribute values) a bare `"` is escaped and a trailing backslash followed by a space,
+ * as can_dbc would read it as an escaped closing quote.
+ */
+
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+
+use can_dbc::{
+    AccessNode, AccessType, AttributeDefinition, AttributeValue, AttributeValueForRelationType, AttributeValueType,
+    ByteOrder, Comment, Dbc, EnvType, MultiplexIndicator, NumericValue, SignalExtendedValueType,
+    Transmitter, ValDescription, ValueDescription, ValueType,
+};
+
+/// Write a DBC to a file
+pub fn save_dbc(dbc: &Dbc, dbc_path: &str) -> io::Result<()> {
+    fs::write(dbc_path, dbc_to_string(dbc))
+}
+
+/// Serialize a DBC to text
+pub fn dbc_to_string(dbc: &Dbc) -> String {
+    let mut out = String::new();
+    // Writing to a String never fails
+    let _ = write_dbc(&mut out, dbc);
+    out
+}
+
+/// Serialize a DBC into any `fmt::Write`
+pub fn write_dbc<W: Write>(out: &mut W, dbc: &Dbc) -> fmt::Result {
+    writeln!(out, "VERSION \"{}\"\n", dbc.version.0)?;
+
+    writeln!(out, "NS_ :")?;
+    for symbol in &dbc.new_symbols {
+        writeln!(out, "\t{}", symbol.0)?;
+    }
+    writeln!(out)?;
+    // Bit timing is obsolete, can_dbc only records whether the section was there
+    if dbc.bit_timing.is_some() {
+        writeln!(out, "BS_:\n")?;
+    }
+
+    write!(out, "BU_:")?;
+    for node in &dbc.nodes {
+        write!(out, " {}", node.0)?;
+    }
+    writeln!(out, "\n")?;
+
+    for table in &dbc.value_tables {
+        write!(out, "VAL_TABLE_ {}", table.name)?;
+        write_descriptions(out, &table.descriptions)?;
+        writeln!(out, " ;")?;
+    }
+    if !dbc.value_tables.is_empty() {
+        writeln!(out)?;
+    }
+
+    for message in &dbc.messages {
+        let transmitter = match &message.transmitter {
+            Transmitter::NodeName(name) => name.as_str(),
+            Transmitter::VectorXXX => "Vector__XXX",
+        };
+        writeln!(out, "BO_ {} {}: {} {}", message.id.raw(), message.name, message.size, transmitter)?;
+        for signal in &message.signals {
+            let multiplexer = match signal.multiplexer_indicator {
+                MultiplexIndicator::Plain => String::new(),
+                MultiplexIndicator::Multiplexor => " M".to_owned(),
+                MultiplexIndicator::MultiplexedSignal(value) => format!(" m{value}"),
+                MultiplexIndicator::MultiplexorAndMultiplexedSignal(value) => format!(" m{value}M"),
+            };
+            let byte_order = match signal.byte_order {
+                ByteOrder::BigEndian => '0',
+                ByteOrder::LittleEndian => '1',
+            };
+            let value_type = match signal.value_type {
+                ValueType::Unsigned => '+',
+                ValueType::Signed => '-',
+            };
+            // The grammar needs at least one receiver
+            let receivers = match signal.receivers.is_empty() {
+                true => "Vector__XXX".to_owned(),
+                false => signal.receivers.join(","),
+            };
+            writeln!(
+                out,
+                " SG_ {}{multiplexer} : {}|{}@{byte_order}{value_type} ({},{}) [{}|{}] \"{}\" {receivers}",
+                signal.name,
+                signal.start_bit,
+                signal.size,
+                float_text(signal.factor),
+                float_text(signal.offset),
+                float_text(signal.min),
+                float_text(signal.max),
+                signal.unit
+            )?;
+        }
+        writeln!(out)?;
+    }
+
+    for transmitters in &dbc.message_transmitters {
+        let names: Vec<&str> = transmitters
+            .transmitter
+            .iter()
+            .map(|t| match t {
+                Transmitter::NodeName(name) => name.as_str(),
+                Transmitter::VectorXXX => "Vector__XXX",
+            })
+            .collect();
+        writeln!(out, "BO_TX_BU_ {} : {};", transmitters.message_id.raw(), names.join(","))?;
+    }
+
+    for variable in &dbc.environment_variables {
+        let typ = match variable.typ {
+            EnvType::Integer => 0,
+            EnvType::Float => 1,
+            EnvType::String => 2,
+        };
+        let access_type = match variable.access_type {
+            AccessType::DummyNodeVector0 => 0,
+            AccessType::DummyNodeVector1 => 1,
+            AccessType::DummyNodeVector2 => 2,
+            AccessType::DummyNodeVector3 => 3,
+        };
+        let nodes: Vec<&str> = variable
+            .access_nodes
+            .iter()
+            .map(|node| match node {
+                AccessNode::Name(name) => name.as_str(),
+                AccessNode::VectorXXX => "VECTOR__XXX",
+            })
+            .collect();
+        writeln!(
+            out,
+            "EV_ {}: {typ} [{}|{}] \"{}\" {} {} DUMMY_NODE_VECTOR{access_type} {};",
+            variable.name,
+            variable.min,
+            variable.max,
+            variable.unit,
+            variable.initial_value,
+            variable.ev_id,
+            nodes.join(",")
+        )?;
+    }
+    for data in &dbc.environment_variable_data {
+        writeln!(out, "ENVVAR_DATA_ {}: {};", data.env_var_name, data.data_size)?;
+    }
+    writeln!(out)?;
+
+    for comment in &dbc.comments {
+        match comment {
+            Comment::Plain { comment } => writeln!(out, "CM_ {};", quoted(comment))?,
+            Comment::Node { name, comment } => writeln!(out, "CM_ BU_ {name} {};", quoted(comment))?,
+            Comment::Message { id, comment } => writeln!(out, "CM_ BO_ {} {};", id.raw(), quoted(comment))?,
+            Comment::Signal { message_id, name, comment } => {
+                writeln!(out, "CM_ SG_ {} {name} {};", message_id.raw(), quoted(comment))?
+            }
+            Comment::EnvVar { name, comment } => writeln!(out, "CM_ EV_ {name} {};", quoted(comment))?,
+        }
+    }
+
+    for definition in &dbc.attribute_definitions {
+        let (object_type, name, value_type) = match definition {
+            AttributeDefinition::Plain(name, value_type) => ("", name, value_type),
+            AttributeDefinition::Node(name, value_type) => ("BU_ ", name, value_type),
+            AttributeDefinition::Message(name, value_type) => ("BO_ ", name, value_type),
+            AttributeDefinition::Signal(name, value_type) => ("SG_ ", name, value_type),
+            AttributeDefinition::EnvironmentVariable(name, value_type) => ("EV_ ", name, value_type),
+        };
+        writeln!(out, "BA_DEF_ {object_type} \"{name}\" {};", attribute_type_text(value_type))?;
+    }
+    for definition in &dbc.relation_attribute_definitions {
+        let (object_type, name, value_type) = match definition {
+            AttributeDefinition::Signal(name, value_type) => ("BU_SG_REL_", name, value_type),
+            AttributeDefinition::EnvironmentVariable(name, value_type) => ("BU_EV_REL_", name, value_type),
+            AttributeDefinition::Message(name, value_type)
+            | AttributeDefinition::Node(name, value_type)
+            | AttributeDefinition::Plain(name, value_type) => ("BU_BO_REL_", name, value_type),
+        };
+        writeln!(out, "BA_DEF_REL_ {object_type} \"{name}\" {};", attribute_type_text(value_type))?;
+    }
+    for default in &dbc.attribute_defaults {
+        writeln!(out, "BA_DEF_DEF_ \"{}\" {};", default.name, attribute_value_text(&default.value))?;
+    }
+    for default in &dbc.relation_attribute_defaults {
+        writeln!(out, "BA_DEF_DEF_REL_ \"{}\" {};", default.name, attribute_value_text(&default.value))?;
+    }
+
+    for value in &dbc.attribute_values_database {
+        writeln!(out, "BA_ \"{}\" {};", value.name, attribute_value_text(&value.value))?;
+    }
+    for value in &dbc.attribute_values_node {
+        writeln!(out, "BA_ \"{}\" BU_ {} {};", value.name, value.node_name, attribute_value_text(&value.value))?;
+    }
+    for value in &dbc.attribute_values_message {
+        writeln!(
+            out,
+            "BA_ \"{}\" BO_ {} {};",
+            value.name,
+            value.message_id.raw(),
+            attribute_value_text(&value.value)
+        )?;
+    }
+    for value in &dbc.attribute_values_signal {
+        writeln!(
+            out,
+            "BA_ \"{}\" SG_ {} {} {};",
+            value.name,
+            value.message_id.raw(),
+            value.signal_name,
+            attribute_value_text(&value.value)
+        )?;
+    }
+    for value in &dbc.attribute_values_env {
+        writeln!(out, "BA_ \"{}\" EV_ {} {};", value.name, value.variable_name, attribute_value_text(&value.value))?;
+    }
+    for value in &dbc.relation_attribute_values {
+        match &value.details {
+            AttributeValueForRelationType::NodeToSignal { node_name, message_id, signal_name, value: v } => writeln!(
+                out,
+                "BA_REL_ \"{}\" BU_SG_REL_ {node_name} SG_ {} {signal_name} {};",
+                value.name,
+                message_id.raw(),
+                attribute_value_text(v)
+            )?,
+            AttributeValueForRelationType::NodeToMessage { node_name, message_id, value: v } => writeln!(
+                out,
+                "BA_REL_ \"{}\" BU_BO_REL_ {node_name} {} {};",
+                value.name,
+                message_id.raw(),
+                attribute_value_text(v)
+            )?,
+        }
+    }
+
+    for description in &dbc.value_descriptions {
+        match description {
+            ValueDescription::Signal { message_id, name, value_descriptions } => {
+                write!(out, "VAL_ {} {name}", message_id.raw())?;
+                write_descriptions(out, value_descriptions)?;
+            }
+            ValueDescription::EnvironmentVariable { name, value_descriptions } => {
+                write!(out, "VAL_ {name}")?;
+                write_descriptions(out, value_descriptions)?;
+            }
+        }
+        writeln!(out, " ;")?;
+    }
+
+    for group in &dbc.signal_groups {
+        writeln!(
+            out,
+            "SIG_GROUP_ {} {} {} : {};",
+            group.message_id.raw(),
+            group.name,
+            group.repetitions,
+            group.signal_names.join(" ")
+        )?;
+    }
+    for value_type in &dbc.signal_extended_value_type_list {
+        let code = match value_type.signal_extended_value_type {
+            SignalExtendedValueType::SignedOrUnsignedInteger => 0,
+            SignalExtendedValueType::IEEEfloat32Bit => 1,
+            SignalExtendedValueType::IEEEdouble64bit => 2,
+        };
+        writeln!(out, "SIG_VALTYPE_ {} {} : {code};", value_type.message_id.raw(), value_type.signal_name)?;
+    }
+    for multiplex in &dbc.extended_multiplex {
+        let ranges: Vec<String> =
+            multiplex.mappings.iter().map(|m| format!("{}-{}", m.min_value, m.max_value)).collect();
+        writeln!(
+            out,
+            "SG_MUL_VAL_ {} {} {} {};",
+            multiplex.message_id.raw(),
+            multiplex.signal_name,
+            multiplex.multiplexor_signal_name,
+            ranges.join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+fn write_descriptions<W: Write>(out: &mut W, descriptions: &[ValDescription]) -> fmt::Result {
+    for description in descriptions {
+        write!(out, " {} {}", description.id, quoted(&description.description))?;
+    }
+    Ok(())
+}
+
+/// Free text in quotes that can_dbc parses back. can_dbc reads any `\"` as an escaped quote,
+/// so a bare `"` gets a backslash and a trailing backslash a space before the closing quote.
+fn quoted(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    let mut after_backslash = false;
+    for c in text.chars() {
+        if c == '"' && !after_backslash {
+            out.push('\\');
+        }
+        after_backslash = c == '\\';
+        out.push(c);
+    }
+    if after_backslash {
+        out.push(' ');
+    }
+    out.push('"');
+    out
+}
+
+/// Shortest text parsing back to the same f64. Whole numbers are written without a
+/// fraction, "1" rather than "1.0", as DBC editors do.
+fn float_text(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{value}")
+    } else {
+        format!("{value:?}")
+    }
+}
+
+/// Doubles keep a fraction or exponent so they parse back as doubles
+fn numeric_text(value: &NumericValue) -> String {
+    match value {
+        NumericValue::Uint(v) => v.to_string(),
+        NumericValue::Int(v) => v.to_string(),
+        NumericValue::Double(v) => format!("{v:?}"),
+    }
+}
+
+fn attribute_value_text(value: &AttributeValue) -> String {
+    match value {
+        AttributeValue::Uint(v) => v.to_string(),
+        AttributeValue::Int(v) => v.to_string(),
+        AttributeValue::Double(v) => format!("{v:?}"),
+        AttributeValue::String(s) => quoted(s),
+    }
+}
+
+fn attribute_type_text(value_type: &AttributeValueType) -> String {
+    match value_type {
+        AttributeValueType::Int(min, max) => format!("INT {} {}", numeric_text(min), numeric_text(max)),
+        AttributeValueType::Hex(min, max) => format!("HEX {} {}", numeric_text(min), numeric_text(max)),
+        AttributeValueType::Float(min, max) => format!("FLOAT {} {}", numeric_text(min), numeric_text(max)),
+        AttributeValueType::String => "STRING".to_owned(),
+        AttributeValueType::Enum(values) => {
+            let values: Vec<String> = values.iter().map(|v| format!("\"{v}\"")).collect();
+            format!("ENUM {}", values.join(","))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::can_decoder;
+    use crate::dbc_loader;
+
+    #[test]
+    fn test_round_trip_sample_dbcs() {
+        for dbc_path in [
+            "can_samples/chrysler_cusw.dbc",
+            "can_samples/chrysler_pacifica_2017_hybrid_private_fusion.dbc",
+            "can_samples/demo-dbc.dbc",
+            "can_samples/aphryx-canx-nissan-leaf/nissan_leaf_2018.dbc",
+            "motohawk.dbc",
+            "multiplex.dbc",
+            "floats.dbc",
+            "signed.dbc",
+        ] {
+            let dbc = can_decoder::load_dbc(dbc_path).unwrap();
+            let text = dbc_to_string(&dbc);
+            let reparsed = can_dbc::Dbc::try_from(text.as_str())
+                .unwrap_or_else(|e| panic!("{dbc_path} written DBC does not parse: {e:?}"));
+            assert_eq!(reparsed, dbc, "{dbc_path} does not round trip");
+            // Writing is stable
+            assert_eq!(dbc_to_string(&reparsed), text);
+        }
+    }
+
+    #[test]
+    fn test_float_text() {
+        assert_eq!(float_text(1.0), "1");
+        assert_eq!(float_text(-40.0), "-40");
+        assert_eq!(float_text(0.01), "0.01");
+        assert_eq!(float_text(1e-9), "1e-9");
+        assert_eq!(float_text(3.4e38), "3.4e38");
+        assert_eq!(float_text(1.7976931348623157e308), "1.7976931348623157e308");
+    }
+
+    #[test]
+    fn test_attribute_values_keep_their_type() {
+        let text = "VERSION \"\"\n\nBU_:\nBA_DEF_ BO_ \"Ratio\" FLOAT 0 10.5;\nBA_DEF_DEF_ \"Ratio\" 2.0;\n";
+        let dbc = can_dbc::Dbc::try_from(text).unwrap();
+        let written = dbc_to_string(&dbc);
+        assert!(written.contains("BA_DEF_DEF_ \"Ratio\" 2.0;"));
+        assert_eq!(can_dbc::Dbc::try_from(written.as_str()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn test_comments_with_backslashes_and_quotes() {
+        let mut dbc = dbc_loader::load("comment_backslash.dbc").unwrap().dbc;
+        dbc.comments.push(Comment::Plain { comment: "say \"hi\", keep \\\"escaped\\\"".to_owned() });
+        let text = dbc_to_string(&dbc);
+        assert!(text.contains("CM_ BO_ 100 \"Logged to C:\\logs\\ \";"), "{text}");
+        assert!(text.contains("CM_ \"say \\\"hi\\\", keep \\\"escaped\\\"\";"), "{text}");
+
+        let reparsed = can_dbc::Dbc::try_from(text.as_str()).unwrap();
+        let comment = |dbc: &Dbc, i: usize| match &dbc.comments[i] {
+            Comment::Plain { comment } | Comment::Message { comment, .. } | Comment::Signal { comment, .. } => {
+                comment.clone()
+            }
+            other => panic!("unexpected comment {other:?}"),
+        };
+        assert_eq!(comment(&reparsed, 0), "Logged to C:\\logs\\ ");
+        assert_eq!(comment(&reparsed, 1), "multi\nline \\ ");
+        assert_eq!(comment(&reparsed, 2), "ok");
+        assert_eq!(comment(&reparsed, 3), "say \\\"hi\\\", keep \\\"escaped\\\"");
+        // Writing is stable
+        assert_eq!(dbc_to_string(&reparsed), text);
+    }
+}
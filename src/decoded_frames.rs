@@ -0,0 +1,199 @@
+/*!
+ * Streaming decode of frame iterators with a CanDatabase, keeping the timestamp and channel
+ * of every frame.
+ * Iterator<CanFrame> -> Iterator<DecodedFrame>
+ *
+ * ```no_run
+ * use std::path::Path;
+ * use rocketcan::can_database::CanDatabase;
+ * use rocketcan::canlog_reader::CanLogParser;
+ * use rocketcan::decoded_frames::DecodeFramesExt;
+ *
+ * let database = CanDatabase::load("motohawk.dbc").unwrap();
+ * let frames = CanLogParser::from_file(Path::new("candump.log")).unwrap();
+ * for decoded in frames.decode_with(&database) {
+ *     println!("{decoded}");
+ * }
+ * ```
+ */
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::can_database::CanDatabase;
+use crate::can_decoder::DecodedCanMessage;
+use crate::canlog_reader::CanFrame;
+
+/// What to do with frames whose ID is not in the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownIds {
+    /// Leave them out of the stream (still counted)
+    #[default]
+    Skip,
+    /// Yield them with no decoded message
+    Report,
+}
+
+/// A frame and its decoded message, None for unknown IDs
+#[derive(Debug, Clone)]
+pub struct DecodedFrame {
+    pub frame: CanFrame,
+    pub message: Option<DecodedCanMessage>,
+}
+
+impl DecodedFrame {
+    pub fn timestamp(&self) -> f64 {
+        self.frame.timestamp
+    }
+
+    pub fn channel(&self) -> &str {
+        &self.frame.channel
+    }
+
+    pub fn is_known(&self) -> bool {
+        self.message.is_some()
+    }
+}
+
+impl fmt::Display for DecodedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:.6}) {} ", self.frame.timestamp, self.frame.channel)?;
+        match &self.message {
+            Some(message) if f.alternate() => write!(f, "{message:#}"),
+            Some(message) => write!(f, "{message}"),
+            None => writeln!(f, "{:03X} unknown", self.frame.id),
+        }
+    }
+}
+
+/// Iterator adapter decoding every frame of `frames` with a database
+pub struct DecodedFrames<'db, I> {
+    database: &'db CanDatabase,
+    frames: I,
+    unknown_ids: UnknownIds,
+    unknown_counts: BTreeMap<u32, u64>,
+}
+
+impl<'db, I: Iterator<Item = CanFrame>> DecodedFrames<'db, I> {
+    pub fn new(database: &'db CanDatabase, frames: I) -> Self {
+        Self {
+            database,
+            frames,
+            unknown_ids: UnknownIds::Skip,
+            unknown_counts: BTreeMap::new(),
+        }
+    }
+
+    /// Choose whether frames with unknown IDs are skipped (default) or yielded
+    pub fn unknown_ids(mut self, unknown_ids: UnknownIds) -> Self {
+        self.unknown_ids = unknown_ids;
+        self
+    }
+
+    /// Number of frames seen so far for each ID missing from the database
+    pub fn unknown_counts(&self) -> &BTreeMap<u32, u64> {
+        &self.unknown_counts
+    }
+}
+
+impl<I: Iterator<Item = CanFrame>> Iterator for DecodedFrames<'_, I> {
+    type Item = DecodedFrame;
+
+    fn next(&mut self) -> Option<DecodedFrame> {
+        for frame in self.frames.by_ref() {
+            match self.database.decode_frame(&frame) {
+                Some(message) => {
+                    return Some(DecodedFrame {
+                        frame,
+                        message: Some(message),
+                    })
+                }
+                None => {
+                    *self.unknown_counts.entry(frame.id).or_default() += 1;
+                    if self.unknown_ids == UnknownIds::Report {
+                        return Some(DecodedFrame { frame, message: None });
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Decode any frame iterator: `frames.decode_with(&database)`
+pub trait DecodeFramesExt: Iterator<Item = CanFrame> + Sized {
+    fn decode_with(self, database: &CanDatabase) -> DecodedFrames<'_, Self> {
+        DecodedFrames::new(database, self)
+    }
+}
+
+impl<I: Iterator<Item = CanFrame>> DecodeFramesExt for I {}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::can_decoder;
+    use crate::canlog_reader::{self, CanLogParser};
+
+    fn frames(lines: &[&str]) -> Vec<CanFrame> {
+        lines
+            .iter()
+            .map(|line| canlog_reader::parse_candump_line(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_keeps_timestamp_and_channel() {
+        let database = CanDatabase::load("motohawk.dbc").unwrap();
+        let input = frames(&[
+            "(1.5) can0 1F0#A5B6D90000000000",
+            "(2.0) can0 123#00",
+            "(2.5) can1 1F0#0077733445566778",
+        ]);
+        let mut decoded = input.into_iter().decode_with(&database);
+        let first = decoded.next().unwrap();
+        assert_eq!(first.timestamp(), 1.5);
+        assert_eq!(first.channel(), "can0");
+        assert_eq!(first.message.as_ref().unwrap().values, [244.14, 1.8, 1.0]);
+
+        // The unknown ID in between is skipped
+        let second = decoded.next().unwrap();
+        assert_eq!(second.timestamp(), 2.5);
+        assert_eq!(second.channel(), "can1");
+        assert!(decoded.next().is_none());
+        assert_eq!(decoded.unknown_counts(), &BTreeMap::from([(0x123, 1)]));
+    }
+
+    #[test]
+    fn test_report_unknown_ids() {
+        let database = CanDatabase::load("motohawk.dbc").unwrap();
+        let input = frames(&["(1.5) can0 1F0#A5B6D90000000000", "(2.0) can0 123#00"]);
+        let decoded: Vec<_> = DecodedFrames::new(&database, input.into_iter())
+            .unknown_ids(UnknownIds::Report)
+            .collect();
+        assert_eq!(decoded.len(), 2);
+        assert!(decoded[0].is_known());
+        assert!(!decoded[1].is_known());
+        assert_eq!(decoded[1].frame.id, 0x123);
+        assert_eq!(decoded[1].to_string(), "(2.000000) can0 123 unknown\n");
+    }
+
+    #[test]
+    fn test_decode_log_file() {
+        let dbc = can_decoder::load_dbc("can_samples/chrysler_cusw.dbc").unwrap();
+        let database = CanDatabase::from_dbc(dbc);
+        let frames = CanLogParser::from_file(Path::new("demo-log.log")).unwrap();
+        let mut decoded = frames.decode_with(&database);
+        let mut count = 0;
+        let mut last_timestamp = f64::MIN;
+        for frame in decoded.by_ref() {
+            assert!(frame.timestamp() >= last_timestamp);
+            last_timestamp = frame.timestamp();
+            count += 1;
+        }
+        assert_eq!(count, 7000);
+        assert!(decoded.unknown_counts().is_empty());
+    }
+}
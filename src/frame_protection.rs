@@ -0,0 +1,687 @@
+/*!
+ * Counter and checksum protection of messages: which signals are rolling counters and
+ * checksums, filling them in when encoding and checking them when decoding.
+ * (CanFrame, can_dbc::Message) -> protected CanFrame / ProtectionCheck
+ *
+ * Signals are declared explicitly, by the attributes of the DBC or by naming convention
+ * (COUNTER, *_CNT, CHECKSUM, *_CRC...). Checksums are computed by a `ChecksumAlgorithm`,
+ * built-in are SAE J1850 CRC8, XOR, sum and AUTOSAR E2E profiles 1, 2 and 5.
+ *
+ * ```no_run
+ * use std::sync::Arc;
+ * use rocketcan::{can_decoder, can_encoder::CanFrameBuilder};
+ * use rocketcan::frame_protection::{Crc8J1850, Protection};
+ *
+ * let dbc = can_decoder::load_dbc("can_samples/chrysler_cusw.dbc").unwrap();
+ * let steering = can_decoder::get_message_spec(&dbc, "STEERING").unwrap();
+ * let mut protection = Protection::new().with_dbc(&dbc, Arc::new(Crc8J1850));
+ * for _ in 0..3 {
+ *     // COUNTER counts up and CHECKSUM is computed on every frame
+ *     let frame = CanFrameBuilder::new(steering)
+ *         .protection(&mut protection)
+ *         .set("STEER_ANGLE", 10.0)
+ *         .unwrap()
+ *         .build();
+ *     println!("{:?}", protection.check(&frame, steering));
+ * }
+ * ```
+ */
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::can_decoder;
+use crate::canlog_reader::CanFrame;
+use crate::dbc_attributes;
+use crate::signal_layout::SignalLayout;
+
+/// Signal attribute declaring the role of a signal: "Counter" or "Checksum"
+pub const SIGNAL_ROLE_ATTRIBUTE: &str = "SigProtectionRole";
+/// Message attribute naming the checksum algorithm, see `checksum_algorithm`
+pub const CHECKSUM_ALGORITHM_ATTRIBUTE: &str = "ChecksumAlgorithm";
+/// Message attribute with the E2E data ID (profiles 1 and 5)
+pub const DATA_ID_ATTRIBUTE: &str = "E2EDataID";
+/// Message attribute with the 16 E2E data IDs of profile 2, comma separated
+pub const DATA_ID_LIST_ATTRIBUTE: &str = "E2EDataIDList";
+
+/// A way of computing the checksum of a frame
+pub trait ChecksumAlgorithm: Send + Sync {
+    /// Short name of the algorithm, e.g. "crc8-j1850"
+    fn name(&self) -> &str;
+
+    /// Compute the checksum of `data`: the frame payload without the bytes of the checksum
+    /// signal (bits of partly covered bytes are zeroed). `counter` is the raw counter
+    /// of the frame when the message has one.
+    fn compute(&self, data: &[u8], counter: Option<u64>) -> u64;
+
+    /// Highest counter value before it wraps to 0, when the algorithm restricts it.
+    /// Otherwise counters use their whole raw range.
+    fn counter_max(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// MSB first CRC8 without final XOR
+fn crc8(data: &[u8], polynomial: u8, init: u8) -> u8 {
+    let mut crc = init;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ polynomial } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// SAE J1850 CRC8: polynomial 0x1D, start value and final XOR 0xFF
+pub fn crc8_j1850(data: &[u8]) -> u8 {
+    crc8(data, 0x1D, 0xFF) ^ 0xFF
+}
+
+/// AUTOSAR CRC8H2F: polynomial 0x2F, start value and final XOR 0xFF
+pub fn crc8_h2f(data: &[u8]) -> u8 {
+    crc8(data, 0x2F, 0xFF) ^ 0xFF
+}
+
+/// CRC16 CCITT-FALSE: polynomial 0x1021, start value 0xFFFF, no final XOR
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// SAE J1850 CRC8 of the payload
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc8J1850;
+
+impl ChecksumAlgorithm for Crc8J1850 {
+    fn name(&self) -> &str {
+        "crc8-j1850"
+    }
+
+    fn compute(&self, data: &[u8], _counter: Option<u64>) -> u64 {
+        crc8_j1850(data) as u64
+    }
+}
+
+/// XOR of the payload bytes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XorChecksum;
+
+impl ChecksumAlgorithm for XorChecksum {
+    fn name(&self) -> &str {
+        "xor"
+    }
+
+    fn compute(&self, data: &[u8], _counter: Option<u64>) -> u64 {
+        data.iter().fold(0u8, |acc, byte| acc ^ byte) as u64
+    }
+}
+
+/// Sum of the payload bytes, truncated to the checksum signal size
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SumChecksum;
+
+impl ChecksumAlgorithm for SumChecksum {
+    fn name(&self) -> &str {
+        "sum"
+    }
+
+    fn compute(&self, data: &[u8], _counter: Option<u64>) -> u64 {
+        data.iter().map(|byte| *byte as u64).sum()
+    }
+}
+
+/// Which bytes of the data ID go into the E2E profile 1 CRC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DataIdMode {
+    /// Low byte then high byte
+    #[default]
+    Both,
+    /// Low byte for even counters, high byte for odd counters
+    Alternating,
+    /// Low byte only, the high byte is 0
+    Low,
+}
+
+/// AUTOSAR E2E profile 1: CRC8 SAE J1850 (start value 0x00, no final XOR) over the data ID
+/// and the payload, 4-bit counter from 0 to 14
+#[derive(Debug, Clone, Copy)]
+pub struct E2eProfile1 {
+    pub data_id: u16,
+    pub data_id_mode: DataIdMode,
+}
+
+impl ChecksumAlgorithm for E2eProfile1 {
+    fn name(&self) -> &str {
+        "e2e-p01"
+    }
+
+    fn compute(&self, data: &[u8], counter: Option<u64>) -> u64 {
+        let [low, high] = self.data_id.to_le_bytes();
+        let data_id = match self.data_id_mode {
+            DataIdMode::Both => vec![low, high],
+            DataIdMode::Alternating if counter.unwrap_or(0).is_multiple_of(2) => vec![low],
+            DataIdMode::Alternating => vec![high],
+            DataIdMode::Low => vec![low],
+        };
+        crc8(&[data_id.as_slice(), data].concat(), 0x1D, 0x00) as u64
+    }
+
+    fn counter_max(&self) -> Option<u64> {
+        Some(14)
+    }
+}
+
+/// AUTOSAR E2E profile 2: CRC8H2F over the payload and the data ID picked
+/// from the list by the counter, 4-bit counter from 0 to 15
+#[derive(Debug, Clone, Copy)]
+pub struct E2eProfile2 {
+    pub data_id_list: [u8; 16],
+}
+
+impl ChecksumAlgorithm for E2eProfile2 {
+    fn name(&self) -> &str {
+        "e2e-p02"
+    }
+
+    fn compute(&self, data: &[u8], counter: Option<u64>) -> u64 {
+        let data_id = self.data_id_list[(counter.unwrap_or(0) % 16) as usize];
+        crc8_h2f(&[data, &[data_id]].concat()) as u64
+    }
+
+    fn counter_max(&self) -> Option<u64> {
+        Some(15)
+    }
+}
+
+/// AUTOSAR E2E profile 5: CRC16 CCITT-FALSE over the payload and the data ID (low byte first),
+/// 8-bit counter
+#[derive(Debug, Clone, Copy)]
+pub struct E2eProfile5 {
+    pub data_id: u16,
+}
+
+impl ChecksumAlgorithm for E2eProfile5 {
+    fn name(&self) -> &str {
+        "e2e-p05"
+    }
+
+    fn compute(&self, data: &[u8], _counter: Option<u64>) -> u64 {
+        crc16_ccitt(&[data, &self.data_id.to_le_bytes()].concat()) as u64
+    }
+}
+
+/// Built-in algorithm by name: crc8-j1850, xor, sum, e2e-p01, e2e-p02 or e2e-p05
+/// (case and '_' / '-' insensitive). E2E profiles use `data_id` (the low byte repeated
+/// for the profile 2 list), 0 when not given.
+pub fn checksum_algorithm(name: &str, data_id: Option<u16>) -> Option<Arc<dyn ChecksumAlgorithm>> {
+    let data_id = data_id.unwrap_or(0);
+    let algorithm: Arc<dyn ChecksumAlgorithm> = match name.to_ascii_lowercase().replace('_', "-").as_str() {
+        "crc8-j1850" | "j1850" => Arc::new(Crc8J1850),
+        "xor" => Arc::new(XorChecksum),
+        "sum" => Arc::new(SumChecksum),
+        "e2e-p01" | "e2e-p1" => Arc::new(E2eProfile1 { data_id, data_id_mode: DataIdMode::Both }),
+        "e2e-p02" | "e2e-p2" => Arc::new(E2eProfile2 { data_id_list: [data_id as u8; 16] }),
+        "e2e-p05" | "e2e-p5" => Arc::new(E2eProfile5 { data_id }),
+        _ => return None,
+    };
+    Some(algorithm)
+}
+
+/// Counter and checksum signals of one message
+#[derive(Clone, Default)]
+pub struct MessageProtection {
+    pub counter: Option<String>,
+    pub checksum: Option<(String, Arc<dyn ChecksumAlgorithm>)>,
+}
+
+impl fmt::Debug for MessageProtection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageProtection")
+            .field("counter", &self.counter)
+            .field("checksum", &self.checksum.as_ref().map(|(signal, algorithm)| (signal, algorithm.name())))
+            .finish()
+    }
+}
+
+/// Result of checking the counter of a frame against the previous frame of the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterCheck {
+    /// First frame of the message on the channel
+    First(u64),
+    Ok(u64),
+    /// Same value as the previous frame
+    Repeated(u64),
+    /// Frames were lost or the sender restarted
+    Jump { expected: u64, actual: u64 },
+}
+
+/// Result of comparing the checksum of a frame with the one computed from its payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumCheck {
+    Ok(u64),
+    Mismatch { expected: u64, actual: u64 },
+}
+
+/// Counter and checksum checks of a frame, None for what the message doesn't have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProtectionCheck {
+    pub counter: Option<CounterCheck>,
+    pub checksum: Option<ChecksumCheck>,
+}
+
+impl ProtectionCheck {
+    /// No counter jump, repeat or checksum mismatch
+    pub fn is_ok(&self) -> bool {
+        matches!(self.counter, None | Some(CounterCheck::First(_) | CounterCheck::Ok(_)))
+            && matches!(self.checksum, None | Some(ChecksumCheck::Ok(_)))
+    }
+}
+
+impl fmt::Display for ProtectionCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut issues = Vec::new();
+        match self.counter {
+            Some(CounterCheck::Repeated(value)) => issues.push(format!("counter repeated: {value}")),
+            Some(CounterCheck::Jump { expected, actual }) => {
+                issues.push(format!("counter jump: expected {expected}, got {actual}"))
+            }
+            _ => {}
+        }
+        if let Some(ChecksumCheck::Mismatch { expected, actual }) = self.checksum {
+            issues.push(format!("checksum mismatch: expected {expected:#X}, got {actual:#X}"));
+        }
+        match issues.is_empty() {
+            true => write!(f, "ok"),
+            false => write!(f, "{}", issues.join(", ")),
+        }
+    }
+}
+
+/// Counter and checksum signals of messages (by name), with the counter state of
+/// encoded messages and of received messages on each channel
+#[derive(Debug, Clone, Default)]
+pub struct Protection {
+    messages: HashMap<String, MessageProtection>,
+    // Last counter encoded, by message
+    sent_counters: HashMap<String, u64>,
+    // Last counter received, by (channel, message)
+    received_counters: HashMap<(String, String), u64>,
+}
+
+impl Protection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare the rolling counter signal of a message
+    pub fn add_counter(&mut self, message_name: &str, signal_name: &str) {
+        self.messages.entry(message_name.to_owned()).or_default().counter = Some(signal_name.to_owned());
+    }
+
+    /// Declare the checksum signal of a message and how it is computed
+    pub fn add_checksum(&mut self, message_name: &str, signal_name: &str, algorithm: Arc<dyn ChecksumAlgorithm>) {
+        self.messages.entry(message_name.to_owned()).or_default().checksum = Some((signal_name.to_owned(), algorithm));
+    }
+
+    /// Declare the counters and checksums of a DBC.
+    ///
+    /// Signals with the `SigProtectionRole` attribute ("Counter" or "Checksum") are used
+    /// first, with the algorithm of the `ChecksumAlgorithm` message attribute and the data ID
+    /// of `E2EDataID` / `E2EDataIDList`. Messages without them use the naming convention
+    /// (COUNTER, *_COUNTER, *_CNT, *_CTR, CHECKSUM, *_CHECKSUM, *_CRC) and `default_algorithm`.
+    /// Messages declared before are replaced.
+    pub fn add_dbc(&mut self, dbc: &can_dbc::Dbc, default_algorithm: Arc<dyn ChecksumAlgorithm>) {
+        for message_spec in &dbc.messages {
+            let by_attribute = |role: &str| {
+                message_spec.signals.iter().find(|spec| {
+                    dbc_attributes::signal_attribute(dbc, message_spec, spec, SIGNAL_ROLE_ATTRIBUTE)
+                        .and_then(|a| a.as_str().map(|r| r.eq_ignore_ascii_case(role)))
+                        .unwrap_or(false)
+                })
+            };
+            let (counter, checksum) = match (by_attribute("Counter"), by_attribute("Checksum")) {
+                (None, None) => {
+                    let by_name = |suffixes: &[&str]| {
+                        message_spec.signals.iter().find(|spec| {
+                            let name = spec.name.to_ascii_uppercase();
+                            suffixes.iter().any(|suffix| name == suffix[1..] || name.ends_with(suffix))
+                        })
+                    };
+                    (by_name(&["_COUNTER", "_CNT", "_CTR"]), by_name(&["_CHECKSUM", "_CRC"]))
+                }
+                by_attribute => by_attribute,
+            };
+            if counter.is_none() && checksum.is_none() {
+                continue;
+            }
+            let algorithm = dbc_attributes::message_attribute(dbc, message_spec, CHECKSUM_ALGORITHM_ATTRIBUTE)
+                .and_then(|a| a.as_str().and_then(|name| attribute_algorithm(dbc, message_spec, name)))
+                .unwrap_or_else(|| default_algorithm.clone());
+            self.messages.insert(
+                message_spec.name.clone(),
+                MessageProtection {
+                    counter: counter.map(|spec| spec.name.clone()),
+                    checksum: checksum.map(|spec| (spec.name.clone(), algorithm)),
+                },
+            );
+        }
+    }
+
+    pub fn with_counter(mut self, message_name: &str, signal_name: &str) -> Self {
+        self.add_counter(message_name, signal_name);
+        self
+    }
+
+    pub fn with_checksum(mut self, message_name: &str, signal_name: &str, algorithm: Arc<dyn ChecksumAlgorithm>) -> Self {
+        self.add_checksum(message_name, signal_name, algorithm);
+        self
+    }
+
+    pub fn with_dbc(mut self, dbc: &can_dbc::Dbc, default_algorithm: Arc<dyn ChecksumAlgorithm>) -> Self {
+        self.add_dbc(dbc, default_algorithm);
+        self
+    }
+
+    /// Counter and checksum signals of a message, None if it has neither
+    pub fn message(&self, message_name: &str) -> Option<&MessageProtection> {
+        self.messages.get(message_name)
+    }
+
+    /// Fill in the counter and checksum of an encoded frame.
+    /// The counter is the one after the previous frame of the message, or, with `keep_counter`,
+    /// the value already in the frame. The checksum is computed last, over the final payload.
+    pub fn protect(&mut self, frame: &mut CanFrame, message_spec: &can_dbc::Message, keep_counter: bool) {
+        let Some(protection) = self.messages.get(&message_spec.name) else {
+            return;
+        };
+        let counter_max = protection.checksum.as_ref().and_then(|(_, algorithm)| algorithm.counter_max());
+        let counter = protection
+            .counter
+            .as_deref()
+            .and_then(|name| can_decoder::get_signal_spec(message_spec, name))
+            .map(|spec| {
+                let layout = SignalLayout::from_spec(spec);
+                let max = counter_max.unwrap_or(raw_max(spec));
+                let value = match self.sent_counters.get(&message_spec.name) {
+                    _ if keep_counter => layout.extract(&frame.data),
+                    Some(previous) => next_counter(*previous, max),
+                    None => 0,
+                };
+                layout.pack(&mut frame.data, value);
+                self.sent_counters.insert(message_spec.name.clone(), value);
+                value
+            });
+        if let Some((spec, algorithm)) = checksum_spec(protection, message_spec) {
+            let checksum = compute_checksum(frame, spec, algorithm.as_ref(), counter);
+            SignalLayout::from_spec(spec).pack(&mut frame.data, checksum);
+        }
+    }
+
+    /// Check the counter and checksum of a received frame.
+    /// Counters are followed per channel, so call it on every frame of the message in order.
+    pub fn check(&mut self, frame: &CanFrame, message_spec: &can_dbc::Message) -> ProtectionCheck {
+        let mut result = ProtectionCheck::default();
+        let Some(protection) = self.messages.get(&message_spec.name) else {
+            return result;
+        };
+        let counter_max = protection.checksum.as_ref().and_then(|(_, algorithm)| algorithm.counter_max());
+        let counter_spec = protection.counter.as_deref().and_then(|name| can_decoder::get_signal_spec(message_spec, name));
+        let counter = counter_spec.map(|spec| SignalLayout::from_spec(spec).extract(&frame.data));
+        if let (Some(spec), Some(actual)) = (counter_spec, counter) {
+            let key = (frame.channel.clone(), message_spec.name.clone());
+            let check = match self.received_counters.get(&key) {
+                None => CounterCheck::First(actual),
+                Some(previous) if *previous == actual => CounterCheck::Repeated(actual),
+                Some(previous) => {
+                    let expected = next_counter(*previous, counter_max.unwrap_or(raw_max(spec)));
+                    match expected == actual {
+                        true => CounterCheck::Ok(actual),
+                        false => CounterCheck::Jump { expected, actual },
+                    }
+                }
+            };
+            self.received_counters.insert(key, actual);
+            result.counter = Some(check);
+        }
+        if let Some((spec, algorithm)) = checksum_spec(protection, message_spec) {
+            let expected = compute_checksum(frame, spec, algorithm.as_ref(), counter);
+            let actual = SignalLayout::from_spec(spec).extract(&frame.data);
+            result.checksum = Some(match expected == actual {
+                true => ChecksumCheck::Ok(actual),
+                false => ChecksumCheck::Mismatch { expected, actual },
+            });
+        }
+        result
+    }
+
+    /// Forget the counters seen so far, e.g. when starting another log
+    pub fn reset_counters(&mut self) {
+        self.sent_counters.clear();
+        self.received_counters.clear();
+    }
+}
+
+/// Algorithm named by the ChecksumAlgorithm attribute, with the E2E data IDs of the message
+fn attribute_algorithm(dbc: &can_dbc::Dbc, message_spec: &can_dbc::Message, name: &str) -> Option<Arc<dyn ChecksumAlgorithm>> {
+    let data_id = dbc_attributes::message_attribute(dbc, message_spec, DATA_ID_ATTRIBUTE)
+        .and_then(|a| a.as_i64())
+        .and_then(|id| u16::try_from(id).ok());
+    let data_id_list = dbc_attributes::message_attribute(dbc, message_spec, DATA_ID_LIST_ATTRIBUTE).and_then(|a| {
+        let ids: Vec<u8> = a.as_str()?.split(',').filter_map(|id| parse_byte(id.trim())).collect();
+        <[u8; 16]>::try_from(ids).ok()
+    });
+    match data_id_list {
+        Some(data_id_list) if name.eq_ignore_ascii_case("E2E_P02") => Some(Arc::new(E2eProfile2 { data_id_list })),
+        _ => checksum_algorithm(name, data_id),
+    }
+}
+
+fn parse_byte(text: &str) -> Option<u8> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn checksum_spec<'a>(
+    protection: &'a MessageProtection,
+    message_spec: &'a can_dbc::Message,
+) -> Option<(&'a can_dbc::Signal, &'a Arc<dyn ChecksumAlgorithm>)> {
+    let (name, algorithm) = protection.checksum.as_ref()?;
+    Some((can_decoder::get_signal_spec(message_spec, name)?, algorithm))
+}
+
+/// Largest raw value of a signal
+fn raw_max(spec: &can_dbc::Signal) -> u64 {
+    if spec.size >= 64 {
+        u64::MAX
+    } else {
+        (1u64 << spec.size) - 1
+    }
+}
+
+fn next_counter(previous: u64, max: u64) -> u64 {
+    if previous >= max {
+        0
+    } else {
+        previous + 1
+    }
+}
+
+/// Checksum of the frame payload: bytes fully covered by the checksum signal are left out,
+/// its bits in partly covered bytes are zeroed. Truncated to the signal size.
+fn compute_checksum(frame: &CanFrame, spec: &can_dbc::Signal, algorithm: &dyn ChecksumAlgorithm, counter: Option<u64>) -> u64 {
+    let len = (frame.len as usize).min(frame.data.len());
+    let masks = SignalLayout::from_spec(spec).byte_masks(len);
+    let data: Vec<u8> = frame.data[..len]
+        .iter()
+        .zip(&masks)
+        .filter(|(_, mask)| **mask != 0xFF)
+        .map(|(byte, mask)| byte & !mask)
+        .collect();
+    algorithm.compute(&data, counter) & raw_max(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::can_decoder;
+    use crate::can_encoder::CanFrameBuilder;
+    use crate::canlog_reader;
+
+    #[test]
+    fn test_crc_check_values() {
+        // Check values of the CRC catalogue for "123456789"
+        assert_eq!(crc8_j1850(b"123456789"), 0x4B);
+        assert_eq!(crc8_h2f(b"123456789"), 0xDF);
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+        assert_eq!(XorChecksum.compute(&[0x12, 0x34, 0x56], None), 0x70);
+        assert_eq!(SumChecksum.compute(&[0xFF, 0xFF, 0x02], None), 0x200);
+    }
+
+    #[test]
+    fn test_chrysler_checksum() {
+        // Chrysler CHECKSUM is the J1850 CRC of the bytes before it
+        let dbc = can_decoder::load_dbc("can_samples/chrysler_cusw.dbc").unwrap();
+        let steering = can_decoder::get_message_spec(&dbc, "STEERING").unwrap();
+        let mut protection = Protection::new().with_dbc(&dbc, Arc::new(Crc8J1850));
+        let message = protection.message("STEERING").unwrap();
+        assert_eq!(message.counter.as_deref(), Some("COUNTER"));
+        assert_eq!(message.checksum.as_ref().map(|(name, _)| name.as_str()), Some("CHECKSUM"));
+
+        let mut frame = CanFrame { id: 494, len: 6, ..Default::default() };
+        frame.data[..5].copy_from_slice(&[0x1C, 0x20, 0x07, 0xD0, 0x00]);
+        frame.data[5] = crc8_j1850(&frame.data[..5]);
+        let check = protection.check(&frame, steering);
+        assert_eq!(check.checksum, Some(ChecksumCheck::Ok(frame.data[5] as u64)));
+        assert_eq!(check.counter, Some(CounterCheck::First(0)));
+    }
+
+    #[test]
+    fn test_encode_then_check() {
+        let dbc = can_decoder::load_dbc("can_samples/chrysler_cusw.dbc").unwrap();
+        let brake = can_decoder::get_message_spec(&dbc, "BRAKE_1").unwrap();
+        let mut sender = Protection::new().with_dbc(&dbc, Arc::new(Crc8J1850));
+        let mut receiver = sender.clone();
+
+        let mut frames = Vec::new();
+        for i in 0..20 {
+            let frame = CanFrameBuilder::new(brake)
+                .protection(&mut sender)
+                .set("VEHICLE_SPEED", i as f64)
+                .unwrap()
+                .build();
+            frames.push(frame);
+        }
+        let counters: Vec<u64> = frames
+            .iter()
+            .map(|frame| can_decoder::decode_signal_by_bytes(frame, can_decoder::get_signal_spec(brake, "COUNTER").unwrap()) as u64)
+            .collect();
+        assert_eq!(counters[..3], [0, 1, 2]);
+        assert_eq!(counters[15..18], [15, 0, 1]);
+        for frame in &frames {
+            assert!(receiver.check(frame, brake).is_ok());
+        }
+
+        // A counter set explicitly is kept, and the next frames continue from it
+        let frame = CanFrameBuilder::new(brake).protection(&mut sender).set("COUNTER", 9.0).unwrap().build();
+        assert_eq!(frame.data[6] & 0x0F, 9);
+        let frame = CanFrameBuilder::new(brake).protection(&mut sender).build();
+        assert_eq!(frame.data[6] & 0x0F, 10);
+        assert_eq!(receiver.check(&frame, brake).counter, Some(CounterCheck::Jump { expected: 4, actual: 10 }));
+        assert_eq!(receiver.check(&frame, brake).counter, Some(CounterCheck::Repeated(10)));
+
+        let mut corrupted = frame.clone();
+        corrupted.data[0] ^= 0x01;
+        let check = receiver.check(&corrupted, brake);
+        assert!(matches!(check.checksum, Some(ChecksumCheck::Mismatch { actual, .. }) if actual == frame.data[7] as u64));
+        assert_eq!(check.to_string(), format!("counter repeated: 10, checksum mismatch: expected {:#X}, got {:#X}",
+            crc8_j1850(&corrupted.data[..7]), frame.data[7]));
+
+        // Counters are followed per channel
+        let mut other_channel = frame.clone();
+        other_channel.channel = "can1".into();
+        assert_eq!(receiver.check(&other_channel, brake).counter, Some(CounterCheck::First(10)));
+    }
+
+    #[test]
+    fn test_e2e_profiles() {
+        let dbc = can_decoder::dbc_from_str(concat!(
+            "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: Ecu\n\n",
+            "BO_ 100 P01: 8 Ecu\n",
+            " SG_ Crc : 0|8@1+ (1,0) [0|255] \"\" Vector__XXX\n",
+            " SG_ Alive : 8|4@1+ (1,0) [0|14] \"\" Vector__XXX\n",
+            " SG_ Value : 16|16@1+ (1,0) [0|65535] \"\" Vector__XXX\n\n",
+            "BO_ 200 P05: 8 Ecu\n",
+            " SG_ Crc : 0|16@1+ (1,0) [0|65535] \"\" Vector__XXX\n",
+            " SG_ Alive : 16|8@1+ (1,0) [0|255] \"\" Vector__XXX\n",
+            " SG_ Value : 24|16@1+ (1,0) [0|65535] \"\" Vector__XXX\n\n",
+            "BA_DEF_ SG_  \"SigProtectionRole\" ENUM  \"None\",\"Counter\",\"Checksum\";\n",
+            "BA_DEF_ BO_  \"ChecksumAlgorithm\" STRING ;\n",
+            "BA_DEF_ BO_  \"E2EDataID\" HEX 0 65535;\n",
+            "BA_DEF_DEF_  \"SigProtectionRole\" \"None\";\n",
+            "BA_DEF_DEF_  \"ChecksumAlgorithm\" \"\";\n",
+            "BA_DEF_DEF_  \"E2EDataID\" 0;\n",
+            "BA_ \"ChecksumAlgorithm\" BO_ 100 \"E2E_P01\";\n",
+            "BA_ \"E2EDataID\" BO_ 100 291;\n",
+            "BA_ \"ChecksumAlgorithm\" BO_ 200 \"E2E_P05\";\n",
+            "BA_ \"E2EDataID\" BO_ 200 4660;\n",
+            "BA_ \"SigProtectionRole\" SG_ 100 Crc 2;\n",
+            "BA_ \"SigProtectionRole\" SG_ 100 Alive 1;\n",
+            "BA_ \"SigProtectionRole\" SG_ 200 Crc 2;\n",
+            "BA_ \"SigProtectionRole\" SG_ 200 Alive 1;\n",
+        ))
+        .unwrap();
+        let mut protection = Protection::new().with_dbc(&dbc, Arc::new(XorChecksum));
+
+        // Profile 1: counter wraps after 14, CRC over data ID 0x0123 and bytes 1..8
+        let p01 = can_decoder::get_message_spec(&dbc, "P01").unwrap();
+        let mut last = None;
+        for _ in 0..16 {
+            last = Some(CanFrameBuilder::new(p01).protection(&mut protection).set("Value", 0x1234 as f64).unwrap().build());
+        }
+        let frame = last.unwrap();
+        assert_eq!(frame.data[1], 0x00);
+        assert_eq!(frame.data[0], crc8(&[0x23, 0x01, 0x00, 0x34, 0x12, 0, 0, 0, 0], 0x1D, 0x00));
+
+        // Profile 5: 16-bit CRC, little endian, over bytes 2..8 and the data ID 0x1234
+        let p05 = can_decoder::get_message_spec(&dbc, "P05").unwrap();
+        let frame = CanFrameBuilder::new(p05).protection(&mut protection).set("Value", 1.0).unwrap().build();
+        let crc = crc16_ccitt(&[0x00, 0x01, 0x00, 0, 0, 0, 0x34, 0x12]);
+        assert_eq!(frame.data[..2], crc.to_le_bytes());
+
+        // Profile 2 picks the data ID of the counter
+        let p02 = E2eProfile2 { data_id_list: core::array::from_fn(|i| i as u8 * 3) };
+        assert_eq!(p02.compute(&[1, 2], Some(5)), crc8_h2f(&[1, 2, 15]) as u64);
+        assert_eq!(checksum_algorithm("E2E_P02", Some(7)).unwrap().compute(&[1], Some(3)), crc8_h2f(&[1, 7]) as u64);
+        assert!(checksum_algorithm("md5", None).is_none());
+    }
+
+    #[test]
+    fn test_nibble_checksum() {
+        // 4-bit XOR checksum sharing its byte with the counter: only its bits are zeroed
+        let dbc = can_decoder::dbc_from_str(concat!(
+            "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: Ecu\n\n",
+            "BO_ 300 Status: 3 Ecu\n",
+            " SG_ Value : 0|16@1+ (1,0) [0|65535] \"\" Vector__XXX\n",
+            " SG_ Status_CNT : 16|4@1+ (1,0) [0|15] \"\" Vector__XXX\n",
+            " SG_ Status_CRC : 20|4@1+ (1,0) [0|15] \"\" Vector__XXX\n",
+        ))
+        .unwrap();
+        let status = can_decoder::get_message_spec(&dbc, "Status").unwrap();
+        let mut protection = Protection::new().with_dbc(&dbc, Arc::new(XorChecksum));
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 12C#A5C307").unwrap();
+        let mut protected = frame.clone();
+        protection.protect(&mut protected, status, true);
+        assert_eq!(protected.data[..3], [0xA5, 0xC3, 0x17]);
+        assert!(protection.check(&protected, status).is_ok());
+    }
+}
@@ -0,0 +1,162 @@
+/*!
+ * J1939 29-bit identifiers: priority, parameter group number (PGN), and source and
+ * destination addresses.
+ * u32 CAN ID <-> J1939Id
+ *
+ * J1939 DBCs define each message with the ID of one sender, while the same PGN arrives
+ * from other addresses and priorities. `CanDatabase::with_j1939` matches frames on the PGN.
+ *
+ * ```no_run
+ * use rocketcan::j1939::J1939Id;
+ *
+ * let id = J1939Id::from_can_id(0x18FEF121);
+ * assert_eq!(id.pgn(), 0xFEF1);
+ * assert_eq!(id.source_address, 0x21);
+ * let from_engine = id.with_source_address(0x00).to_can_id();
+ * ```
+ */
+
+use std::fmt;
+
+/// PDU format values below this are PDU1 (destination specific), PS is the destination address
+const PDU2_MIN_FORMAT: u8 = 240;
+
+/// Fields of a 29-bit J1939 CAN ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct J1939Id {
+    /// 0 (highest) to 7
+    pub priority: u8,
+    /// Extended data page (bit 25) and data page (bit 24)
+    pub extended_data_page: bool,
+    pub data_page: bool,
+    /// PF
+    pub pdu_format: u8,
+    /// PS: destination address for PDU1, group extension for PDU2
+    pub pdu_specific: u8,
+    pub source_address: u8,
+}
+
+impl J1939Id {
+    /// Split a 29-bit CAN ID, higher bits are ignored
+    pub fn from_can_id(id: u32) -> Self {
+        Self {
+            priority: ((id >> 26) & 0x7) as u8,
+            extended_data_page: id & (1 << 25) != 0,
+            data_page: id & (1 << 24) != 0,
+            pdu_format: (id >> 16) as u8,
+            pdu_specific: (id >> 8) as u8,
+            source_address: id as u8,
+        }
+    }
+
+    /// Build an ID from a PGN. For PDU1 PGNs the destination is the global address (0xFF).
+    pub fn new(priority: u8, pgn: u32, source_address: u8) -> Self {
+        let mut id = Self::from_can_id((priority as u32 & 0x7) << 26 | (pgn & 0x3FFFF) << 8 | source_address as u32);
+        if id.is_pdu1() {
+            id.pdu_specific = 0xFF;
+        }
+        id
+    }
+
+    pub fn to_can_id(&self) -> u32 {
+        (self.priority as u32 & 0x7) << 26
+            | (self.extended_data_page as u32) << 25
+            | (self.data_page as u32) << 24
+            | (self.pdu_format as u32) << 16
+            | (self.pdu_specific as u32) << 8
+            | self.source_address as u32
+    }
+
+    /// Destination specific PDU: PS holds the destination address
+    pub fn is_pdu1(&self) -> bool {
+        self.pdu_format < PDU2_MIN_FORMAT
+    }
+
+    /// Parameter group number, without the destination address of PDU1 messages
+    pub fn pgn(&self) -> u32 {
+        let group_extension = if self.is_pdu1() { 0 } else { self.pdu_specific as u32 };
+        (self.extended_data_page as u32) << 17
+            | (self.data_page as u32) << 16
+            | (self.pdu_format as u32) << 8
+            | group_extension
+    }
+
+    /// Destination address of PDU1 messages, None for broadcast PDU2 messages
+    pub fn destination_address(&self) -> Option<u8> {
+        self.is_pdu1().then_some(self.pdu_specific)
+    }
+
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority & 0x7;
+        self
+    }
+
+    pub fn with_source_address(mut self, source_address: u8) -> Self {
+        self.source_address = source_address;
+        self
+    }
+
+    /// Set the destination of a PDU1 message, no effect on PDU2 messages
+    pub fn with_destination_address(mut self, destination_address: u8) -> Self {
+        if self.is_pdu1() {
+            self.pdu_specific = destination_address;
+        }
+        self
+    }
+}
+
+impl fmt::Display for J1939Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PGN {:#X} priority {} SA {:#04X}", self.pgn(), self.priority, self.source_address)?;
+        if let Some(destination_address) = self.destination_address() {
+            write!(f, " DA {destination_address:#04X}")?;
+        }
+        Ok(())
+    }
+}
+
+/// PGN of a 29-bit CAN ID
+pub fn pgn(id: u32) -> u32 {
+    J1939Id::from_can_id(id).pgn()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdu2() {
+        // EEC1 from the engine
+        let id = J1939Id::from_can_id(0x0CF00400);
+        assert_eq!(id.priority, 3);
+        assert_eq!(id.pgn(), 61444);
+        assert_eq!(id.source_address, 0);
+        assert_eq!(id.destination_address(), None);
+        assert_eq!(id.to_can_id(), 0x0CF00400);
+        assert_eq!(id.to_string(), "PGN 0xF004 priority 3 SA 0x00");
+
+        let moved = id.with_priority(6).with_source_address(0x21).with_destination_address(0x10);
+        assert_eq!(moved.to_can_id(), 0x18F00421);
+        assert_eq!(moved.pgn(), id.pgn());
+        assert_eq!(J1939Id::new(3, 61444, 0), id);
+    }
+
+    #[test]
+    fn test_pdu1() {
+        // TSC1 from the transmission to the engine
+        let id = J1939Id::from_can_id(0x0C000003);
+        assert_eq!(id.pgn(), 0);
+        assert_eq!(id.destination_address(), Some(0x00));
+        assert_eq!(id.to_string(), "PGN 0x0 priority 3 SA 0x03 DA 0x00");
+
+        // Request PGN to the global address
+        let request = J1939Id::new(6, 0xEA00, 0xF9);
+        assert_eq!(request.to_can_id(), 0x18EAFFF9);
+        assert_eq!(pgn(0x18EA00F9), 0xEA00);
+        assert_eq!(request.with_destination_address(0x17).to_can_id(), 0x18EA17F9);
+
+        let paged = J1939Id::from_can_id(0x1BEAFFF9);
+        assert!(paged.extended_data_page && paged.data_page);
+        assert_eq!(paged.pgn(), 0x3EA00);
+    }
+}
@@ -0,0 +1,397 @@
+/*!
+ * KCD (Kayak CAN definition, XML) import.
+ * KCD file -> can_dbc::Dbc, decoded and encoded like any DBC
+ *
+ * - Bus: all buses merged with `parse`, or one Dbc per bus with `parse_buses`
+ * - Node, Message, Signal: BU_, BO_, SG_. Names are made valid DBC identifiers.
+ * - Notes: CM_ comments
+ * - Value type single/double: SIG_VALTYPE_ float32/float64
+ * - LabelSet: VAL_ labels, LabelGroup ranges are skipped
+ * - Multiplex: the multiplexer switch (M), the signals of each MuxGroup are m<count>
+ *
+ * Big endian offsets are the MSB numbered from the most significant bit of byte 0,
+ * as other KCD tools write them.
+ *
+ * ```no_run
+ * use rocketcan::can_database::CanDatabase;
+ * use rocketcan::kcd_loader;
+ *
+ * let mut database = CanDatabase::new();
+ * for (bus, dbc) in kcd_loader::parse_buses(&std::fs::read_to_string("network.kcd").unwrap()).unwrap() {
+ *     database.add_dbc(dbc, Some(&bus));
+ * }
+ * ```
+ */
+
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::{anyhow, bail, Context, Result};
+use can_dbc::{Dbc, MultiplexIndicator, Signal, SignalExtendedValueType};
+use roxmltree::{Document, Node};
+
+use crate::dbc_editor::{self, DbcEditor, MessageBuilder, SignalBuilder};
+use crate::xml_util::{self, child, children, position};
+
+/// Load a KCD file, the messages of all its buses in one Dbc
+pub fn load(kcd_path: &str) -> Result<Dbc> {
+    let text = fs::read_to_string(kcd_path).with_context(|| format!("reading {kcd_path}"))?;
+    parse(&text).with_context(|| format!("loading {kcd_path}"))
+}
+
+/// Parse a KCD document, the messages of all buses in one Dbc
+pub fn parse(text: &str) -> Result<Dbc> {
+    let document = Document::parse(text)?;
+    let root = network_definition(&document)?;
+    let nodes = node_names(root);
+    let mut editor = new_editor(root);
+    for bus in children(root, "Bus") {
+        add_bus(&mut editor, bus, &nodes)?;
+    }
+    Ok(editor.into_dbc())
+}
+
+/// Parse a KCD document, one Dbc per bus with the bus name
+pub fn parse_buses(text: &str) -> Result<Vec<(String, Dbc)>> {
+    let document = Document::parse(text)?;
+    let root = network_definition(&document)?;
+    let nodes = node_names(root);
+    let mut buses = Vec::new();
+    for bus in children(root, "Bus") {
+        let mut editor = new_editor(root);
+        add_bus(&mut editor, bus, &nodes)?;
+        buses.push((bus.attribute("name").unwrap_or_default().to_owned(), editor.into_dbc()));
+    }
+    Ok(buses)
+}
+
+/// A signal of a KCD message and what goes outside of its SG_ line
+struct KcdSignal {
+    signal: Signal,
+    value_type: SignalExtendedValueType,
+    labels: Vec<(i64, String)>,
+    notes: Option<String>,
+    /// End of the signal, in bits from the start of the message
+    end: u64,
+}
+
+fn network_definition<'a>(document: &'a Document) -> Result<Node<'a, 'a>> {
+    let root = document.root_element();
+    if !root.has_tag_name("NetworkDefinition") {
+        bail!("not a KCD document, root element is {}", root.tag_name().name());
+    }
+    Ok(root)
+}
+
+/// DBC node names by KCD node id
+fn node_names(root: Node) -> HashMap<String, String> {
+    children(root, "Node")
+        .filter_map(|n| Some((n.attribute("id")?.to_owned(), dbc_editor::identifier(n.attribute("name")?))))
+        .collect()
+}
+
+/// Editor of a new Dbc with the nodes of the document
+fn new_editor(root: Node) -> DbcEditor {
+    let mut editor = DbcEditor::new();
+    for node in children(root, "Node") {
+        let name = dbc_editor::identifier(node.attribute("name").unwrap_or_default());
+        if !editor.dbc().nodes.iter().any(|n| n.0 == name) {
+            editor.add_node(&name).expect("node names are deduplicated");
+        }
+    }
+    editor
+}
+
+/// Node names referenced by the NodeRef children of `node`
+fn node_refs<'a>(node: Option<Node>, nodes: &'a HashMap<String, String>) -> Vec<&'a str> {
+    node.into_iter()
+        .flat_map(|n| children(n, "NodeRef"))
+        .filter_map(|r| nodes.get(r.attribute("id")?))
+        .map(String::as_str)
+        .collect()
+}
+
+fn notes(node: Node) -> Option<String> {
+    child(node, "Notes").and_then(|n| n.text()).map(str::trim).filter(|t| !t.is_empty()).map(str::to_owned)
+}
+
+fn attribute<T: std::str::FromStr>(node: Node, name: &str) -> Result<Option<T>> {
+    match node.attribute(name) {
+        Some(text) => text
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow!("invalid {} {:?} at {}", name, text, position(node))),
+        None => Ok(None),
+    }
+}
+
+/// Decimal or 0x prefixed hex number
+fn parse_id(node: Node) -> Result<u32> {
+    let text = node.attribute("id").ok_or_else(|| anyhow!("message without id at {}", position(node)))?;
+    xml_util::number(text)
+        .ok()
+        .and_then(|id| u32::try_from(id).ok())
+        .ok_or_else(|| anyhow!("invalid message id {:?} at {}", text, position(node)))
+}
+
+fn add_bus(editor: &mut DbcEditor, bus: Node, nodes: &HashMap<String, String>) -> Result<()> {
+    for message in children(bus, "Message") {
+        add_message(editor, message, nodes)?;
+    }
+    Ok(())
+}
+
+fn add_message(editor: &mut DbcEditor, node: Node, nodes: &HashMap<String, String>) -> Result<()> {
+    let name = dbc_editor::identifier(node.attribute("name").unwrap_or_default());
+    let id = parse_id(node)?;
+
+    let mut signals = Vec::new();
+    for element in node.children().filter(|c| c.is_element()) {
+        match element.tag_name().name() {
+            "Signal" => signals.push(kcd_signal(element, nodes)?),
+            "Multiplex" => {
+                let mut switch = kcd_signal(element, nodes)?;
+                switch.signal.multiplexer_indicator = MultiplexIndicator::Multiplexor;
+                signals.push(switch);
+                for group in children(element, "MuxGroup") {
+                    let count: u64 = attribute(group, "count")?
+                        .ok_or_else(|| anyhow!("MuxGroup without count at {}", position(group)))?;
+                    for signal in children(group, "Signal") {
+                        let mut page_signal = kcd_signal(signal, nodes)?;
+                        page_signal.signal.multiplexer_indicator = MultiplexIndicator::MultiplexedSignal(count);
+                        signals.push(page_signal);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let size = match node.attribute("length") {
+        Some(length) if length != "auto" => attribute(node, "length")?.unwrap_or(8),
+        _ => signals.iter().map(|s| s.end.div_ceil(8)).max().unwrap_or(0),
+    };
+    let mut builder = MessageBuilder::new(&name, id, size);
+    if node.attribute("format") == Some("extended") {
+        builder = builder.extended();
+    }
+    if let Some(producer) = node_refs(child(node, "Producer"), nodes).first() {
+        builder = builder.transmitter(producer);
+    }
+    for signal in &signals {
+        builder = builder.signal(signal.signal.clone());
+    }
+    editor.add_message(builder.build()).with_context(|| format!("at {}", position(node)))?;
+
+    if let Some(notes) = notes(node) {
+        editor.set_message_comment(&name, &notes)?;
+    }
+    for signal in signals {
+        let signal_name = &signal.signal.name;
+        if signal.value_type != SignalExtendedValueType::SignedOrUnsignedInteger {
+            editor.set_signal_value_type(&name, signal_name, signal.value_type)?;
+        }
+        if !signal.labels.is_empty() {
+            let labels: Vec<(i64, &str)> = signal.labels.iter().map(|(v, l)| (*v, l.as_str())).collect();
+            editor.set_value_descriptions(&name, signal_name, &labels)?;
+        }
+        if let Some(notes) = &signal.notes {
+            editor.set_signal_comment(&name, signal_name, notes)?;
+        }
+    }
+    Ok(())
+}
+
+/// A Signal or Multiplex element
+fn kcd_signal(node: Node, nodes: &HashMap<String, String>) -> Result<KcdSignal> {
+    let name = dbc_editor::identifier(node.attribute("name").unwrap_or_default());
+    let offset: u64 = attribute(node, "offset")?.ok_or_else(|| anyhow!("signal {} without offset", name))?;
+    let length: u64 = attribute(node, "length")?.unwrap_or(1);
+    let big_endian = node.attribute("endianess") == Some("big");
+
+    let start_bit = if big_endian { dbc_editor::msb_first_start_bit(offset) } else { offset };
+    let mut builder = SignalBuilder::new(&name, start_bit, length);
+    if big_endian {
+        builder = builder.big_endian();
+    }
+    for receiver in node_refs(child(node, "Consumer"), nodes) {
+        builder = builder.receiver(receiver);
+    }
+
+    let mut value_type = SignalExtendedValueType::SignedOrUnsignedInteger;
+    let (mut min, mut max) = (None, None);
+    if let Some(value) = child(node, "Value") {
+        match value.attribute("type").unwrap_or("unsigned") {
+            "unsigned" => {}
+            "signed" => builder = builder.signed(),
+            "single" => value_type = SignalExtendedValueType::IEEEfloat32Bit,
+            "double" => value_type = SignalExtendedValueType::IEEEdouble64bit,
+            other => bail!("unknown value type {:?} of signal {} at {}", other, name, position(value)),
+        }
+        let slope = attribute(value, "slope")?.unwrap_or(1.0);
+        let intercept = attribute(value, "intercept")?.unwrap_or(0.0);
+        builder = builder.scale(slope, intercept);
+        if let Some(unit) = value.attribute("unit") {
+            builder = builder.unit(unit);
+        }
+        min = attribute(value, "min")?;
+        max = attribute(value, "max")?;
+    }
+    if value_type != SignalExtendedValueType::SignedOrUnsignedInteger {
+        builder = builder.float();
+    }
+    let mut signal = builder.build();
+    signal.min = min.unwrap_or(signal.min);
+    signal.max = max.unwrap_or(signal.max);
+
+    let mut labels = Vec::new();
+    if let Some(label_set) = child(node, "LabelSet") {
+        for label in children(label_set, "Label") {
+            let value = attribute(label, "value")?.ok_or_else(|| anyhow!("label without value"))?;
+            labels.push((value, label.attribute("name").unwrap_or_default().to_owned()));
+        }
+    }
+
+    Ok(KcdSignal {
+        signal,
+        value_type,
+        labels,
+        notes: notes(node),
+        end: offset + length,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::can_decoder::{decode_message, decode_message_with_dbc, load_dbc};
+    use crate::can_encoder::CanFrameBuilder;
+    use crate::canlog_reader::CanFrame;
+    use can_dbc::MessageId;
+
+    #[test]
+    fn test_motohawk_kcd_matches_dbc() {
+        let kcd = load("motohawk.kcd").unwrap();
+        let dbc = load_dbc("motohawk.dbc").unwrap();
+        assert_eq!(kcd.messages, dbc.messages);
+        assert_eq!(kcd.value_descriptions, dbc.value_descriptions);
+        assert_eq!(kcd.comments, dbc.comments);
+
+        let frame = CanFrameBuilder::new(&kcd.messages[0])
+            .dbc(&kcd)
+            .set("Temperature", 255.5)
+            .unwrap()
+            .set("AverageRadius", 3.2)
+            .unwrap()
+            .set("Enable", "Enabled")
+            .unwrap()
+            .build();
+        let decoded = decode_message_with_dbc(&frame, &dbc.messages[0], &dbc);
+        assert_eq!(decoded.labels[2].as_deref(), Some("Enabled"));
+        for (value, expected) in decoded.values.iter().zip([255.5, 3.2, 1.0]) {
+            assert!((value - expected).abs() < 1e-9, "{value} != {expected}");
+        }
+    }
+
+    const MULTIPLEXED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<NetworkDefinition xmlns="http://kayak.2codeornot2code.org/1.0">
+  <Document name="test"/>
+  <Node id="1" name="Engine ECU"/>
+  <Node id="2" name="Tester"/>
+  <Bus name="Diag">
+    <Message id="0x542" name="Diagnostics" length="auto">
+      <Producer><NodeRef id="1"/></Producer>
+      <Multiplex name="Page" offset="0" length="8">
+        <Consumer><NodeRef id="2"/></Consumer>
+        <LabelSet>
+          <Label name="Version" value="1"/>
+          <Label name="Supply" value="2"/>
+        </LabelSet>
+        <MuxGroup count="1">
+          <Signal name="SW_Major" offset="16" length="8"/>
+        </MuxGroup>
+        <MuxGroup count="2">
+          <Signal name="Voltage" offset="16" length="16">
+            <Value slope="0.001" unit="V"/>
+          </Signal>
+          <Signal name="Current" offset="32" length="16">
+            <Value type="signed" slope="0.01" unit="A"/>
+          </Signal>
+        </MuxGroup>
+      </Multiplex>
+      <Signal name="Status" offset="8" length="4"/>
+    </Message>
+  </Bus>
+  <Bus name="Body">
+    <Message id="0x18FEF100" name="Speed" length="8" format="extended">
+      <Notes>Wheel based speed</Notes>
+      <Signal name="Speed" offset="8" length="32">
+        <Value type="single" unit="km/h"/>
+      </Signal>
+    </Message>
+  </Bus>
+</NetworkDefinition>
+"#;
+
+    #[test]
+    fn test_multiplexed_kcd() {
+        let dbc = parse(MULTIPLEXED).unwrap();
+        assert_eq!(dbc.nodes.iter().map(|n| n.0.as_str()).collect::<Vec<_>>(), ["Engine_ECU", "Tester"]);
+
+        let diagnostics = &dbc.messages[0];
+        assert_eq!(diagnostics.size, 6);
+        assert_eq!(diagnostics.transmitter, can_dbc::Transmitter::NodeName("Engine_ECU".to_owned()));
+        let indicators: Vec<_> = diagnostics.signals.iter().map(|s| (s.name.as_str(), s.multiplexer_indicator)).collect();
+        assert_eq!(
+            indicators,
+            [
+                ("Page", MultiplexIndicator::Multiplexor),
+                ("SW_Major", MultiplexIndicator::MultiplexedSignal(1)),
+                ("Voltage", MultiplexIndicator::MultiplexedSignal(2)),
+                ("Current", MultiplexIndicator::MultiplexedSignal(2)),
+                ("Status", MultiplexIndicator::Plain),
+            ]
+        );
+        assert_eq!(diagnostics.signals[0].receivers, ["Tester"]);
+
+        let frame = CanFrame {
+            id: 0x542,
+            len: 8,
+            data: {
+                let mut data = [0u8; 64];
+                data[..6].copy_from_slice(&[2, 0x03, 0xB8, 0x0B, 0x9C, 0xFF]);
+                data
+            },
+            ..Default::default()
+        };
+        let decoded = decode_message_with_dbc(&frame, diagnostics, &dbc);
+        assert_eq!(decoded.signals, ["Page", "Voltage", "Current", "Status"]);
+        assert_eq!(decoded.labels[0].as_deref(), Some("Supply"));
+        assert!((decoded.values[1] - 3.0).abs() < 1e-9);
+        assert!((decoded.values[2] + 1.0).abs() < 1e-9);
+        assert_eq!(decoded.values[3], 3.0);
+
+        let speed = &dbc.messages[1];
+        assert_eq!(speed.id, MessageId::Extended(0x18FEF100));
+        assert_eq!(dbc.message_comment(speed.id), Some("Wheel based speed"));
+        let frame = CanFrameBuilder::new(speed).dbc(&dbc).set("Speed", 88.25).unwrap().build();
+        assert_eq!(decode_message_with_dbc(&frame, speed, &dbc).values, [88.25]);
+        assert_ne!(decode_message(&frame, speed).values, [88.25]);
+    }
+
+    #[test]
+    fn test_buses() {
+        let buses = parse_buses(MULTIPLEXED).unwrap();
+        let names: Vec<_> = buses.iter().map(|(bus, dbc)| (bus.as_str(), dbc.messages.len())).collect();
+        assert_eq!(names, [("Diag", 1), ("Body", 1)]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(parse("<Foo/>").unwrap_err().to_string().contains("not a KCD document"));
+        let bad_offset = MULTIPLEXED.replace(r#"offset="8" length="4""#, r#"offset="x" length="4""#);
+        let error = parse(&bad_offset).unwrap_err().to_string();
+        assert!(error.starts_with("invalid offset \"x\" at 27:7"), "{error}");
+    }
+}
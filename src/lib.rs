@@ -1,9 +1,30 @@
 pub mod can_decoder;
 pub mod can_encoder;
+pub mod can_database;
+pub mod decoded_frames;
+pub mod signal_table;
 pub mod canlog_writer;
 pub mod canlog_reader;
+pub mod canlog_format;
+pub mod canlog_adapters;
 pub mod signal_layout;
 pub mod series_builder;
+pub mod codegen;
+pub mod dbc_attributes;
+pub mod dbc_diff;
+pub mod dbc_editor;
+pub mod dbc_lint;
+pub mod dbc_loader;
+pub mod dbc_writer;
+pub mod kcd_loader;
+pub mod loader;
+pub mod sym_loader;
+pub mod j1939;
+pub mod frame_protection;
+pub mod arxml_loader;
+mod xml_util;
+#[cfg(feature = "async")]
+pub mod canlog_async;
 
 
 pub use canlog_reader::CanFrame;
@@ -68,6 +89,7 @@ pub fn create_f64_plot(x_data: Vec<f64>, y_data: Vec<f64>, plot_name: &str) {
     root.present().unwrap();
 }*/
 
+#[deprecated(note = "use signal_table::SignalColumn, extracted from logs by signal_table::SignalExtractor")]
 pub struct SignalSeries<T> {
     time: Vec<T>,
     values: Vec<T>,
@@ -75,6 +97,7 @@ pub struct SignalSeries<T> {
 
 /// Create a time series that steps up by 1 each time step.
 /// Rolls over at uint8 max, 255, to 0.
+#[allow(deprecated)]
 fn create_step_time_series(num_series: i32) -> SignalSeries<i32> {
     let mut series = SignalSeries {
         time: Vec::new(),
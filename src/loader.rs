@@ -0,0 +1,56 @@
+/*!
+ * Database files of every supported format, picked by file extension.
+ * .dbc (or any other extension) -> `dbc_loader`, .kcd -> `kcd_loader`, .sym -> `sym_loader`,
+ * .arxml -> `arxml_loader`, all as a can_dbc::Dbc
+ *
+ * Kept apart from `can_decoder` so that decoding doesn't depend on the importers.
+ */
+
+use std::io;
+use std::path::Path;
+
+use crate::arxml_loader;
+use crate::dbc_loader::{self, LoadedDbc};
+use crate::kcd_loader;
+use crate::sym_loader;
+
+/// Load a DBC, KCD, SYM or ARXML file, by file extension.
+/// The frames of all ARXML channels are merged, see `arxml_loader` for container frames.
+pub fn load(path: &str) -> io::Result<can_dbc::Dbc> {
+    Ok(load_with_warnings(path)?.dbc)
+}
+
+/// Same as `load`, with the warnings of DBC files (see `dbc_loader`). Converted formats have none.
+pub fn load_with_warnings(path: &str) -> io::Result<LoadedDbc> {
+    let converted = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("arxml") => arxml_loader::load_dbc(path),
+        Some(e) if e.eq_ignore_ascii_case("kcd") => kcd_loader::load(path),
+        Some(e) if e.eq_ignore_ascii_case("sym") => sym_loader::load(path),
+        _ => return Ok(dbc_loader::load(path)?),
+    };
+    let dbc = converted.map_err(|e| {
+        let kind = e.root_cause().downcast_ref::<io::Error>().map_or(io::ErrorKind::InvalidData, io::Error::kind);
+        io::Error::new(kind, format!("{e:#}"))
+    })?;
+    Ok(LoadedDbc { dbc, warnings: Vec::new() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_by_extension() {
+        let dbc = load("motohawk.dbc").unwrap();
+        for path in ["motohawk.kcd", "motohawk.sym"] {
+            let converted = load(path).unwrap();
+            assert_eq!(converted.messages[0].signals.len(), dbc.messages[0].signals.len(), "{path}");
+        }
+        let error = load("missing.kcd").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+        let loaded = load_with_warnings("comment_backslash.dbc").unwrap();
+        assert!(!loaded.warnings.is_empty());
+        assert!(load_with_warnings("motohawk.kcd").unwrap().warnings.is_empty());
+    }
+}
@@ -1,125 +1,634 @@
-use can_dbc::{Dbc, Message};
-use ::rocketcan::SignalSeries;
-//use ablf::BlfFile;
-use rocketcan::{CanFrame, can_decoder, can_encoder, canlog_reader, canlog_writer::{self, CandumpWriter}};
-use std::{fs::File, io::Write};
+/*!
+ * rocketcan command line tool
+ *
+ * Examples:
+ *   rocketcan info candump.log
+ *   rocketcan convert candump.asc -o candump.log
+ *   rocketcan decode --dbc motohawk.dbc candump.log
+ *   rocketcan decode --dbc can_samples/chrysler_cusw.dbc --check-protection crc8-j1850 demo.log
+ *   cat candump.log | rocketcan filter - --id 0x100-0x1FF --channel vcan0
+ *   rocketcan generate --dbc can_samples/chrysler_cusw.dbc --demo -o demo.log
+ *   rocketcan replay demo.log --speed 2
+ *   rocketcan dbc show motohawk.dbc
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
 use rand::Rng;
-use rocketcan::series_builder;
-fn main() {
-    println!("Hello, world!");
-    println!("{:?}", rocketcan::create_saw_signal(1, 10));
-    let signal = rocketcan::create_saw_signal(0, 10);
-    let mut x_vals = Vec::new();
-    for i in 0..signal.len() as i32 {
-        x_vals.push(i);
+use rocketcan::can_database::CanDatabase;
+use rocketcan::canlog_adapters::{self, CanWriterExt};
+use rocketcan::canlog_format::{self, CanLogFormat, CandumpFormat};
+use rocketcan::canlog_reader::CanLogParser;
+use rocketcan::canlog_writer::CanWriter;
+use rocketcan::codegen::Codegen;
+use rocketcan::dbc_diff;
+use rocketcan::dbc_lint::{self, Severity};
+use rocketcan::loader;
+use rocketcan::decoded_frames::{DecodeFramesExt, UnknownIds};
+use rocketcan::frame_protection::{self, Crc8J1850, Protection};
+use rocketcan::{can_decoder, can_encoder, CanFrame};
+
+#[derive(Parser)]
+#[command(name = "rocketcan", version, about = "Read, convert, decode and generate CAN logs")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert a log from one format to another
+    Convert {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Decode frames of a log with a DBC
+    Decode {
+        #[command(flatten)]
+        input: InputArgs,
+        /// DBC used to decode frames, as [CHANNEL=]PATH[@PRIORITY]. Repeatable: a DBC
+        /// with a channel only decodes frames of that channel, higher priorities win ID conflicts.
+        #[arg(long = "dbc", required = true, value_parser = parse_dbc_binding)]
+        dbcs: Vec<DbcBinding>,
+        /// Decode frames of channel ALIAS as frames of CHANNEL, as ALIAS=CHANNEL. Repeatable.
+        #[arg(long = "channel-alias", value_parser = parse_channel_alias)]
+        channel_aliases: Vec<(String, String)>,
+        /// Only decode these messages (by name)
+        #[arg(long = "message")]
+        messages: Vec<String>,
+        /// Print frames that don't match any DBC message
+        #[arg(long)]
+        show_unknown: bool,
+        /// Match extended IDs on the PGN of J1939 messages, whatever the source address and priority.
+        /// J1939 messages have VFrameFormat J1939PG or come from a DBC with ProtocolType J1939
+        #[arg(long)]
+        j1939: bool,
+        /// Flag counter jumps and checksum mismatches of COUNTER/CHECKSUM signals, computing
+        /// checksums with ALGORITHM (crc8-j1850, xor, sum) unless the DBC attributes name one
+        #[arg(long, value_name = "ALGORITHM")]
+        check_protection: Option<String>,
+    },
+    /// Print statistics about a log
+    Info {
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Keep only frames matching all of the given filters
+    Filter {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        /// ID or ID range to keep, e.g. 0x1A0, 416 or 0x100-0x1FF. Repeatable.
+        #[arg(long = "id", value_parser = parse_id_range)]
+        ids: Vec<RangeInclusive<u32>>,
+        /// Channel to keep. Repeatable.
+        #[arg(long = "channel")]
+        channels: Vec<String>,
+        /// Keep only CAN FD frames
+        #[arg(long)]
+        fd_only: bool,
+    },
+    /// Generate a log of encoded frames from a DBC
+    Generate(GenerateArgs),
+    /// Re-emit a log paced by its timestamps
+    Replay {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        /// Playback speed multiplier
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+        /// Replace timestamps with the time since replay start
+        #[arg(long)]
+        retime: bool,
+    },
+    /// Inspect DBC files
+    Dbc {
+        #[command(subcommand)]
+        command: DbcCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbcCommand {
+    /// List messages and signals of a DBC
+    Show {
+        dbc: String,
+    },
+    /// Generate Rust structs with encode/decode methods for the messages of a DBC
+    Codegen {
+        dbc: String,
+        /// Output Rust file, stdout when not given
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Path of the rocketcan crate in the generated code
+        #[arg(long, default_value = "rocketcan")]
+        crate_path: String,
+    },
+    /// List the changes between two versions of a DBC
+    Diff {
+        old: String,
+        new: String,
+        /// Also decode this log with both versions and report the signals that decode differently
+        #[arg(long)]
+        log: Option<String>,
+        /// Log format name, detected from extension or contents when not given
+        #[arg(long)]
+        from: Option<String>,
+    },
+    /// Check a DBC for overlapping signals, bad ranges and other mistakes.
+    /// Fails when errors are found.
+    Lint {
+        dbc: String,
+        /// Don't print warnings
+        #[arg(long)]
+        errors_only: bool,
+    },
+}
+
+#[derive(Args)]
+struct InputArgs {
+    /// Input log file, "-" for stdin
+    input: String,
+    /// Input format name, detected from extension or contents when not given
+    #[arg(long)]
+    from: Option<String>,
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Output log file, stdout when not given
+    #[arg(short, long)]
+    output: Option<String>,
+    /// Output format name, from the output extension or candump when not given
+    #[arg(long)]
+    to: Option<String>,
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    /// DBC to encode frames with
+    #[arg(long)]
+    dbc: String,
+    #[command(flatten)]
+    output: OutputArgs,
+    /// Messages to generate (by name), all messages when not given
+    #[arg(long = "message")]
+    messages: Vec<String>,
+    /// Length of the generated log (seconds)
+    #[arg(long, default_value_t = 10.0)]
+    duration: f64,
+    /// Time between frames of a message (seconds)
+    #[arg(long, default_value_t = 0.01)]
+    period: f64,
+    /// Channel name written to frames
+    #[arg(long, default_value = "vcan0")]
+    channel: String,
+    /// Generate the Chrysler CUSW driving scenario (needs can_samples/chrysler_cusw.dbc)
+    #[arg(long)]
+    demo: bool,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Convert { input, output } => convert(&input, &output),
+        Command::Decode { input, dbcs, channel_aliases, messages, show_unknown, j1939, check_protection } => {
+            decode(&input, &dbcs, &channel_aliases, &messages, show_unknown, j1939, check_protection.as_deref())
+        }
+        Command::Info { input } => info(&input),
+        Command::Filter { input, output, ids, channels, fd_only } => {
+            filter(&input, &output, ids, channels, fd_only)
+        }
+        Command::Generate(args) => generate(&args),
+        Command::Replay { input, output, speed, retime } => replay(&input, &output, speed, retime),
+        Command::Dbc { command } => match command {
+            DbcCommand::Show { dbc } => dbc_show(&dbc),
+            DbcCommand::Codegen { dbc, output, crate_path } => dbc_codegen(&dbc, output.as_deref(), &crate_path),
+            DbcCommand::Diff { old, new, log, from } => dbc_diff(&old, &new, log, from),
+            DbcCommand::Lint { dbc, errors_only } => dbc_lint(&dbc, errors_only),
+        },
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // Output was closed early, e.g. piped into head
+        Err(e) if is_broken_pipe(&e) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Whether the error, or any error it wraps (e.g. behind a `.context()`), is a broken pipe
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+fn format_by_name(name: &str) -> Result<Arc<dyn CanLogFormat>> {
+    canlog_format::registry()
+        .by_name(name)
+        .ok_or_else(|| anyhow!("unknown log format '{name}'"))
+}
+
+/// Open the input log, "-" reads from stdin
+fn open_input(args: &InputArgs) -> Result<CanLogParser> {
+    let format = args.from.as_deref().map(format_by_name).transpose()?;
+    if args.input == "-" {
+        let mut reader = BufReader::new(io::stdin());
+        let format = match format {
+            Some(format) => format,
+            None => canlog_format::registry()
+                .sniff(reader.fill_buf()?)
+                .unwrap_or_else(|| Arc::new(CandumpFormat)),
+        };
+        return Ok(CanLogParser::from_reader(reader, format.as_ref())?);
     }
 
-    //Use Case 1: Parse and plot a signal from canfile
-    let desired_signal_name = "s7big";
-    let desired_message_name = "Message378910";
-    //ERROR DBC
-    let error_dbc = "/home/jlucero/Downloads/bmw_fr_updated.dbc";
-    //let can_dbc = match rocketcan::can_decoder::load_dbc(error_dbc) {
-    let can_dbc = match rocketcan::can_decoder::load_dbc("signed.dbc") {
-        Ok(can_dbc) => can_dbc,
-        Err(err) => {
-            panic!("Error loading dbc: {err}");
+    let path = Path::new(&args.input);
+    let parser = match format {
+        Some(format) => {
+            let file = std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+            CanLogParser::from_reader(BufReader::new(file), format.as_ref())?
+        }
+        None => CanLogParser::from_file(path).with_context(|| format!("opening {}", path.display()))?,
+    };
+    Ok(parser)
+}
+
+/// Open the output writer, stdout when no path is given
+fn open_output(args: &OutputArgs) -> Result<Box<dyn CanWriter>> {
+    let format = match (&args.to, &args.output) {
+        (Some(name), _) => format_by_name(name)?,
+        (None, Some(path)) => canlog_format::registry()
+            .for_path(Path::new(path))
+            .ok_or_else(|| anyhow!("can't tell output format of {path}, use --to"))?,
+        (None, None) => Arc::new(CandumpFormat),
+    };
+    let writer: Box<dyn Write> = match &args.output {
+        Some(path) if path != "-" => {
+            Box::new(std::fs::File::create(path).with_context(|| format!("creating {path}"))?)
+        }
+        _ => Box::new(io::stdout().lock()),
+    };
+    Ok(format.writer(writer)?)
+}
+
+fn convert(input: &InputArgs, output: &OutputArgs) -> Result<()> {
+    let mut writer = open_output(output)?;
+    for frame in open_input(input)? {
+        writer.write(&frame)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// A `--dbc` argument of decode
+#[derive(Clone)]
+struct DbcBinding {
+    channel: Option<String>,
+    path: String,
+    priority: i32,
+}
+
+/// Parse [CHANNEL=]PATH[@PRIORITY]
+fn parse_dbc_binding(arg: &str) -> Result<DbcBinding, String> {
+    let (channel, rest) = match arg.split_once('=') {
+        Some((channel, rest)) if !channel.is_empty() => (Some(channel.to_owned()), rest),
+        _ => (None, arg),
+    };
+    let (path, priority) = match rest.rsplit_once('@') {
+        Some((path, priority)) => {
+            let priority = priority.parse().map_err(|e| format!("invalid priority '{priority}': {e}"))?;
+            (path, priority)
         }
+        None => (rest, 0),
     };
-    let log_reader = canlog_reader::CanLogReader::from_file("s7big.log");
-    let target_message = can_decoder::get_message_spec(&can_dbc, desired_message_name).unwrap();
-    let target_signal =
-        can_decoder::get_signal_spec(&target_message, &desired_signal_name).unwrap();
-    let mut timestamps = Vec::new();
-    let mut data = Vec::new();
-    for can_frame in log_reader {
-        timestamps.push(can_frame.timestamp);
-        //Check can_frame ID
-        //sv = can_dbc.get_signal(can_frame,signal)
-        let signal_value =
-            rocketcan::can_decoder::decode_signal_by_bytes(&can_frame, &target_signal);
-        data.push(signal_value)
+    if path.is_empty() {
+        return Err(format!("missing DBC path in '{arg}'"));
     }
-    println!("{:?}", data);
-    //rocketcan::create_f64_plot(timestamps, data, "s7big-plot.png");
-    //rocketcan::create_i32_plot(timestamps, data, "MySignalPlot");
-    //Use case 2: Printing data values
-    let log_reader = canlog_reader::CanLogReader::from_file("s7big.log");
-    for can_frame in log_reader {
-        let can_msg = can_decoder::decode_message(&can_frame, &target_message);
-        print!("{:#}", can_msg);
+    Ok(DbcBinding { channel, path: path.to_owned(), priority })
+}
+
+/// Parse ALIAS=CHANNEL
+fn parse_channel_alias(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((alias, channel)) if !alias.is_empty() && !channel.is_empty() => {
+            Ok((alias.to_owned(), channel.to_owned()))
+        }
+        _ => Err(format!("expected ALIAS=CHANNEL, got '{arg}'")),
     }
+}
 
-    /* converting formats
-    for can_frame in log_reader {
-        let blf_line: str = can_frame.into_blf_line();
-        file.write(blf_line)
+fn decode(
+    input: &InputArgs,
+    dbcs: &[DbcBinding],
+    channel_aliases: &[(String, String)],
+    messages: &[String],
+    show_unknown: bool,
+    j1939: bool,
+    check_protection: Option<&str>,
+) -> Result<()> {
+    let checksum = check_protection
+        .map(|name| {
+            frame_protection::checksum_algorithm(name, None).ok_or_else(|| anyhow!("unknown checksum algorithm '{name}'"))
+        })
+        .transpose()?;
+    let mut protection = Protection::new();
+    let mut database = CanDatabase::new();
+    database.set_j1939(j1939);
+    for binding in dbcs {
+        let dbc = load_dbc(&binding.path)?;
+        if let Some(checksum) = &checksum {
+            protection.add_dbc(&dbc, checksum.clone());
+        }
+        database.add_dbc_with_priority(dbc, binding.channel.as_deref(), binding.priority);
+    }
+    for (alias, channel) in channel_aliases {
+        database.add_channel_alias(alias, channel);
     }
-    log_reader.iter().map(|frame| frame.into_blf_line()).write
-     */
-
-    /// Re-write timestamps
-    let log = canlog_reader::CanLogReader::from_file(
-        "/home/jlucero/projects/rocketcan/can_samples/aphryx-canx-nissan-leaf/demo_meet_200k.log",
-    );
-    //let writer = canlog_reader::CanLogWriter("")
-    let output_path =
-        "/home/jlucero/projects/rocketcan/can_samples/aphryx-canx-nissan-leaf/demo_meet_200k_revised.log";
-    let mut output_file = std::fs::File::create(output_path).unwrap();
-
-    let mut time = 0.;
-    let mut first_time = true;
-    for mut can_frame in log {
-        if first_time {
-            time = can_frame.timestamp;
-            first_time = false;
+    for name in messages {
+        if database.message(name).is_none() {
+            bail!("message {name} not found in the DBCs");
         }
+    }
 
-        can_frame.timestamp = time;
+    let unknown_ids = if show_unknown { UnknownIds::Report } else { UnknownIds::Skip };
+    let decoded_frames = open_input(input)?.decode_with(&database).unknown_ids(unknown_ids);
+    let mut out = io::stdout().lock();
+    for decoded in decoded_frames {
+        let selected = match &decoded.message {
+            Some(message) => messages.is_empty() || messages.contains(&message.name),
+            None => true,
+        };
+        if selected {
+            write!(out, "{decoded}")?;
+        }
+        let message = match &decoded.message {
+            Some(_) if checksum.is_some() => database.message_by_id(&decoded.frame.channel, decoded.frame.id),
+            _ => None,
+        };
+        if let Some(message) = message {
+            let check = protection.check(&decoded.frame, message.spec());
+            if selected && !check.is_ok() {
+                writeln!(out, "! {}: {check}", message.name())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn info(input: &InputArgs) -> Result<()> {
+    let mut frames: u64 = 0;
+    let mut fd_frames: u64 = 0;
+    let mut first_time = None;
+    let mut last_time = 0.0;
+    let mut channels = BTreeSet::new();
+    let mut ids: BTreeMap<u32, u64> = BTreeMap::new();
+    for frame in open_input(input)? {
+        frames += 1;
+        if frame.is_fd {
+            fd_frames += 1;
+        }
+        first_time.get_or_insert(frame.timestamp);
+        last_time = frame.timestamp;
+        if !channels.contains(&frame.channel) {
+            channels.insert(frame.channel.clone());
+        }
+        *ids.entry(frame.id).or_default() += 1;
+    }
 
-        writeln!(
-            output_file,
-            "{}",
-            canlog_writer::frame_to_candump_line(&can_frame)
-        )
-        .unwrap();
-        time += 0.1;
+    let duration = first_time.map_or(0.0, |first| last_time - first);
+    let mut out = io::stdout().lock();
+    writeln!(out, "frames:   {frames} ({fd_frames} CAN FD)")?;
+    writeln!(out, "duration: {duration:.6} s")?;
+    writeln!(out, "channels: {}", channels.into_iter().collect::<Vec<_>>().join(", "))?;
+    writeln!(out, "ids:      {}", ids.len())?;
+    for (id, count) in ids {
+        let period_ms = if count > 1 { duration / (count - 1) as f64 * 1000.0 } else { 0.0 };
+        writeln!(out, "  {id:03X}  {count:>8} frames  ~{period_ms:.1} ms")?;
+    }
+    Ok(())
+}
+
+/// Parse "0x1A0", "416" or an inclusive range "0x100-0x1FF"
+fn parse_id_range(arg: &str) -> Result<RangeInclusive<u32>, String> {
+    fn parse_id(id: &str) -> Result<u32, String> {
+        let id = id.trim();
+        let parsed = match id.strip_prefix("0x").or_else(|| id.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => id.parse(),
+        };
+        parsed.map_err(|e| format!("invalid CAN ID '{id}': {e}"))
+    }
+    match arg.split_once('-') {
+        Some((start, end)) => Ok(parse_id(start)?..=parse_id(end)?),
+        None => {
+            let id = parse_id(arg)?;
+            Ok(id..=id)
+        }
     }
+}
 
-    //TESTING BLF READING
-    /*println!("--Testing BLF Reading---");
-    let file = File::open("can_samples/gpl-licensed-blf-technica/test_CanFdMessage.blf").unwrap();
-    let reader = std::io::BufReader::new(file);
-    let blf = BlfFile::from_reader(reader).unwrap();
-    println!("{:?}", blf.file_stats);
-    for t in blf {
-        dbg!(t);
-    }*/
-
-
-    //let filename = "~/rust_projects/aphryx-canx-nissan-leaf/demo_meet_200k.log";
-    //or line in
-    let out_file = "generated-demo-3.log";
-    gen_demo_file(out_file);
+fn filter(
+    input: &InputArgs,
+    output: &OutputArgs,
+    ids: Vec<RangeInclusive<u32>>,
+    channels: Vec<String>,
+    fd_only: bool,
+) -> Result<()> {
+    let id_filter = canlog_adapters::id_ranges(&ids);
+    let mut writer = open_output(output)?.filter(|frame: &CanFrame| {
+        (ids.is_empty() || id_filter(frame))
+            && (channels.is_empty() || channels.contains(&frame.channel))
+            && (!fd_only || frame.is_fd)
+    });
+    for frame in open_input(input)? {
+        writer.write(&frame)?;
+    }
+    writer.flush()?;
+    Ok(())
 }
 
-fn gen_demo_file(output_path: &str) {
-    let dbc = can_decoder::load_dbc("can_samples/chrysler_cusw.dbc")
-        .expect("failed to load Chrysler DBC");
+fn replay(input: &InputArgs, output: &OutputArgs, speed: f64, retime: bool) -> Result<()> {
+    if speed <= 0.0 {
+        bail!("--speed must be positive");
+    }
+    let mut writer = open_output(output)?;
+    let start = Instant::now();
+    let mut first_time = None;
+    for mut frame in open_input(input)? {
+        let log_offset = frame.timestamp - *first_time.get_or_insert(frame.timestamp);
+        let due = Duration::from_secs_f64((log_offset / speed).max(0.0));
+        if let Some(wait) = due.checked_sub(start.elapsed()) {
+            std::thread::sleep(wait);
+        }
+        if retime {
+            frame.timestamp = start.elapsed().as_secs_f64();
+        }
+        writer.write(&frame)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
 
-    let steering_msg = can_decoder::get_message_spec(&dbc, "STEERING").unwrap();
-    let levers_msg = can_decoder::get_message_spec(&dbc, "STEERING_LEVERS").unwrap();
-    let gearbox_msg = can_decoder::get_message_spec(&dbc, "GEARBOX_1").unwrap();
-    let brake1_msg = can_decoder::get_message_spec(&dbc, "BRAKE_1").unwrap();
-    let brake2_msg = can_decoder::get_message_spec(&dbc, "BRAKE_2").unwrap();
-    let wheels_rear_msg = can_decoder::get_message_spec(&dbc, "WHEEL_SPEEDS_REAR").unwrap();
-    let wheels_front_msg = can_decoder::get_message_spec(&dbc, "WHEEL_SPEEDS_FRONT").unwrap();
+/// Load a DBC, printing its warnings to stderr. KCD, SYM and ARXML files are converted.
+fn load_dbc(dbc_path: &str) -> Result<can_dbc::Dbc> {
+    let loaded = loader::load_with_warnings(dbc_path)?;
+    for warning in &loaded.warnings {
+        eprintln!("warning: {dbc_path}: {warning}");
+    }
+    Ok(loaded.dbc)
+}
 
-    let mut writer = CandumpWriter::from_path(output_path)
-        .expect("failed to create output file");
+fn dbc_show(dbc_path: &str) -> Result<()> {
+    let dbc = load_dbc(dbc_path)?;
+    let mut out = io::stdout().lock();
+    for msg in &dbc.messages {
+        writeln!(out, "{} : {:#X} ({} bytes)", msg.name, can_encoder::get_can_id(msg), msg.size)?;
+        for signal in &msg.signals {
+            let byte_order = match signal.byte_order {
+                can_dbc::ByteOrder::LittleEndian => "LE",
+                can_dbc::ByteOrder::BigEndian => "BE",
+            };
+            let sign = match signal.value_type {
+                can_dbc::ValueType::Signed => "signed",
+                can_dbc::ValueType::Unsigned => "unsigned",
+            };
+            writeln!(
+                out,
+                "  {}: {}|{} {byte_order} {sign} ({},{}) [{}|{}] \"{}\"",
+                signal.name, signal.start_bit, signal.size, signal.factor, signal.offset, signal.min, signal.max, signal.unit
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn dbc_codegen(dbc_path: &str, output: Option<&str>, crate_path: &str) -> Result<()> {
+    let dbc = load_dbc(dbc_path)?;
+    let code = Codegen::new(&dbc).crate_path(crate_path).generate();
+    match output {
+        Some(path) => fs::write(path, code).with_context(|| format!("writing {path}"))?,
+        None => io::stdout().lock().write_all(code.as_bytes())?,
+    }
+    Ok(())
+}
+
+fn dbc_diff(old_path: &str, new_path: &str, log: Option<String>, from: Option<String>) -> Result<()> {
+    let old = load_dbc(old_path)?;
+    let new = load_dbc(new_path)?;
+    let mut out = io::stdout().lock();
+    for change in dbc_diff::diff(&old, &new) {
+        writeln!(out, "{change}")?;
+    }
+    if let Some(input) = log {
+        let frames = open_input(&InputArgs { input, from })?;
+        let report = dbc_diff::replay_impact(&CanDatabase::from_dbc(old), &CanDatabase::from_dbc(new), frames);
+        write!(out, "\n{report}")?;
+    }
+    Ok(())
+}
+
+fn dbc_lint(dbc_path: &str, errors_only: bool) -> Result<()> {
+    let dbc = load_dbc(dbc_path)?;
+    let issues = dbc_lint::lint(&dbc);
+    let mut out = io::stdout().lock();
+    for issue in &issues {
+        if !errors_only || issue.severity == Severity::Error {
+            writeln!(out, "{issue}")?;
+        }
+    }
+    let errors = issues.iter().filter(|issue| issue.severity == Severity::Error).count();
+    if errors > 0 {
+        bail!("{dbc_path}: {errors} errors");
+    }
+    Ok(())
+}
+
+fn generate(args: &GenerateArgs) -> Result<()> {
+    let dbc = load_dbc(&args.dbc)?;
+    let mut writer = open_output(&args.output)?;
+    if args.demo {
+        gen_demo_file(&dbc, writer.as_mut(), args.duration, args.period, &args.channel)?;
+    } else {
+        gen_sine_signals(&dbc, writer.as_mut(), args)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Every signal of the selected messages follows a sine wave between its DBC min and max,
+/// each signal with its own period. Multiplexed signals are left out.
+fn gen_sine_signals(dbc: &can_dbc::Dbc, writer: &mut dyn CanWriter, args: &GenerateArgs) -> Result<()> {
+    let mut messages = Vec::new();
+    for name in &args.messages {
+        let msg = can_decoder::get_message_spec(dbc, name)
+            .ok_or_else(|| anyhow!("message {name} not found in {}", args.dbc))?;
+        messages.push(msg);
+    }
+    if messages.is_empty() {
+        messages = dbc.messages.iter().collect();
+    }
+
+    let steps = (args.duration / args.period) as usize;
+    for i in 0..steps {
+        let t = i as f64 * args.period;
+        for msg in &messages {
+            let mut builder = can_encoder::CanFrameBuilder::new(msg);
+            for (n, signal) in msg.signals.iter().enumerate() {
+                if can_decoder::mux_condition(msg, signal, &dbc.extended_multiplex).is_some() {
+                    continue;
+                }
+                let (min, max) = if signal.min < signal.max { (signal.min, signal.max) } else { (0.0, 0.0) };
+                let signal_period = 2.0 + n as f64;
+                let phase = (2.0 * std::f64::consts::PI * t / signal_period).sin();
+                let value = min + (max - min) * (phase + 1.0) / 2.0;
+                builder = builder.set(&signal.name, value)?;
+            }
+            let frame = builder.timestamp(t).channel(args.channel.clone()).build();
+            writer.write(&frame)?;
+        }
+    }
+    Ok(())
+}
+
+/// Chrysler CUSW driving scenario: steering sine wave, accelerate, coast and brake to a stop
+fn gen_demo_file(
+    dbc: &can_dbc::Dbc,
+    writer: &mut dyn CanWriter,
+    duration: f64,
+    dt: f64,
+    channel: &str,
+) -> Result<()> {
+    let message = |name: &str| {
+        can_decoder::get_message_spec(dbc, name)
+            .ok_or_else(|| anyhow!("--demo needs the chrysler_cusw DBC, message {name} not found"))
+    };
+    let steering_msg = message("STEERING")?;
+    let levers_msg = message("STEERING_LEVERS")?;
+    let gearbox_msg = message("GEARBOX_1")?;
+    let brake1_msg = message("BRAKE_1")?;
+    let brake2_msg = message("BRAKE_2")?;
+    let wheels_rear_msg = message("WHEEL_SPEEDS_REAR")?;
+    let wheels_front_msg = message("WHEEL_SPEEDS_FRONT")?;
 
-    let dt = 0.01_f64;
-    let duration = 10.0_f64;
     let steps = (duration / dt) as usize;
 
     // Steering: sine wave -90..90 deg, 4-second period
@@ -128,20 +637,15 @@ fn gen_demo_file(output_path: &str) {
     let steer_amplitude = 90.0_f64;
 
     // Speed profile: ramp 0→18 m/s, coast, brake to 0
-    let ramp_end = 3.0_f64;
-    let coast_end = 7.0_f64;
+    let ramp_end = 0.3 * duration;
+    let coast_end = 0.7 * duration;
     let target_speed = 18.0_f64;
     let brake_torque_val = 500.0_f64;
 
     let mut prev_angle = 0.0_f64;
 
-    // Per-message 4-bit counters (0-15, rollover)
-    let mut steering_ctr: u64 = 0;
-    let mut gearbox_ctr: u64 = 0;
-    let mut brake1_ctr: u64 = 0;
-    let mut brake2_ctr: u64 = 0;
-    let mut wheels_rear_ctr: u64 = 0;
-    let mut wheels_front_ctr: u64 = 0;
+    // COUNTER rolls over every message, CHECKSUM is the J1850 CRC8 of the other bytes
+    let mut protection = Protection::new().with_dbc(dbc, Arc::new(Crc8J1850));
 
     // Wheel speed sensor noise: fraction of current speed (0.05 = ±5%)
     let wheel_noise_factor = 0.03_f64;
@@ -155,15 +659,14 @@ fn gen_demo_file(output_path: &str) {
         let steer_rate = (steer_angle - prev_angle) / dt;
         prev_angle = steer_angle;
 
-        let frame = can_encoder::CanFrameBuilder::new(&steering_msg)
-            .set("STEER_ANGLE", steer_angle).unwrap()
-            .set("STEERING_RATE", steer_rate).unwrap()
-            .set("COUNTER", steering_ctr as f64).unwrap()
+        let frame = can_encoder::CanFrameBuilder::new(steering_msg)
+            .protection(&mut protection)
+            .set("STEER_ANGLE", steer_angle)?
+            .set("STEERING_RATE", steer_rate)?
             .timestamp(t)
-            .channel("vcan0".into())
+            .channel(channel.into())
             .build();
-        writer.write(&frame).unwrap();
-        steering_ctr = (steering_ctr + 1) % 16;
+        writer.write(&frame)?;
 
         // --- STEERING_LEVERS (1264): turn signals track steering direction ---
         let turn_signal = if steer_angle < -5.0 {
@@ -173,23 +676,22 @@ fn gen_demo_file(output_path: &str) {
         } else {
             0.0 // off
         };
-        let frame = can_encoder::CanFrameBuilder::new(&levers_msg)
-            .set("TURN_SIGNALS", turn_signal).unwrap()
+        let frame = can_encoder::CanFrameBuilder::new(levers_msg)
+            .set("TURN_SIGNALS", turn_signal)?
             .timestamp(t)
-            .channel("vcan0".into())
+            .channel(channel.into())
             .build();
-        writer.write(&frame).unwrap();
+        writer.write(&frame)?;
 
         // --- GEARBOX_1 (500): always in Drive (4) ---
-        let frame = can_encoder::CanFrameBuilder::new(&gearbox_msg)
-            .set("DESIRED_GEAR", 4.0).unwrap()
-            .set("ACTUAL_GEAR", 4.0).unwrap()
-            .set("COUNTER", gearbox_ctr as f64).unwrap()
+        let frame = can_encoder::CanFrameBuilder::new(gearbox_msg)
+            .protection(&mut protection)
+            .set("DESIRED_GEAR", 4.0)?
+            .set("ACTUAL_GEAR", 4.0)?
             .timestamp(t)
-            .channel("vcan0".into())
+            .channel(channel.into())
             .build();
-        writer.write(&frame).unwrap();
-        gearbox_ctr = (gearbox_ctr + 1) % 16;
+        writer.write(&frame)?;
 
         // --- BRAKE_1 (484): vehicle speed + brake PSI ---
         let speed = if t < ramp_end {
@@ -213,15 +715,14 @@ fn gen_demo_file(output_path: &str) {
             0.0
         };
 
-        let frame = can_encoder::CanFrameBuilder::new(&brake1_msg)
-            .set("VEHICLE_SPEED", speed).unwrap()
-            .set("BRAKE_PSI", brake_psi).unwrap()
-            .set("COUNTER", brake1_ctr as f64).unwrap()
+        let frame = can_encoder::CanFrameBuilder::new(brake1_msg)
+            .protection(&mut protection)
+            .set("VEHICLE_SPEED", speed)?
+            .set("BRAKE_PSI", brake_psi)?
             .timestamp(t)
-            .channel("vcan0".into())
+            .channel(channel.into())
             .build();
-        writer.write(&frame).unwrap();
-        brake1_ctr = (brake1_ctr + 1) % 16;
+        writer.write(&frame)?;
 
         // --- BRAKE_2 (738): brake torque, lights, human ---
         let torque_ramp_dur = 0.5;
@@ -235,44 +736,39 @@ fn gen_demo_file(output_path: &str) {
         };
         let brake_lights = if brake_torque > 0.0 { 1.0 } else { 0.0 };
         let brake_human = if brake_torque > 0.0 { 1.0 } else { 0.0 };
-        let frame = can_encoder::CanFrameBuilder::new(&brake2_msg)
-            .set("BRAKE_TORQUE", brake_torque).unwrap()
-            .set("BRAKE_LIGHTS", brake_lights).unwrap()
-            .set("BRAKE_HUMAN", brake_human).unwrap()
-            .set("COUNTER", brake2_ctr as f64).unwrap()
+        let frame = can_encoder::CanFrameBuilder::new(brake2_msg)
+            .protection(&mut protection)
+            .set("BRAKE_TORQUE", brake_torque)?
+            .set("BRAKE_LIGHTS", brake_lights)?
+            .set("BRAKE_HUMAN", brake_human)?
             .timestamp(t)
-            .channel("vcan0".into())
+            .channel(channel.into())
             .build();
-        writer.write(&frame).unwrap();
-        brake2_ctr = (brake2_ctr + 1) % 16;
+        writer.write(&frame)?;
 
         // --- WHEEL_SPEEDS_REAR (740) ---
-        let noise_rl = speed * wheel_noise_factor * rng.gen_range(-1.0..1.0_f64);
-        let noise_rr = speed * wheel_noise_factor * rng.gen_range(-1.0..1.0_f64);
-        let frame = can_encoder::CanFrameBuilder::new(&wheels_rear_msg)
-            .set("WHEEL_SPEED_RL", (speed + noise_rl).max(0.0)).unwrap()
-            .set("WHEEL_SPEED_RR", (speed + noise_rr).max(0.0)).unwrap()
-            .set("COUNTER", wheels_rear_ctr as f64).unwrap()
+        let noise_rl = speed * wheel_noise_factor * rng.random_range(-1.0..1.0_f64);
+        let noise_rr = speed * wheel_noise_factor * rng.random_range(-1.0..1.0_f64);
+        let frame = can_encoder::CanFrameBuilder::new(wheels_rear_msg)
+            .protection(&mut protection)
+            .set("WHEEL_SPEED_RL", (speed + noise_rl).max(0.0))?
+            .set("WHEEL_SPEED_RR", (speed + noise_rr).max(0.0))?
             .timestamp(t)
-            .channel("vcan0".into())
+            .channel(channel.into())
             .build();
-        writer.write(&frame).unwrap();
-        wheels_rear_ctr = (wheels_rear_ctr + 1) % 16;
+        writer.write(&frame)?;
 
         // --- WHEEL_SPEEDS_FRONT (742) ---
-        let noise_fl = speed * wheel_noise_factor * rng.gen_range(-1.0..1.0_f64);
-        let noise_fr = speed * wheel_noise_factor * rng.gen_range(-1.0..1.0_f64);
-        let frame = can_encoder::CanFrameBuilder::new(&wheels_front_msg)
-            .set("WHEEL_SPEED_FL", (speed + noise_fl).max(0.0)).unwrap()
-            .set("WHEEL_SPEED_FR", (speed + noise_fr).max(0.0)).unwrap()
-            .set("COUNTER", wheels_front_ctr as f64).unwrap()
+        let noise_fl = speed * wheel_noise_factor * rng.random_range(-1.0..1.0_f64);
+        let noise_fr = speed * wheel_noise_factor * rng.random_range(-1.0..1.0_f64);
+        let frame = can_encoder::CanFrameBuilder::new(wheels_front_msg)
+            .protection(&mut protection)
+            .set("WHEEL_SPEED_FL", (speed + noise_fl).max(0.0))?
+            .set("WHEEL_SPEED_FR", (speed + noise_fr).max(0.0))?
             .timestamp(t)
-            .channel("vcan0".into())
+            .channel(channel.into())
             .build();
-        writer.write(&frame).unwrap();
-        wheels_front_ctr = (wheels_front_ctr + 1) % 16;
+        writer.write(&frame)?;
     }
-
-    writer.flush().unwrap();
-    println!("Wrote {} frames to {}", steps * 7, output_path);
+    Ok(())
 }
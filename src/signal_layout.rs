@@ -26,7 +26,7 @@ pub struct BitSpan {
 /// Built once from a `can_dbc::Signal` spec via `from_spec()`. The same layout
 /// is used by both `extract` (decode) and `pack` (encode), guaranteeing they
 /// are inverses by construction.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SignalLayout {
     /// Each segment describes one contiguous run of bits within a single byte.
     /// A 64-bit signal spanning all 8 bytes produces at most 9 segments
@@ -97,13 +97,7 @@ impl SignalLayout {
     /// Iterates over the precomputed segments, masking and shifting bits
     /// from each byte into the correct position in the result.
     pub fn extract(&self, data: &[u8; 64]) -> u64 {
-        let mut result: u64 = 0;
-        for span in &self.segments {
-            let mask = ((1u16 << span.num_bits) - 1) as u8;
-            let bits = (data[span.byte_index] >> span.bit_offset) & mask;
-            result |= (bits as u64) << span.value_shift;
-        }
-        result
+        extract_spans(&self.segments, data)
     }
 
     /// Pack a raw unsigned value into the CAN frame data bytes.
@@ -112,12 +106,18 @@ impl SignalLayout {
     /// and writing them into the correct byte positions. Clears target bits
     /// before writing so that multiple signals can be packed into the same frame.
     pub fn pack(&self, data: &mut [u8; 64], raw: u64) {
+        pack_spans(&self.segments, data, raw)
+    }
+
+    /// Bits of the first `len` data bytes that belong to this signal, one mask per byte.
+    pub fn byte_masks(&self, len: usize) -> Vec<u8> {
+        let mut masks = vec![0u8; len];
         for span in &self.segments {
-            let mask = ((1u16 << span.num_bits) - 1) as u8;
-            let bits = ((raw >> span.value_shift) as u8) & mask;
-            data[span.byte_index] &= !(mask << span.bit_offset);
-            data[span.byte_index] |= bits << span.bit_offset;
+            if let Some(mask) = masks.get_mut(span.byte_index) {
+                *mask |= (((1u16 << span.num_bits) - 1) as u8) << span.bit_offset;
+            }
         }
+        masks
     }
 
     /// Decode a signal from a CAN frame, returning the physical value.
@@ -136,6 +136,61 @@ impl SignalLayout {
         };
         final_value * spec.factor + spec.offset
     }
+
+    /// Decode a signal from a CAN frame according to its SIG_VALTYPE_ value type.
+    ///
+    /// IEEE float signals are 32 or 64 bit long, the extracted bits are the
+    /// f32 / f64 value in either byte order. Factor and offset still apply.
+    pub fn decode_typed(
+        &self,
+        frame: &CanFrame,
+        spec: &can_dbc::Signal,
+        value_type: can_dbc::SignalExtendedValueType,
+    ) -> f64 {
+        let float_value = match value_type {
+            can_dbc::SignalExtendedValueType::SignedOrUnsignedInteger => return self.decode(frame, spec),
+            can_dbc::SignalExtendedValueType::IEEEfloat32Bit => f32::from_bits(self.extract(&frame.data) as u32) as f64,
+            can_dbc::SignalExtendedValueType::IEEEdouble64bit => f64::from_bits(self.extract(&frame.data)),
+        };
+        float_value * spec.factor + spec.offset
+    }
+}
+
+/// Extract a raw value from segments computed by `SignalLayout::from_spec`.
+/// Used by generated code, where the segments are constants.
+pub fn extract_spans(segments: &[BitSpan], data: &[u8; 64]) -> u64 {
+    let mut result: u64 = 0;
+    for span in segments {
+        let mask = ((1u16 << span.num_bits) - 1) as u8;
+        let bits = (data[span.byte_index] >> span.bit_offset) & mask;
+        result |= (bits as u64) << span.value_shift;
+    }
+    result
+}
+
+/// Pack a raw value with segments computed by `SignalLayout::from_spec`.
+/// Used by generated code, where the segments are constants.
+pub fn pack_spans(segments: &[BitSpan], data: &mut [u8; 64], raw: u64) {
+    for span in segments {
+        let mask = ((1u16 << span.num_bits) - 1) as u8;
+        let bits = ((raw >> span.value_shift) as u8) & mask;
+        data[span.byte_index] &= !(mask << span.bit_offset);
+        data[span.byte_index] |= bits << span.bit_offset;
+    }
+}
+
+/// Bits of a message that no signal owns, one mask per byte of the message.
+/// Bits of every multiplexer page are owned, whatever page a frame is on.
+/// These are the bits an encoder leaves as they are in the base frame.
+pub fn unowned_bits(message_spec: &can_dbc::Message) -> Vec<u8> {
+    let len = message_spec.size as usize;
+    let mut unowned = vec![0xFFu8; len];
+    for spec in &message_spec.signals {
+        for (bits, owned) in unowned.iter_mut().zip(SignalLayout::from_spec(spec).byte_masks(len)) {
+            *bits &= !owned;
+        }
+    }
+    unowned
 }
 
 #[cfg(test)]
@@ -649,4 +704,64 @@ mod tests {
 
         assert!(signals_checked > 0, "no signals were checked");
     }
+
+    // ---------------------------------------------------------------
+    // IEEE float signals (SIG_VALTYPE_)
+    // ---------------------------------------------------------------
+
+    #[test]
+    fn test_decode_typed_float_both_byte_orders() {
+        let dbc = can_decoder::load_dbc("floats.dbc").unwrap();
+        // (message, frame, [(signal, expected)])
+        type Case<'a> = (&'a str, &'a str, &'a [(&'a str, f64)]);
+        let cases: &[Case] = &[
+            ("FloatsLE", "(0.0) vcan0 300#00004841000050C0", &[("Speed", 12.5), ("Gain", 3.5)]),
+            ("FloatsBE", "(0.0) vcan0 301#42CAA666BE000000", &[("Pressure", 101.325f32 as f64), ("Ratio", -0.125)]),
+            ("DoubleLE", "(0.0) vcan0 302#ADFA5C6D454A9340", &[("Position", 1234.5678)]),
+            ("DoubleBE", "(0.0) vcan0 303#C0C1486E147AE148", &[("Altitude", -8848.86)]),
+        ];
+        for (msg_name, line, expected) in cases {
+            let frame = canlog_reader::parse_candump_line(line).unwrap();
+            let msg = get_message_spec(&dbc, msg_name).unwrap();
+            let mut data = [0u8; 64];
+            for (signal_name, expected_value) in expected.iter() {
+                let spec = can_decoder::get_signal_spec(msg, signal_name).unwrap();
+                let layout = SignalLayout::from_spec(spec);
+                let value_type = can_decoder::get_value_type(&dbc, msg, spec);
+                assert_eq!(layout.decode_typed(&frame, spec, value_type), *expected_value, "{msg_name}.{signal_name}");
+
+                // Pack the float bits back into an empty frame
+                layout.pack(&mut data, layout.extract(&frame.data));
+            }
+            assert_eq!(&data[..8], &frame.data[..8], "{msg_name}");
+        }
+
+        // Integer signals are unchanged
+        let frame = canlog_reader::parse_candump_line("(0.0) vcan0 304#0000803F00000000").unwrap();
+        let msg = get_message_spec(&dbc, "Counter").unwrap();
+        let spec = can_decoder::get_signal_spec(msg, "Ticks").unwrap();
+        let value_type = can_decoder::get_value_type(&dbc, msg, spec);
+        assert_eq!(value_type, can_dbc::SignalExtendedValueType::SignedOrUnsignedInteger);
+        assert_eq!(SignalLayout::from_spec(spec).decode_typed(&frame, spec, value_type), 1065353216.0);
+    }
+
+    // ---------------------------------------------------------------
+    // Bits not owned by any signal
+    // ---------------------------------------------------------------
+
+    #[test]
+    fn test_unowned_bits() {
+        // Temperature ends in the top 3 bits of byte 2, nothing after it
+        let dbc = can_decoder::load_dbc("motohawk.dbc").unwrap();
+        let msg = get_message_spec(&dbc, "ExampleMessage").unwrap();
+        assert_eq!(unowned_bits(msg), [0x00, 0x00, 0x1F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+
+        let temperature = can_decoder::get_signal_spec(msg, "Temperature").unwrap();
+        assert_eq!(SignalLayout::from_spec(temperature).byte_masks(3), [0x01, 0xFF, 0xE0]);
+
+        // Signals of every multiplexer page own their bits: bytes 4 and 5 are only used on page 2
+        let dbc = can_decoder::load_dbc("multiplex.dbc").unwrap();
+        let msg = get_message_spec(&dbc, "Diagnostics").unwrap();
+        assert_eq!(unowned_bits(msg), [0x00, 0xF0, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF]);
+    }
 }
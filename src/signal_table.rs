@@ -0,0 +1,398 @@
+/*!
+ * Columnar time series of decoded signals, extracted from a log in a single pass.
+ * Iterator<CanFrame> -> SignalTable { "Message.Signal" -> (timestamps, values) }
+ *
+ * Columns of DBCs bound to a channel are keyed "channel:Message.Signal", so messages
+ * with the same name on two buses get a column each.
+ *
+ * ```no_run
+ * use std::path::Path;
+ * use rocketcan::can_database::CanDatabase;
+ * use rocketcan::canlog_reader::CanLogParser;
+ * use rocketcan::signal_table::SignalExtractor;
+ *
+ * let database = CanDatabase::load("can_samples/chrysler_cusw.dbc").unwrap();
+ * let frames = CanLogParser::from_file(Path::new("demo-log.log")).unwrap();
+ * let table = SignalExtractor::new(&database)
+ *     .signal("STEERING.STEER_ANGLE")
+ *     .message("BRAKE_1")
+ *     .extract(frames)
+ *     .unwrap();
+ * let steer = table.get("STEERING.STEER_ANGLE").unwrap();
+ * println!("{} points in {}", steer.len(), steer.unit);
+ * ```
+ */
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::can_database::CanDatabase;
+use crate::canlog_reader::CanFrame;
+
+/// Time series of one signal, with its DBC metadata
+#[derive(Debug, Clone, Default)]
+pub struct SignalColumn {
+    /// Channel the message's DBC is bound to, None for DBCs of every channel
+    pub channel: Option<String>,
+    pub message: String,
+    pub signal: String,
+    pub message_id: u32,
+    pub unit: String,
+    /// Minimum and maximum physical values declared in the DBC
+    pub min: f64,
+    pub max: f64,
+    pub timestamps: Vec<f64>,
+    pub values: Vec<f64>,
+}
+
+impl SignalColumn {
+    /// Key of the column in a `SignalTable`: "Message.Signal", or "channel:Message.Signal"
+    pub fn key(&self) -> String {
+        match &self.channel {
+            Some(channel) => format!("{}:{}.{}", channel, self.message, self.signal),
+            None => format!("{}.{}", self.message, self.signal),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterate over (timestamp, value) points
+    pub fn points(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.timestamps.iter().copied().zip(self.values.iter().copied())
+    }
+
+    /// Last value at or before `timestamp`, None if the signal wasn't seen yet
+    pub fn value_at(&self, timestamp: f64) -> Option<f64> {
+        let index = self.timestamps.partition_point(|t| *t <= timestamp);
+        index.checked_sub(1).map(|i| self.values[i])
+    }
+}
+
+/// Per message index in the CanDatabase, the (signal index, column index) pairs to fill from its frames
+type ExtractionPlan = HashMap<usize, Vec<(usize, usize)>>;
+
+/// Signal columns keyed by "Message.Signal" (or "channel:Message.Signal"), in the order of the DBC
+#[derive(Debug, Clone, Default)]
+pub struct SignalTable {
+    columns: Vec<SignalColumn>,
+    index: HashMap<String, usize>,
+}
+
+impl SignalTable {
+    fn push(&mut self, column: SignalColumn) -> usize {
+        let position = self.columns.len();
+        self.index.insert(column.key(), position);
+        self.columns.push(column);
+        position
+    }
+
+    /// Retrieve a column by "Message.Signal" or "channel:Message.Signal" key
+    pub fn get(&self, key: &str) -> Option<&SignalColumn> {
+        self.index.get(key).map(|&i| &self.columns[i])
+    }
+
+    pub fn columns(&self) -> &[SignalColumn] {
+        &self.columns
+    }
+
+    /// Keys of all columns
+    pub fn keys(&self) -> impl Iterator<Item = String> + '_ {
+        self.columns.iter().map(SignalColumn::key)
+    }
+
+    /// Columns of the messages named `message`, on every channel
+    pub fn message_columns<'a>(&'a self, message: &'a str) -> impl Iterator<Item = &'a SignalColumn> + 'a {
+        self.columns.iter().filter(move |c| c.message == message)
+    }
+
+    /// Number of columns
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Consume the table and return its columns
+    pub fn into_columns(self) -> Vec<SignalColumn> {
+        self.columns
+    }
+}
+
+/// Selects signals of a database and extracts their time series from frames.
+/// Without any selection every signal of every message is extracted.
+pub struct SignalExtractor<'db> {
+    database: &'db CanDatabase,
+    signals: Vec<String>,
+    messages: Vec<String>,
+}
+
+impl<'db> SignalExtractor<'db> {
+    pub fn new(database: &'db CanDatabase) -> Self {
+        Self {
+            database,
+            signals: Vec::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    /// Split the optional "channel:" prefix of a selection
+    fn split_channel(selection: &str) -> (Option<&str>, &str) {
+        match selection.split_once(':') {
+            Some((channel, rest)) => (Some(channel), rest),
+            None => (None, selection),
+        }
+    }
+
+    /// Extract one signal, by "Message.Signal" key for every channel, or "channel:Message.Signal"
+    pub fn signal(mut self, key: &str) -> Self {
+        self.signals.push(key.to_owned());
+        self
+    }
+
+    /// Extract several signals, by keys as in `signal`
+    pub fn signals<S: AsRef<str>>(mut self, keys: impl IntoIterator<Item = S>) -> Self {
+        self.signals.extend(keys.into_iter().map(|k| k.as_ref().to_owned()));
+        self
+    }
+
+    /// Extract every signal of a message, by name for every channel, or "channel:Message"
+    pub fn message(mut self, name: &str) -> Self {
+        self.messages.push(name.to_owned());
+        self
+    }
+
+    /// Build the empty table and the plan to fill it
+    fn plan(&self) -> Result<(SignalTable, ExtractionPlan)> {
+        let mut table = SignalTable::default();
+        let mut plan = ExtractionPlan::new();
+        let select_all = self.signals.is_empty() && self.messages.is_empty();
+
+        // Messages that own their name on their channel, a message with the same name
+        // in a later DBC of the same channel gets no columns as keys must be unique
+        let candidates: Vec<usize> = (0..self.database.messages().len())
+            .filter(|&index| {
+                let channel = self.database.message_channel(index);
+                let name = self.database.messages()[index].name();
+                self.database.message_index_in(channel, name) == Some(index)
+            })
+            .collect();
+        let matches = |selection: (Option<&str>, &str), index: usize| {
+            let (channel, name) = selection;
+            self.database.messages()[index].name() == name
+                && channel.is_none_or(|channel| self.database.message_channel(index) == Some(channel))
+        };
+
+        let mut wanted_messages = Vec::with_capacity(self.messages.len());
+        for selection in &self.messages {
+            let selection = Self::split_channel(selection);
+            if !candidates.iter().any(|&index| matches(selection, index)) {
+                return Err(anyhow!("unknown message: {}", selection.1));
+            }
+            wanted_messages.push(selection);
+        }
+        let mut wanted_signals = Vec::with_capacity(self.signals.len());
+        for key in &self.signals {
+            let (channel, name) = Self::split_channel(key);
+            let (message_name, signal_name) = name
+                .split_once('.')
+                .ok_or_else(|| anyhow!("signal key {} is not Message.Signal", key))?;
+            let selection = (channel, message_name);
+            let mut selected = candidates.iter().filter(|&&index| matches(selection, index)).peekable();
+            if selected.peek().is_none() {
+                return Err(anyhow!("unknown message: {}", message_name));
+            }
+            if !selected.any(|&index| self.database.messages()[index].signal_index(signal_name).is_some()) {
+                return Err(anyhow!("unknown signal: {}", key));
+            }
+            wanted_signals.push((selection, signal_name));
+        }
+
+        for message_index in candidates {
+            let message = &self.database.messages()[message_index];
+            let channel = self.database.message_channel(message_index);
+            let whole_message =
+                select_all || wanted_messages.iter().any(|&selection| matches(selection, message_index));
+            for (signal_index, signal_spec) in message.spec().signals.iter().enumerate() {
+                let selected = whole_message
+                    || wanted_signals.iter().any(|&(selection, signal)| {
+                        signal == signal_spec.name && matches(selection, message_index)
+                    });
+                if !selected {
+                    continue;
+                }
+                let column = SignalColumn {
+                    channel: channel.map(str::to_owned),
+                    message: message.name().to_owned(),
+                    signal: signal_spec.name.clone(),
+                    message_id: message.id(),
+                    unit: signal_spec.unit.clone(),
+                    min: signal_spec.min,
+                    max: signal_spec.max,
+                    ..Default::default()
+                };
+                // Keep the first of duplicate signal names
+                if table.index.contains_key(&column.key()) {
+                    continue;
+                }
+                let column = table.push(column);
+                plan.entry(message_index).or_default().push((signal_index, column));
+            }
+        }
+        Ok((table, plan))
+    }
+
+    /// Decode the selected signals of every frame in a single pass.
+    /// Errors if a selected message or signal is not in the database.
+    pub fn extract<I: IntoIterator<Item = CanFrame>>(&self, frames: I) -> Result<SignalTable> {
+        let (mut table, plan) = self.plan()?;
+        for frame in frames {
+            let Some(message_index) = self.database.message_index_by_id(&frame.channel, frame.id) else {
+                continue;
+            };
+            let Some(columns) = plan.get(&message_index) else {
+                continue;
+            };
+            let message = &self.database.messages()[message_index];
+            for &(signal_index, column) in columns {
+                // Multiplexed signals only get a point when their page is in the frame
+                if let Some(value) = message.decode_signal_at(&frame, signal_index) {
+                    let column = &mut table.columns[column];
+                    column.timestamps.push(frame.timestamp);
+                    column.values.push(value);
+                }
+            }
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::can_decoder;
+    use crate::canlog_reader::{self, CanLogParser};
+
+    fn demo_database() -> CanDatabase {
+        CanDatabase::load("can_samples/chrysler_cusw.dbc").unwrap()
+    }
+
+    #[test]
+    fn test_extract_selected_signals() {
+        let database = demo_database();
+        let frames = CanLogParser::from_file(Path::new("demo-log.log")).unwrap();
+        let table = SignalExtractor::new(&database)
+            .signal("STEERING.STEER_ANGLE")
+            .message("BRAKE_1")
+            .extract(frames)
+            .unwrap();
+
+        let keys: Vec<_> = table.keys().collect();
+        assert!(keys.contains(&"STEERING.STEER_ANGLE".to_string()));
+        assert!(keys.contains(&"BRAKE_1.VEHICLE_SPEED".to_string()));
+        assert!(table.get("STEERING.STEERING_RATE").is_none());
+        assert_eq!(table.message_columns("BRAKE_1").count(), database.message("BRAKE_1").unwrap().spec().signals.len());
+
+        // demo-log.log has 1000 frames of each of its 7 messages
+        let steer = table.get("STEERING.STEER_ANGLE").unwrap();
+        assert_eq!(steer.len(), 1000);
+        assert_eq!(steer.timestamps.len(), steer.values.len());
+        assert_eq!(steer.unit, database.message("STEERING").unwrap().signal("STEER_ANGLE").unwrap().0.unit);
+        assert!(steer.timestamps.windows(2).all(|w| w[0] <= w[1]));
+
+        // Same values as decoding frame by frame
+        let frames = CanLogParser::from_file(Path::new("demo-log.log")).unwrap();
+        let steering = database.message("STEERING").unwrap();
+        let expected: Vec<f64> = frames
+            .filter(|f| f.id == steering.id())
+            .map(|f| steering.decode(&f).values[0])
+            .collect();
+        assert_eq!(steer.values, expected);
+    }
+
+    #[test]
+    fn test_extract_all_signals() {
+        let database = demo_database();
+        let frames = CanLogParser::from_file(Path::new("demo-log.log")).unwrap();
+        let table = SignalExtractor::new(&database).extract(frames).unwrap();
+        let signal_count: usize = database.messages().iter().map(|m| m.spec().signals.len()).sum();
+        assert_eq!(table.len(), signal_count);
+        // Messages missing from the log have empty columns
+        assert!(table.columns().iter().any(|c| c.is_empty()));
+        assert_eq!(table.get("GEARBOX_1.ACTUAL_GEAR").unwrap().value_at(5.0), Some(4.0));
+    }
+
+    #[test]
+    fn test_extract_multiplexed_signals() {
+        let database = CanDatabase::from_dbc(can_decoder::load_dbc("multiplex.dbc").unwrap());
+        let frames: Vec<_> = [
+            "(0.1) vcan0 542#0105020AFFFF0000",
+            "(0.2) vcan0 542#0205D430FEFF0000",
+            "(0.3) vcan0 542#0105020BFFFF0000",
+        ]
+        .iter()
+        .map(|line| canlog_reader::parse_candump_line(line).unwrap())
+        .collect();
+        let table = SignalExtractor::new(&database).message("Diagnostics").extract(frames).unwrap();
+
+        let minor = table.get("Diagnostics.SW_Minor").unwrap();
+        assert_eq!(minor.timestamps, [0.1, 0.3]);
+        assert_eq!(minor.values, [10.0, 11.0]);
+        let voltage = table.get("Diagnostics.Voltage").unwrap();
+        assert_eq!(voltage.points().collect::<Vec<_>>(), [(0.2, 12.5)]);
+        assert_eq!(voltage.value_at(0.15), None);
+        assert_eq!(voltage.value_at(0.25), Some(12.5));
+        assert_eq!(table.get("Diagnostics.Page").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_extract_same_message_on_two_channels() {
+        let motohawk = can_decoder::load_dbc("motohawk.dbc").unwrap();
+        let database = CanDatabase::new()
+            .with_channel_dbc("can0", motohawk.clone())
+            .with_channel_dbc("can1", motohawk.clone())
+            .with_dbc(can_decoder::load_dbc("multiplex.dbc").unwrap());
+        let frames: Vec<_> = [
+            "(0.1) can0 1F0#A5E0C4FF00000000",
+            "(0.2) can1 1F0#A5E0C4FF00000000",
+            "(0.3) can1 1F0#A5E0C4FF00000000",
+            "(0.4) can0 542#0105020AFFFF0000",
+        ]
+        .iter()
+        .map(|line| canlog_reader::parse_candump_line(line).unwrap())
+        .collect();
+
+        let table = SignalExtractor::new(&database).extract(frames.clone()).unwrap();
+        assert_eq!(table.get("can0:ExampleMessage.Temperature").unwrap().timestamps, [0.1]);
+        assert_eq!(table.get("can1:ExampleMessage.Temperature").unwrap().timestamps, [0.2, 0.3]);
+        assert!(table.get("ExampleMessage.Temperature").is_none());
+        assert_eq!(table.get("Diagnostics.SW_Minor").unwrap().values, [10.0]);
+        assert_eq!(table.message_columns("ExampleMessage").count(), 2 * motohawk.messages[0].signals.len());
+
+        // Selections without a channel pick the message on every channel
+        let table = SignalExtractor::new(&database).signal("ExampleMessage.Enable").extract(frames.clone()).unwrap();
+        assert_eq!(table.keys().collect::<Vec<_>>(), ["can0:ExampleMessage.Enable", "can1:ExampleMessage.Enable"]);
+        let table = SignalExtractor::new(&database).message("can1:ExampleMessage").extract(frames).unwrap();
+        assert!(table.columns().iter().all(|c| c.channel.as_deref() == Some("can1")));
+        assert!(SignalExtractor::new(&database).message("can2:ExampleMessage").extract(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_extract_unknown_selection() {
+        let database = demo_database();
+        assert!(SignalExtractor::new(&database).signal("STEERING.NOPE").extract(Vec::new()).is_err());
+        assert!(SignalExtractor::new(&database).signal("NOPE.STEER_ANGLE").extract(Vec::new()).is_err());
+        assert!(SignalExtractor::new(&database).signal("STEER_ANGLE").extract(Vec::new()).is_err());
+        assert!(SignalExtractor::new(&database).message("NOPE").extract(Vec::new()).is_err());
+    }
+}
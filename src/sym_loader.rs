@@ -0,0 +1,599 @@
+/*!
+ * PCAN Symbol (SYM) import, the format of PEAK's PCAN Symbol Editor.
+ * SYM file -> can_dbc::Dbc, decoded and encoded like any DBC
+ *
+ * - [Message] sections: BO_ with their ID=, Type= and Len= (or DLC=)
+ * - Var=: signals defined in the message, Sig=: signals of the {SIGNALS} section
+ * - {ENUMS}: VAL_ labels of the signals referencing them with /e:
+ * - Types float/double: SIG_VALTYPE_ float32/float64
+ * - Mux=: sections repeating a message name are its pages, the Mux= signal is the
+ *   multiplexer switch (M). Signals on several pages get an SG_MUL_VAL_ entry.
+ * - `//` comments after a signal: CM_ comments
+ *
+ * Big endian (-m) start bits are the MSB numbered from the most significant bit
+ * of byte 0, as other SYM tools read them.
+ *
+ * ```no_run
+ * use rocketcan::can_decoder;
+ * use rocketcan::sym_loader;
+ *
+ * let dbc = sym_loader::load("network.sym").unwrap();
+ * let message = can_decoder::get_message_spec(&dbc, "ExampleMessage").unwrap();
+ * ```
+ */
+
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::{anyhow, bail, Context, Result};
+use can_dbc::{Dbc, MultiplexIndicator, SignalExtendedValueType};
+
+use crate::dbc_editor::{self, DbcEditor, MessageBuilder, SignalBuilder};
+
+/// Load a SYM file
+pub fn load(sym_path: &str) -> Result<Dbc> {
+    let text = fs::read_to_string(sym_path).with_context(|| format!("reading {sym_path}"))?;
+    parse(&text).with_context(|| format!("loading {sym_path}"))
+}
+
+/// A signal definition: a Var= or Mux= line, or a Sig= line of {SIGNALS}
+#[derive(Debug, Clone)]
+struct SymSignal {
+    name: String,
+    signed: bool,
+    value_type: SignalExtendedValueType,
+    length: u64,
+    big_endian: bool,
+    factor: f64,
+    offset: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    unit: String,
+    enum_name: Option<String>,
+    comment: Option<String>,
+}
+
+/// A [Message] section, one page of the message when it has a Mux= line
+#[derive(Debug, Default)]
+struct SymSection {
+    name: String,
+    line: usize,
+    id: Option<u32>,
+    extended: bool,
+    len: Option<u64>,
+    /// Multiplexer switch, its start bit and the value selecting this page
+    mux: Option<(SymSignal, u64, u64)>,
+    /// Signals and their start bit
+    signals: Vec<(SymSignal, u64)>,
+}
+
+/// Parse the text of a SYM file
+pub fn parse(text: &str) -> Result<Dbc> {
+    let mut enums: HashMap<String, Vec<(i64, String)>> = HashMap::new();
+    let mut shared_signals: HashMap<String, SymSignal> = HashMap::new();
+    let mut sections: Vec<SymSection> = Vec::new();
+    let mut in_enums = false;
+    let mut in_signals = false;
+    // Enum= definitions may span several lines until their closing parenthesis
+    let mut pending_enum: Option<(usize, String)> = None;
+
+    for (index, raw_line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let (line, comment) = split_comment(raw_line);
+        let line = line.trim();
+
+        if let Some((start, mut definition)) = pending_enum.take() {
+            definition.push(' ');
+            definition.push_str(line);
+            if is_closed(&definition) {
+                let (name, labels) = parse_enum(&definition).with_context(|| format!("line {start}"))?;
+                enums.insert(name, labels);
+            } else {
+                pending_enum = Some((start, definition));
+            }
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('{') {
+            in_enums = line.eq_ignore_ascii_case("{ENUMS}");
+            in_signals = line.eq_ignore_ascii_case("{SIGNALS}");
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push(SymSection {
+                name: dbc_editor::identifier(name),
+                line: line_number,
+                ..Default::default()
+            });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("line {line_number}: expected key=value, got {line:?}");
+        };
+        let key = key.trim();
+        let value = value.trim();
+        let at_line = || format!("line {line_number}");
+
+        if in_enums && key.eq_ignore_ascii_case("enum") {
+            if is_closed(value) {
+                let (name, labels) = parse_enum(value).with_context(at_line)?;
+                enums.insert(name, labels);
+            } else {
+                pending_enum = Some((line_number, value.to_owned()));
+            }
+            continue;
+        }
+        if in_signals && key == "Sig" {
+            let tokens = tokenize(value);
+            let signal = parse_signal(&tokens, None, comment).with_context(at_line)?;
+            shared_signals.insert(signal.name.clone(), signal);
+            continue;
+        }
+        let Some(section) = sections.last_mut() else {
+            // FormatVersion=, Title= and other file settings
+            continue;
+        };
+        match key {
+            "ID" => {
+                // A range of IDs like 100h-10Fh shares the definition, keep the first
+                let first = value.split('-').next().unwrap_or_default();
+                let id = parse_number(first).with_context(at_line)?;
+                section.id = Some(u32::try_from(id).map_err(|_| anyhow!("{}: ID out of range", at_line()))?);
+            }
+            "Type" => section.extended = value.to_ascii_lowercase().contains("extended"),
+            "Len" | "DLC" => section.len = Some(parse_number(value).with_context(at_line)?),
+            "Var" | "Mux" => {
+                let tokens = tokenize(value);
+                let position = if key == "Mux" { 1 } else { 2 };
+                let (start, length) =
+                    parse_position(tokens.get(position).map(String::as_str)).with_context(at_line)?;
+                let mut rest: Vec<String> = vec![tokens[0].clone()];
+                if key == "Mux" {
+                    // Mux=<name> <start>,<length> <value> [flags] [options], always unsigned
+                    let page_value = tokens.get(2).ok_or_else(|| anyhow!("{}: Mux without value", at_line()))?;
+                    let page_value = parse_number(page_value).with_context(at_line)?;
+                    rest.push("unsigned".to_owned());
+                    rest.extend(tokens.iter().skip(3).cloned());
+                    let signal = parse_signal(&rest, length, comment).with_context(at_line)?;
+                    section.mux = Some((signal, start, page_value));
+                } else {
+                    // Var=<name> <type> <start>[,<length>] [flags] [options]
+                    rest.extend(tokens.get(1).cloned());
+                    rest.extend(tokens.iter().skip(3).cloned());
+                    let signal = parse_signal(&rest, length, comment).with_context(at_line)?;
+                    section.signals.push((signal, start));
+                }
+            }
+            "Sig" => {
+                // Sig=<name> <start> [-m]
+                let tokens = tokenize(value);
+                let name = tokens.first().map(String::as_str).unwrap_or_default();
+                let mut signal = shared_signals
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("{}: unknown signal {}", at_line(), name))?;
+                let (start, _) = parse_position(tokens.get(1).map(String::as_str)).with_context(at_line)?;
+                signal.big_endian |= tokens.iter().any(|t| t == "-m");
+                if comment.is_some() {
+                    signal.comment = comment.map(str::to_owned);
+                }
+                section.signals.push((signal, start));
+            }
+            _ => {}
+        }
+    }
+    if let Some((start, _)) = pending_enum {
+        bail!("line {start}: unterminated Enum");
+    }
+
+    build_dbc(&sections, &enums)
+}
+
+/// Convert the sections into messages, merging the pages of multiplexed messages
+fn build_dbc(sections: &[SymSection], enums: &HashMap<String, Vec<(i64, String)>>) -> Result<Dbc> {
+    let mut editor = DbcEditor::new();
+
+    let mut names: Vec<&str> = Vec::new();
+    for section in sections {
+        if !names.contains(&section.name.as_str()) {
+            names.push(&section.name);
+        }
+    }
+    for name in names {
+        let pages: Vec<&SymSection> = sections.iter().filter(|s| s.name == name).collect();
+        let first = pages[0];
+        let at_line = || format!("message {} at line {}", name, first.line);
+        let id = pages.iter().find_map(|p| p.id).ok_or_else(|| anyhow!("{}: no ID", at_line()))?;
+
+        // Signals in order with the pages they are on, None for every page
+        let mut signals: Vec<(&SymSignal, u64, Vec<u64>)> = Vec::new();
+        let mut mux: Option<(&SymSignal, u64)> = None;
+        for page in &pages {
+            if let Some((switch, start, _)) = &page.mux {
+                mux.get_or_insert((switch, *start));
+            }
+            let page_value = page.mux.as_ref().map(|(_, _, value)| *value);
+            for (signal, start) in &page.signals {
+                match signals.iter_mut().find(|(s, _, _)| s.name == signal.name) {
+                    Some((_, _, values)) => values.extend(page_value),
+                    None => signals.push((signal, *start, page_value.into_iter().collect())),
+                }
+            }
+        }
+
+        let end = |signal: &SymSignal, start: u64| start + signal.length;
+        let len = first.len.or_else(|| pages.iter().find_map(|p| p.len)).unwrap_or_else(|| {
+            let mux_end = mux.map(|(s, start)| end(s, start));
+            signals.iter().map(|(s, start, _)| end(s, *start)).chain(mux_end).max().unwrap_or(0).div_ceil(8)
+        });
+        let mut builder = MessageBuilder::new(name, id, len);
+        if pages.iter().any(|p| p.extended) {
+            builder = builder.extended();
+        }
+        if let Some((switch, start)) = mux {
+            let mut signal = to_signal(switch, start);
+            signal.multiplexer_indicator = MultiplexIndicator::Multiplexor;
+            builder = builder.signal(signal);
+        }
+        for (signal, start, _) in &signals {
+            builder = builder.signal(to_signal(signal, *start));
+        }
+        editor.add_message(builder.build()).with_context(at_line)?;
+
+        let all_signals = mux.map(|(switch, _)| switch).into_iter().chain(signals.iter().map(|(s, _, _)| *s));
+        for signal in all_signals {
+            if signal.value_type != SignalExtendedValueType::SignedOrUnsignedInteger {
+                editor.set_signal_value_type(name, &signal.name, signal.value_type)?;
+            }
+            if let Some(enum_name) = &signal.enum_name {
+                let labels = enums
+                    .get(enum_name)
+                    .ok_or_else(|| anyhow!("{}: unknown enum {} of signal {}", at_line(), enum_name, signal.name))?;
+                let labels: Vec<(i64, &str)> = labels.iter().map(|(v, l)| (*v, l.as_str())).collect();
+                editor.set_value_descriptions(name, &signal.name, &labels)?;
+            }
+            if let Some(comment) = &signal.comment {
+                editor.set_signal_comment(name, &signal.name, comment)?;
+            }
+        }
+        for (signal, _, values) in &signals {
+            if !values.is_empty() {
+                editor.set_multiplexor_values(name, &signal.name, values)?;
+            }
+        }
+    }
+
+    Ok(editor.into_dbc())
+}
+
+fn to_signal(signal: &SymSignal, start: u64) -> can_dbc::Signal {
+    let start_bit = if signal.big_endian { dbc_editor::msb_first_start_bit(start) } else { start };
+    let mut builder = SignalBuilder::new(&signal.name, start_bit, signal.length)
+        .scale(signal.factor, signal.offset)
+        .unit(&signal.unit);
+    if signal.big_endian {
+        builder = builder.big_endian();
+    }
+    if signal.signed {
+        builder = builder.signed();
+    }
+    if signal.value_type != SignalExtendedValueType::SignedOrUnsignedInteger {
+        builder = builder.float();
+    }
+    let mut dbc_signal = builder.build();
+    dbc_signal.min = signal.min.unwrap_or(dbc_signal.min);
+    dbc_signal.max = signal.max.unwrap_or(dbc_signal.max);
+    dbc_signal
+}
+
+/// Signal from its name, type, flags and options. `length` is None for types with a fixed size.
+fn parse_signal(tokens: &[String], length: Option<u64>, comment: Option<&str>) -> Result<SymSignal> {
+    let name = tokens.first().ok_or_else(|| anyhow!("signal without name"))?;
+    let kind = tokens.get(1).ok_or_else(|| anyhow!("signal {} without type", name))?;
+    let mut rest = &tokens[2..];
+    // In {SIGNALS} the length follows the type
+    let mut length = length;
+    if let Some(first) = rest.first().filter(|t| t.chars().all(|c| c.is_ascii_digit())) {
+        length = Some(first.parse()?);
+        rest = &rest[1..];
+    }
+
+    let (signed, value_type, fixed_length) = match kind.to_ascii_lowercase().as_str() {
+        "unsigned" | "string" | "raw" => (false, SignalExtendedValueType::SignedOrUnsignedInteger, None),
+        "signed" => (true, SignalExtendedValueType::SignedOrUnsignedInteger, None),
+        "bit" => (false, SignalExtendedValueType::SignedOrUnsignedInteger, Some(1)),
+        "char" => (false, SignalExtendedValueType::SignedOrUnsignedInteger, Some(8)),
+        "float" => (false, SignalExtendedValueType::IEEEfloat32Bit, Some(32)),
+        "double" => (false, SignalExtendedValueType::IEEEdouble64bit, Some(64)),
+        other => bail!("unknown type {} of signal {}", other, name),
+    };
+    let length = length
+        .or(fixed_length)
+        .ok_or_else(|| anyhow!("signal {} without length", name))?;
+
+    let mut signal = SymSignal {
+        name: dbc_editor::identifier(name),
+        signed,
+        value_type,
+        length,
+        big_endian: false,
+        factor: 1.0,
+        offset: 0.0,
+        min: None,
+        max: None,
+        unit: String::new(),
+        enum_name: None,
+        comment: comment.map(str::to_owned),
+    };
+    for token in rest {
+        if token == "-m" {
+            signal.big_endian = true;
+            continue;
+        }
+        // Other flags are display hints: -h hex, -b binary, -t
+        let Some((option, value)) = token.strip_prefix('/').and_then(|t| t.split_once(':')) else {
+            continue;
+        };
+        let number = || value.parse::<f64>().map_err(|_| anyhow!("invalid /{}:{} of signal {}", option, value, name));
+        match option {
+            "u" => signal.unit = value.to_owned(),
+            "f" => signal.factor = number()?,
+            "o" => signal.offset = number()?,
+            "min" => signal.min = Some(number()?),
+            "max" => signal.max = Some(number()?),
+            "e" => signal.enum_name = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+    Ok(signal)
+}
+
+/// `<start>,<length>` or `<start>`
+fn parse_position(token: Option<&str>) -> Result<(u64, Option<u64>)> {
+    let token = token.ok_or_else(|| anyhow!("missing start bit"))?;
+    let (start, length) = match token.split_once(',') {
+        Some((start, length)) => (start, Some(length)),
+        None => (token, None),
+    };
+    let start = start.trim().parse().map_err(|_| anyhow!("invalid start bit {:?}", token))?;
+    let length = length
+        .map(|l| l.trim().parse().map_err(|_| anyhow!("invalid length {:?}", token)))
+        .transpose()?;
+    Ok((start, length))
+}
+
+/// Decimal, or hex with an h suffix as in `ID=1F0h`
+fn parse_number(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let parsed = match text.strip_suffix('h').or_else(|| text.strip_suffix('H')) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| anyhow!("invalid number {:?}", text))
+}
+
+/// `Name(0="Off", 1="On")`
+fn parse_enum(definition: &str) -> Result<(String, Vec<(i64, String)>)> {
+    let (name, values) = definition
+        .split_once('(')
+        .ok_or_else(|| anyhow!("invalid Enum {:?}", definition))?;
+    let values = values.trim_end().strip_suffix(')').unwrap_or(values);
+    let mut labels = Vec::new();
+    for entry in split_outside_quotes(values, ',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let (value, label) = entry
+            .split_once('=')
+            .ok_or_else(|| anyhow!("invalid Enum value {:?}", entry))?;
+        let value = value.trim().parse().map_err(|_| anyhow!("invalid Enum value {:?}", entry))?;
+        labels.push((value, label.trim().trim_matches('"').to_owned()));
+    }
+    Ok((name.trim().to_owned(), labels))
+}
+
+/// Whether the parentheses of an Enum definition are balanced
+fn is_closed(definition: &str) -> bool {
+    let mut depth = 0i32;
+    let mut in_quotes = false;
+    for c in definition.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '(' if !in_quotes => depth += 1,
+            ')' if !in_quotes => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0 && definition.contains('(')
+}
+
+fn split_outside_quotes(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c == separator && !in_quotes {
+            parts.push(&text[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Split a line at `//` outside of quotes: the content and the comment
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut in_quotes = false;
+    let bytes = line.as_bytes();
+    for i in 0..bytes.len() {
+        match bytes[i] {
+            b'"' => in_quotes = !in_quotes,
+            b'/' if !in_quotes && bytes.get(i + 1) == Some(&b'/') => {
+                let comment = line[i + 2..].trim();
+                return (&line[..i], Some(comment).filter(|c| !c.is_empty()));
+            }
+            _ => {}
+        }
+    }
+    (line, None)
+}
+
+/// Whitespace separated tokens, quotes group words and are removed
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in text.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::can_decoder::{decode_message_with_dbc, load_dbc};
+    use crate::can_encoder::CanFrameBuilder;
+    use can_dbc::{ExtendedMultiplexMapping, MessageId};
+
+    #[test]
+    fn test_motohawk_sym_matches_dbc() {
+        let sym = load("motohawk.sym").unwrap();
+        let mut dbc = load_dbc("motohawk.dbc").unwrap();
+        // SYM files have no nodes
+        for message in &mut dbc.messages {
+            message.transmitter = can_dbc::Transmitter::VectorXXX;
+            for signal in &mut message.signals {
+                signal.receivers = vec!["Vector__XXX".to_owned()];
+            }
+        }
+        assert_eq!(sym.messages, dbc.messages);
+        assert_eq!(sym.value_descriptions, dbc.value_descriptions);
+
+        let frame = CanFrameBuilder::new(&sym.messages[0])
+            .dbc(&sym)
+            .set("Temperature", 244.2)
+            .unwrap()
+            .set("Enable", "Enabled")
+            .unwrap()
+            .build();
+        let decoded = decode_message_with_dbc(&frame, &dbc.messages[0], &dbc);
+        assert!((decoded.values[0] - 244.2).abs() < 1e-9);
+        assert_eq!(decoded.labels[2].as_deref(), Some("Enabled"));
+    }
+
+    const MULTIPLEXED: &str = r#"FormatVersion=6.0 // Do not edit this line!
+Title="test"
+
+{ENUMS}
+Enum=Pages(1="Version", 2="Supply",
+  3="Extra")
+
+{SIGNALS}
+Sig=Status unsigned 4 // Operating state
+
+{SENDRECEIVE}
+
+[Diagnostics]
+ID=542h
+Type=Standard
+Len=8
+Mux=Page 0,8 1 /e:Pages
+Sig=Status 8
+Var=SW_Major unsigned 16,8
+Var=Counter unsigned 56,8
+
+[Diagnostics]
+Len=8
+Mux=Page 0,8 2
+Var=Voltage unsigned 16,16 /u:V /f:0.001
+Var=Current signed 32,16 /u:"milli amps" /f:10
+Var=Counter unsigned 56,8
+
+[Diagnostics]
+Mux=Page 0,8 3
+Var=Counter unsigned 56,8
+
+[Speed]
+ID=18FEF100h
+Type=Extended
+Var=Speed float 8,32 -m /u:km/h
+"#;
+
+    #[test]
+    fn test_multiplexed_sym() {
+        let dbc = parse(MULTIPLEXED).unwrap();
+        let diagnostics = &dbc.messages[0];
+        assert_eq!(diagnostics.id, MessageId::Standard(0x542));
+        let indicators: Vec<_> = diagnostics.signals.iter().map(|s| (s.name.as_str(), s.multiplexer_indicator)).collect();
+        assert_eq!(
+            indicators,
+            [
+                ("Page", MultiplexIndicator::Multiplexor),
+                ("Status", MultiplexIndicator::MultiplexedSignal(1)),
+                ("SW_Major", MultiplexIndicator::MultiplexedSignal(1)),
+                ("Counter", MultiplexIndicator::MultiplexedSignal(1)),
+                ("Voltage", MultiplexIndicator::MultiplexedSignal(2)),
+                ("Current", MultiplexIndicator::MultiplexedSignal(2)),
+            ]
+        );
+        assert_eq!(diagnostics.signals[5].unit, "milli amps");
+        assert_eq!(dbc.extended_multiplex.len(), 1);
+        assert_eq!(dbc.extended_multiplex[0].signal_name, "Counter");
+        assert_eq!(dbc.extended_multiplex[0].mappings, [ExtendedMultiplexMapping { min_value: 1, max_value: 3 }]);
+        assert_eq!(dbc.signal_comment(diagnostics.id, "Status"), Some("Operating state"));
+
+        let frame = CanFrameBuilder::new(diagnostics)
+            .dbc(&dbc)
+            .set("Page", "Supply")
+            .unwrap()
+            .set("Voltage", 12.5)
+            .unwrap()
+            .set("Current", -200.0)
+            .unwrap()
+            .set("Counter", 7.0)
+            .unwrap()
+            .build();
+        let decoded = decode_message_with_dbc(&frame, diagnostics, &dbc);
+        assert_eq!(decoded.signals, ["Page", "Counter", "Voltage", "Current"]);
+        assert_eq!(decoded.labels[0].as_deref(), Some("Supply"));
+        assert_eq!(decoded.values[1], 7.0);
+        assert!((decoded.values[2] - 12.5).abs() < 1e-9);
+        assert_eq!(decoded.values[3], -200.0);
+
+        let speed = &dbc.messages[1];
+        assert_eq!(speed.id, MessageId::Extended(0x18FEF100));
+        assert_eq!(speed.size, 5);
+        assert_eq!(speed.signals[0].start_bit, 15);
+        let frame = CanFrameBuilder::new(speed).dbc(&dbc).set("Speed", 88.25).unwrap().build();
+        assert_eq!(frame.data[1..5], 88.25f32.to_bits().to_be_bytes());
+        assert_eq!(decode_message_with_dbc(&frame, speed, &dbc).values, [88.25]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = parse("[Foo]\nID=12h\nVar=A float32 0,32\n").unwrap_err();
+        assert_eq!(format!("{error:#}"), "line 3: unknown type float32 of signal A");
+        let error = parse("[Foo]\nVar=A unsigned 0,8\n").unwrap_err();
+        assert_eq!(error.to_string(), "message Foo at line 1: no ID");
+        let error = parse("{ENUMS}\nEnum=A(0=\"x\",\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: unterminated Enum");
+    }
+}
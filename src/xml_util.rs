@@ -0,0 +1,38 @@
+/*!
+ * roxmltree helpers shared by the XML database loaders (KCD and ARXML)
+ */
+
+use anyhow::{anyhow, Result};
+use roxmltree::Node;
+
+pub(crate) fn children<'a, 'input>(node: Node<'a, 'input>, tag: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |c| c.has_tag_name(tag))
+}
+
+pub(crate) fn child<'a, 'input>(node: Node<'a, 'input>, tag: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, tag).next()
+}
+
+pub(crate) fn descendants<'a, 'input>(node: Node<'a, 'input>, tag: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.descendants().filter(move |n| n.has_tag_name(tag))
+}
+
+pub(crate) fn descendant<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.descendants().find(|n| n.has_tag_name(tag))
+}
+
+/// "row:column" of the start of a node, for error messages
+pub(crate) fn position(node: Node) -> String {
+    let pos = node.document().text_pos_at(node.range().start);
+    format!("{}:{}", pos.row, pos.col)
+}
+
+/// Decimal or 0x prefixed hex integer
+pub(crate) fn number(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| anyhow!("invalid number {:?}", text))
+}
@@ -0,0 +1,98 @@
+/*!
+ * End to end tests of the rocketcan command line tool, run on the sample files of the repository
+ */
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+use rocketcan::canlog_reader::CanLogParser;
+use rocketcan::CanFrame;
+
+/// The rocketcan binary, run from the repository root where the sample files are
+fn rocketcan(args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rocketcan"));
+    command.current_dir(env!("CARGO_MANIFEST_DIR")).args(args);
+    command
+}
+
+fn run(args: &[&str]) -> Output {
+    let output = rocketcan(args).output().unwrap();
+    assert!(output.status.success(), "rocketcan {args:?} failed: {}", String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+fn frames(log: &[u8]) -> Vec<CanFrame> {
+    CanLogParser::from_bytes(log.to_vec()).unwrap().collect()
+}
+
+fn sample_frames(path: &str) -> Vec<CanFrame> {
+    CanLogParser::from_file(&Path::new(env!("CARGO_MANIFEST_DIR")).join(path)).unwrap().collect()
+}
+
+#[test]
+fn test_convert_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let converted = dir.path().join("converted.log");
+    run(&["convert", "candump.log", "-o", converted.to_str().unwrap()]);
+    let output = run(&["convert", converted.to_str().unwrap(), "--to", "candump"]);
+    assert_eq!(frames(&output.stdout), sample_frames("candump.log"));
+
+    // Vector ASCII input, timestamps are relative to the start of the log
+    let output = run(&["convert", "candump.asc"]);
+    let from_asc = frames(&output.stdout);
+    assert_eq!(from_asc.len(), 10);
+    assert_eq!((from_asc[0].id, from_asc[0].len), (0x044, 5));
+}
+
+#[test]
+fn test_decode_demo_log() {
+    let output = run(&["decode", "--dbc", "can_samples/chrysler_cusw.dbc", "demo-log.log"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("(0.000000) vcan0 STEERING : 0x1EE {\nSTEER_ANGLE: 0,\n"), "{stdout:.200}");
+    // Every frame of the demo log is a message of the DBC
+    let messages = stdout.lines().filter(|line| line.ends_with(" {")).count();
+    assert_eq!(messages, sample_frames("demo-log.log").len());
+}
+
+#[test]
+fn test_filter_stdin() {
+    let mut child = rocketcan(&["filter", "-", "--id", "0x100-0x1FF"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let log = std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("candump.log")).unwrap();
+    child.stdin.take().unwrap().write_all(&log).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let mut expected = sample_frames("candump.log");
+    expected.retain(|f| (0x100..=0x1FF).contains(&f.id));
+    assert!(!expected.is_empty());
+    assert_eq!(frames(&output.stdout), expected);
+}
+
+#[test]
+fn test_closed_stdout_succeeds() {
+    // Decoded text of demo-log.log is larger than a pipe buffer, so writes fail once the reader is gone
+    let mut child = rocketcan(&["decode", "--dbc", "can_samples/chrysler_cusw.dbc", "demo-log.log"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert!(!stderr.contains("error:"), "{stderr}");
+}
+
+#[test]
+fn test_missing_dbc_fails() {
+    let output = rocketcan(&["decode", "--dbc", "missing.dbc", "candump.log"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: missing.dbc: "), "{stderr}");
+}
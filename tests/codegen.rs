@@ -0,0 +1,48 @@
+/*!
+ * Compile check of the code generated for a full DBC, against the rocketcan library of this build.
+ * The small snapshots in codegen_samples cover the behavior, this covers the breadth of a real DBC.
+ */
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rocketcan::can_decoder;
+use rocketcan::codegen::Codegen;
+
+/// rocketcan rlib this test binary was linked with, the newest one in its deps directory
+fn rocketcan_rlib(deps: &Path) -> PathBuf {
+    fs::read_dir(deps)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("librocketcan-") && name.ends_with(".rlib")
+        })
+        .max_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).unwrap())
+        .expect("rocketcan rlib next to the test binary")
+}
+
+#[test]
+fn test_full_dbc_compiles() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let dbc = can_decoder::load_dbc(manifest_dir.join("can_samples/chrysler_cusw.dbc").to_str().unwrap()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("chrysler.rs");
+    fs::write(&source, Codegen::new(&dbc).generate()).unwrap();
+
+    let deps = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .args(["--edition", "2021", "--crate-type", "lib", "--emit", "metadata", "--crate-name", "chrysler"])
+        .arg("--out-dir")
+        .arg(dir.path())
+        .arg("-L")
+        .arg(format!("dependency={}", deps.display()))
+        .arg("--extern")
+        .arg(format!("rocketcan={}", rocketcan_rlib(&deps).display()))
+        .arg(&source)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}